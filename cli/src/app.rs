@@ -1,5 +1,6 @@
 use std::{
     io,
+    path::{Path, PathBuf},
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc,
@@ -8,34 +9,122 @@ use std::{
 
 use clap::Parser;
 use crossterm::terminal::size;
-use lib::emulator::Emulator;
+use lib::{
+    audio::DEFAULT_SAMPLE_RATE,
+    emulator::Emulator,
+    fds::{is_fds_image, sidecar_path},
+    nsf::{Nsf, NsfPlayer},
+    record::audio_dump,
+    region::Region,
+    render::{filter::Filter, utils::palette::Palette},
+    trace::TraceOptions,
+};
 
-use crate::{joypad::CliJoypadHandler, renderer::CliRenderer, speaker::CliSpeaker};
+use crate::{joypad::CliJoypadHandler, player, renderer::CliRenderer, speaker::CliSpeaker};
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 pub struct App {
     path: String,
+    #[arg(
+        long,
+        help = "ntsc, pal or dendy (default: detected from the ROM header)"
+    )]
+    region: Option<Region>,
+    #[arg(
+        long,
+        help = "Built-in palette (default, 2c03, composite) or path to a .pal file"
+    )]
+    palette: Option<String>,
+    #[arg(
+        long,
+        default_value = "none",
+        help = "Upscaling filter: none, scale2x, scale3x, hq2x, xbr or crt"
+    )]
+    filter: Filter,
+    #[command(flatten)]
+    trace: TraceOptions,
+    #[arg(long, help = "Write the mixed APU output to this WAV file")]
+    dump_audio: Option<PathBuf>,
+    #[arg(
+        long,
+        requires = "dump_audio",
+        help = "Also write each channel to <name>.<channel>.wav (pulse1, pulse2, triangle, noise, dmc, expansion)"
+    )]
+    audio_stems: bool,
+    #[arg(
+        long,
+        help = "Track to start an NSF from (default: the file's starting track)"
+    )]
+    track: Option<u8>,
+    #[arg(
+        long,
+        help = "Famicom Disk System BIOS (disksys.rom), required for .fds images"
+    )]
+    fds_bios: Option<PathBuf>,
 }
 
 #[derive(Debug)]
 pub enum Error {
     Io(io::Error),
+    InvalidPalette(String),
+    InvalidNsf(String),
+    InvalidFds(String),
+    Record(String),
     FailedJoin,
 }
 
 impl App {
     pub fn run(&self) -> Result<(), Error> {
         let rom_data = std::fs::read(&self.path).map_err(Error::Io)?;
+        if Nsf::is_nsf(&rom_data) {
+            return self.play_nsf(&rom_data);
+        }
+        let palette = self
+            .palette
+            .as_deref()
+            .map(Palette::load)
+            .transpose()
+            .map_err(Error::InvalidPalette)?;
+        let trace_logger = self.trace.logger().map_err(Error::Io)?;
 
         let running = Arc::new(AtomicBool::new(true));
+        let screenshot = Arc::new(AtomicBool::new(false));
+        let switch_disk_side = Arc::new(AtomicBool::new(false));
         let (width, height) = size().map_err(Error::Io)?;
-        let mut emulator = Emulator::new(
-            rom_data,
-            CliSpeaker,
-            CliJoypadHandler::new(running.clone()),
-            CliRenderer::new(width as usize, (height - 2) as usize),
+        let handler = CliJoypadHandler::new(
+            running.clone(),
+            screenshot.clone(),
+            switch_disk_side.clone(),
+        );
+        let renderer = CliRenderer::new(
+            width as usize,
+            (height - 2) as usize,
+            self.filter,
+            screenshot,
         );
+        let mut emulator = if is_fds_image(&rom_data) {
+            let bios = self.fds_bios()?;
+            let patch = read_sidecar(&self.path)?;
+            Emulator::new_fds(rom_data, patch, bios, CliSpeaker, handler, renderer)
+                .map_err(Error::InvalidFds)?
+        } else {
+            Emulator::new(rom_data, CliSpeaker, handler, renderer)
+        };
+        if let Some(region) = self.region {
+            emulator.set_region(region);
+        }
+        let frame_rate = emulator.frame_rate();
+        emulator.renderer_mut().set_frame_rate(frame_rate);
+        if let Some(palette) = palette {
+            emulator.set_palette(palette);
+        }
+        emulator.set_trace_logger(trace_logger);
+        if let Some(path) = &self.dump_audio {
+            let recorder =
+                audio_dump(path, self.audio_stems, DEFAULT_SAMPLE_RATE).map_err(Error::Record)?;
+            emulator.start_audio_dump(recorder).map_err(Error::Record)?;
+        }
 
         {
             let r = running.clone();
@@ -47,9 +136,79 @@ impl App {
 
         emulator.reset();
         while running.load(Ordering::SeqCst) {
+            if switch_disk_side.swap(false, Ordering::SeqCst) {
+                emulator.switch_disk_side();
+            }
             emulator.step();
         }
 
+        // NOTE: 実行中の標準出力はゲーム画面なので、診断情報は終了後にまとめて出す
+        let counters = emulator.diagnostics().counters();
+        let trace_error = emulator.trace_logger_mut().and_then(|logger| {
+            logger.flush();
+            logger.error().map(|e| e.to_string())
+        });
+        let audio_error = emulator.stop_audio_dump().and_then(|result| result.err());
+        let disk_patch = emulator.disk_patch();
+        drop(emulator);
+        for (category, feature, count) in counters {
+            eprintln!("[{}] {}: {}", category, feature, count);
+        }
+        if let Some(e) = trace_error {
+            eprintln!("[trace] {}", e);
+        }
+        if let Some(e) = audio_error {
+            eprintln!("[audio] {}", e);
+        }
+        if let Some(patch) = disk_patch {
+            std::fs::write(sidecar_path(Path::new(&self.path)), patch).map_err(Error::Io)?;
+        }
+
+        Ok(())
+    }
+
+    fn fds_bios(&self) -> Result<Vec<u8>, Error> {
+        let Some(path) = &self.fds_bios else {
+            return Err(Error::InvalidFds("FDS images need --fds-bios".to_string()));
+        };
+        std::fs::read(path).map_err(Error::Io)
+    }
+
+    // NOTE: NSF / NSFe は PPU なしで鳴らす
+    fn play_nsf(&self, data: &[u8]) -> Result<(), Error> {
+        let nsf = Nsf::new(data).map_err(Error::InvalidNsf)?;
+        let song = self
+            .track
+            .map_or(nsf.starting_song, |track| track.saturating_sub(1));
+
+        let mut player = NsfPlayer::new(nsf, CliSpeaker);
+        if let Some(region) = self.region {
+            player.set_region(region);
+        }
+        let audio_dump = match &self.dump_audio {
+            Some(path) => {
+                let recorder = audio_dump(path, self.audio_stems, DEFAULT_SAMPLE_RATE)
+                    .map_err(Error::Record)?;
+                player.set_audio_capture(Some(recorder.sample_rate()));
+                Some(recorder)
+            }
+            None => None,
+        };
+
+        player::play(&mut player, song, audio_dump);
+
+        for (category, feature, count) in player.diagnostics().counters() {
+            eprintln!("[{}] {}: {}", category, feature, count);
+        }
         Ok(())
     }
 }
+
+// NOTE: 前回までにディスクへ書き込んだ内容
+fn read_sidecar(path: &str) -> Result<Option<Vec<u8>>, Error> {
+    match std::fs::read(sidecar_path(Path::new(path))) {
+        Ok(patch) => Ok(Some(patch)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(Error::Io(e)),
+    }
+}
@@ -33,9 +33,18 @@ pub struct CliJoypadHandler {
 }
 
 impl CliJoypadHandler {
-    pub fn new(running: Arc<AtomicBool>) -> Self {
+    pub fn new(
+        running: Arc<AtomicBool>,
+        screenshot: Arc<AtomicBool>,
+        switch_disk_side: Arc<AtomicBool>,
+    ) -> Self {
         let inputs = Arc::new(Mutex::new(InputState::default()));
-        let handle = handle_event(running.clone(), inputs.clone());
+        let handle = handle_event(
+            running.clone(),
+            screenshot,
+            switch_disk_side,
+            inputs.clone(),
+        );
         enable_raw_mode().unwrap();
 
         Self {
@@ -66,7 +75,12 @@ impl Drop for CliJoypadHandler {
     }
 }
 
-fn handle_event(running: Arc<AtomicBool>, inputs: Arc<Mutex<InputState>>) -> JoinHandle<()> {
+fn handle_event(
+    running: Arc<AtomicBool>,
+    screenshot: Arc<AtomicBool>,
+    switch_disk_side: Arc<AtomicBool>,
+    inputs: Arc<Mutex<InputState>>,
+) -> JoinHandle<()> {
     let r = running.clone();
     thread::spawn(move || loop {
         if event::poll(Duration::from_millis(10)).unwrap() {
@@ -84,6 +98,12 @@ fn handle_event(running: Arc<AtomicBool>, inputs: Arc<Mutex<InputState>>) -> Joi
                     KeyCode::Char('s') => inputs.b = state,
                     KeyCode::Char('z') => inputs.start = state,
                     KeyCode::Char('x') => inputs.select = state,
+                    KeyCode::Char('p') if event.kind == event::KeyEventKind::Press => {
+                        screenshot.store(true, Ordering::SeqCst);
+                    }
+                    KeyCode::Char('d') if event.kind == event::KeyEventKind::Press => {
+                        switch_disk_side.store(true, Ordering::SeqCst);
+                    }
                     KeyCode::Char('c') if event.modifiers.contains(KeyModifiers::CONTROL) => {
                         r.store(false, Ordering::SeqCst);
                         break;
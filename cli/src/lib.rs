@@ -1,4 +1,5 @@
 pub mod app;
 mod joypad;
+mod player;
 mod renderer;
 mod speaker;
@@ -0,0 +1,153 @@
+use std::{
+    io::{stdout, Write},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{self, KeyCode, KeyEventKind, KeyModifiers},
+    execute, queue,
+    style::Print,
+    terminal::{
+        disable_raw_mode, enable_raw_mode, Clear, ClearType, EnterAlternateScreen,
+        LeaveAlternateScreen,
+    },
+};
+use lib::{audio::Channel, nsf::NsfPlayer, record::Recorder, speaker::Speaker};
+
+const BAR_WIDTH: usize = 32;
+
+enum Command {
+    Previous,
+    Next,
+    Quit,
+}
+
+// NOTE: 端末の設定を Drop で戻すためのガード。曲の情報とチャンネルごとの音量を表示する
+struct PlayerView;
+
+impl PlayerView {
+    fn new() -> Self {
+        execute!(stdout(), Hide, EnterAlternateScreen, Clear(ClearType::All)).unwrap();
+        enable_raw_mode().unwrap();
+        Self
+    }
+
+    fn draw<S: Speaker>(&self, player: &NsfPlayer<S>, elapsed: Duration) {
+        let nsf = player.nsf();
+        let song = player.song();
+        let track = match nsf.track_label(song) {
+            Some(label) => format!("{}/{} {}", song + 1, nsf.songs, label),
+            None => format!("{}/{}", song + 1, nsf.songs),
+        };
+        let seconds = elapsed.as_secs();
+
+        let mut lines = vec![
+            format!("Title:     {}", nsf.title),
+            format!("Artist:    {}", nsf.artist),
+            format!("Copyright: {}", nsf.copyright),
+            format!("Track:     {}", track),
+            format!(
+                "Time:      {}:{:02} ({}, {:.2} Hz)",
+                seconds / 60,
+                seconds % 60,
+                player.region(),
+                1.0 / player.play_period()
+            ),
+            String::new(),
+        ];
+        for (channel, level) in Channel::ALL.iter().zip(player.channel_levels()) {
+            let filled = (level * BAR_WIDTH as f32).round() as usize;
+            lines.push(format!(
+                "{:<9}  {}{}",
+                channel.to_string(),
+                "#".repeat(filled),
+                ".".repeat(BAR_WIDTH - filled)
+            ));
+        }
+        lines.push(String::new());
+        lines.push("Left/Right: track  Esc: quit".to_string());
+
+        let mut stdout = stdout();
+        for (y, line) in lines.iter().enumerate() {
+            queue!(
+                stdout,
+                MoveTo(0, y as u16),
+                Clear(ClearType::CurrentLine),
+                Print(line)
+            )
+            .unwrap();
+        }
+        stdout.flush().unwrap();
+    }
+
+    fn poll(&self) -> Option<Command> {
+        while event::poll(Duration::ZERO).unwrap_or(false) {
+            let Ok(event::Event::Key(event)) = event::read() else {
+                continue;
+            };
+            if event.kind != KeyEventKind::Press {
+                continue;
+            }
+            match event.code {
+                KeyCode::Left => return Some(Command::Previous),
+                KeyCode::Right => return Some(Command::Next),
+                KeyCode::Char('c') if event.modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Some(Command::Quit)
+                }
+                KeyCode::Esc | KeyCode::Char('q') => return Some(Command::Quit),
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+impl Drop for PlayerView {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+        let _ = execute!(stdout(), Show, LeaveAlternateScreen);
+    }
+}
+
+// NOTE: 端末からは音が出せないので、聴くときは --dump-audio で書き出す
+pub fn play<S: Speaker>(player: &mut NsfPlayer<S>, song: u8, mut audio_dump: Option<Recorder>) {
+    let view = PlayerView::new();
+    let period = Duration::from_secs_f32(player.play_period());
+
+    player.start(song);
+    let mut started = Instant::now();
+    let mut next_play = Instant::now();
+    loop {
+        match view.poll() {
+            Some(Command::Quit) => break,
+            Some(Command::Previous) => {
+                player.start(player.song().saturating_sub(1));
+                started = Instant::now();
+            }
+            Some(Command::Next) => {
+                player.start(player.song() + 1);
+                started = Instant::now();
+            }
+            None => {}
+        }
+
+        player.play();
+        if let (Some(recorder), Some(audio)) = (audio_dump.as_mut(), player.take_audio()) {
+            recorder.push_audio(&audio);
+        }
+        view.draw(player, started.elapsed());
+
+        let now = Instant::now();
+        if next_play > now {
+            thread::sleep(next_play - now);
+        }
+        next_play = next_play.max(now) + period;
+    }
+    drop(view);
+
+    if let Some(Err(e)) = audio_dump.map(|recorder| recorder.finish()) {
+        eprintln!("[audio] {}", e);
+    }
+}
@@ -1,4 +1,12 @@
-use std::io::{stdout, Write};
+use std::{
+    io::{stdout, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
 use crossterm::{
     cursor::{Hide, MoveTo, Show},
@@ -6,41 +14,97 @@ use crossterm::{
     style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor},
     terminal::{Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use lib::render::{utils::frame::Frame, Renderer};
+use lib::render::{
+    filter::Filter,
+    utils::{frame::Frame, png::encode_png},
+    Renderer,
+};
 
 pub struct CliRenderer {
     width: usize,
     height: usize,
+    filter: Filter,
+    buffer: Vec<u8>,
+    screenshot: Arc<AtomicBool>,
+    frame_duration: Duration,
+    next_frame: Instant,
 }
 
 impl CliRenderer {
-    const WIDTH: usize = 256;
-    const HEIGHT: usize = 240;
-
-    pub fn new(width: usize, height: usize) -> Self {
+    pub fn new(width: usize, height: usize, filter: Filter, screenshot: Arc<AtomicBool>) -> Self {
         execute!(stdout(), Hide, EnterAlternateScreen, Clear(ClearType::All)).unwrap();
 
-        Self { width, height }
+        Self {
+            width,
+            height,
+            filter,
+            buffer: vec![],
+            screenshot,
+            frame_duration: Duration::ZERO,
+            next_frame: Instant::now(),
+        }
+    }
+
+    pub fn set_frame_rate(&mut self, frame_rate: f32) {
+        self.frame_duration = Duration::from_secs_f32(1.0 / frame_rate);
+    }
+
+    fn wait_next_frame(&mut self) {
+        let now = Instant::now();
+        if self.next_frame > now {
+            thread::sleep(self.next_frame - now);
+        }
+        self.next_frame = self.next_frame.max(now) + self.frame_duration;
+    }
+
+    // NOTE: 端末に縮小する前の、フィルタを通した解像度で保存する。結果は画面の下の行に出す
+    fn save_screenshot(&self) {
+        let (width, height) = self.filter.output_size();
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let path = format!("screenshot-{}.png", millis);
+        let message = match std::fs::write(&path, encode_png(&self.buffer, width, height)) {
+            Ok(()) => format!("Saved {}", path),
+            Err(e) => format!("Failed to save {}: {}", path, e),
+        };
+
+        queue!(
+            stdout(),
+            MoveTo(0, self.height as u16),
+            ResetColor,
+            Clear(ClearType::CurrentLine),
+            Print(message)
+        )
+        .unwrap();
     }
 }
 
 impl Renderer for CliRenderer {
     fn render(&mut self, frame: &Frame) {
+        self.wait_next_frame();
         let mut stdout = stdout();
 
+        let (src_width, src_height) = self.filter.output_size();
+        self.filter.apply(frame, &mut self.buffer);
+        if self.screenshot.swap(false, Ordering::SeqCst) {
+            self.save_screenshot();
+        }
+
         for y in 0..self.height {
-            let src_y = (y as f32 / self.height as f32) * Self::HEIGHT as f32;
+            let src_y = (y as f32 / self.height as f32) * src_height as f32;
             let src_y = src_y.round() as usize;
 
             queue!(stdout, MoveTo(0, y as u16)).unwrap();
             for x in 0..self.width {
-                let src_x = (x as f32 / self.width as f32) * Self::WIDTH as f32;
+                let src_x = (x as f32 / self.width as f32) * src_width as f32;
                 let src_x = src_x.round() as usize;
 
-                let i = (src_y * Self::WIDTH + src_x) * 3;
-                let r = frame.data[i];
-                let g = frame.data[i + 1];
-                let b = frame.data[i + 2];
+                let i = (src_y * src_width + src_x) * 3;
+                let r = self.buffer[i];
+                let g = self.buffer[i + 1];
+                let b = self.buffer[i + 2];
 
                 queue!(
                     stdout,
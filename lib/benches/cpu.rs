@@ -0,0 +1,71 @@
+use std::time::{Duration, Instant};
+
+use lib::{
+    emulator::Emulator,
+    testrom::{NullJoypadHandler, NullRenderer, NullSpeaker},
+};
+
+const INSTRUCTIONS: usize = 5_000_000;
+const ROUNDS: usize = 5;
+
+// NOTE: よく使われる命令を一通り含むループを実行するだけの NROM
+fn bench_rom() -> Vec<u8> {
+    #[rustfmt::skip]
+    let program = [
+        0xA2, 0x00,       // LDX #$00
+        0xA0, 0x00,       // LDY #$00
+        0xB5, 0x00,       // LDA $00,X
+        0x69, 0x03,       // ADC #$03
+        0x95, 0x00,       // STA $00,X
+        0x9D, 0x00, 0x02, // STA $0200,X
+        0x1D, 0x00, 0x02, // ORA $0200,X
+        0x49, 0x5A,       // EOR #$5A
+        0x0A,             // ASL A
+        0x26, 0x10,       // ROL $10
+        0xE8,             // INX
+        0xC8,             // INY
+        0xC0, 0x40,       // CPY #$40
+        0xD0, 0xE9,       // BNE $8004
+        0x48,             // PHA
+        0x68,             // PLA
+        0x20, 0x23, 0x80, // JSR $8023
+        0x4C, 0x04, 0x80, // JMP $8004
+        0x18,             // CLC
+        0x60,             // RTS
+    ];
+
+    let mut prg_rom = vec![0xEA; 0x4000];
+    prg_rom[..program.len()].copy_from_slice(&program);
+    // NOTE: RESET ベクタ ($FFFC) を $8000 に向ける
+    prg_rom[0x3FFC] = 0x00;
+    prg_rom[0x3FFD] = 0x80;
+
+    let mut raw = vec![0x4E, 0x45, 0x53, 0x1A, 0x01, 0x01, 0x00, 0x00];
+    raw.resize(16, 0);
+    raw.extend(prg_rom);
+    raw.extend(vec![0; 0x2000]);
+    raw
+}
+
+fn run(emulator: &mut Emulator<NullSpeaker, NullJoypadHandler, NullRenderer>) -> Duration {
+    let start = Instant::now();
+    for _ in 0..INSTRUCTIONS {
+        emulator.step();
+    }
+    start.elapsed()
+}
+
+fn main() {
+    let mut emulator = Emulator::new(bench_rom(), NullSpeaker, NullJoypadHandler, NullRenderer);
+    emulator.reset();
+    run(&mut emulator);
+
+    let best = (0..ROUNDS).map(|_| run(&mut emulator)).min().unwrap();
+    let per_sec = INSTRUCTIONS as f64 / best.as_secs_f64();
+    println!(
+        "cpu/step: {} instructions in {:?} ({:.2} M instructions/sec)",
+        INSTRUCTIONS,
+        best,
+        per_sec / 1_000_000.0
+    );
+}
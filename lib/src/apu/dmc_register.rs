@@ -0,0 +1,251 @@
+use bitflags::bitflags;
+
+use crate::region::Region;
+
+bitflags! {
+    struct Control: u8 {
+        const IRQ_ENABLE = 0b1000_0000;
+        const LOOP = 0b0100_0000;
+        const RATE = 0b0000_1111;
+    }
+
+    struct DirectLoad: u8 {
+        const OUTPUT_LEVEL = 0b0111_1111;
+    }
+}
+
+// NOTE: 出力ユニットが 1 ビット進むまでの CPU サイクル数
+const RATE_MAP: [u16; 16] = [
+    428, 380, 340, 320, 286, 254, 226, 214, 190, 160, 142, 128, 106, 84, 72, 54,
+];
+
+const PAL_RATE_MAP: [u16; 16] = [
+    398, 354, 316, 298, 276, 236, 210, 198, 176, 148, 132, 118, 98, 78, 66, 50,
+];
+
+// NOTE: サンプルを読むときの CPU の停止 (DMA) はバスが受け持つ
+pub struct DmcRegister {
+    control: Control,
+    direct_load: DirectLoad,
+    sample_address: u8,
+    sample_length: u8,
+    current_address: u16,
+    bytes_remaining: u16,
+    sample_buffer: Option<u8>,
+    shift_register: u8,
+    bits_remaining: u8,
+    silence: bool,
+    timer: u16,
+    irq: bool,
+}
+
+impl DmcRegister {
+    pub fn new() -> Self {
+        Self {
+            control: Control::empty(),
+            direct_load: DirectLoad::empty(),
+            sample_address: 0,
+            sample_length: 0,
+            current_address: 0xC000,
+            bytes_remaining: 0,
+            sample_buffer: None,
+            shift_register: 0,
+            bits_remaining: 8,
+            silence: true,
+            timer: 0,
+            irq: false,
+        }
+    }
+
+    pub fn write(&mut self, addr: u16, data: u8) {
+        match addr {
+            0 => {
+                self.control = Control::from_bits_truncate(data);
+                if !self.control.contains(Control::IRQ_ENABLE) {
+                    self.irq = false;
+                }
+            }
+            1 => self.direct_load = DirectLoad::from_bits_truncate(data),
+            2 => self.sample_address = data,
+            3 => self.sample_length = data,
+            _ => panic!("Invalid dmc register address: {}", addr),
+        }
+    }
+
+    // NOTE: $4015 のビット 4。止めると残りのバイトを捨て、止まっているときに有効にすると最初から再生する
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.irq = false;
+        if !enabled {
+            self.bytes_remaining = 0;
+        } else if self.bytes_remaining == 0 {
+            self.restart();
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.bytes_remaining > 0
+    }
+
+    pub fn irq(&self) -> bool {
+        self.irq
+    }
+
+    pub fn get_output_level(&self) -> u8 {
+        self.direct_load.bits()
+    }
+
+    // NOTE: サンプルバッファが空で残りがあれば、次に読むアドレスを返す。読んだ値は fill で渡す
+    pub fn fetch_address(&self) -> Option<u16> {
+        if self.sample_buffer.is_none() && self.bytes_remaining > 0 {
+            Some(self.current_address)
+        } else {
+            None
+        }
+    }
+
+    pub fn fill(&mut self, data: u8) {
+        self.sample_buffer = Some(data);
+        // NOTE: $FFFF の次は $8000 に戻る
+        self.current_address = self.current_address.checked_add(1).unwrap_or(0x8000);
+        self.bytes_remaining -= 1;
+        if self.bytes_remaining == 0 {
+            if self.control.contains(Control::LOOP) {
+                self.restart();
+            } else if self.control.contains(Control::IRQ_ENABLE) {
+                self.irq = true;
+            }
+        }
+    }
+
+    pub fn tick(&mut self, cycles: u8, region: Region) {
+        let period = self.get_period(region);
+        for _ in 0..cycles {
+            if self.timer == 0 {
+                self.timer = period;
+                self.clock();
+            }
+            self.timer -= 1;
+        }
+    }
+
+    fn get_period(&self, region: Region) -> u16 {
+        let idx = (self.control.bits() & Control::RATE.bits()) as usize;
+        match region {
+            Region::Ntsc | Region::Dendy => RATE_MAP[idx],
+            Region::Pal => PAL_RATE_MAP[idx],
+        }
+    }
+
+    fn restart(&mut self) {
+        self.current_address = 0xC000 | (self.sample_address as u16) << 6;
+        self.bytes_remaining = (self.sample_length as u16) << 4 | 1;
+    }
+
+    fn clock(&mut self) {
+        if !self.silence {
+            let level = self.direct_load.bits();
+            let level = if self.shift_register & 0x01 != 0 {
+                if level <= 125 {
+                    level + 2
+                } else {
+                    level
+                }
+            } else if level >= 2 {
+                level - 2
+            } else {
+                level
+            };
+            self.direct_load = DirectLoad::from_bits_truncate(level);
+        }
+        self.shift_register >>= 1;
+
+        self.bits_remaining -= 1;
+        if self.bits_remaining == 0 {
+            self.bits_remaining = 8;
+            match self.sample_buffer.take() {
+                Some(data) => {
+                    self.silence = false;
+                    self.shift_register = data;
+                }
+                None => self.silence = true,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::region::Region;
+
+    use super::DmcRegister;
+
+    fn play(dmc: &mut DmcRegister, sample: &[u8], cycles: usize) -> Vec<u16> {
+        let mut addresses = vec![];
+        for _ in 0..cycles {
+            if let Some(addr) = dmc.fetch_address() {
+                addresses.push(addr);
+                dmc.fill(sample[addresses.len() - 1]);
+            }
+            dmc.tick(1, Region::Ntsc);
+        }
+        addresses
+    }
+
+    #[test]
+    fn test_sample_playback() {
+        let mut dmc = DmcRegister::new();
+        // NOTE: 最速のレートで $C040 から 17 バイト
+        dmc.write(0, 0x0F);
+        dmc.write(1, 0x40);
+        dmc.write(2, 0x01);
+        dmc.write(3, 0x01);
+        dmc.set_enabled(true);
+        assert!(dmc.is_active());
+
+        let sample = [0xFF; 17];
+        let addresses = play(&mut dmc, &sample, 54 * 8 * 4);
+        assert_eq!(addresses[..3], [0xC040, 0xC041, 0xC042]);
+        // NOTE: 最初の 8 ビットは無音。そのあと 1 ビットごとに 2 ずつ上がる
+        assert_eq!(dmc.get_output_level(), 0x40 + 2 * 8 * 3);
+
+        play(&mut dmc, &sample, 54 * 8 * 20);
+        assert!(!dmc.is_active());
+        assert!(!dmc.irq());
+        // NOTE: 126 からは上がらない
+        assert_eq!(dmc.get_output_level(), 126);
+    }
+
+    #[test]
+    fn test_irq_and_loop() {
+        let mut dmc = DmcRegister::new();
+        dmc.write(0, 0x80);
+        dmc.set_enabled(true);
+        dmc.fill(0x00);
+        assert!(!dmc.is_active());
+        assert!(dmc.irq());
+
+        // NOTE: $4015 への書き込みで IRQ は消える
+        dmc.set_enabled(false);
+        assert!(!dmc.irq());
+
+        dmc.write(0, 0x40);
+        dmc.set_enabled(true);
+        dmc.fill(0x00);
+        assert!(dmc.is_active());
+        assert_eq!(dmc.fetch_address(), None);
+    }
+
+    #[test]
+    fn test_address_wraps() {
+        let mut dmc = DmcRegister::new();
+        dmc.write(0, 0x0F);
+        dmc.write(2, 0xFF);
+        dmc.write(3, 0x04);
+        dmc.set_enabled(true);
+        assert_eq!(dmc.fetch_address(), Some(0xFFC0));
+
+        let sample = [0x00; 0x41];
+        let addresses = play(&mut dmc, &sample, 54 * 8 * 0x42);
+        assert_eq!(addresses[0x3F..=0x40], [0xFFFF, 0x8000]);
+    }
+}
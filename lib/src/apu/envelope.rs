@@ -0,0 +1,69 @@
+// NOTE: 矩形波とノイズの音量。1/4 フレームごとに 15 から 0 へ減衰し、ループが有効なら 15 に戻る
+#[derive(Default)]
+pub struct Envelope {
+    start: bool,
+    divider: u8,
+    decay: u8,
+}
+
+impl Envelope {
+    // NOTE: $4003 / $400F への書き込みで、次の 1/4 フレームから 15 で鳴り直す
+    pub fn restart(&mut self) {
+        self.start = true;
+    }
+
+    pub fn clock(&mut self, period: u8, looping: bool) {
+        if self.start {
+            self.start = false;
+            self.decay = 15;
+            self.divider = period;
+            return;
+        }
+
+        if self.divider > 0 {
+            self.divider -= 1;
+            return;
+        }
+        self.divider = period;
+        if self.decay > 0 {
+            self.decay -= 1;
+        } else if looping {
+            self.decay = 15;
+        }
+    }
+
+    pub fn volume(&self, constant: bool, volume: u8) -> u8 {
+        if constant {
+            volume
+        } else {
+            self.decay
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Envelope;
+
+    #[test]
+    fn test_decay() {
+        let mut envelope = Envelope::default();
+        envelope.restart();
+        envelope.clock(1, false);
+        assert_eq!(envelope.volume(false, 1), 15);
+        assert_eq!(envelope.volume(true, 1), 1);
+
+        // NOTE: 分周器の周期は period + 1
+        for _ in 0..2 * 15 {
+            envelope.clock(1, false);
+        }
+        assert_eq!(envelope.volume(false, 1), 0);
+        envelope.clock(1, false);
+        envelope.clock(1, false);
+        assert_eq!(envelope.volume(false, 1), 0);
+
+        envelope.clock(1, true);
+        envelope.clock(1, true);
+        assert_eq!(envelope.volume(false, 1), 15);
+    }
+}
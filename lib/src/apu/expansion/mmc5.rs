@@ -0,0 +1,266 @@
+use super::ExpansionAudio;
+
+const PULSE1: u16 = 0x5000;
+const PULSE1_END: u16 = 0x5003;
+const PULSE2: u16 = 0x5004;
+const PULSE2_END: u16 = 0x5007;
+const PCM_CONTROL: u16 = 0x5010;
+const PCM_DATA: u16 = 0x5011;
+const STATUS: u16 = 0x5015;
+
+// NOTE: 2A03 のフレームカウンターと違い、エンベロープも長さカウンターも 240Hz 固定で進む
+const QUARTER_FRAME_CYCLES: u16 = 7457;
+
+const LENGTH_TABLE: [u8; 32] = [
+    10, 254, 20, 2, 40, 4, 80, 6, 160, 8, 60, 10, 14, 12, 26, 14, 12, 16, 24, 18, 48, 20, 96, 22,
+    192, 24, 72, 26, 16, 28, 32, 30,
+];
+const DUTY_TABLE: [[u8; 8]; 4] = [
+    [0, 1, 0, 0, 0, 0, 0, 0],
+    [0, 1, 1, 0, 0, 0, 0, 0],
+    [0, 1, 1, 1, 1, 0, 0, 0],
+    [1, 0, 0, 1, 1, 1, 1, 1],
+];
+
+#[derive(Default)]
+struct Pulse {
+    enabled: bool,
+    duty: u8,
+    halt: bool,
+    constant_volume: bool,
+    volume: u8,
+    period: u16,
+    timer: u16,
+    step: u8,
+    length: u8,
+    envelope_start: bool,
+    envelope_divider: u8,
+    envelope_decay: u8,
+}
+
+impl Pulse {
+    fn write(&mut self, reg: u16, data: u8) {
+        match reg {
+            0 => {
+                self.duty = data >> 6;
+                self.halt = data & 0x20 != 0;
+                self.constant_volume = data & 0x10 != 0;
+                self.volume = data & 0x0F;
+            }
+            // NOTE: スイープはない
+            1 => {}
+            2 => self.period = (self.period & 0x0700) | data as u16,
+            _ => {
+                self.period = (self.period & 0x00FF) | ((data as u16 & 0x07) << 8);
+                if self.enabled {
+                    self.length = LENGTH_TABLE[(data >> 3) as usize];
+                }
+                self.step = 0;
+                self.envelope_start = true;
+            }
+        }
+    }
+
+    fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.length = 0;
+        }
+    }
+
+    // NOTE: APU サイクル (CPU 2 サイクル) ごと
+    fn tick_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.period;
+            self.step = (self.step + 1) & 0x07;
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    fn tick_quarter_frame(&mut self) {
+        if self.envelope_start {
+            self.envelope_start = false;
+            self.envelope_decay = 15;
+            self.envelope_divider = self.volume;
+        } else if self.envelope_divider == 0 {
+            self.envelope_divider = self.volume;
+            if self.envelope_decay > 0 {
+                self.envelope_decay -= 1;
+            } else if self.halt {
+                self.envelope_decay = 15;
+            }
+        } else {
+            self.envelope_divider -= 1;
+        }
+
+        if !self.halt && self.length > 0 {
+            self.length -= 1;
+        }
+    }
+
+    // NOTE: スイープがないので周期が短くても消えない
+    fn output(&self) -> u8 {
+        if self.length == 0 || DUTY_TABLE[self.duty as usize][self.step as usize] == 0 {
+            return 0;
+        }
+        if self.constant_volume {
+            self.volume
+        } else {
+            self.envelope_decay
+        }
+    }
+}
+
+// NOTE: MMC5 の矩形波 2 つ (2A03 と同じものからスイープを除いたもの) と 8bit PCM
+#[derive(Default)]
+pub(crate) struct Mmc5Audio {
+    pulse1: Pulse,
+    pulse2: Pulse,
+    pcm_read_mode: bool,
+    pcm_irq_enabled: bool,
+    pcm_irq: bool,
+    pcm: u8,
+    cycles: u16,
+    odd_cycle: bool,
+}
+
+impl Mmc5Audio {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn irq(&self) -> bool {
+        self.pcm_irq && self.pcm_irq_enabled
+    }
+
+    // NOTE: 0 は IRQ を起こして無視される
+    fn write_pcm(&mut self, data: u8) {
+        if data == 0 {
+            self.pcm_irq = true;
+        } else {
+            self.pcm = data;
+        }
+    }
+}
+
+impl ExpansionAudio for Mmc5Audio {
+    fn read(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            PCM_CONTROL => {
+                let irq = self.irq();
+                self.pcm_irq = false;
+                Some(if irq { 0x80 } else { 0x00 } | self.pcm_read_mode as u8)
+            }
+            STATUS => {
+                let mut status = 0;
+                if self.pulse1.length > 0 {
+                    status |= 0x01;
+                }
+                if self.pulse2.length > 0 {
+                    status |= 0x02;
+                }
+                Some(status)
+            }
+            _ => None,
+        }
+    }
+
+    fn write(&mut self, addr: u16, data: u8) {
+        match addr {
+            PULSE1..=PULSE1_END => self.pulse1.write(addr - PULSE1, data),
+            PULSE2..=PULSE2_END => self.pulse2.write(addr - PULSE2, data),
+            PCM_CONTROL => {
+                self.pcm_read_mode = data & 0x01 != 0;
+                self.pcm_irq_enabled = data & 0x80 != 0;
+            }
+            // NOTE: 読み込みモードでは $8000-$BFFF から読んだ値が入るので、書き込みは無視する
+            PCM_DATA if !self.pcm_read_mode => self.write_pcm(data),
+            STATUS => {
+                self.pulse1.set_enabled(data & 0x01 != 0);
+                self.pulse2.set_enabled(data & 0x02 != 0);
+            }
+            _ => {}
+        }
+    }
+
+    fn tick(&mut self) {
+        self.odd_cycle = !self.odd_cycle;
+        if self.odd_cycle {
+            self.pulse1.tick_timer();
+            self.pulse2.tick_timer();
+        }
+
+        self.cycles += 1;
+        if self.cycles >= QUARTER_FRAME_CYCLES {
+            self.cycles = 0;
+            self.pulse1.tick_quarter_frame();
+            self.pulse2.tick_quarter_frame();
+        }
+    }
+
+    // NOTE: 矩形波は 2A03 と同じ大きさ。PCM は 255 で矩形波 2 本分くらい
+    fn output(&self) -> f32 {
+        let pulses = (self.pulse1.output() + self.pulse2.output()) as f32 * 2.0 / 15.0;
+        pulses + self.pcm as f32 * 4.0 / 255.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ExpansionAudio, Mmc5Audio, QUARTER_FRAME_CYCLES};
+
+    #[test]
+    fn test_pulse_length() {
+        let mut audio = Mmc5Audio::new();
+        audio.write(0x5015, 0x01);
+        audio.write(0x5000, 0x9F);
+        audio.write(0x5002, 0x00);
+        // NOTE: 長さ 2
+        audio.write(0x5003, 0x18);
+        assert_eq!(audio.read(0x5015), Some(0x01));
+
+        let mut high = 0;
+        for _ in 0..16 {
+            audio.tick();
+            if audio.output() > 0.0 {
+                high += 1;
+            }
+        }
+        // NOTE: duty 50% を周期 0 (APU の 1 サイクルごと) で鳴らす
+        assert_eq!(high, 8);
+
+        for _ in 0..QUARTER_FRAME_CYCLES as usize * 2 {
+            audio.tick();
+        }
+        assert_eq!(audio.read(0x5015), Some(0x00));
+        assert_eq!(audio.output(), 0.0);
+    }
+
+    #[test]
+    fn test_disabled_channel_ignores_length() {
+        let mut audio = Mmc5Audio::new();
+        audio.write(0x5007, 0x18);
+        assert_eq!(audio.read(0x5015), Some(0x00));
+    }
+
+    #[test]
+    fn test_pcm() {
+        let mut audio = Mmc5Audio::new();
+        audio.write(0x5011, 0xFF);
+        assert_eq!(audio.output(), 4.0);
+
+        // NOTE: 0 を書くと値はそのままで IRQ が立つ
+        audio.write(0x5010, 0x80);
+        audio.write(0x5011, 0x00);
+        assert_eq!(audio.output(), 4.0);
+        assert!(audio.irq());
+        assert_eq!(audio.read(0x5010), Some(0x80));
+        assert!(!audio.irq());
+
+        // NOTE: 読み込みモードでは $5011 への書き込みを無視する
+        audio.write(0x5010, 0x01);
+        audio.write(0x5011, 0x40);
+        assert_eq!(audio.output(), 4.0);
+    }
+}
@@ -0,0 +1,26 @@
+pub(crate) use mmc5::Mmc5Audio;
+pub(crate) use n163::N163Audio;
+pub(crate) use sunsoft5b::Sunsoft5bAudio;
+pub(crate) use vrc6::Vrc6Audio;
+pub(crate) use vrc7::Vrc7Audio;
+
+mod mmc5;
+mod n163;
+mod sunsoft5b;
+mod vrc6;
+mod vrc7;
+
+// NOTE: カートリッジ側の音源チップ。アドレスは NSF と同じ各チップの標準の番地で受け取るので、
+//       配線が違うマッパーは渡す前に並べ替える。出力は 2A03 の矩形波 1 本 (最大で振幅 1.0) を基準にした大きさ
+pub(crate) trait ExpansionAudio {
+    fn read(&mut self, _addr: u16) -> Option<u8> {
+        None
+    }
+
+    fn write(&mut self, addr: u16, data: u8);
+
+    // NOTE: CPU の 1 サイクルごとに呼ぶ
+    fn tick(&mut self);
+
+    fn output(&self) -> f32;
+}
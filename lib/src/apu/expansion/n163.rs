@@ -0,0 +1,190 @@
+use super::ExpansionAudio;
+
+const DATA_PORT: u16 = 0x4800;
+const DATA_PORT_END: u16 = 0x4FFF;
+const ADDRESS_PORT: u16 = 0xF800;
+const ADDRESS_PORT_END: u16 = 0xFFFF;
+
+// NOTE: 1 チャンネルの計算に 15 CPU サイクルかかる
+const CYCLES_PER_CHANNEL: u8 = 15;
+// NOTE: チャンネルのレジスタは内部 RAM の末尾 8 バイトずつ。チャンネル 8 が $78-$7F
+const CHANNEL_REGISTERS: usize = 0x40;
+const CHANNEL_COUNT_REGISTER: usize = 0x7F;
+const CHANNELS: usize = 8;
+
+// NOTE: ナムコ 163。128 バイトの内部 RAM に置いた 4bit の波形を最大 8 チャンネルで鳴らす。
+//       実機は 1 本の DAC でチャンネルを順番に切り替えて出力するので、使うチャンネルが増えるほど
+//       それぞれの更新が遅くなる。切り替えの速さの音は出さず、各チャンネルの最新の値の平均を出力する
+pub(crate) struct N163Audio {
+    ram: [u8; 0x80],
+    address: u8,
+    auto_increment: bool,
+    current: usize,
+    cycles: u8,
+    outputs: [i16; CHANNELS],
+}
+
+impl N163Audio {
+    pub(crate) fn new() -> Self {
+        Self {
+            ram: [0; 0x80],
+            address: 0,
+            auto_increment: false,
+            current: 0,
+            cycles: 0,
+            outputs: [0; CHANNELS],
+        }
+    }
+
+    // NOTE: 鳴らすのはチャンネル 8 から数えて (1-8) 個
+    fn channel_count(&self) -> usize {
+        (((self.ram[CHANNEL_COUNT_REGISTER] >> 4) & 0x07) + 1) as usize
+    }
+
+    fn next_address(&mut self) {
+        if self.auto_increment {
+            self.address = (self.address + 1) & 0x7F;
+        }
+    }
+
+    fn update_channel(&mut self, channel: usize) {
+        let base = CHANNEL_REGISTERS + channel * 8;
+        let reg = |i: usize| self.ram[base + i] as u32;
+
+        let frequency = reg(0) | (reg(2) << 8) | ((reg(4) & 0x03) << 16);
+        let length = (256 - (reg(4) & 0xFC)) << 16;
+        let mut phase = reg(1) | (reg(3) << 8) | (reg(5) << 16);
+        let offset = reg(6);
+        let volume = (reg(7) & 0x0F) as i16;
+
+        phase = (phase + frequency) % length;
+        self.ram[base + 1] = phase as u8;
+        self.ram[base + 3] = (phase >> 8) as u8;
+        self.ram[base + 5] = (phase >> 16) as u8;
+
+        let index = ((phase >> 16) + offset) & 0xFF;
+        let sample = (self.ram[(index >> 1) as usize] >> ((index & 0x01) * 4)) & 0x0F;
+        self.outputs[channel] = (sample as i16 - 8) * volume;
+    }
+}
+
+impl ExpansionAudio for N163Audio {
+    fn read(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            DATA_PORT..=DATA_PORT_END => {
+                let data = self.ram[self.address as usize];
+                self.next_address();
+                Some(data)
+            }
+            _ => None,
+        }
+    }
+
+    fn write(&mut self, addr: u16, data: u8) {
+        match addr {
+            DATA_PORT..=DATA_PORT_END => {
+                self.ram[self.address as usize] = data;
+                self.next_address();
+            }
+            ADDRESS_PORT..=ADDRESS_PORT_END => {
+                self.address = data & 0x7F;
+                self.auto_increment = data & 0x80 != 0;
+            }
+            _ => {}
+        }
+    }
+
+    fn tick(&mut self) {
+        self.cycles += 1;
+        if self.cycles < CYCLES_PER_CHANNEL {
+            return;
+        }
+        self.cycles = 0;
+
+        let first = CHANNELS - self.channel_count();
+        if self.current < first {
+            self.current = first;
+        }
+        self.update_channel(self.current);
+        self.current += 1;
+        if self.current == CHANNELS {
+            self.current = first;
+        }
+    }
+
+    // NOTE: 音量 15 の 1 チャンネルが 2A03 の矩形波より少し大きいくらい
+    fn output(&self) -> f32 {
+        let count = self.channel_count();
+        let sum = self.outputs[CHANNELS - count..]
+            .iter()
+            .map(|&output| output as f32)
+            .sum::<f32>();
+        sum / count as f32 / 64.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ExpansionAudio, N163Audio, CYCLES_PER_CHANNEL};
+
+    fn write_ram(audio: &mut N163Audio, addr: u8, data: &[u8]) {
+        audio.write(0xF800, 0x80 | addr);
+        for &value in data {
+            audio.write(0x4800, value);
+        }
+    }
+
+    #[test]
+    fn test_ram_port() {
+        let mut audio = N163Audio::new();
+        write_ram(&mut audio, 0x10, &[0x12, 0x34]);
+
+        audio.write(0xF800, 0x90);
+        assert_eq!(audio.read(0x4800), Some(0x12));
+        assert_eq!(audio.read(0x4800), Some(0x34));
+        // NOTE: 自動インクリメントなし
+        audio.write(0xF800, 0x10);
+        assert_eq!(audio.read(0x4800), Some(0x12));
+        assert_eq!(audio.read(0x4800), Some(0x12));
+    }
+
+    #[test]
+    fn test_wave() {
+        let mut audio = N163Audio::new();
+        // NOTE: 4 サンプルの波形 F, 0, F, 0 をアドレス 0 に置く
+        write_ram(&mut audio, 0x00, &[0x0F, 0x0F]);
+        // NOTE: チャンネル 8 だけ、1 回の更新で 1 サンプル進む、長さ 4、音量 15
+        write_ram(
+            &mut audio,
+            0x78,
+            &[0x00, 0x00, 0x00, 0x00, 0x01 | 0xFC, 0x00, 0x00, 0x0F],
+        );
+
+        let mut samples = vec![];
+        for _ in 0..4 {
+            for _ in 0..CYCLES_PER_CHANNEL {
+                audio.tick();
+            }
+            samples.push(audio.output());
+        }
+        let high = 7.0 * 15.0 / 64.0;
+        let low = -8.0 * 15.0 / 64.0;
+        assert_eq!(samples, vec![low, high, low, high]);
+    }
+
+    #[test]
+    fn test_multiplex() {
+        let mut audio = N163Audio::new();
+        // NOTE: 2 チャンネル (7 と 8) 鳴らすと 30 サイクルで 1 周する
+        write_ram(&mut audio, 0x7F, &[0x1F]);
+
+        let mut updated = vec![];
+        for _ in 0..4 {
+            for _ in 0..CYCLES_PER_CHANNEL {
+                audio.tick();
+            }
+            updated.push(audio.current);
+        }
+        assert_eq!(updated, vec![7, 6, 7, 6]);
+    }
+}
@@ -0,0 +1,305 @@
+use super::ExpansionAudio;
+
+const ADDRESS: u16 = 0xC000;
+const ADDRESS_END: u16 = 0xDFFF;
+const DATA: u16 = 0xE000;
+const DATA_END: u16 = 0xFFFF;
+
+// NOTE: トーン・ノイズ・エンベロープは CPU の 16 サイクルごとに進む
+const PRESCALER: u8 = 16;
+const CHANNELS: usize = 3;
+
+#[derive(Default, Clone, Copy)]
+struct Tone {
+    period: u16,
+    counter: u16,
+    high: bool,
+    volume: u8,
+    use_envelope: bool,
+}
+
+impl Tone {
+    // NOTE: 周期ごとに反転するので、周波数は CPU / (32 * period)
+    fn tick(&mut self) {
+        self.counter += 1;
+        if self.counter >= self.period {
+            self.counter = 0;
+            self.high = !self.high;
+        }
+    }
+}
+
+struct Envelope {
+    period: u16,
+    counter: u16,
+    step: u8,
+    attack: bool,
+    alternate: bool,
+    hold: bool,
+    continue_: bool,
+    holding: bool,
+}
+
+impl Envelope {
+    fn new() -> Self {
+        Self {
+            period: 0,
+            counter: 0,
+            step: 0,
+            attack: false,
+            alternate: false,
+            hold: false,
+            continue_: false,
+            holding: true,
+        }
+    }
+
+    fn write_shape(&mut self, data: u8) {
+        self.continue_ = data & 0x08 != 0;
+        self.attack = data & 0x04 != 0;
+        self.alternate = data & 0x02 != 0;
+        self.hold = data & 0x01 != 0;
+        self.step = 0;
+        self.counter = 0;
+        self.holding = false;
+    }
+
+    fn tick(&mut self) {
+        if self.holding {
+            return;
+        }
+        self.counter += 1;
+        if self.counter < self.period.max(1) {
+            return;
+        }
+        self.counter = 0;
+
+        self.step += 1;
+        if self.step < 16 {
+            return;
+        }
+        self.step = 0;
+
+        // NOTE: 1 周したあとの動き。continue が 0 なら 0 で止まる
+        if !self.continue_ {
+            self.attack = false;
+            self.holding = true;
+        } else if self.hold {
+            if self.alternate {
+                self.attack = !self.attack;
+            }
+            self.holding = true;
+        } else if self.alternate {
+            self.attack = !self.attack;
+        }
+    }
+
+    fn level(&self) -> u8 {
+        if self.holding {
+            if self.attack {
+                15
+            } else {
+                0
+            }
+        } else if self.attack {
+            self.step
+        } else {
+            15 - self.step
+        }
+    }
+}
+
+// NOTE: Sunsoft 5B (FME-7 に AY-3-8910 互換の音源を足したもの)。矩形波 3 つとノイズ、エンベロープ
+pub(crate) struct Sunsoft5bAudio {
+    address: u8,
+    tones: [Tone; CHANNELS],
+    noise_period: u8,
+    noise_counter: u8,
+    noise_shift: u32,
+    tone_disabled: [bool; CHANNELS],
+    noise_disabled: [bool; CHANNELS],
+    envelope: Envelope,
+    cycles: u8,
+    half: bool,
+}
+
+impl Sunsoft5bAudio {
+    pub(crate) fn new() -> Self {
+        Self {
+            address: 0,
+            tones: [Tone::default(); CHANNELS],
+            noise_period: 0,
+            noise_counter: 0,
+            noise_shift: 1,
+            tone_disabled: [true; CHANNELS],
+            noise_disabled: [true; CHANNELS],
+            envelope: Envelope::new(),
+            cycles: 0,
+            half: false,
+        }
+    }
+
+    fn write_register(&mut self, data: u8) {
+        let reg = self.address;
+        match reg {
+            0x00..=0x05 => {
+                let tone = &mut self.tones[(reg >> 1) as usize];
+                tone.period = if reg & 0x01 == 0 {
+                    (tone.period & 0x0F00) | data as u16
+                } else {
+                    (tone.period & 0x00FF) | ((data as u16 & 0x0F) << 8)
+                };
+            }
+            0x06 => self.noise_period = data & 0x1F,
+            0x07 => {
+                for i in 0..CHANNELS {
+                    self.tone_disabled[i] = data & (0x01 << i) != 0;
+                    self.noise_disabled[i] = data & (0x08 << i) != 0;
+                }
+            }
+            0x08..=0x0A => {
+                let tone = &mut self.tones[(reg - 0x08) as usize];
+                tone.volume = data & 0x0F;
+                tone.use_envelope = data & 0x10 != 0;
+            }
+            0x0B => self.envelope.period = (self.envelope.period & 0xFF00) | data as u16,
+            0x0C => self.envelope.period = (self.envelope.period & 0x00FF) | ((data as u16) << 8),
+            0x0D => self.envelope.write_shape(data),
+            _ => {}
+        }
+    }
+
+    // NOTE: 17bit の LFSR
+    fn tick_noise(&mut self) {
+        self.noise_counter += 1;
+        if self.noise_counter < self.noise_period.max(1) {
+            return;
+        }
+        self.noise_counter = 0;
+        let feedback = (self.noise_shift ^ (self.noise_shift >> 3)) & 0x01;
+        self.noise_shift = (self.noise_shift >> 1) | (feedback << 16);
+    }
+}
+
+// NOTE: 音量は 1 段 3dB
+fn amplitude(level: u8) -> f32 {
+    if level == 0 {
+        0.0
+    } else {
+        10f32.powf(-((15 - level) as f32) * 3.0 / 20.0)
+    }
+}
+
+impl ExpansionAudio for Sunsoft5bAudio {
+    fn write(&mut self, addr: u16, data: u8) {
+        match addr {
+            ADDRESS..=ADDRESS_END => self.address = data & 0x0F,
+            DATA..=DATA_END => self.write_register(data),
+            _ => {}
+        }
+    }
+
+    fn tick(&mut self) {
+        self.cycles += 1;
+        if self.cycles < PRESCALER {
+            return;
+        }
+        self.cycles = 0;
+
+        for tone in &mut self.tones {
+            tone.tick();
+        }
+        // NOTE: ノイズとエンベロープはさらに半分の速さ
+        self.half = !self.half;
+        if self.half {
+            self.tick_noise();
+            self.envelope.tick();
+        }
+    }
+
+    fn output(&self) -> f32 {
+        let noise = self.noise_shift & 0x01 != 0;
+        let mut output = 0.0;
+        for (i, tone) in self.tones.iter().enumerate() {
+            let tone_on = tone.high || self.tone_disabled[i];
+            let noise_on = noise || self.noise_disabled[i];
+            if !tone_on || !noise_on {
+                continue;
+            }
+            let level = if tone.use_envelope {
+                self.envelope.level()
+            } else {
+                tone.volume
+            };
+            output += amplitude(level);
+        }
+        output * 2.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ExpansionAudio, Sunsoft5bAudio, PRESCALER};
+
+    fn write(audio: &mut Sunsoft5bAudio, reg: u8, data: u8) {
+        audio.write(0xC000, reg);
+        audio.write(0xE000, data);
+    }
+
+    fn tick(audio: &mut Sunsoft5bAudio, steps: usize) {
+        for _ in 0..steps * PRESCALER as usize {
+            audio.tick();
+        }
+    }
+
+    #[test]
+    fn test_tone() {
+        let mut audio = Sunsoft5bAudio::new();
+        write(&mut audio, 0x00, 0x04);
+        write(&mut audio, 0x07, 0x3E);
+        write(&mut audio, 0x08, 0x0F);
+
+        // NOTE: 4 ステップごとに反転する
+        let mut levels = vec![];
+        for _ in 0..4 {
+            tick(&mut audio, 4);
+            levels.push(audio.output() > 0.0);
+        }
+        assert_eq!(levels, vec![true, false, true, false]);
+    }
+
+    #[test]
+    fn test_volume() {
+        let mut audio = Sunsoft5bAudio::new();
+        // NOTE: トーンもノイズも無効にすると音量がそのまま出る
+        write(&mut audio, 0x07, 0x3F);
+        write(&mut audio, 0x08, 0x0F);
+        assert_eq!(audio.output(), 2.0);
+        write(&mut audio, 0x08, 0x0D);
+        assert!((audio.output() - 2.0 * 10f32.powf(-0.3)).abs() < 1e-6);
+        write(&mut audio, 0x08, 0x00);
+        assert_eq!(audio.output(), 0.0);
+    }
+
+    #[test]
+    fn test_envelope() {
+        let mut audio = Sunsoft5bAudio::new();
+        write(&mut audio, 0x07, 0x3F);
+        write(&mut audio, 0x08, 0x10);
+        write(&mut audio, 0x0B, 0x01);
+        // NOTE: 上がって 15 で止まる
+        write(&mut audio, 0x0D, 0x0D);
+        assert_eq!(audio.envelope.level(), 0);
+        tick(&mut audio, 2 * 8);
+        assert_eq!(audio.envelope.level(), 8);
+        tick(&mut audio, 2 * 100);
+        assert_eq!(audio.envelope.level(), 15);
+
+        // NOTE: 下がって 0 で止まる
+        write(&mut audio, 0x0D, 0x00);
+        assert_eq!(audio.envelope.level(), 15);
+        tick(&mut audio, 2 * 100);
+        assert_eq!(audio.envelope.level(), 0);
+        assert_eq!(audio.output(), 0.0);
+    }
+}
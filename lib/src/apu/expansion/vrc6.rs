@@ -0,0 +1,244 @@
+use super::ExpansionAudio;
+
+const PULSE1: u16 = 0x9000;
+const PULSE1_END: u16 = 0x9002;
+const FREQUENCY_CONTROL: u16 = 0x9003;
+const PULSE2: u16 = 0xA000;
+const PULSE2_END: u16 = 0xA002;
+const SAW: u16 = 0xB000;
+const SAW_END: u16 = 0xB002;
+
+#[derive(Default)]
+struct Timer {
+    period: u16,
+    counter: u16,
+    enabled: bool,
+}
+
+impl Timer {
+    fn write_low(&mut self, data: u8) {
+        self.period = (self.period & 0x0F00) | data as u16;
+    }
+
+    fn write_high(&mut self, data: u8) {
+        self.period = (self.period & 0x00FF) | ((data as u16 & 0x0F) << 8);
+        self.enabled = data & 0x80 != 0;
+    }
+
+    // NOTE: shift は $9003 の周波数の倍率 (4 なら 16 倍、8 なら 256 倍)
+    fn tick(&mut self, shift: u8) -> bool {
+        if self.counter == 0 {
+            self.counter = self.period >> shift;
+            true
+        } else {
+            self.counter -= 1;
+            false
+        }
+    }
+}
+
+#[derive(Default)]
+struct Pulse {
+    timer: Timer,
+    volume: u8,
+    duty: u8,
+    ignore_duty: bool,
+    step: u8,
+}
+
+impl Pulse {
+    fn write(&mut self, reg: u16, data: u8) {
+        match reg {
+            0 => {
+                self.volume = data & 0x0F;
+                self.duty = (data >> 4) & 0x07;
+                self.ignore_duty = data & 0x80 != 0;
+            }
+            1 => self.timer.write_low(data),
+            _ => {
+                self.timer.write_high(data);
+                if !self.timer.enabled {
+                    self.step = 0;
+                }
+            }
+        }
+    }
+
+    fn tick(&mut self, shift: u8) {
+        if self.timer.enabled && self.timer.tick(shift) {
+            self.step = (self.step + 1) & 0x0F;
+        }
+    }
+
+    // NOTE: 16 ステップのうち duty + 1 ステップだけ鳴る
+    fn output(&self) -> u8 {
+        if self.timer.enabled && (self.ignore_duty || self.step <= self.duty) {
+            self.volume
+        } else {
+            0
+        }
+    }
+}
+
+#[derive(Default)]
+struct Saw {
+    timer: Timer,
+    rate: u8,
+    accumulator: u8,
+    step: u8,
+}
+
+impl Saw {
+    fn write(&mut self, reg: u16, data: u8) {
+        match reg {
+            0 => self.rate = data & 0x3F,
+            1 => self.timer.write_low(data),
+            _ => {
+                self.timer.write_high(data);
+                if !self.timer.enabled {
+                    self.step = 0;
+                    self.accumulator = 0;
+                }
+            }
+        }
+    }
+
+    // NOTE: 2 クロックごとに rate を足し、7 回足したら 0 に戻る
+    fn tick(&mut self, shift: u8) {
+        if !self.timer.enabled || !self.timer.tick(shift) {
+            return;
+        }
+        self.step += 1;
+        if self.step == 14 {
+            self.step = 0;
+            self.accumulator = 0;
+        } else if self.step.is_multiple_of(2) {
+            self.accumulator = self.accumulator.wrapping_add(self.rate);
+        }
+    }
+
+    fn output(&self) -> u8 {
+        self.accumulator >> 3
+    }
+}
+
+// NOTE: 悪魔城伝説などの VRC6。矩形波 2 つとノコギリ波
+#[derive(Default)]
+pub(crate) struct Vrc6Audio {
+    pulse1: Pulse,
+    pulse2: Pulse,
+    saw: Saw,
+    halted: bool,
+    shift: u8,
+}
+
+impl Vrc6Audio {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ExpansionAudio for Vrc6Audio {
+    fn write(&mut self, addr: u16, data: u8) {
+        match addr {
+            PULSE1..=PULSE1_END => self.pulse1.write(addr - PULSE1, data),
+            FREQUENCY_CONTROL => {
+                self.halted = data & 0x01 != 0;
+                self.shift = if data & 0x04 != 0 {
+                    8
+                } else if data & 0x02 != 0 {
+                    4
+                } else {
+                    0
+                };
+            }
+            PULSE2..=PULSE2_END => self.pulse2.write(addr - PULSE2, data),
+            SAW..=SAW_END => self.saw.write(addr - SAW, data),
+            _ => {}
+        }
+    }
+
+    fn tick(&mut self) {
+        if self.halted {
+            return;
+        }
+        self.pulse1.tick(self.shift);
+        self.pulse2.tick(self.shift);
+        self.saw.tick(self.shift);
+    }
+
+    // NOTE: 矩形波の音量 15 が 2A03 の矩形波の最大と同じくらい。ノコギリ波は最大 31
+    fn output(&self) -> f32 {
+        let sum = self.pulse1.output() + self.pulse2.output() + self.saw.output();
+        sum as f32 * 2.0 / 15.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ExpansionAudio, Vrc6Audio};
+
+    fn outputs(audio: &mut Vrc6Audio, count: usize) -> Vec<f32> {
+        (0..count)
+            .map(|_| {
+                audio.tick();
+                audio.output()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_pulse_duty() {
+        let mut audio = Vrc6Audio::new();
+        // NOTE: 音量 15、duty 3 (16 ステップ中 4 ステップ)、周期 0 で毎サイクル進める
+        audio.write(0x9000, 0x3F);
+        audio.write(0x9001, 0x00);
+        audio.write(0x9002, 0x80);
+
+        let high = outputs(&mut audio, 16)
+            .into_iter()
+            .filter(|&value| value > 0.0)
+            .count();
+        assert_eq!(high, 4);
+
+        // NOTE: bit7 でデューティを無視して鳴らしっぱなしにする
+        audio.write(0x9000, 0x8F);
+        assert!(outputs(&mut audio, 16).into_iter().all(|value| value > 0.0));
+
+        audio.write(0x9002, 0x00);
+        assert!(outputs(&mut audio, 16)
+            .into_iter()
+            .all(|value| value == 0.0));
+    }
+
+    #[test]
+    fn test_saw() {
+        let mut audio = Vrc6Audio::new();
+        audio.write(0xB000, 0x2A);
+        audio.write(0xB001, 0x00);
+        audio.write(0xB002, 0x80);
+
+        let levels = outputs(&mut audio, 14)
+            .into_iter()
+            .map(|value| (value * 15.0 / 2.0).round() as u8)
+            .collect::<Vec<_>>();
+        // NOTE: 0x2A を 6 回足して 0xFC、上位 5bit で 31 まで上がってから 0 に戻る
+        assert_eq!(
+            levels,
+            vec![0, 5, 5, 10, 10, 15, 15, 21, 21, 26, 26, 31, 31, 0]
+        );
+    }
+
+    #[test]
+    fn test_halt() {
+        let mut audio = Vrc6Audio::new();
+        audio.write(0x9000, 0x0F);
+        audio.write(0x9002, 0x80);
+        audio.write(0x9003, 0x01);
+        let before = audio.output();
+        for _ in 0..32 {
+            audio.tick();
+        }
+        assert_eq!(audio.output(), before);
+    }
+}
@@ -0,0 +1,431 @@
+use std::f32::consts::TAU;
+
+use super::ExpansionAudio;
+
+const REGISTER_SELECT: u16 = 0x9010;
+const REGISTER_WRITE: u16 = 0x9030;
+
+// NOTE: OPLL は CPU の 36 サイクルに 1 回 (約 49.7kHz) 全チャンネルを計算する
+const CYCLES_PER_SAMPLE: u8 = 36;
+const SAMPLE_RATE: f32 = 1_789_773.0 / CYCLES_PER_SAMPLE as f32;
+const CHANNELS: usize = 6;
+
+// NOTE: VRC7 に内蔵されている音色 (1-15)。0 は $00-$07 に書き込むユーザー音色
+const PATCHES: [[u8; 8]; 16] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+    [0x03, 0x21, 0x05, 0x06, 0xE8, 0x81, 0x42, 0x27],
+    [0x13, 0x41, 0x14, 0x0D, 0xD8, 0xF6, 0x23, 0x12],
+    [0x11, 0x11, 0x08, 0x08, 0xFA, 0xB2, 0x20, 0x12],
+    [0x31, 0x61, 0x0C, 0x07, 0xA8, 0x64, 0x61, 0x27],
+    [0x32, 0x21, 0x1E, 0x06, 0xE1, 0x76, 0x01, 0x28],
+    [0x02, 0x01, 0x06, 0x00, 0xA3, 0xE2, 0xF4, 0xF4],
+    [0x21, 0x61, 0x1D, 0x07, 0x82, 0x81, 0x11, 0x07],
+    [0x23, 0x21, 0x22, 0x17, 0xA2, 0x72, 0x01, 0x17],
+    [0x35, 0x11, 0x25, 0x00, 0x40, 0x73, 0x72, 0x01],
+    [0xB5, 0x01, 0x0F, 0x0F, 0xA8, 0xA5, 0x51, 0x02],
+    [0x17, 0xC1, 0x24, 0x07, 0xF8, 0xF8, 0x22, 0x12],
+    [0x71, 0x23, 0x11, 0x06, 0x65, 0x74, 0x18, 0x16],
+    [0x01, 0x02, 0xD3, 0x05, 0xC9, 0x95, 0x03, 0x02],
+    [0x61, 0x63, 0x0C, 0x00, 0x94, 0xC0, 0x33, 0xF6],
+    [0x21, 0x72, 0x0D, 0x00, 0xC1, 0xD5, 0x56, 0x06],
+];
+
+const MULTIPLIERS: [f32; 16] = [
+    0.5, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0, 10.0, 12.0, 12.0, 15.0, 15.0,
+];
+// NOTE: ブロック 7 での F-Number の上位 4bit ごとの減衰量 (dB)。ブロックが 1 つ下がるごとに 6dB 減らす
+const KEY_SCALE_LEVELS: [f32; 16] = [
+    0.0, 18.0, 24.0, 27.75, 30.0, 32.25, 33.75, 35.25, 36.0, 37.5, 38.25, 39.0, 39.75, 40.5, 41.25,
+    42.0,
+];
+const KEY_SCALE_SHIFTS: [f32; 4] = [0.0, 0.5, 0.25, 1.0];
+
+// NOTE: エンベロープは 0.375dB 刻みの 7bit (0 が最大音量、127 で無音)
+const ENVELOPE_MAX: f32 = 127.0;
+const ENVELOPE_DB: f32 = 0.375;
+// NOTE: キーオフ時にサステインが立っていると、このレートでゆっくり消える
+const SUSTAIN_RELEASE_RATE: u8 = 5;
+
+const VIBRATO_HZ: f32 = 6.4;
+const VIBRATO_DEPTH: f32 = 0.0081; // NOTE: 約 14 セント
+const TREMOLO_HZ: f32 = 3.7;
+const TREMOLO_DB: f32 = 4.8;
+
+#[derive(Default, Clone, Copy)]
+struct OperatorPatch {
+    tremolo: bool,
+    vibrato: bool,
+    sustained: bool,
+    key_scale_rate: bool,
+    multiplier: f32,
+    key_scale_level: u8,
+    rectified: bool,
+    attack: u8,
+    decay: u8,
+    sustain_level: u8,
+    release: u8,
+}
+
+impl OperatorPatch {
+    // NOTE: op は 0 がモジュレーター、1 がキャリア
+    fn new(patch: &[u8; 8], op: usize) -> Self {
+        let flags = patch[op];
+        Self {
+            tremolo: flags & 0x80 != 0,
+            vibrato: flags & 0x40 != 0,
+            sustained: flags & 0x20 != 0,
+            key_scale_rate: flags & 0x10 != 0,
+            multiplier: MULTIPLIERS[(flags & 0x0F) as usize],
+            key_scale_level: patch[2 + op] >> 6,
+            rectified: patch[3] & (0x08 << op) != 0,
+            attack: patch[4 + op] >> 4,
+            decay: patch[4 + op] & 0x0F,
+            sustain_level: patch[6 + op] >> 4,
+            release: patch[6 + op] & 0x0F,
+        }
+    }
+}
+
+#[derive(Default, Clone, Copy, PartialEq, Debug)]
+enum EnvelopeState {
+    Attack,
+    Decay,
+    Sustain,
+    Release,
+    #[default]
+    Off,
+}
+
+#[derive(Clone, Copy)]
+struct Operator {
+    phase: f32,
+    envelope: f32,
+    state: EnvelopeState,
+}
+
+impl Default for Operator {
+    fn default() -> Self {
+        Self {
+            phase: 0.0,
+            envelope: ENVELOPE_MAX,
+            state: EnvelopeState::Off,
+        }
+    }
+}
+
+impl Operator {
+    fn key_on(&mut self) {
+        self.phase = 0.0;
+        self.state = EnvelopeState::Attack;
+    }
+
+    fn key_off(&mut self) {
+        if self.state != EnvelopeState::Off {
+            self.state = EnvelopeState::Release;
+        }
+    }
+
+    // NOTE: レート (0-63) で 1 サンプルあたりのエンベロープの変化量。4 上がるごとに倍速になる
+    fn step(rate: u8, seconds_at_rate_4: f32) -> f32 {
+        if rate < 4 {
+            return 0.0;
+        }
+        let seconds = seconds_at_rate_4 / 2f32.powf((rate - 4) as f32 / 4.0);
+        ENVELOPE_MAX / (seconds * SAMPLE_RATE)
+    }
+
+    fn tick_envelope(&mut self, patch: &OperatorPatch, key_scale: u8, sustain: bool) {
+        let rks = if patch.key_scale_rate {
+            key_scale
+        } else {
+            key_scale >> 2
+        };
+        let rate = |r: u8| if r == 0 { 0 } else { (r * 4 + rks).min(63) };
+
+        match self.state {
+            EnvelopeState::Attack => {
+                let attack = rate(patch.attack);
+                if attack >= 60 {
+                    self.envelope = 0.0;
+                } else {
+                    // NOTE: 減衰量に比例して減らす (指数的に立ち上がる)
+                    let step = Self::step(attack, 2.8);
+                    self.envelope -= (self.envelope / 16.0 + 1.0) * step * 4.0;
+                }
+                if self.envelope <= 0.0 {
+                    self.envelope = 0.0;
+                    self.state = EnvelopeState::Decay;
+                }
+            }
+            EnvelopeState::Decay => {
+                self.envelope += Self::step(rate(patch.decay), 20.0);
+                let sustain_level = patch.sustain_level as f32 * 3.0 / ENVELOPE_DB;
+                if self.envelope >= sustain_level {
+                    self.envelope = sustain_level;
+                    self.state = EnvelopeState::Sustain;
+                }
+            }
+            // NOTE: 持続音でなければサステインレベルからリリースレートで減衰を続ける
+            EnvelopeState::Sustain => {
+                if !patch.sustained {
+                    self.envelope += Self::step(rate(patch.release), 20.0);
+                }
+            }
+            EnvelopeState::Release => {
+                let release = if sustain {
+                    SUSTAIN_RELEASE_RATE
+                } else if patch.sustained {
+                    patch.release
+                } else {
+                    7
+                };
+                self.envelope += Self::step(rate(release), 20.0);
+            }
+            EnvelopeState::Off => self.envelope = ENVELOPE_MAX,
+        }
+
+        if self.envelope >= ENVELOPE_MAX {
+            self.envelope = ENVELOPE_MAX;
+            if self.state != EnvelopeState::Attack {
+                self.state = EnvelopeState::Off;
+            }
+        }
+    }
+
+    // NOTE: modulation は位相のずれ (1.0 で 1 周期)。attenuation は dB
+    fn output(&self, patch: &OperatorPatch, modulation: f32, attenuation: f32) -> f32 {
+        if self.state == EnvelopeState::Off {
+            return 0.0;
+        }
+        let wave = (TAU * (self.phase + modulation)).sin();
+        let wave = if patch.rectified { wave.max(0.0) } else { wave };
+        let db = self.envelope * ENVELOPE_DB + attenuation;
+        wave * 10f32.powf(-db / 20.0)
+    }
+}
+
+#[derive(Default, Clone, Copy)]
+struct FmChannel {
+    fnum: u16,
+    block: u8,
+    key_on: bool,
+    sustain: bool,
+    instrument: u8,
+    volume: u8,
+    modulator: Operator,
+    carrier: Operator,
+    feedback: [f32; 2],
+}
+
+impl FmChannel {
+    fn set_key(&mut self, key_on: bool) {
+        if key_on && !self.key_on {
+            self.modulator.key_on();
+            self.carrier.key_on();
+        } else if !key_on && self.key_on {
+            self.modulator.key_off();
+            self.carrier.key_off();
+        }
+        self.key_on = key_on;
+    }
+
+    fn key_scale(&self) -> u8 {
+        (self.block << 1) | (self.fnum >> 8) as u8
+    }
+
+    fn key_scale_level(&self, level: u8) -> f32 {
+        let base = KEY_SCALE_LEVELS[(self.fnum >> 5) as usize] - 6.0 * (7 - self.block) as f32;
+        base.max(0.0) * KEY_SCALE_SHIFTS[level as usize]
+    }
+
+    fn sample(&mut self, patch: &[u8; 8], vibrato: f32, tremolo: f32) -> f32 {
+        let modulator = OperatorPatch::new(patch, 0);
+        let carrier = OperatorPatch::new(patch, 1);
+
+        // NOTE: 1 サンプルあたりの位相の進み。fnum * 2^block / 2^19 周期
+        let base = self.fnum as f32 * (1u32 << self.block) as f32 / (1u32 << 19) as f32;
+        for (operator, op_patch) in [
+            (&mut self.modulator, &modulator),
+            (&mut self.carrier, &carrier),
+        ] {
+            let pitch = if op_patch.vibrato { vibrato } else { 1.0 };
+            operator.phase = (operator.phase + base * op_patch.multiplier * pitch) % 1.0;
+        }
+
+        let key_scale = self.key_scale();
+        self.modulator
+            .tick_envelope(&modulator, key_scale, self.sustain);
+        self.carrier
+            .tick_envelope(&carrier, key_scale, self.sustain);
+
+        let feedback_level = patch[3] & 0x07;
+        let feedback = if feedback_level == 0 {
+            0.0
+        } else {
+            (self.feedback[0] + self.feedback[1]) / 2.0 / (1 << (7 - feedback_level)) as f32 * 2.0
+        };
+        let attenuation = |op_patch: &OperatorPatch, level: f32| {
+            let am = if op_patch.tremolo { tremolo } else { 0.0 };
+            level + self.key_scale_level(op_patch.key_scale_level) + am
+        };
+
+        let modulator_level = attenuation(&modulator, (patch[2] & 0x3F) as f32 * 0.75);
+        let carrier_level = attenuation(&carrier, self.volume as f32 * 3.0);
+
+        let modulation = self.modulator.output(&modulator, feedback, modulator_level);
+        self.feedback = [self.feedback[1], modulation];
+        self.carrier
+            .output(&carrier, modulation * 2.0, carrier_level)
+    }
+}
+
+// NOTE: ラグランジュポイントの VRC7。YM2413 (OPLL) の 2 オペレーター FM 音源 6 チャンネル
+pub(crate) struct Vrc7Audio {
+    address: u8,
+    custom_patch: [u8; 8],
+    channels: [FmChannel; CHANNELS],
+    cycles: u8,
+    lfo_time: f32,
+    output: f32,
+}
+
+impl Vrc7Audio {
+    pub(crate) fn new() -> Self {
+        Self {
+            address: 0,
+            custom_patch: [0; 8],
+            channels: [FmChannel::default(); CHANNELS],
+            cycles: 0,
+            lfo_time: 0.0,
+            output: 0.0,
+        }
+    }
+
+    fn write_register(&mut self, data: u8) {
+        let reg = self.address;
+        let ch = (reg & 0x0F) as usize;
+        match reg {
+            0x00..=0x07 => self.custom_patch[reg as usize] = data,
+            0x10..=0x15 => {
+                let channel = &mut self.channels[ch];
+                channel.fnum = (channel.fnum & 0x100) | data as u16;
+            }
+            0x20..=0x25 => {
+                let channel = &mut self.channels[ch];
+                channel.fnum = (channel.fnum & 0xFF) | ((data as u16 & 0x01) << 8);
+                channel.block = (data >> 1) & 0x07;
+                channel.sustain = data & 0x20 != 0;
+                channel.set_key(data & 0x10 != 0);
+            }
+            0x30..=0x35 => {
+                let channel = &mut self.channels[ch];
+                channel.instrument = data >> 4;
+                channel.volume = data & 0x0F;
+            }
+            _ => {}
+        }
+    }
+
+    fn sample(&mut self) {
+        self.lfo_time = (self.lfo_time + 1.0 / SAMPLE_RATE) % 100.0;
+        let vibrato = 1.0 + VIBRATO_DEPTH * (TAU * VIBRATO_HZ * self.lfo_time).sin();
+        let tremolo = TREMOLO_DB * (1.0 + (TAU * TREMOLO_HZ * self.lfo_time).sin()) / 2.0;
+
+        let mut output = 0.0;
+        for i in 0..CHANNELS {
+            let patch = match self.channels[i].instrument {
+                0 => self.custom_patch,
+                instrument => PATCHES[instrument as usize],
+            };
+            output += self.channels[i].sample(&patch, vibrato, tremolo);
+        }
+        self.output = output;
+    }
+}
+
+impl ExpansionAudio for Vrc7Audio {
+    fn write(&mut self, addr: u16, data: u8) {
+        match addr {
+            REGISTER_SELECT => self.address = data,
+            REGISTER_WRITE => self.write_register(data),
+            _ => {}
+        }
+    }
+
+    fn tick(&mut self) {
+        self.cycles += 1;
+        if self.cycles == CYCLES_PER_SAMPLE {
+            self.cycles = 0;
+            self.sample();
+        }
+    }
+
+    fn output(&self) -> f32 {
+        self.output
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ExpansionAudio, Vrc7Audio, CYCLES_PER_SAMPLE};
+
+    fn write(audio: &mut Vrc7Audio, reg: u8, data: u8) {
+        audio.write(0x9010, reg);
+        audio.write(0x9030, data);
+    }
+
+    fn peak(audio: &mut Vrc7Audio, samples: usize) -> f32 {
+        let mut peak = 0.0f32;
+        for _ in 0..samples * CYCLES_PER_SAMPLE as usize {
+            audio.tick();
+            peak = peak.max(audio.output().abs());
+        }
+        peak
+    }
+
+    #[test]
+    fn test_key_on_off() {
+        let mut audio = Vrc7Audio::new();
+        assert_eq!(peak(&mut audio, 100), 0.0);
+
+        // NOTE: フルート (4)、音量最大で A4 くらい
+        write(&mut audio, 0x30, 0x40);
+        write(&mut audio, 0x10, 0xAC);
+        write(&mut audio, 0x20, 0x18 | 0x04);
+        assert!(peak(&mut audio, 2000) > 0.1);
+
+        write(&mut audio, 0x20, 0x04);
+        peak(&mut audio, 50_000);
+        assert_eq!(peak(&mut audio, 100), 0.0);
+    }
+
+    #[test]
+    fn test_volume() {
+        let play = |volume: u8| {
+            let mut audio = Vrc7Audio::new();
+            write(&mut audio, 0x30, 0x30 | volume);
+            write(&mut audio, 0x10, 0xAC);
+            write(&mut audio, 0x20, 0x18 | 0x04);
+            peak(&mut audio, 5000)
+        };
+        assert!(play(0x00) > play(0x04));
+        assert!(play(0x04) > play(0x0F));
+    }
+
+    #[test]
+    fn test_custom_patch() {
+        let mut audio = Vrc7Audio::new();
+        // NOTE: 即座に立ち上がって減衰しない正弦波
+        for (reg, data) in [0x21, 0x21, 0x3F, 0x00, 0xF0, 0xF0, 0x00, 0x00]
+            .into_iter()
+            .enumerate()
+        {
+            write(&mut audio, reg as u8, data);
+        }
+        write(&mut audio, 0x30, 0x00);
+        write(&mut audio, 0x10, 0x00);
+        write(&mut audio, 0x20, 0x10 | 0x01 | 0x08);
+        let peak = peak(&mut audio, 5000);
+        assert!(peak > 0.9 && peak <= 1.0, "{}", peak);
+    }
+}
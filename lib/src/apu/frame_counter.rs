@@ -0,0 +1,141 @@
+use bitflags::bitflags;
+
+use crate::region::Region;
+
+bitflags! {
+    struct Control: u8 {
+        const FIVE_STEP = 0b1000_0000;
+        const IRQ_INHIBIT = 0b0100_0000;
+    }
+}
+
+// NOTE: 各ステップの CPU サイクル。4 ステップモードは 4 つ目、5 ステップモードは 5 つ目で一周する
+const STEPS: [u32; 5] = [7457, 14913, 22371, 29829, 37281];
+const PAL_STEPS: [u32; 5] = [8313, 16627, 24939, 33253, 41565];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrameClock {
+    // NOTE: エンベロープと三角波の線形カウンタ
+    Quarter,
+    // NOTE: Quarter に加えて長さカウンタとスイープ
+    Half,
+}
+
+// NOTE: $4017 の書き込みから実機では 3-4 サイクル遅れてリセットされるが、ここではすぐにリセットする
+pub struct FrameCounter {
+    control: Control,
+    cycle: u32,
+    irq: bool,
+}
+
+impl FrameCounter {
+    pub fn new() -> Self {
+        Self {
+            control: Control::empty(),
+            cycle: 0,
+            irq: false,
+        }
+    }
+
+    // NOTE: 5 ステップモードにすると、すぐに 1/2 フレームのクロックを出す
+    pub fn write(&mut self, data: u8) -> Option<FrameClock> {
+        self.control = Control::from_bits_truncate(data);
+        if self.control.contains(Control::IRQ_INHIBIT) {
+            self.irq = false;
+        }
+        self.cycle = 0;
+
+        if self.control.contains(Control::FIVE_STEP) {
+            Some(FrameClock::Half)
+        } else {
+            None
+        }
+    }
+
+    pub fn irq(&self) -> bool {
+        self.irq
+    }
+
+    // NOTE: $4015 を読むと消える
+    pub fn clear_irq(&mut self) {
+        self.irq = false;
+    }
+
+    pub fn tick(&mut self, region: Region) -> Option<FrameClock> {
+        let steps = match region {
+            Region::Ntsc | Region::Dendy => STEPS,
+            Region::Pal => PAL_STEPS,
+        };
+        let five_step = self.control.contains(Control::FIVE_STEP);
+
+        self.cycle += 1;
+        let clock = match steps.iter().position(|&step| step == self.cycle) {
+            Some(0) | Some(2) => Some(FrameClock::Quarter),
+            Some(1) => Some(FrameClock::Half),
+            Some(3) if !five_step => {
+                if !self.control.contains(Control::IRQ_INHIBIT) {
+                    self.irq = true;
+                }
+                Some(FrameClock::Half)
+            }
+            Some(4) if five_step => Some(FrameClock::Half),
+            _ => None,
+        };
+
+        let last = if five_step { steps[4] } else { steps[3] };
+        if self.cycle > last {
+            self.cycle = 0;
+        }
+        clock
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::region::Region;
+
+    use super::{FrameClock, FrameCounter};
+
+    fn run(counter: &mut FrameCounter, cycles: u32) -> Vec<(u32, FrameClock)> {
+        (1..=cycles)
+            .filter_map(|n| counter.tick(Region::Ntsc).map(|clock| (n, clock)))
+            .collect()
+    }
+
+    #[test]
+    fn test_four_step() {
+        let mut counter = FrameCounter::new();
+        let clocks = run(&mut counter, 29830 + 7457);
+        assert_eq!(
+            clocks,
+            vec![
+                (7457, FrameClock::Quarter),
+                (14913, FrameClock::Half),
+                (22371, FrameClock::Quarter),
+                (29829, FrameClock::Half),
+                (29830 + 7457, FrameClock::Quarter),
+            ]
+        );
+        assert!(counter.irq());
+
+        counter.clear_irq();
+        assert!(!counter.irq());
+    }
+
+    #[test]
+    fn test_five_step() {
+        let mut counter = FrameCounter::new();
+        assert_eq!(counter.write(0xC0), Some(FrameClock::Half));
+        let clocks = run(&mut counter, 37282);
+        assert_eq!(clocks.len(), 4);
+        assert_eq!(clocks[3], (37281, FrameClock::Half));
+        assert!(!counter.irq());
+
+        // NOTE: IRQ 禁止ビットを立てると、立っていた IRQ も消える
+        counter.write(0x00);
+        run(&mut counter, 29829);
+        assert!(counter.irq());
+        assert_eq!(counter.write(0x40), None);
+        assert!(!counter.irq());
+    }
+}
@@ -0,0 +1,61 @@
+// NOTE: 書き込まれた 5bit の値から引く長さ (1/2 フレーム単位)
+const LENGTH_TABLE: [u8; 32] = [
+    10, 254, 20, 2, 40, 4, 80, 6, 160, 8, 60, 10, 14, 12, 26, 14, 12, 16, 24, 18, 48, 20, 96, 22,
+    192, 24, 72, 26, 16, 28, 32, 30,
+];
+
+// NOTE: 0 になるとチャンネルを止める。$4015 で無効にしている間は 0 のままで、書き込みも無視する
+#[derive(Default)]
+pub struct LengthCounter {
+    enabled: bool,
+    counter: u8,
+}
+
+impl LengthCounter {
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.counter = 0;
+        }
+    }
+
+    pub fn load(&mut self, index: u8) {
+        if self.enabled {
+            self.counter = LENGTH_TABLE[(index & 0x1F) as usize];
+        }
+    }
+
+    pub fn clock(&mut self, halt: bool) {
+        if !halt && self.counter > 0 {
+            self.counter -= 1;
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.counter > 0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::LengthCounter;
+
+    #[test]
+    fn test_length_counter() {
+        let mut counter = LengthCounter::default();
+        counter.load(0x01);
+        assert!(!counter.is_active());
+
+        counter.set_enabled(true);
+        counter.load(0x03);
+        counter.clock(true);
+        assert!(counter.is_active());
+        counter.clock(false);
+        counter.clock(false);
+        assert!(!counter.is_active());
+
+        counter.load(0x01);
+        counter.set_enabled(false);
+        assert!(!counter.is_active());
+    }
+}
@@ -0,0 +1,34 @@
+// NOTE: 2A03 の DAC は非線形で、矩形波 2 本と三角波・ノイズ・DMC の 2 系統をそれぞれまとめて出力する。
+//       値は nesdev wiki の近似式で、全チャンネルが最大のとき合計がほぼ 1.0 になる
+pub fn pulse_out(pulse1: u8, pulse2: u8) -> f32 {
+    let sum = (pulse1 + pulse2) as f32;
+    if sum == 0.0 {
+        return 0.0;
+    }
+    95.88 / (8128.0 / sum + 100.0)
+}
+
+pub fn tnd_out(triangle: u8, noise: u8, dmc: u8) -> f32 {
+    let sum = triangle as f32 / 8227.0 + noise as f32 / 12241.0 + dmc as f32 / 22638.0;
+    if sum == 0.0 {
+        return 0.0;
+    }
+    159.79 / (1.0 / sum + 100.0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{pulse_out, tnd_out};
+
+    #[test]
+    fn test_mixer() {
+        assert_eq!(pulse_out(0, 0), 0.0);
+        assert_eq!(tnd_out(0, 0, 0), 0.0);
+
+        let full = pulse_out(15, 15) + tnd_out(15, 15, 127);
+        assert!((0.99..1.01).contains(&full), "{}", full);
+
+        // NOTE: 2 本鳴らしても 2 倍にはならない
+        assert!(pulse_out(15, 15) < 2.0 * pulse_out(15, 0));
+    }
+}
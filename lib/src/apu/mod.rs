@@ -1,9 +1,22 @@
+use dmc_register::DmcRegister;
+use frame_counter::{FrameClock, FrameCounter};
 use noise_register::NoiseRegister;
 use pulse_register::PulseRegister;
 use triangle_register::TriangleRegister;
 
-use crate::speaker::{Speaker, SpeakerEvent};
+use crate::{
+    audio::{AudioBuffer, ExpansionStream, Sampler, DEFAULT_SAMPLE_RATE},
+    diagnostics::{Category, Diagnostics},
+    region::Region,
+    speaker::{Speaker, SpeakerEvent, EXPANSION_CH},
+};
 
+mod dmc_register;
+mod envelope;
+pub(crate) mod expansion;
+mod frame_counter;
+mod length_counter;
+mod mixer;
 mod noise_register;
 mod pulse_register;
 mod triangle_register;
@@ -21,11 +34,16 @@ const APU_DMC_REGISTERS_END: u16 = 0x4013;
 const APU_STATUS_REGISTERS: u16 = 0x4015;
 const APU_FRAME_COUNTER_REGISTERS: u16 = 0x4017;
 
-// 1.789773 MHz
-const CPU_CLOCK: f32 = 1_789_773.0;
+const PULSE1_ENABLE: u8 = 0b0000_0001;
+const PULSE2_ENABLE: u8 = 0b0000_0010;
+const TRIANGLE_ENABLE: u8 = 0b0000_0100;
+const NOISE_ENABLE: u8 = 0b0000_1000;
+const DMC_ENABLE: u8 = 0b0001_0000;
+const FRAME_IRQ: u8 = 0b0100_0000;
+const DMC_IRQ: u8 = 0b1000_0000;
 
-fn calc_hz(frequency: u16) -> f32 {
-    CPU_CLOCK / (16.0 * (frequency as f32 + 1.0))
+fn calc_hz(region: Region, frequency: u16) -> f32 {
+    region.cpu_clock() / (16.0 * (frequency as f32 + 1.0))
 }
 
 pub struct APU<S: Speaker> {
@@ -34,66 +52,239 @@ pub struct APU<S: Speaker> {
     pulse2: PulseRegister,
     triangle: TriangleRegister,
     noise: NoiseRegister,
+    dmc: DmcRegister,
+    frame_counter: FrameCounter,
+    // NOTE: 矩形波とノイズのタイマーは CPU の 2 サイクルに 1 回進む
+    odd_cycle: bool,
+    region: Region,
+    diagnostics: Diagnostics,
+    sampler: Option<Sampler>,
+    expansion: Option<f32>,
+    expansion_stream: ExpansionStream,
 }
 
 impl<S: Speaker> APU<S> {
     pub fn new(speaker: S) -> Self {
         Self {
             speaker,
-            pulse1: PulseRegister::new(),
-            pulse2: PulseRegister::new(),
+            pulse1: PulseRegister::new(true),
+            pulse2: PulseRegister::new(false),
             triangle: TriangleRegister::new(),
             noise: NoiseRegister::new(),
+            dmc: DmcRegister::new(),
+            frame_counter: FrameCounter::new(),
+            odd_cycle: false,
+            region: Region::default(),
+            diagnostics: Diagnostics::default(),
+            sampler: None,
+            expansion: None,
+            expansion_stream: ExpansionStream::new(DEFAULT_SAMPLE_RATE, Region::default()),
         }
     }
 
+    pub fn set_region(&mut self, region: Region) {
+        self.region = region;
+        self.expansion_stream.set_region(region);
+        if let Some(sampler) = &mut self.sampler {
+            sampler.set_region(region);
+        }
+    }
+
+    // NOTE: 録音用に各チャンネルの出力とミキサーの出力を PCM にするかどうか。スピーカーへのイベントはこれとは関係なく送る
+    pub fn set_audio_capture(&mut self, sample_rate: Option<u32>) {
+        self.sampler = sample_rate.map(|rate| Sampler::new(rate, self.region));
+    }
+
+    pub fn audio_sample_rate(&self) -> Option<u32> {
+        self.sampler.as_ref().map(|sampler| sampler.sample_rate())
+    }
+
+    pub fn take_audio(&mut self) -> Option<AudioBuffer> {
+        self.sampler.as_mut().map(|sampler| sampler.take())
+    }
+
+    // NOTE: カートリッジの拡張音源の出力。録音用のミックスに入れるほか、PCM にしてスピーカーへ送る
+    pub fn set_expansion_output(&mut self, output: Option<f32>) {
+        self.expansion = output;
+    }
+
+    pub fn tick(&mut self, cycles: u8) {
+        for _ in 0..cycles {
+            if let Some(clock) = self.frame_counter.tick(self.region) {
+                self.clock_frame(clock);
+            }
+
+            self.triangle.tick();
+            if self.odd_cycle {
+                self.pulse1.tick();
+                self.pulse2.tick();
+                self.noise.tick(self.region);
+            }
+            self.odd_cycle = !self.odd_cycle;
+            self.dmc.tick(1, self.region);
+
+            let mix = self.sampler.is_some().then(|| self.mix());
+            if let (Some(sampler), Some((mixed, channels))) = (&mut self.sampler, mix) {
+                sampler.push(mixed, channels);
+            }
+        }
+
+        let Some(output) = self.expansion else {
+            return;
+        };
+        if let Some(samples) = self.expansion_stream.tick(cycles, output) {
+            let sample_rate = self.expansion_stream.sample_rate();
+            self.speaker.send(
+                EXPANSION_CH,
+                SpeakerEvent::ExpansionSamples {
+                    sample_rate,
+                    samples,
+                },
+            );
+        }
+    }
+
+    // NOTE: DMC が次に読むサンプルのアドレス。バスが読んで dmc_fill で渡す
+    pub fn dmc_fetch_address(&self) -> Option<u16> {
+        self.dmc.fetch_address()
+    }
+
+    pub fn dmc_fill(&mut self, data: u8) {
+        self.dmc.fill(data);
+    }
+
+    pub fn irq(&self) -> bool {
+        self.dmc.irq() || self.frame_counter.irq()
+    }
+
+    fn clock_frame(&mut self, clock: FrameClock) {
+        self.pulse1.clock_quarter_frame();
+        self.pulse2.clock_quarter_frame();
+        self.triangle.clock_quarter_frame();
+        self.noise.clock_quarter_frame();
+
+        if clock == FrameClock::Half {
+            self.pulse1.clock_half_frame();
+            self.pulse2.clock_half_frame();
+            self.triangle.clock_half_frame();
+            self.noise.clock_half_frame();
+        }
+    }
+
+    // NOTE: 非線形ミキサーを通した出力と、各チャンネルを単独でミキサーに通した出力 (並びは Channel::ALL と同じ)。
+    //       拡張音源は矩形波 1 本を基準にした大きさなので、音量最大の矩形波 1 本と同じ重みで足す
+    fn mix(&self) -> (f32, [f32; 6]) {
+        let pulse1 = self.pulse1.output();
+        let pulse2 = self.pulse2.output();
+        let triangle = self.triangle.output();
+        let noise = self.noise.output();
+        let dmc = self.dmc.get_output_level();
+        let expansion = self.expansion.unwrap_or(0.0) * mixer::pulse_out(15, 0);
+
+        let mixed = mixer::pulse_out(pulse1, pulse2) + mixer::tnd_out(triangle, noise, dmc);
+        (
+            mixed + expansion,
+            [
+                mixer::pulse_out(pulse1, 0),
+                mixer::pulse_out(0, pulse2),
+                mixer::tnd_out(triangle, 0, 0),
+                mixer::tnd_out(0, noise, 0),
+                mixer::tnd_out(0, 0, dmc),
+                expansion,
+            ],
+        )
+    }
+
+    // NOTE: 各チャンネルがどれくらい鳴っているか (0.0..=1.0)。並びは Channel::ALL と同じで、拡張音源は含まない
+    pub fn channel_levels(&self) -> [f32; 5] {
+        let pulse = |register: &PulseRegister| {
+            if !register.is_audible() {
+                0.0
+            } else {
+                register.get_volume_level() as f32 / 15.0
+            }
+        };
+        let triangle = if self.triangle.is_playing() { 1.0 } else { 0.0 };
+        let noise = if self.noise.is_active() {
+            self.noise.get_volume_level() as f32 / 15.0
+        } else {
+            0.0
+        };
+
+        [
+            pulse(&self.pulse1),
+            pulse(&self.pulse2),
+            triangle,
+            noise,
+            self.dmc.get_output_level() as f32 / 127.0,
+        ]
+    }
+
+    fn send(&mut self, ch: u8, event: SpeakerEvent) {
+        self.speaker.send(ch, event);
+    }
+
+    pub fn set_diagnostics(&mut self, diagnostics: Diagnostics) {
+        self.diagnostics = diagnostics;
+    }
+
     pub fn write(&mut self, addr: u16, data: u8) {
         match addr {
             APU_PULSE1_REGISTERS..=APU_PULSE1_REGISTERS_END => {
                 self.pulse1.write(addr - APU_PULSE1_REGISTERS, data);
-                self.speaker.send(
-                    1,
-                    SpeakerEvent::SquareNote {
-                        duty: self.pulse1.get_duty(),
-                        hz: calc_hz(self.pulse1.get_frequency()),
-                        volume: self.pulse1.get_volume(),
-                    },
-                );
+                self.send(1, self.event(1));
             }
             APU_PULSE2_REGISTERS..=APU_PULSE2_REGISTERS_END => {
                 self.pulse2.write(addr - APU_PULSE2_REGISTERS, data);
-                self.speaker.send(
-                    2,
-                    SpeakerEvent::SquareNote {
-                        duty: self.pulse2.get_duty(),
-                        hz: calc_hz(self.pulse2.get_frequency()),
-                        volume: self.pulse2.get_volume(),
-                    },
-                );
+                self.send(2, self.event(2));
             }
             APU_TRIANGLE_REGISTERS..=APU_TRIANGLE_REGISTERS_END => {
                 self.triangle.write(addr - APU_TRIANGLE_REGISTERS, data);
-                self.speaker.send(
-                    3,
-                    SpeakerEvent::TriangleNote {
-                        hz: calc_hz(self.triangle.get_frequency()),
-                    },
-                );
+                self.send(3, self.event(3));
             }
             APU_NOISE_REGISTERS..=APU_NOISE_REGISTERS_END => {
                 self.noise.write(addr - APU_NOISE_REGISTERS, data);
-                self.speaker.send(
-                    4,
-                    SpeakerEvent::NoiseNote {
-                        mode: self.noise.get_mode(),
-                        hz: calc_hz(self.noise.get_frequency()),
-                        volume: self.noise.get_volume(),
-                    },
-                );
+                self.send(4, self.event(4));
             }
-            _ => {
-                eprintln!("Not implemented: {:04X}", addr);
+            APU_DMC_REGISTERS..=APU_DMC_REGISTERS_END => {
+                self.dmc.write(addr - APU_DMC_REGISTERS, data);
+            }
+            APU_STATUS_REGISTERS => {
+                self.pulse1.set_enabled(data & PULSE1_ENABLE != 0);
+                self.pulse2.set_enabled(data & PULSE2_ENABLE != 0);
+                self.triangle.set_enabled(data & TRIANGLE_ENABLE != 0);
+                self.noise.set_enabled(data & NOISE_ENABLE != 0);
+                self.dmc.set_enabled(data & DMC_ENABLE != 0);
+            }
+            APU_FRAME_COUNTER_REGISTERS => {
+                if let Some(clock) = self.frame_counter.write(data) {
+                    self.clock_frame(clock);
+                }
             }
+            _ => self.report_unimplemented(addr),
+        }
+    }
+
+    fn event(&self, ch: u8) -> SpeakerEvent {
+        match ch {
+            1 => SpeakerEvent::SquareNote {
+                duty: self.pulse1.get_duty(),
+                hz: calc_hz(self.region, self.pulse1.get_frequency()),
+                volume: self.pulse1.get_volume(),
+            },
+            2 => SpeakerEvent::SquareNote {
+                duty: self.pulse2.get_duty(),
+                hz: calc_hz(self.region, self.pulse2.get_frequency()),
+                volume: self.pulse2.get_volume(),
+            },
+            3 => SpeakerEvent::TriangleNote {
+                hz: calc_hz(self.region, self.triangle.get_frequency()),
+            },
+            _ => SpeakerEvent::NoiseNote {
+                mode: self.noise.get_mode(),
+                hz: calc_hz(self.region, self.noise.get_frequency(self.region)),
+                volume: self.noise.get_volume(),
+            },
         }
     }
 
@@ -103,10 +294,89 @@ impl<S: Speaker> APU<S> {
             | APU_PULSE2_REGISTERS..=APU_PULSE2_REGISTERS_END
             | APU_TRIANGLE_REGISTERS..=APU_TRIANGLE_REGISTERS_END
             | APU_NOISE_REGISTERS..=APU_NOISE_REGISTERS_END => 0x40,
+            // NOTE: 長さカウンタが残っているチャンネルと IRQ。読むとフレーム IRQ は消える
+            APU_STATUS_REGISTERS => {
+                let flags = [
+                    (self.pulse1.is_active(), PULSE1_ENABLE),
+                    (self.pulse2.is_active(), PULSE2_ENABLE),
+                    (self.triangle.is_active(), TRIANGLE_ENABLE),
+                    (self.noise.is_active(), NOISE_ENABLE),
+                    (self.dmc.is_active(), DMC_ENABLE),
+                    (self.frame_counter.irq(), FRAME_IRQ),
+                    (self.dmc.irq(), DMC_IRQ),
+                ];
+                self.frame_counter.clear_irq();
+                flags
+                    .iter()
+                    .filter(|(set, _)| *set)
+                    .fold(0, |status, (_, bit)| status | bit)
+            }
             _ => {
-                eprintln!("Not implemented: {:04X}", addr);
+                self.report_unimplemented(addr);
                 0
             }
         }
     }
+
+    fn report_unimplemented(&self, addr: u16) {
+        self.diagnostics.report(Category::Apu, "register", || {
+            format!("Access to unimplemented APU register {:#06X}", addr)
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{audio::Channel, testrom::NullSpeaker};
+
+    use super::APU;
+
+    fn run_frames(apu: &mut APU<NullSpeaker>, frames: usize) {
+        for _ in 0..frames * 29830 {
+            apu.tick(1);
+        }
+    }
+
+    #[test]
+    fn test_capture_follows_length_counter() {
+        let mut apu = APU::new(NullSpeaker);
+        apu.write(0x4017, 0x40);
+        apu.write(0x4015, 0x01);
+        apu.set_audio_capture(Some(44_100));
+        // NOTE: 矩形波 1 を定音量 15、周期 $FF、長さ 10 (1/2 フレーム 10 回ぶん) で鳴らす
+        apu.write(0x4000, 0x9F);
+        apu.write(0x4002, 0xFF);
+        apu.write(0x4003, 0x00);
+        assert_eq!(apu.read(0x4015), 0x01);
+
+        run_frames(&mut apu, 3);
+        let audio = apu.take_audio().unwrap();
+        let pulse1 = audio.channel(Channel::Pulse1);
+        assert!(pulse1.iter().any(|&v| v > 0.05));
+        assert!(pulse1.iter().any(|&v| v < -0.05));
+        for channel in [Channel::Pulse2, Channel::Triangle, Channel::Noise, Channel::Dmc] {
+            assert!(audio.channel(channel).iter().all(|&v| v == 0.0), "{}", channel);
+        }
+
+        // NOTE: 5 フレームで長さカウンタが尽きて無音になる
+        run_frames(&mut apu, 3);
+        assert_eq!(apu.read(0x4015), 0x00);
+        let audio = apu.take_audio().unwrap();
+        assert!(audio.mixed[audio.len() - 100..]
+            .iter()
+            .all(|&v| v.abs() < 0.001));
+    }
+
+    #[test]
+    fn test_frame_irq() {
+        let mut apu = APU::new(NullSpeaker);
+        run_frames(&mut apu, 1);
+        assert!(apu.irq());
+        assert_eq!(apu.read(0x4015), 0x40);
+        assert!(!apu.irq());
+
+        apu.write(0x4017, 0x40);
+        run_frames(&mut apu, 1);
+        assert!(!apu.irq());
+    }
 }
@@ -1,6 +1,8 @@
-use crate::speaker::NoiseMode;
+use crate::{region::Region, speaker::NoiseMode};
 use bitflags::bitflags;
 
+use super::{envelope::Envelope, length_counter::LengthCounter};
+
 bitflags! {
     struct VolumeControl: u8 {
         const KEY_OFF_FLAG = 0b0010_0000;
@@ -23,10 +25,20 @@ const NOISE_MAP: [u16; 16] = [
     0x1FC, 0x3F9, 0x7F2,
 ];
 
+const PAL_NOISE_MAP: [u16; 16] = [
+    0x002, 0x004, 0x007, 0x00F, 0x01E, 0x02C, 0x03B, 0x04A, 0x05E, 0x076, 0x0B1, 0x0EC, 0x162,
+    0x1D8, 0x3B1, 0x761,
+];
+
 pub struct NoiseRegister {
     volume_control: VolumeControl,
     mode_control: ModeControl,
     key_control: KeyControl,
+    timer: u16,
+    // NOTE: 15bit の LFSR。電源投入時は 1
+    shift_register: u16,
+    envelope: Envelope,
+    length_counter: LengthCounter,
 }
 
 impl NoiseRegister {
@@ -35,19 +47,78 @@ impl NoiseRegister {
             volume_control: VolumeControl::empty(),
             mode_control: ModeControl::empty(),
             key_control: KeyControl::empty(),
+            timer: 0,
+            shift_register: 1,
+            envelope: Envelope::default(),
+            length_counter: LengthCounter::default(),
         }
     }
 
     pub fn write(&mut self, addr: u16, value: u8) {
         match addr {
             0 => self.volume_control = VolumeControl::from_bits_truncate(value),
-            1 => eprintln!("unused register: {}", addr),
+            // NOTE: $400D は未使用
+            1 => {}
             2 => self.mode_control = ModeControl::from_bits_truncate(value),
-            3 => self.key_control = KeyControl::from_bits_truncate(value),
+            3 => {
+                self.key_control = KeyControl::from_bits_truncate(value);
+                self.length_counter.load(self.key_control.bits() >> 3);
+                self.envelope.restart();
+            }
             _ => panic!("Invalid noise register address: {}", addr),
         }
     }
 
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.length_counter.set_enabled(enabled);
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.length_counter.is_active()
+    }
+
+    // NOTE: APU サイクル (CPU の 2 サイクル) ごとに呼ぶ。短周期モードは 6 ビット目、通常は 1 ビット目と XOR を取る
+    pub fn tick(&mut self, region: Region) {
+        if self.timer > 0 {
+            self.timer -= 1;
+            return;
+        }
+        self.timer = self.get_frequency(region);
+
+        let tap = match self.get_mode() {
+            NoiseMode::Short => 6,
+            NoiseMode::Long => 1,
+        };
+        let feedback = (self.shift_register ^ (self.shift_register >> tap)) & 0x01;
+        self.shift_register = (self.shift_register >> 1) | (feedback << 14);
+    }
+
+    pub fn clock_quarter_frame(&mut self) {
+        self.envelope.clock(
+            self.volume_control.bits() & VolumeControl::VOLUME.bits(),
+            self.volume_control.contains(VolumeControl::KEY_OFF_FLAG),
+        );
+    }
+
+    pub fn clock_half_frame(&mut self) {
+        self.length_counter
+            .clock(self.volume_control.contains(VolumeControl::KEY_OFF_FLAG));
+    }
+
+    pub fn output(&self) -> u8 {
+        if !self.length_counter.is_active() || self.shift_register & 0x01 != 0 {
+            return 0;
+        }
+        self.get_volume_level()
+    }
+
+    pub fn get_volume_level(&self) -> u8 {
+        self.envelope.volume(
+            self.volume_control.contains(VolumeControl::ENVELOPE_FLAG),
+            self.volume_control.bits() & VolumeControl::VOLUME.bits(),
+        )
+    }
+
     pub fn get_mode(&self) -> NoiseMode {
         if self.mode_control.contains(ModeControl::MODE) {
             NoiseMode::Short
@@ -56,12 +127,54 @@ impl NoiseRegister {
         }
     }
 
-    pub fn get_frequency(&self) -> u16 {
+    pub fn get_frequency(&self, region: Region) -> u16 {
         let idx = self.mode_control.bits() as u16 & ModeControl::FREQUENCY.bits() as u16;
-        NOISE_MAP[idx as usize]
+        match region {
+            Region::Ntsc | Region::Dendy => NOISE_MAP[idx as usize],
+            Region::Pal => PAL_NOISE_MAP[idx as usize],
+        }
     }
 
     pub fn get_volume(&self) -> f32 {
         (self.volume_control.bits() & VolumeControl::VOLUME.bits()) as f32 / 15.0
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::region::Region;
+
+    use super::NoiseRegister;
+
+    fn run(noise: &mut NoiseRegister, ticks: usize) -> Vec<u8> {
+        (0..ticks)
+            .map(|_| {
+                noise.tick(Region::Ntsc);
+                noise.output()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_noise() {
+        let mut noise = NoiseRegister::new();
+        noise.set_enabled(true);
+        // NOTE: 定音量 8、最短の周期、長さ 254
+        noise.write(0, 0b0001_1000);
+        noise.write(2, 0x00);
+        noise.write(3, 0x08);
+
+        let outputs = run(&mut noise, 3 * 32767);
+        assert!(outputs.iter().all(|&v| v == 0 || v == 8));
+        // NOTE: 通常モードは 32767 ステップで一周し、そのうち 16383 ステップで鳴る
+        assert_eq!(outputs.iter().filter(|&&v| v == 8).count(), 3 * 16383);
+
+        // NOTE: 短周期モードは 93 ステップで一周する
+        noise.write(2, 0x80);
+        let outputs = run(&mut noise, 3 * 93 * 4);
+        assert_eq!(outputs[..3 * 93], outputs[3 * 93..2 * 3 * 93]);
+
+        noise.set_enabled(false);
+        assert!(run(&mut noise, 16).iter().all(|&v| v == 0));
+    }
+}
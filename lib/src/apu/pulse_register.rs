@@ -1,5 +1,7 @@
 use bitflags::bitflags;
 
+use super::{envelope::Envelope, length_counter::LengthCounter};
+
 bitflags! {
     struct ToneVolumeController: u8 {
         const DUTY_HI = 0b1000_0000;
@@ -16,16 +18,20 @@ bitflags! {
         const SWEEP_FREQUENCY = 0b0000_0111;
     }
 
-    struct LoFrequency: u8 {
-        const LO_FREQUENCY = 0b1111_1111;
-    }
-
     struct HiFrequency: u8 {
         const HI_FREQUENCY = 0b0000_0111;
         const KEY_ON = 0b1111_1000;
     }
 }
 
+// NOTE: シーケンサは 7, 6, ..., 0 の順に進む
+const DUTY_TABLE: [[u8; 8]; 4] = [
+    [0, 1, 0, 0, 0, 0, 0, 0],
+    [0, 1, 1, 0, 0, 0, 0, 0],
+    [0, 1, 1, 1, 1, 0, 0, 0],
+    [1, 0, 0, 1, 1, 1, 1, 1],
+];
+
 enum SweepDirection {
     Increase,
     Decrease,
@@ -34,30 +40,129 @@ enum SweepDirection {
 pub struct PulseRegister {
     tone_volume_controller: ToneVolumeController,
     sweep_controller: SweepController,
-    lo_frequency: LoFrequency,
-    hi_frequency: HiFrequency,
+    // NOTE: 矩形波 1 は周期を下げるときに 1 の補数で引くので、2 より 1 だけ低くなる
+    ones_complement: bool,
+    period: u16,
+    timer: u16,
+    sequence: u8,
+    sweep_divider: u8,
+    sweep_reload: bool,
+    envelope: Envelope,
+    length_counter: LengthCounter,
 }
 
 impl PulseRegister {
-    pub fn new() -> Self {
+    pub fn new(ones_complement: bool) -> Self {
         Self {
             tone_volume_controller: ToneVolumeController::empty(),
             sweep_controller: SweepController::empty(),
-            lo_frequency: LoFrequency::empty(),
-            hi_frequency: HiFrequency::empty(),
+            ones_complement,
+            period: 0,
+            timer: 0,
+            sequence: 0,
+            sweep_divider: 0,
+            sweep_reload: false,
+            envelope: Envelope::default(),
+            length_counter: LengthCounter::default(),
         }
     }
 
     pub fn write(&mut self, addr: u16, data: u8) {
         match addr {
             0 => self.tone_volume_controller = ToneVolumeController::from_bits_truncate(data),
-            1 => self.sweep_controller = SweepController::from_bits_truncate(data),
-            2 => self.lo_frequency = LoFrequency::from_bits_truncate(data),
-            3 => self.hi_frequency = HiFrequency::from_bits_truncate(data),
+            1 => {
+                self.sweep_controller = SweepController::from_bits_truncate(data);
+                self.sweep_reload = true;
+            }
+            // NOTE: スイープも同じ周期を書き換えるので、書き込むのは片側のバイトだけ
+            2 => self.period = (self.period & 0x0700) | data as u16,
+            3 => {
+                let hi = data & HiFrequency::HI_FREQUENCY.bits();
+                self.period = (self.period & 0x00FF) | (hi as u16) << 8;
+                self.length_counter.load(data >> 3);
+                self.envelope.restart();
+                self.sequence = 0;
+            }
             _ => panic!("Invalid address: {}", addr),
         }
     }
 
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.length_counter.set_enabled(enabled);
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.length_counter.is_active()
+    }
+
+    // NOTE: APU サイクル (CPU の 2 サイクル) ごとに呼ぶ
+    pub fn tick(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.period;
+            self.sequence = self.sequence.wrapping_sub(1) & 0x07;
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    pub fn clock_quarter_frame(&mut self) {
+        self.envelope.clock(
+            self.tone_volume_controller.bits() & ToneVolumeController::VOLUME.bits(),
+            self.tone_volume_controller
+                .contains(ToneVolumeController::LOOP),
+        );
+    }
+
+    pub fn clock_half_frame(&mut self) {
+        self.length_counter.clock(
+            self.tone_volume_controller
+                .contains(ToneVolumeController::LOOP),
+        );
+
+        let shift = self.get_sweep_frequency();
+        if self.sweep_divider == 0 && self.get_sweep_enable() && shift > 0 && !self.is_muted() {
+            self.period = self.sweep_target();
+        }
+        if self.sweep_divider == 0 || self.sweep_reload {
+            self.sweep_divider = self.get_sweep_timer();
+            self.sweep_reload = false;
+        } else {
+            self.sweep_divider -= 1;
+        }
+    }
+
+    // NOTE: DAC に入る 0..=15 の値。エンベロープ、長さカウンタ、スイープによる消音を反映する
+    pub fn output(&self) -> u8 {
+        let duty = (self.tone_volume_controller.bits() >> 6) as usize;
+        if !self.length_counter.is_active()
+            || self.is_muted()
+            || DUTY_TABLE[duty][self.sequence as usize] == 0
+        {
+            return 0;
+        }
+        self.get_volume_level()
+    }
+
+    pub fn is_audible(&self) -> bool {
+        self.length_counter.is_active() && !self.is_muted()
+    }
+
+    // NOTE: 周期が短すぎるか、スイープの行き先が 11bit に収まらないと鳴らない
+    fn is_muted(&self) -> bool {
+        self.period < 8 || self.sweep_target() > 0x7FF
+    }
+
+    fn sweep_target(&self) -> u16 {
+        let change = self.period >> self.get_sweep_frequency();
+        match self.get_sweep_direction() {
+            SweepDirection::Increase => self.period + change,
+            SweepDirection::Decrease if self.ones_complement => {
+                self.period.saturating_sub(change + 1)
+            }
+            SweepDirection::Decrease => self.period.saturating_sub(change),
+        }
+    }
+
     pub fn get_duty(&self) -> f32 {
         match (
             self.tone_volume_controller
@@ -73,15 +178,16 @@ impl PulseRegister {
     }
 
     pub fn get_volume(&self) -> f32 {
-        if self
-            .tone_volume_controller
-            .contains(ToneVolumeController::CONSTANT_VOLUME)
-        {
-            (self.tone_volume_controller.bits() & ToneVolumeController::VOLUME.bits()) as f32 / 15.0
-        } else {
-            // TODO: ちゃんと計算する
-            (self.tone_volume_controller.bits() & ToneVolumeController::VOLUME.bits()) as f32 / 15.0
-        }
+        (self.tone_volume_controller.bits() & ToneVolumeController::VOLUME.bits()) as f32 / 15.0
+    }
+
+    // NOTE: 今エンベロープが出している音量
+    pub fn get_volume_level(&self) -> u8 {
+        self.envelope.volume(
+            self.tone_volume_controller
+                .contains(ToneVolumeController::CONSTANT_VOLUME),
+            self.tone_volume_controller.bits() & ToneVolumeController::VOLUME.bits(),
+        )
     }
 
     pub fn get_sweep_enable(&self) -> bool {
@@ -93,14 +199,15 @@ impl PulseRegister {
         (self.sweep_controller.bits() & SweepController::SWEEP_TIMER.bits()) >> 4
     }
 
-    pub fn get_sweep_direction(&self) -> SweepDirection {
+    fn get_sweep_direction(&self) -> SweepDirection {
+        // NOTE: ビットが立っていると周期が下がる (音が高くなる)
         if self
             .sweep_controller
             .contains(SweepController::SWEEP_DIRECTION)
         {
-            SweepDirection::Increase
-        } else {
             SweepDirection::Decrease
+        } else {
+            SweepDirection::Increase
         }
     }
 
@@ -109,8 +216,88 @@ impl PulseRegister {
     }
 
     pub fn get_frequency(&self) -> u16 {
-        let lo = self.lo_frequency.bits() as u16;
-        let hi = (self.hi_frequency.bits() & HiFrequency::HI_FREQUENCY.bits()) as u16;
-        (hi << 8) | lo
+        self.period
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::PulseRegister;
+
+    fn enabled_pulse(ones_complement: bool) -> PulseRegister {
+        let mut pulse = PulseRegister::new(ones_complement);
+        pulse.set_enabled(true);
+        pulse
+    }
+
+    #[test]
+    fn test_duty_and_length() {
+        let mut pulse = enabled_pulse(false);
+        // NOTE: デューティ 50%、定音量 15、周期 $10、長さ 2 (インデックス 3)
+        pulse.write(0, 0b1001_1111);
+        pulse.write(2, 0x10);
+        pulse.write(3, 0b0001_1000);
+
+        let mut outputs = vec![];
+        for _ in 0..8 * 0x11 {
+            pulse.tick();
+            outputs.push(pulse.output());
+        }
+        assert_eq!(outputs.iter().filter(|&&v| v == 15).count(), 4 * 0x11);
+        assert!(outputs.iter().all(|&v| v == 0 || v == 15));
+
+        pulse.clock_half_frame();
+        assert!(pulse.is_active());
+        pulse.clock_half_frame();
+        assert!(!pulse.is_active());
+        assert!((0..16).all(|_| {
+            pulse.tick();
+            pulse.output() == 0
+        }));
+    }
+
+    #[test]
+    fn test_envelope() {
+        let mut pulse = enabled_pulse(false);
+        // NOTE: エンベロープの周期 0 (1/4 フレームごとに 1 下がる)
+        pulse.write(0, 0b1000_0000);
+        pulse.write(2, 0x10);
+        pulse.write(3, 0x08);
+
+        pulse.clock_quarter_frame();
+        assert_eq!(pulse.get_volume_level(), 15);
+        for _ in 0..5 {
+            pulse.clock_quarter_frame();
+        }
+        assert_eq!(pulse.get_volume_level(), 10);
+    }
+
+    #[test]
+    fn test_sweep() {
+        let mut pulse = enabled_pulse(false);
+        pulse.write(0, 0b1011_1111);
+        pulse.write(2, 0x00);
+        pulse.write(3, 0x09);
+        // NOTE: 有効、分周器 0、上げる、シフト 1
+        pulse.write(1, 0b1000_0001);
+
+        for expected in [0x180, 0x240, 0x360, 0x510, 0x798] {
+            pulse.clock_half_frame();
+            assert_eq!(pulse.get_frequency(), expected);
+        }
+        // NOTE: 行き先が $7FF を超えるので止まり、音も出ない
+        pulse.clock_half_frame();
+        assert_eq!(pulse.get_frequency(), 0x798);
+        assert_eq!(pulse.output(), 0);
+
+        // NOTE: 下げる向きは矩形波 1 だけ 1 多く下がる
+        for (ones_complement, expected) in [(true, 0x100 - 0x80 - 1), (false, 0x100 - 0x80)] {
+            let mut pulse = enabled_pulse(ones_complement);
+            pulse.write(2, 0x00);
+            pulse.write(3, 0x09);
+            pulse.write(1, 0b1000_1001);
+            pulse.clock_half_frame();
+            assert_eq!(pulse.get_frequency(), expected);
+        }
     }
 }
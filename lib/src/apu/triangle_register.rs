@@ -1,45 +1,144 @@
 use bitflags::bitflags;
 
+use super::length_counter::LengthCounter;
+
 bitflags! {
     struct ToneControl: u8 {
+        // NOTE: 長さカウンタの停止と、線形カウンタを毎回リロードし続けるフラグを兼ねる
         const LENGTH_COUNTER_HALT = 0b1000_0000;
         const LENGTH = 0b0111_1111;
     }
-    struct LoFrequency: u8 {
-        const LO_FREQUENCY = 0b1111_1111;
-    }
     struct HiFrequency: u8 {
         const HI_FREQUENCY = 0b0000_0111;
         const KEY_ON = 0b1111_1000;
     }
 }
 
+const SEQUENCE: [u8; 32] = [
+    15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12,
+    13, 14, 15,
+];
+
 pub struct TriangleRegister {
     tone_control: ToneControl,
-    lo_frequency: LoFrequency,
-    hi_frequency: HiFrequency,
+    period: u16,
+    timer: u16,
+    sequence: u8,
+    linear_counter: u8,
+    linear_reload: bool,
+    length_counter: LengthCounter,
 }
 
 impl TriangleRegister {
     pub fn new() -> Self {
         Self {
             tone_control: ToneControl::empty(),
-            lo_frequency: LoFrequency::empty(),
-            hi_frequency: HiFrequency::empty(),
+            period: 0,
+            timer: 0,
+            sequence: 0,
+            linear_counter: 0,
+            linear_reload: false,
+            length_counter: LengthCounter::default(),
         }
     }
 
     pub fn write(&mut self, addr: u16, data: u8) {
         match addr {
             0 => self.tone_control = ToneControl::from_bits_truncate(data),
-            1 => eprintln!("unused register: {}", addr),
-            2 => self.lo_frequency = LoFrequency::from_bits_truncate(data),
-            3 => self.hi_frequency = HiFrequency::from_bits_truncate(data),
-            _ => eprintln!("Not implemented: {:04X}", addr),
+            2 => self.period = (self.period & 0x0700) | data as u16,
+            3 => {
+                let hi = data & HiFrequency::HI_FREQUENCY.bits();
+                self.period = (self.period & 0x00FF) | (hi as u16) << 8;
+                self.length_counter.load(data >> 3);
+                self.linear_reload = true;
+            }
+            // NOTE: $4009 は未使用
+            _ => {}
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.length_counter.set_enabled(enabled);
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.length_counter.is_active()
+    }
+
+    // NOTE: CPU の 1 サイクルごとに呼ぶ。どちらかのカウンタが 0 ならシーケンサは止まり、最後の値を出し続ける
+    pub fn tick(&mut self) {
+        if self.timer > 0 {
+            self.timer -= 1;
+            return;
+        }
+        self.timer = self.period;
+        if self.linear_counter > 0 && self.length_counter.is_active() {
+            self.sequence = (self.sequence + 1) & 0x1F;
+        }
+    }
+
+    pub fn clock_quarter_frame(&mut self) {
+        let control = self.tone_control.contains(ToneControl::LENGTH_COUNTER_HALT);
+        if self.linear_reload {
+            self.linear_counter = self.tone_control.bits() & ToneControl::LENGTH.bits();
+        } else if self.linear_counter > 0 {
+            self.linear_counter -= 1;
         }
+        if !control {
+            self.linear_reload = false;
+        }
+    }
+
+    pub fn clock_half_frame(&mut self) {
+        self.length_counter
+            .clock(self.tone_control.contains(ToneControl::LENGTH_COUNTER_HALT));
+    }
+
+    pub fn output(&self) -> u8 {
+        SEQUENCE[self.sequence as usize]
+    }
+
+    // NOTE: シーケンサが進んでいるか。周期が 2 未満だと超音波になって聞こえない
+    pub fn is_playing(&self) -> bool {
+        self.linear_counter > 0 && self.length_counter.is_active() && self.period >= 2
     }
 
     pub fn get_frequency(&self) -> u16 {
-        (self.hi_frequency.bits() as u16) << 8 | self.lo_frequency.bits() as u16
+        self.period
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::TriangleRegister;
+
+    #[test]
+    fn test_linear_counter() {
+        let mut triangle = TriangleRegister::new();
+        triangle.set_enabled(true);
+        // NOTE: 線形カウンタ 2、周期 0 (1 サイクルごとに進む)
+        triangle.write(0, 0x02);
+        triangle.write(2, 0x00);
+        triangle.write(3, 0x08);
+
+        triangle.tick();
+        assert_eq!(triangle.output(), 15);
+
+        triangle.clock_quarter_frame();
+        let outputs = (0..32)
+            .map(|_| {
+                triangle.tick();
+                triangle.output()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(outputs[..3], [14, 13, 12]);
+        assert_eq!(outputs[31], 15);
+
+        triangle.clock_quarter_frame();
+        triangle.clock_quarter_frame();
+        assert!(!triangle.is_playing());
+        let last = triangle.output();
+        triangle.tick();
+        assert_eq!(triangle.output(), last);
     }
 }
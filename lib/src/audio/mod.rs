@@ -0,0 +1,341 @@
+use std::{f32::consts::PI, fmt::Display};
+
+use crate::region::Region;
+
+pub const DEFAULT_SAMPLE_RATE: u32 = 44_100;
+
+// NOTE: 実機の出力段にある 1 次のフィルタ (ハイパス 2 段とローパス 1 段) のカットオフ周波数
+const HIGH_PASS_HZ: [f32; 2] = [90.0, 440.0];
+const LOW_PASS_HZ: f32 = 14_000.0;
+// NOTE: スピーカーへまとめて送る拡張音源のサンプル数。44.1kHz で 12ms ほど
+const EXPANSION_CHUNK: usize = 512;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    Pulse1,
+    Pulse2,
+    Triangle,
+    Noise,
+    Dmc,
+    Expansion,
+}
+
+impl Channel {
+    pub const ALL: [Channel; 6] = [
+        Channel::Pulse1,
+        Channel::Pulse2,
+        Channel::Triangle,
+        Channel::Noise,
+        Channel::Dmc,
+        Channel::Expansion,
+    ];
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+impl Display for Channel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Channel::Pulse1 => "pulse1",
+            Channel::Pulse2 => "pulse2",
+            Channel::Triangle => "triangle",
+            Channel::Noise => "noise",
+            Channel::Dmc => "dmc",
+            Channel::Expansion => "expansion",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// NOTE: -1.0..=1.0 のモノラル。mixed は APU の非線形ミキサーの出力で、channels は Channel::ALL と同じ並びで
+//       各チャンネルを単独でミキサーに通した出力。どちらも実機と同じフィルタで直流分を取り除いてある
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AudioBuffer {
+    pub sample_rate: u32,
+    pub mixed: Vec<f32>,
+    pub channels: [Vec<f32>; 6],
+}
+
+impl AudioBuffer {
+    pub fn channel(&self, channel: Channel) -> &[f32] {
+        &self.channels[channel.index()]
+    }
+
+    pub fn len(&self) -> usize {
+        self.mixed.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mixed.is_empty()
+    }
+}
+
+// NOTE: APU が CPU サイクルごとに出す値を、1 サンプルの間で平均して sample_rate に間引く
+pub(crate) struct Sampler {
+    sample_rate: u32,
+    cycles_per_sample: f32,
+    cycles: f32,
+    count: u32,
+    mixed: f32,
+    channels: [f32; 6],
+    mixed_filter: OutputFilter,
+    channel_filters: [OutputFilter; 6],
+    buffer: AudioBuffer,
+}
+
+impl Sampler {
+    pub(crate) fn new(sample_rate: u32, region: Region) -> Self {
+        let filter = OutputFilter::new(sample_rate);
+        Self {
+            sample_rate,
+            cycles_per_sample: region.cpu_clock() / sample_rate as f32,
+            cycles: 0.0,
+            count: 0,
+            mixed: 0.0,
+            channels: [0.0; 6],
+            mixed_filter: filter,
+            channel_filters: [filter; 6],
+            buffer: AudioBuffer {
+                sample_rate,
+                ..Default::default()
+            },
+        }
+    }
+
+    pub(crate) fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub(crate) fn set_region(&mut self, region: Region) {
+        self.cycles_per_sample = region.cpu_clock() / self.sample_rate as f32;
+    }
+
+    // NOTE: CPU の 1 サイクルごとに呼ぶ
+    pub(crate) fn push(&mut self, mixed: f32, channels: [f32; 6]) {
+        self.mixed += mixed;
+        for (sum, value) in self.channels.iter_mut().zip(channels) {
+            *sum += value;
+        }
+        self.count += 1;
+
+        self.cycles += 1.0;
+        if self.cycles >= self.cycles_per_sample {
+            self.cycles -= self.cycles_per_sample;
+            self.sample();
+        }
+    }
+
+    pub(crate) fn take(&mut self) -> AudioBuffer {
+        let empty = AudioBuffer {
+            sample_rate: self.sample_rate,
+            ..Default::default()
+        };
+        std::mem::replace(&mut self.buffer, empty)
+    }
+
+    fn sample(&mut self) {
+        let count = self.count as f32;
+        let mixed = self.mixed_filter.process(self.mixed / count);
+        self.buffer.mixed.push(mixed.clamp(-1.0, 1.0));
+
+        for ((channel, filter), sum) in self
+            .buffer
+            .channels
+            .iter_mut()
+            .zip(self.channel_filters.iter_mut())
+            .zip(self.channels)
+        {
+            channel.push(filter.process(sum / count).clamp(-1.0, 1.0));
+        }
+
+        self.mixed = 0.0;
+        self.channels = [0.0; 6];
+        self.count = 0;
+    }
+}
+
+// NOTE: 1 次の RC フィルタ。alpha はサンプリングレートとカットオフ周波数から決まる
+#[derive(Clone, Copy)]
+struct RcFilter {
+    high_pass: bool,
+    alpha: f32,
+    input: f32,
+    output: f32,
+}
+
+impl RcFilter {
+    fn new(high_pass: bool, sample_rate: u32, cutoff: f32) -> Self {
+        let rc = 1.0 / (2.0 * PI * cutoff);
+        let dt = 1.0 / sample_rate as f32;
+        let alpha = if high_pass {
+            rc / (rc + dt)
+        } else {
+            dt / (rc + dt)
+        };
+        Self {
+            high_pass,
+            alpha,
+            input: 0.0,
+            output: 0.0,
+        }
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        self.output = if self.high_pass {
+            self.alpha * (self.output + input - self.input)
+        } else {
+            self.output + self.alpha * (input - self.output)
+        };
+        self.input = input;
+        self.output
+    }
+}
+
+// NOTE: 途中から録り始めても直流分でプツッと鳴らないよう、最初の入力がずっと続いていたことにする
+#[derive(Clone, Copy)]
+struct OutputFilter {
+    filters: [RcFilter; 3],
+    primed: bool,
+}
+
+impl OutputFilter {
+    fn new(sample_rate: u32) -> Self {
+        Self {
+            filters: [
+                RcFilter::new(true, sample_rate, HIGH_PASS_HZ[0]),
+                RcFilter::new(true, sample_rate, HIGH_PASS_HZ[1]),
+                RcFilter::new(false, sample_rate, LOW_PASS_HZ),
+            ],
+            primed: false,
+        }
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        if !self.primed {
+            self.filters[0].input = input;
+            self.primed = true;
+        }
+        self.filters
+            .iter_mut()
+            .fold(input, |value, filter| filter.process(value))
+    }
+}
+
+// NOTE: 拡張音源の出力をスピーカー向けに CPU サイクルに合わせて間引き、EXPANSION_CHUNK 個たまったら返す
+pub(crate) struct ExpansionStream {
+    sample_rate: u32,
+    cycles_per_sample: f32,
+    cycles: f32,
+    samples: Vec<f32>,
+}
+
+impl ExpansionStream {
+    pub(crate) fn new(sample_rate: u32, region: Region) -> Self {
+        Self {
+            sample_rate,
+            cycles_per_sample: region.cpu_clock() / sample_rate as f32,
+            cycles: 0.0,
+            samples: Vec::with_capacity(EXPANSION_CHUNK),
+        }
+    }
+
+    pub(crate) fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub(crate) fn set_region(&mut self, region: Region) {
+        self.cycles_per_sample = region.cpu_clock() / self.sample_rate as f32;
+    }
+
+    pub(crate) fn tick(&mut self, cycles: u8, output: f32) -> Option<Vec<f32>> {
+        self.cycles += cycles as f32;
+        while self.cycles >= self.cycles_per_sample {
+            self.cycles -= self.cycles_per_sample;
+            self.samples.push(output);
+        }
+
+        if self.samples.len() < EXPANSION_CHUNK {
+            return None;
+        }
+        Some(std::mem::replace(
+            &mut self.samples,
+            Vec::with_capacity(EXPANSION_CHUNK),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::region::Region;
+
+    use super::{Channel, ExpansionStream, Sampler, EXPANSION_CHUNK};
+
+    #[test]
+    fn test_sample_count() {
+        let mut sampler = Sampler::new(44_100, Region::Ntsc);
+        // NOTE: 1 秒分
+        for _ in 0..1_789_773 {
+            sampler.push(0.0, [0.0; 6]);
+        }
+
+        let buffer = sampler.take();
+        assert!((44_099..=44_100).contains(&buffer.len()));
+        assert!(buffer.channels.iter().all(|c| c.len() == buffer.len()));
+        assert!(sampler.take().is_empty());
+    }
+
+    #[test]
+    fn test_square() {
+        let mut sampler = Sampler::new(44_100, Region::Ntsc);
+        // NOTE: 矩形波 1 だけが 441Hz (4058 サイクル周期) で鳴っている
+        for n in 0..1_789_773 / 10 {
+            let value = if n % 4058 < 2029 { 0.15 } else { 0.0 };
+            sampler.push(value, [value, 0.0, 0.0, 0.0, 0.0, 0.0]);
+        }
+
+        let buffer = sampler.take();
+        let pulse1 = buffer.channel(Channel::Pulse1);
+        // NOTE: 直流分が抜けて 0 を挟んで振れ、無音のチャンネルは 0 のまま
+        let last = &pulse1[pulse1.len() - 100..];
+        assert!(last.iter().any(|&v| v > 0.05));
+        assert!(last.iter().any(|&v| v < -0.05));
+        assert_eq!(buffer.mixed, pulse1);
+        assert!(buffer.channel(Channel::Triangle).iter().all(|&v| v == 0.0));
+    }
+
+    #[test]
+    fn test_dc_is_removed() {
+        let mut sampler = Sampler::new(44_100, Region::Ntsc);
+        // NOTE: 最初から続いている直流分は出ない
+        for _ in 0..4058 {
+            sampler.push(0.25, [0.0, 0.0, 0.0, 0.0, 0.25, 0.0]);
+        }
+        for _ in 0..1_789_773 / 10 {
+            sampler.push(0.5, [0.0, 0.0, 0.0, 0.0, 0.5, 0.0]);
+        }
+
+        let buffer = sampler.take();
+        assert!(buffer.mixed[..99].iter().all(|&v| v == 0.0));
+        assert!(buffer.mixed[101] > 0.1);
+        assert!(buffer.mixed.last().unwrap().abs() < 0.001);
+        assert_eq!(buffer.channel(Channel::Dmc), buffer.mixed);
+    }
+
+    #[test]
+    fn test_expansion_stream() {
+        let mut stream = ExpansionStream::new(44_100, Region::Ntsc);
+        let mut chunks = vec![];
+        // NOTE: 1 秒分
+        for n in 0..1_789_773 / 3 {
+            let output = if n < 1_789_773 / 6 { 0.5 } else { 1.0 };
+            chunks.extend(stream.tick(3, output));
+        }
+
+        assert_eq!(chunks.len(), 44_100 / EXPANSION_CHUNK);
+        assert!(chunks.iter().all(|c| c.len() == EXPANSION_CHUNK));
+        assert_eq!(chunks[0][0], 0.5);
+        assert_eq!(chunks.last().unwrap()[EXPANSION_CHUNK - 1], 1.0);
+    }
+}
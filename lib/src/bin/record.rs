@@ -0,0 +1,102 @@
+use std::{fs::File, io::BufWriter, process::ExitCode};
+
+use lib::{
+    audio::DEFAULT_SAMPLE_RATE,
+    debugger::RunMode,
+    emulator::Emulator,
+    record::Recorder,
+    testrom::{parse_input_script, NullRenderer, NullSpeaker, ScriptedJoypadHandler},
+};
+
+const USAGE: &str = "usage: record <rom> --frames <n> [--input <script> | --input-file <path>] [--y4m <path>] [--wav <path>] [--gif <path>]";
+
+#[derive(Default)]
+struct Args {
+    rom: Option<String>,
+    frames: Option<usize>,
+    input: String,
+    y4m: Option<String>,
+    wav: Option<String>,
+    gif: Option<String>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args::default();
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().ok_or(format!("Missing value: {}", arg));
+        match arg.as_str() {
+            "--frames" => {
+                let v = value()?;
+                args.frames = Some(v.parse().map_err(|_| format!("Invalid frames: {}", v))?);
+            }
+            "--input" => args.input = value()?,
+            "--input-file" => {
+                let path = value()?;
+                args.input =
+                    std::fs::read_to_string(&path).map_err(|e| format!("{}: {}", path, e))?;
+            }
+            "--y4m" => args.y4m = Some(value()?),
+            "--wav" => args.wav = Some(value()?),
+            "--gif" => args.gif = Some(value()?),
+            _ if arg.starts_with("--") => return Err(format!("Unknown option: {}", arg)),
+            _ => args.rom = Some(arg),
+        }
+    }
+    Ok(args)
+}
+
+fn create(path: &str) -> Result<BufWriter<File>, String> {
+    File::create(path)
+        .map(BufWriter::new)
+        .map_err(|e| format!("{}: {}", path, e))
+}
+
+fn record(args: Args) -> Result<usize, String> {
+    let (Some(rom), Some(frames)) = (args.rom, args.frames) else {
+        return Err(USAGE.to_string());
+    };
+    let rom_data = std::fs::read(&rom).map_err(|e| format!("{}: {}", rom, e))?;
+    let script = parse_input_script(&args.input)?;
+
+    let mut emulator = Emulator::new(
+        rom_data,
+        NullSpeaker,
+        ScriptedJoypadHandler::new(script),
+        NullRenderer,
+    );
+    emulator.reset();
+
+    let mut recorder = Recorder::new(emulator.frame_rate(), DEFAULT_SAMPLE_RATE);
+    if let Some(path) = &args.y4m {
+        recorder.set_video(Box::new(create(path)?))?;
+    }
+    if let Some(path) = &args.wav {
+        recorder.set_audio(Box::new(create(path)?))?;
+    }
+    if let Some(path) = &args.gif {
+        recorder.set_gif(Box::new(create(path)?), 2)?;
+    }
+    emulator.start_recording(recorder)?;
+
+    while emulator.frame_count() < frames && !emulator.is_halted() {
+        emulator.run(RunMode::ToFrame, 100_000);
+    }
+
+    let recorded = emulator.recorder().map_or(0, |r| r.frames());
+    emulator.stop_recording().unwrap_or(Ok(()))?;
+    Ok(recorded)
+}
+
+fn main() -> ExitCode {
+    match parse_args().and_then(record) {
+        Ok(frames) => {
+            println!("recorded {} frames", frames);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use std::process::ExitCode;
+
+use lib::testrom::{self, TestStatus, DEFAULT_TIMEOUT_FRAMES};
+
+fn main() -> ExitCode {
+    let paths = std::env::args().skip(1).collect::<Vec<_>>();
+    if paths.is_empty() {
+        eprintln!("usage: testrom <rom>...");
+        return ExitCode::from(2);
+    }
+
+    let mut failed = 0;
+    for path in &paths {
+        let rom_data = match std::fs::read(path) {
+            Ok(data) => data,
+            Err(e) => {
+                eprintln!("{}: {}", path, e);
+                failed += 1;
+                continue;
+            }
+        };
+
+        let outcome = testrom::run(rom_data, DEFAULT_TIMEOUT_FRAMES);
+        println!("{}: {} in {} frames", path, outcome.status, outcome.frames);
+        for line in outcome.message.lines() {
+            println!("    {}", line);
+        }
+        if outcome.status != TestStatus::Passed {
+            failed += 1;
+        }
+    }
+
+    if failed > 0 {
+        println!("{} of {} failed", failed, paths.len());
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
@@ -1,9 +1,16 @@
 use crate::{
     apu::APU,
+    audio::AudioBuffer,
+    debugger::{AddressSpace, Debugger},
+    diagnostics::Diagnostics,
     joypad::{register::Joypad, JoypadHandler},
+    mapper::{Cartridge, Mapper},
     ppu::PPU,
-    render::{utils::frame::Frame, Renderer},
-    rom::Rom,
+    region::Region,
+    render::{
+        utils::{frame::Frame, palette::Palette},
+        Renderer,
+    },
     speaker::Speaker,
 };
 
@@ -19,6 +26,11 @@ pub(crate) trait Bus {
     fn poll_nmi_status(&mut self) -> Option<bool>;
     fn get_cycles(&self) -> (usize, usize);
     fn get_scanline(&self) -> u16;
+
+    // NOTE: IRQ はレベルトリガーなので、要求している間ずっと true を返す
+    fn poll_irq_status(&self) -> bool {
+        false
+    }
 }
 
 pub struct NESBus<S, J, R>
@@ -28,11 +40,17 @@ where
     R: Renderer,
 {
     cpu_vram: [u8; 0x0800],
-    prg_rom: Vec<u8>,
+    mapper: Box<dyn Mapper>,
     ppu: PPU,
     apu: APU<S>,
     joypad: Joypad,
+    open_bus: u8,
     cycles: usize,
+    ppu_clock_remainder: u16,
+    region: Region,
+    frame: Frame,
+    diagnostics: Diagnostics,
+    debugger: Debugger,
     joypad_handler: J,
     renderer: R,
 }
@@ -43,31 +61,182 @@ where
     J: JoypadHandler,
     R: Renderer,
 {
-    pub(crate) fn new(rom: Rom, speaker: S, joypad_handler: J, renderer: R) -> Self {
-        let ppu = PPU::new(rom.chr_rom, rom.is_chr_ram, rom.screen_mirroring);
-        let apu = APU::new(speaker);
+    pub(crate) fn new(cartridge: Cartridge, speaker: S, joypad_handler: J, renderer: R) -> Self {
+        let region = cartridge.region.unwrap_or_default();
+        let diagnostics = Diagnostics::default();
+        let mut ppu = PPU::new(cartridge.chr_rom, cartridge.is_chr_ram, cartridge.mirroring);
+        ppu.set_diagnostics(diagnostics.clone());
+        let mut mapper = cartridge.mapper;
+        mapper.set_diagnostics(diagnostics.clone());
+        let mut apu = APU::new(speaker);
+        apu.set_diagnostics(diagnostics.clone());
         let joypad = Joypad::new();
 
-        Self {
+        let mut bus = Self {
             cpu_vram: [0; 0x0800],
-            prg_rom: rom.prg_rom,
+            mapper,
             ppu,
             apu,
             joypad,
+            open_bus: 0,
             joypad_handler,
             renderer,
             cycles: 0,
+            ppu_clock_remainder: 0,
+            region,
+            frame: Frame::new(),
+            diagnostics,
+            debugger: Debugger::default(),
+        };
+        bus.set_region(region);
+        bus.sync_mapper();
+
+        bus
+    }
+
+    pub(crate) fn set_region(&mut self, region: Region) {
+        self.region = region;
+        self.ppu_clock_remainder = 0;
+        self.ppu.set_region(region);
+        self.apu.set_region(region);
+    }
+
+    pub(crate) fn get_region(&self) -> Region {
+        self.region
+    }
+
+    pub(crate) fn set_palette(&mut self, palette: Palette) {
+        self.frame.set_palette(palette);
+    }
+
+    pub(crate) fn diagnostics(&self) -> &Diagnostics {
+        &self.diagnostics
+    }
+
+    pub(crate) fn debugger(&self) -> &Debugger {
+        &self.debugger
+    }
+
+    pub(crate) fn debugger_mut(&mut self) -> &mut Debugger {
+        &mut self.debugger
+    }
+
+    pub(crate) fn get_frame_count(&self) -> usize {
+        self.ppu.get_frame_count()
+    }
+
+    pub(crate) fn renderer_mut(&mut self) -> &mut R {
+        &mut self.renderer
+    }
+
+    pub(crate) fn frame(&self) -> &Frame {
+        &self.frame
+    }
+
+    pub(crate) fn set_audio_capture(&mut self, sample_rate: Option<u32>) {
+        self.apu.set_audio_capture(sample_rate);
+    }
+
+    pub(crate) fn audio_sample_rate(&self) -> Option<u32> {
+        self.apu.audio_sample_rate()
+    }
+
+    pub(crate) fn take_audio(&mut self) -> Option<AudioBuffer> {
+        self.apu.take_audio()
+    }
+
+    // NOTE: OAM DMA の間 CPU は止まる。書き込みの完了待ちで 1 サイクル、奇数サイクルから始まる場合は
+    //       読み込みに揃えるためさらに 1 サイクル待ち、その後 256 バイトを読み書き 2 サイクルずつで転送する (513 / 514 サイクル)。
+    //       途中で DMC がサンプルを読むときは、OAM DMA の読み込みサイクルを 1 つ借りて読み、揃え直しに 1 サイクルかかる
+    fn run_oam_dma(&mut self, page: u8) {
+        self.clock(1);
+        if self.cycles % 2 == 1 {
+            self.clock(1);
         }
+
+        let base = (page as u16) << 8;
+        for i in 0..256 {
+            self.run_dmc_dma(DMC_DMA_IN_OAM_DMA_CYCLES);
+            self.clock(1);
+            let value = self.mem_read(base + i);
+            self.clock(1);
+            self.ppu.write_to_io_latch(value);
+            self.ppu.write_to_oam_data(value);
+        }
+        // NOTE: 最後の書き込みサイクルで読みたくなった場合は、CPU はすでに止まっているので停止の 1 サイクルがいらない
+        self.run_dmc_dma(DMC_DMA_CYCLES - 1);
     }
 
-    fn read_prg_rom(&self, addr: u16) -> u8 {
-        let prog_addr = addr - 0x8000;
-        let prog_addr = if self.prg_rom.len() == 0x4000 && prog_addr >= 0x4000 {
-            prog_addr % 0x4000
-        } else {
-            prog_addr
+    // NOTE: DMC がサンプルを読む間 CPU は止まる。止まるまでの 1 サイクル、空読み 1 サイクル、読み込みに揃える 1 サイクルのあと、
+    //       最後のサイクルで読む (4 サイクル)。サンプルは必ず $8000-$FFFF から読む
+    fn run_dmc_dma(&mut self, cycles: u8) {
+        let Some(addr) = self.apu.dmc_fetch_address() else {
+            return;
         };
-        self.prg_rom[prog_addr as usize]
+        self.clock(cycles - 1);
+        let data = self.mapper.peek(addr).unwrap_or(self.open_bus);
+        self.clock(1);
+        self.apu.dmc_fill(data);
+    }
+
+    fn clock(&mut self, cycles: u8) {
+        self.cycles += cycles as usize;
+        self.mapper.tick(cycles);
+        self.apu.set_expansion_output(self.mapper.audio_output());
+        self.apu.tick(cycles);
+
+        let (numerator, denominator) = self.region.ppu_clock_ratio();
+        let dots = cycles as u16 * numerator + self.ppu_clock_remainder;
+        self.ppu_clock_remainder = dots % denominator;
+
+        let nmi_before = self.ppu.get_nmi_interrupt().is_some();
+        self.ppu.tick((dots / denominator) as u8);
+        let nmi_after = self.ppu.get_nmi_interrupt().is_some();
+
+        if !nmi_before && nmi_after {
+            let mapper = &mut self.mapper;
+            self.frame.render(&mut self.ppu, &mut |addr| {
+                if mapper.observe_pattern_fetch(addr) {
+                    mapper.chr_banks()
+                } else {
+                    None
+                }
+            });
+            self.renderer.render(&self.frame);
+            self.joypad_handler.handle(&mut self.joypad);
+        }
+    }
+
+    pub(crate) fn mapper(&self) -> &dyn Mapper {
+        self.mapper.as_ref()
+    }
+
+    pub(crate) fn mapper_mut(&mut self) -> &mut dyn Mapper {
+        self.mapper.as_mut()
+    }
+
+    // NOTE: 副作用なしで読む。RAM とカートリッジのメモリ以外はオープンバスの値を返す
+    pub(crate) fn peek(&self, addr: u16) -> u8 {
+        match addr {
+            RAM..=RAM_MIRRORS_END => self.cpu_vram[(addr & 0x07FF) as usize],
+            CARTRIDGE..=CARTRIDGE_END => self.mapper.peek(addr).unwrap_or(self.open_bus),
+            _ => self.open_bus,
+        }
+    }
+
+    fn write_mapper(&mut self, addr: u16, data: u8) {
+        self.mapper.write(addr, data);
+        self.sync_mapper();
+    }
+
+    // NOTE: マッパーが切り替えたミラーリングと CHR のバンクを PPU に反映する
+    fn sync_mapper(&mut self) {
+        if let Some(mirroring) = self.mapper.mirroring() {
+            self.ppu.set_mirroring(mirroring);
+        }
+        if let Some(banks) = self.mapper.chr_banks() {
+            self.ppu.set_chr_banks(banks);
+        }
     }
 }
 
@@ -85,14 +254,18 @@ const PPU_DATA_REGISTERS: u16 = 0x2007;
 const PPU_REGISTERS_MIRRORS_END: u16 = 0x3FFF;
 const PPU_OAM_DAM_REGISTERS: u16 = 0x4014;
 
+const DMC_DMA_CYCLES: u8 = 4;
+const DMC_DMA_IN_OAM_DMA_CYCLES: u8 = 2;
+
 const APU: u16 = 0x4000;
 const APU_END: u16 = 0x4017;
+const APU_STATUS_REGISTERS: u16 = 0x4015;
 
 const JOYPAD1_READ_REGISTERS: u16 = 0x4016;
 const JOYPAD2_READ_REGISTERS: u16 = 0x4017;
 
-const ROM: u16 = 0x8000;
-const ROM_END: u16 = 0xFFFF;
+const CARTRIDGE: u16 = 0x4020;
+const CARTRIDGE_END: u16 = 0xFFFF;
 
 impl<S, J, R> Mem for NESBus<S, J, R>
 where
@@ -100,8 +273,9 @@ where
     J: JoypadHandler,
     R: Renderer,
 {
+    // NOTE: 何も応答しないアドレスやビットには、データバスに最後に乗った値 (オープンバス) が見える
     fn mem_read(&mut self, addr: u16) -> u8 {
-        match addr {
+        let value = match addr {
             RAM..=RAM_MIRRORS_END => {
                 let mirror_down_addr = addr & 0x07FF;
                 self.cpu_vram[mirror_down_addr as usize]
@@ -110,32 +284,42 @@ where
             | PPU_MASK_REGISTERS
             | PPU_OAM_ADDRESS_REGISTERS
             | PPU_SCROLL_REGISTERS
-            | PPU_ADDRESS_REGISTERS
-            | PPU_OAM_DAM_REGISTERS => {
-                eprintln!("Attempt to read from write-only PPU address {:#04X}", addr);
-                0
-            }
+            | PPU_ADDRESS_REGISTERS => self.ppu.read_io_latch(),
             PPU_STATUS_REGISTERS => self.ppu.read_status(),
             PPU_OAM_DATA_REGISTERS => self.ppu.read_oam_data(),
-            PPU_DATA_REGISTERS => self.ppu.read_data(),
+            PPU_DATA_REGISTERS => {
+                let vram_addr = self.ppu.get_vram_addr();
+                let value = self.ppu.read_data();
+                self.debugger
+                    .check_access(AddressSpace::Ppu, vram_addr, value, false);
+                value
+            }
             0x2008..=PPU_REGISTERS_MIRRORS_END => {
                 let mirror_down_addr = addr & 0x2007;
                 self.mem_read(mirror_down_addr)
             }
-            JOYPAD1_READ_REGISTERS => self.joypad.read(),
-            JOYPAD2_READ_REGISTERS => {
-                todo!("Ignoring read from joypad 2");
-            }
-            APU..=APU_END => self.apu.read(addr),
-            ROM..=ROM_END => self.read_prg_rom(addr),
-            _ => {
-                eprintln!("Ignoring mem access at {:#04X}", addr);
-                0xFF
-            }
-        }
+            // NOTE: コントローラーが駆動するのは下位ビットだけ
+            JOYPAD1_READ_REGISTERS => (self.open_bus & 0xE0) | self.joypad.read(),
+            JOYPAD2_READ_REGISTERS => self.open_bus & 0xE0,
+            APU_STATUS_REGISTERS => self.apu.read(addr),
+            CARTRIDGE..=CARTRIDGE_END => self.mapper.read(addr).unwrap_or(self.open_bus),
+            _ => self.open_bus,
+        };
+
+        self.open_bus = value;
+        self.debugger
+            .check_access(AddressSpace::Cpu, addr, value, false);
+        value
     }
 
     fn mem_write(&mut self, addr: u16, data: u8) {
+        self.open_bus = data;
+        self.debugger
+            .check_access(AddressSpace::Cpu, addr, data, true);
+        if let PPU_CONTROL_REGISTERS..=PPU_DATA_REGISTERS = addr {
+            self.ppu.write_to_io_latch(data);
+        }
+
         match addr {
             RAM..=RAM_MIRRORS_END => {
                 let mirror_down_addr = addr & 0x07FF;
@@ -147,9 +331,7 @@ where
             PPU_MASK_REGISTERS => {
                 self.ppu.write_to_mask(data);
             }
-            PPU_STATUS_REGISTERS => {
-                eprintln!("Ignoring write to PPU status registers");
-            }
+            PPU_STATUS_REGISTERS => {}
             PPU_OAM_ADDRESS_REGISTERS => {
                 self.ppu.write_to_oam_addr(data);
             }
@@ -163,23 +345,20 @@ where
                 self.ppu.write_to_addr(data);
             }
             PPU_DATA_REGISTERS => {
+                let vram_addr = self.ppu.get_vram_addr();
                 self.ppu.write_to_data(data);
+                self.debugger
+                    .check_access(AddressSpace::Ppu, vram_addr, data, true);
             }
             PPU_OAM_DAM_REGISTERS => {
-                let addr = (data as u16) << 8;
-                let mut data = [0; 256];
-                for i in 0..256 {
-                    data[i] = self.mem_read(addr + i as u16);
-                }
-
-                self.ppu.write_to_oam_dma(&data);
+                self.run_oam_dma(data);
             }
             0x2008..=PPU_REGISTERS_MIRRORS_END => {
                 let mirror_down_addr = addr & 0x2007;
                 self.mem_write(mirror_down_addr, data);
             }
-            ROM..=ROM_END => {
-                eprintln!("Ignoring write to ROM at {:#04X}", addr);
+            CARTRIDGE..=CARTRIDGE_END => {
+                self.write_mapper(addr, data);
             }
             JOYPAD1_READ_REGISTERS => {
                 self.joypad.write(data);
@@ -187,9 +366,7 @@ where
             APU..=APU_END => {
                 self.apu.write(addr, data);
             }
-            _ => {
-                println!("Ignoring mem write-access at {:#04X}", addr);
-            }
+            _ => {}
         }
     }
 
@@ -214,18 +391,8 @@ where
     R: Renderer,
 {
     fn tick(&mut self, cycles: u8) {
-        self.cycles += cycles as usize;
-
-        let nmi_before = self.ppu.get_nmi_interrupt().is_some();
-        self.ppu.tick(cycles * 3);
-        let nmi_after = self.ppu.get_nmi_interrupt().is_some();
-
-        if !nmi_before && nmi_after {
-            let mut frame = Frame::new();
-            frame.render(&self.ppu);
-            self.renderer.render(&frame);
-            self.joypad_handler.handle(&mut self.joypad);
-        }
+        self.clock(cycles);
+        self.run_dmc_dma(DMC_DMA_CYCLES);
     }
 
     fn poll_nmi_status(&mut self) -> Option<bool> {
@@ -239,4 +406,126 @@ where
     fn get_scanline(&self) -> u16 {
         self.ppu.get_scanline()
     }
+
+    fn poll_irq_status(&self) -> bool {
+        self.mapper.irq() || self.apu.irq()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        diagnostics::Category,
+        rom::Rom,
+        testrom::{NullJoypadHandler, NullRenderer, NullSpeaker},
+    };
+
+    use super::{Bus, Mem, NESBus};
+
+    fn test_bus() -> NESBus<NullSpeaker, NullJoypadHandler, NullRenderer> {
+        let mut raw = vec![0x4E, 0x45, 0x53, 0x1A, 0x01, 0x00, 0x00, 0x00];
+        raw.resize(16 + 16 * 1024, 0x00);
+        let rom = Rom::new(&raw).unwrap();
+
+        NESBus::new(rom.into(), NullSpeaker, NullJoypadHandler, NullRenderer)
+    }
+
+    #[test]
+    fn test_open_bus() {
+        let mut bus = test_bus();
+
+        bus.mem_write(0x0000, 0x5A);
+        assert_eq!(bus.mem_read(0x0000), 0x5A);
+        assert_eq!(bus.mem_read(0x5000), 0x5A);
+        assert_eq!(bus.mem_read(0x4017), 0x40);
+
+        bus.mem_write(0x2000, 0x00);
+        bus.mem_write(0x2001, 0x3C);
+        assert_eq!(bus.mem_read(0x2005), 0x3C);
+        assert_eq!(bus.mem_read(0x2002) & 0x1F, 0x1C);
+    }
+
+    #[test]
+    fn test_prg_ram() {
+        let mut bus = test_bus();
+        bus.mem_write(0x6000, 0x80);
+        bus.mem_write(0x7FFF, 0x61);
+
+        assert_eq!(bus.mem_read(0x6000), 0x80);
+        assert_eq!(bus.peek(0x7FFF), 0x61);
+    }
+
+    #[test]
+    fn test_rom_write_is_counted() {
+        let mut bus = test_bus();
+        bus.mem_write(0x8000, 0x01);
+        bus.mem_write(0xC000, 0x02);
+
+        assert_eq!(
+            bus.diagnostics().counters(),
+            vec![(Category::Bus, "rom-write", 2)]
+        );
+    }
+
+    #[test]
+    fn test_oam_dma_stall() {
+        for (start, expected) in [(0, 513), (1, 514)] {
+            let mut bus = test_bus();
+            bus.tick(start);
+
+            // NOTE: CPU と同じく書き込みサイクルを進めてから書く
+            bus.tick(1);
+            let before = bus.get_cycles().0;
+            bus.mem_write(0x4014, 0x02);
+
+            assert_eq!(bus.get_cycles().0 - before, expected, "start: {}", start);
+        }
+    }
+
+    #[test]
+    fn test_dmc_dma_stall() {
+        let mut bus = test_bus();
+        // NOTE: $C000 から 1 バイトを最も遅いレートで読む。有効にすると次のサイクルで読みに行く
+        bus.mem_write(0x4013, 0x00);
+        bus.mem_write(0x4015, 0x10);
+
+        let before = bus.get_cycles().0;
+        bus.tick(1);
+        assert_eq!(bus.get_cycles().0 - before, 1 + 4);
+        assert_eq!(bus.apu.dmc_fetch_address(), None);
+    }
+
+    #[test]
+    fn test_dmc_dma_during_oam_dma() {
+        for (start, expected) in [(0, 513 + 2), (1, 514 + 2)] {
+            let mut bus = test_bus();
+            bus.tick(start);
+            bus.tick(1);
+
+            // NOTE: OAM DMA の最初の読み込みで DMC も読みたくなるので、OAM DMA に割り込んで 2 サイクル延びる
+            bus.mem_write(0x4013, 0x00);
+            bus.mem_write(0x4015, 0x10);
+            let before = bus.get_cycles().0;
+            bus.mem_write(0x4014, 0x02);
+
+            assert_eq!(bus.get_cycles().0 - before, expected, "start: {}", start);
+            assert_eq!(bus.apu.dmc_fetch_address(), None);
+        }
+    }
+
+    #[test]
+    fn test_oam_dma_copies_page() {
+        let mut bus = test_bus();
+        for i in 0..256 {
+            bus.mem_write(0x0200 + i, i as u8);
+        }
+
+        bus.mem_write(0x2003, 0x00);
+        bus.mem_write(0x4014, 0x02);
+
+        for i in 0..256 {
+            bus.mem_write(0x2003, i as u8);
+            assert_eq!(bus.mem_read(0x2004), i as u8);
+        }
+    }
 }
@@ -24,6 +24,10 @@ impl Interrupt {
     pub fn get_cycles(&self) -> u8 {
         self.cycles
     }
+
+    pub fn is_hardware(&self) -> bool {
+        matches!(self._type, InterruptType::NMI | InterruptType::IRQ)
+    }
 }
 
 pub const NMI: Interrupt = Interrupt {
@@ -1,21 +1,27 @@
 use std::fmt::Display;
 
 use addressing_mode::AddressingMode;
-use interrupt::{Interrupt, BRK, NMI, RESET};
-use opecode::OPCODE_MAP;
+use interrupt::{Interrupt, BRK, IRQ, NMI, RESET};
+use opecode::{Mnemonic, OPCODE_TABLE};
 use status::ProcessorStatus;
 
 use crate::bus::{Bus, Mem};
 
-mod addressing_mode;
+pub(crate) mod addressing_mode;
 mod interrupt;
-mod opecode;
-mod status;
-mod trace;
+pub(crate) mod opecode;
+pub(crate) mod status;
 
 #[cfg(test)]
 mod test;
 
+#[derive(PartialEq, Eq)]
+enum Access {
+    Read,
+    Write,
+    Modify,
+}
+
 pub struct CPU<M: Mem + Bus> {
     pub program_counter: u16,
     pub stack_pointer: u8,
@@ -24,6 +30,7 @@ pub struct CPU<M: Mem + Bus> {
     pub register_y: u8,
     pub status: ProcessorStatus,
     pub bus: M,
+    halted: bool,
 }
 
 impl<M: Mem + Bus> Mem for CPU<M> {
@@ -54,6 +61,7 @@ impl<M: Mem + Bus> CPU<M> {
             register_y: 0,
             status: ProcessorStatus::new(),
             bus,
+            halted: false,
         }
     }
 
@@ -63,12 +71,12 @@ impl<M: Mem + Bus> CPU<M> {
         self.register_x = 0;
         self.register_y = 0;
         self.status = ProcessorStatus::new();
+        self.halted = false;
 
-        let cycle = self.interrupt(RESET);
-
-        self.program_counter = self.mem_read_u16(0xFFFC);
+        // NOTE: RESET はスタックへの書き込みを行わない (SP が 3 減るだけで、その結果が 0xFD)
+        self.program_counter = self.mem_read_u16(RESET.get_address());
 
-        self.bus.tick(cycle);
+        self.bus.tick(RESET.get_cycles());
     }
 
     pub fn reset_with_pc(&mut self, pc: u16) {
@@ -77,422 +85,366 @@ impl<M: Mem + Bus> CPU<M> {
         self.register_x = 0;
         self.register_y = 0;
         self.status = ProcessorStatus::new();
+        self.halted = false;
 
         self.program_counter = pc;
 
         self.bus.tick(7);
     }
 
-    pub fn run_with_callback<F>(&mut self, mut callback: F)
-    where
-        F: FnMut(&mut CPU<M>),
-    {
-        loop {
-            callback(self);
-            self.step();
-        }
+    pub fn is_halted(&self) -> bool {
+        self.halted
     }
 
     pub fn step(&mut self) {
-        if let Some(_nmi) = self.bus.poll_nmi_status() {
-            let cycle = self.interrupt(NMI);
-            self.bus.tick(cycle);
+        // NOTE: KIL で止まったら RESET までは命令を実行しない。PPU などは動き続けるのでバスだけ進める
+        if self.halted {
+            self.bus.tick(1);
+            return;
         }
 
-        let opcode = self.mem_read(self.program_counter);
-        let op = OPCODE_MAP.get(&opcode);
-
-        let op = op.unwrap();
-
-        self.program_counter = self.program_counter.wrapping_add(1);
-
-        let additional_cycle = match op.name {
-            "ADC" => {
-                let is_crossed_page = self.adc(&op.addr_mode);
-                self.program_counter = self.program_counter.wrapping_add((op.size - 1) as u16);
-
-                if is_crossed_page {
-                    1
-                } else {
-                    0
-                }
-            }
-            "AND" => {
-                let is_crossed_page = self.and(&op.addr_mode);
-                self.program_counter = self.program_counter.wrapping_add((op.size - 1) as u16);
-
-                if is_crossed_page {
-                    1
-                } else {
-                    0
-                }
-            }
-            "ASL" => {
-                self.asl(&op.addr_mode);
-                self.program_counter = self.program_counter.wrapping_add((op.size - 1) as u16);
-
-                0
-            }
-            "BCC" => self.branch(!self.status.contains(ProcessorStatus::CARRY)),
-            "BCS" => self.branch(self.status.contains(ProcessorStatus::CARRY)),
-            "BEQ" => self.branch(self.status.contains(ProcessorStatus::ZERO)),
-            "BIT" => {
-                self.bit(&op.addr_mode);
-                self.program_counter = self.program_counter.wrapping_add((op.size - 1) as u16);
-
-                0
-            }
-            "BMI" => self.branch(self.status.contains(ProcessorStatus::NEGATIVE)),
-            "BNE" => self.branch(!self.status.contains(ProcessorStatus::ZERO)),
-            "BPL" => self.branch(!self.status.contains(ProcessorStatus::NEGATIVE)),
-            "BRK" => {
-                if !self.status.contains(ProcessorStatus::INTERRUPT) {
-                    self.interrupt(BRK)
-                } else {
-                    0
-                };
-                return;
-            }
-            "BVC" => self.branch(!self.status.contains(ProcessorStatus::OVERFLOW)),
-            "BVS" => self.branch(self.status.contains(ProcessorStatus::OVERFLOW)),
-            "CLC" => {
-                self.clc();
-
-                0
-            }
-            "CLD" => {
-                self.cld();
-
-                0
-            }
-            "CLI" => {
-                self.cli();
-
-                0
-            }
-            "CLV" => {
-                self.clv();
-
-                0
-            }
-            "CMP" => {
-                let is_crossed_page = self.cmp(self.register_a, &op.addr_mode);
-                self.program_counter = self.program_counter.wrapping_add((op.size - 1) as u16);
-
-                if is_crossed_page {
-                    1
-                } else {
-                    0
-                }
-            }
-            "CPX" => {
-                self.cmp(self.register_x, &op.addr_mode);
-                self.program_counter = self.program_counter.wrapping_add((op.size - 1) as u16);
-
-                0
-            }
-            "CPY" => {
-                self.cmp(self.register_y, &op.addr_mode);
-                self.program_counter = self.program_counter.wrapping_add((op.size - 1) as u16);
-
-                0
-            }
-            "DCP" => {
-                self.dec(&op.addr_mode);
-                self.cmp(self.register_a, &op.addr_mode);
-                self.program_counter = self.program_counter.wrapping_add((op.size - 1) as u16);
-
-                0
-            }
-            "DEC" => {
-                self.dec(&op.addr_mode);
-                self.program_counter = self.program_counter.wrapping_add((op.size - 1) as u16);
-
-                0
-            }
-            "DEX" => {
-                self.dex();
-
-                0
-            }
-            "DEY" => {
-                self.dey();
-
-                0
-            }
-            "EOR" => {
-                let is_crossed_page = self.eor(&op.addr_mode);
-                self.program_counter = self.program_counter.wrapping_add((op.size - 1) as u16);
-
-                if is_crossed_page {
-                    1
-                } else {
-                    0
-                }
-            }
-            "INC" => {
-                self.inc(&op.addr_mode);
-                self.program_counter = self.program_counter.wrapping_add((op.size - 1) as u16);
-
-                0
-            }
-            "INX" => {
-                self.inx();
-
-                0
-            }
-            "INY" => {
-                self.iny();
+        if let Some(_nmi) = self.bus.poll_nmi_status() {
+            self.interrupt(NMI);
+        } else if self.bus.poll_irq_status() && !self.status.contains(ProcessorStatus::INTERRUPT) {
+            self.interrupt(IRQ);
+        }
 
-                0
-            }
-            "ISB" => {
-                self.inc(&op.addr_mode);
-                self.sbc(&op.addr_mode);
-                self.program_counter = self.program_counter.wrapping_add((op.size - 1) as u16);
+        let code = self.fetch();
+        let op = OPCODE_TABLE[code as usize];
+        let mode = &op.addr_mode;
 
-                0
-            }
-            "JMP" => {
-                self.jmp(&op.addr_mode);
-
-                0
-            }
-            "JSR" => {
-                self.jsr(&op.addr_mode);
+        // NOTE: 1 バイト命令も 2 サイクル目で次のバイトを読んで捨てる
+        if op.size == 1 {
+            self.read(self.program_counter);
+        }
 
-                0
-            }
-            "LAX" => {
-                let is_crossed_page = self.lda(&op.addr_mode);
+        match op.mnemonic {
+            Mnemonic::Adc => {
+                let value = self.read_operand(mode);
+                self.adc(value);
+            }
+            Mnemonic::Ahx => self.unstable_store(mode, self.register_a & self.register_x),
+            Mnemonic::Alr => {
+                let value = self.read_operand(mode);
+                self.and(value);
+                self.register_a = self.lsr(self.register_a);
+            }
+            Mnemonic::Anc => {
+                let value = self.read_operand(mode);
+                self.and(value);
+                self.status
+                    .set_carry(self.status.contains(ProcessorStatus::NEGATIVE));
+            }
+            Mnemonic::And => {
+                let value = self.read_operand(mode);
+                self.and(value);
+            }
+            Mnemonic::Arr => {
+                let value = self.read_operand(mode);
+                self.arr(value);
+            }
+            Mnemonic::Asl => {
+                self.modify(mode, Self::asl);
+            }
+            Mnemonic::Axs => {
+                let value = self.read_operand(mode);
+                self.axs(value);
+            }
+            Mnemonic::Bcc => self.branch(!self.status.contains(ProcessorStatus::CARRY)),
+            Mnemonic::Bcs => self.branch(self.status.contains(ProcessorStatus::CARRY)),
+            Mnemonic::Beq => self.branch(self.status.contains(ProcessorStatus::ZERO)),
+            Mnemonic::Bit => {
+                let value = self.read_operand(mode);
+                self.bit(value);
+            }
+            Mnemonic::Bmi => self.branch(self.status.contains(ProcessorStatus::NEGATIVE)),
+            Mnemonic::Bne => self.branch(!self.status.contains(ProcessorStatus::ZERO)),
+            Mnemonic::Bpl => self.branch(!self.status.contains(ProcessorStatus::NEGATIVE)),
+            Mnemonic::Brk => {
+                // NOTE: BRK の次の 1 バイトはパディングとして読み飛ばす
+                self.program_counter = self.program_counter.wrapping_add(1);
+                self.interrupt(BRK);
+            }
+            Mnemonic::Bvc => self.branch(!self.status.contains(ProcessorStatus::OVERFLOW)),
+            Mnemonic::Bvs => self.branch(self.status.contains(ProcessorStatus::OVERFLOW)),
+            Mnemonic::Clc => self.clc(),
+            Mnemonic::Cld => self.cld(),
+            Mnemonic::Cli => self.cli(),
+            Mnemonic::Clv => self.clv(),
+            Mnemonic::Cmp => {
+                let value = self.read_operand(mode);
+                self.cmp(self.register_a, value);
+            }
+            Mnemonic::Cpx => {
+                let value = self.read_operand(mode);
+                self.cmp(self.register_x, value);
+            }
+            Mnemonic::Cpy => {
+                let value = self.read_operand(mode);
+                self.cmp(self.register_y, value);
+            }
+            Mnemonic::Dcp => {
+                let value = self.modify(mode, Self::dec);
+                self.cmp(self.register_a, value);
+            }
+            Mnemonic::Dec => {
+                self.modify(mode, Self::dec);
+            }
+            Mnemonic::Dex => self.dex(),
+            Mnemonic::Dey => self.dey(),
+            Mnemonic::Eor => {
+                let value = self.read_operand(mode);
+                self.eor(value);
+            }
+            Mnemonic::Inc => {
+                self.modify(mode, Self::inc);
+            }
+            Mnemonic::Inx => self.inx(),
+            Mnemonic::Iny => self.iny(),
+            Mnemonic::Isb => {
+                let value = self.modify(mode, Self::inc);
+                self.sbc(value);
+            }
+            Mnemonic::Jmp => self.jmp(mode),
+            Mnemonic::Jsr => self.jsr(),
+            Mnemonic::Kil => self.halted = true,
+            Mnemonic::Las => {
+                let value = self.read_operand(mode) & self.stack_pointer;
+                self.stack_pointer = value;
+                self.register_x = value;
+                self.lda(value);
+            }
+            Mnemonic::Lax => {
+                let value = self.read_operand(mode);
+                self.lda(value);
                 self.tax();
-                self.program_counter = self.program_counter.wrapping_add((op.size - 1) as u16);
-
-                if is_crossed_page {
-                    1
-                } else {
-                    0
-                }
             }
-            "LDA" => {
-                let is_crossed_page = self.lda(&op.addr_mode);
-                self.program_counter = self.program_counter.wrapping_add((op.size - 1) as u16);
-
-                if is_crossed_page {
-                    1
-                } else {
-                    0
-                }
+            Mnemonic::Lda => {
+                let value = self.read_operand(mode);
+                self.lda(value);
             }
-            "LDX" => {
-                let is_crossed_page = self.ldx(&op.addr_mode);
-                self.program_counter = self.program_counter.wrapping_add((op.size - 1) as u16);
-
-                if is_crossed_page {
-                    1
-                } else {
-                    0
-                }
+            Mnemonic::Ldx => {
+                let value = self.read_operand(mode);
+                self.ldx(value);
             }
-            "LDY" => {
-                let is_crossed_page = self.ldy(&op.addr_mode);
-                self.program_counter = self.program_counter.wrapping_add((op.size - 1) as u16);
-
-                if is_crossed_page {
-                    1
-                } else {
-                    0
-                }
+            Mnemonic::Ldy => {
+                let value = self.read_operand(mode);
+                self.ldy(value);
             }
-            "LSR" => {
-                self.lsr(&op.addr_mode);
-                self.program_counter = self.program_counter.wrapping_add((op.size - 1) as u16);
-
-                0
+            Mnemonic::Lsr => {
+                self.modify(mode, Self::lsr);
             }
-            "NOP" => {
-                let (_, is_crossed_page) = if op.addr_mode == AddressingMode::NoneAddressing {
-                    (0, false)
-                } else {
-                    self.get_operand_address(&op.addr_mode)
-                };
-                self.program_counter = self.program_counter.wrapping_add((op.size - 1) as u16);
-
-                if is_crossed_page {
-                    1
-                } else {
-                    0
-                }
-            }
-            "ORA" => {
-                let is_crossed_page = self.ora(&op.addr_mode);
-                self.program_counter = self.program_counter.wrapping_add((op.size - 1) as u16);
-
-                if is_crossed_page {
-                    1
-                } else {
-                    0
+            Mnemonic::Nop => {
+                if *mode != AddressingMode::NoneAddressing {
+                    self.read_operand(mode);
                 }
             }
-            "PHA" => {
-                self.pha();
-
-                0
-            }
-            "PHP" => {
-                self.php();
-
-                0
+            Mnemonic::Ora => {
+                let value = self.read_operand(mode);
+                self.ora(value);
+            }
+            Mnemonic::Pha => self.pha(),
+            Mnemonic::Php => self.php(),
+            Mnemonic::Pla => self.pla(),
+            Mnemonic::Plp => self.plp(),
+            Mnemonic::Rla => {
+                let value = self.modify(mode, Self::rol);
+                self.and(value);
+            }
+            Mnemonic::Rol => {
+                self.modify(mode, Self::rol);
+            }
+            Mnemonic::Ror => {
+                self.modify(mode, Self::ror);
+            }
+            Mnemonic::Rra => {
+                let value = self.modify(mode, Self::ror);
+                self.adc(value);
+            }
+            Mnemonic::Rti => self.rti(),
+            Mnemonic::Rts => self.rts(),
+            Mnemonic::Sax => {
+                let addr = self.operand_address(mode, Access::Write);
+                self.write(addr, self.register_a & self.register_x);
+            }
+            Mnemonic::Sbc => {
+                let value = self.read_operand(mode);
+                self.sbc(value);
+            }
+            Mnemonic::Sec => self.sec(),
+            Mnemonic::Sed => self.sed(),
+            Mnemonic::Sei => self.sei(),
+            Mnemonic::Shx => self.unstable_store(mode, self.register_x),
+            Mnemonic::Shy => self.unstable_store(mode, self.register_y),
+            Mnemonic::Slo => {
+                let value = self.modify(mode, Self::asl);
+                self.ora(value);
+            }
+            Mnemonic::Sre => {
+                let value = self.modify(mode, Self::lsr);
+                self.eor(value);
+            }
+            Mnemonic::Sta => {
+                let addr = self.operand_address(mode, Access::Write);
+                self.write(addr, self.register_a);
+            }
+            Mnemonic::Stx => {
+                let addr = self.operand_address(mode, Access::Write);
+                self.write(addr, self.register_x);
+            }
+            Mnemonic::Sty => {
+                let addr = self.operand_address(mode, Access::Write);
+                self.write(addr, self.register_y);
+            }
+            Mnemonic::Tas => {
+                self.stack_pointer = self.register_a & self.register_x;
+                self.unstable_store(mode, self.stack_pointer);
+            }
+            Mnemonic::Tax => self.tax(),
+            Mnemonic::Tay => self.tay(),
+            Mnemonic::Tsx => self.tsx(),
+            Mnemonic::Txa => self.txa(),
+            Mnemonic::Txs => self.txs(),
+            Mnemonic::Tya => self.tya(),
+            Mnemonic::Xaa => {
+                let value = self.read_operand(mode);
+                self.xaa(value);
             }
-            "PLA" => {
-                self.pla();
-
-                0
-            }
-            "PLP" => {
-                self.plp();
-
-                0
-            }
-            "RLA" => {
-                self.rol(&op.addr_mode);
-                self.and(&op.addr_mode);
-                self.program_counter = self.program_counter.wrapping_add((op.size - 1) as u16);
-
-                0
-            }
-            "ROL" => {
-                self.rol(&op.addr_mode);
-                self.program_counter = self.program_counter.wrapping_add((op.size - 1) as u16);
-
-                0
-            }
-            "ROR" => {
-                self.ror(&op.addr_mode);
-                self.program_counter = self.program_counter.wrapping_add((op.size - 1) as u16);
+        }
+    }
 
-                0
-            }
-            "RRA" => {
-                self.ror(&op.addr_mode);
-                self.adc(&op.addr_mode);
-                self.program_counter = self.program_counter.wrapping_add((op.size - 1) as u16);
+    // NOTE: CPU からのメモリアクセスは 1 回につき 1 サイクル。アクセスの前にバスを進める
+    fn read(&mut self, addr: u16) -> u8 {
+        self.bus.tick(1);
+        self.bus.mem_read(addr)
+    }
 
-                0
-            }
-            "RTI" => {
-                self.rti();
+    fn write(&mut self, addr: u16, data: u8) {
+        self.bus.tick(1);
+        self.bus.mem_write(addr, data);
+    }
 
-                0
-            }
-            "RTS" => {
-                self.rts();
+    fn fetch(&mut self) -> u8 {
+        let value = self.read(self.program_counter);
+        self.program_counter = self.program_counter.wrapping_add(1);
+        value
+    }
 
-                0
-            }
-            "SAX" => {
-                self.sax(&op.addr_mode);
-                self.program_counter = self.program_counter.wrapping_add((op.size - 1) as u16);
+    fn fetch_u16(&mut self) -> u16 {
+        let lo = self.fetch() as u16;
+        let hi = self.fetch() as u16;
+        (hi << 8) | lo
+    }
 
-                0
-            }
-            "SBC" => {
-                let is_crossed_page = self.sbc(&op.addr_mode);
-                self.program_counter = self.program_counter.wrapping_add((op.size - 1) as u16);
+    fn read_operand(&mut self, mode: &AddressingMode) -> u8 {
+        let addr = self.operand_address(mode, Access::Read);
+        self.read(addr)
+    }
 
-                if is_crossed_page {
-                    1
-                } else {
-                    0
-                }
-            }
-            "SEC" => {
-                self.sec();
+    // NOTE: Read-Modify-Write 命令は読んだ値を一度そのまま書き戻してから結果を書き込む
+    fn modify(&mut self, mode: &AddressingMode, f: fn(&mut Self, u8) -> u8) -> u8 {
+        if *mode == AddressingMode::NoneAddressing {
+            let result = f(self, self.register_a);
+            self.register_a = result;
+            return result;
+        }
 
-                0
-            }
-            "SED" => {
-                self.sed();
+        let addr = self.operand_address(mode, Access::Modify);
+        let value = self.read(addr);
+        self.write(addr, value);
+        let result = f(self, value);
+        self.write(addr, result);
 
-                0
-            }
-            "SEI" => {
-                self.sei();
+        result
+    }
 
-                0
+    fn operand_address(&mut self, mode: &AddressingMode, access: Access) -> u16 {
+        match mode {
+            AddressingMode::Immediate => {
+                let addr = self.program_counter;
+                self.program_counter = self.program_counter.wrapping_add(1);
+                addr
             }
-            "SLO" => {
-                self.asl(&op.addr_mode);
-                self.ora(&op.addr_mode);
-                self.program_counter = self.program_counter.wrapping_add((op.size - 1) as u16);
-
-                0
+            AddressingMode::ZeroPage => self.fetch() as u16,
+            AddressingMode::ZeroPageX => {
+                let base = self.fetch();
+                self.read(base as u16);
+                base.wrapping_add(self.register_x) as u16
             }
-            "STA" => {
-                self.sta(&op.addr_mode);
-                self.program_counter = self.program_counter.wrapping_add((op.size - 1) as u16);
-
-                0
+            AddressingMode::ZeroPageY => {
+                let base = self.fetch();
+                self.read(base as u16);
+                base.wrapping_add(self.register_y) as u16
             }
-            "STX" => {
-                self.stx(&op.addr_mode);
-                self.program_counter = self.program_counter.wrapping_add((op.size - 1) as u16);
-
-                0
+            AddressingMode::Absolute => self.fetch_u16(),
+            AddressingMode::AbsoluteX => {
+                let base = self.fetch_u16();
+                self.indexed_address(base, self.register_x, access)
             }
-            "STY" => {
-                self.sty(&op.addr_mode);
-                self.program_counter = self.program_counter.wrapping_add((op.size - 1) as u16);
-
-                0
+            AddressingMode::AbsoluteY => {
+                let base = self.fetch_u16();
+                self.indexed_address(base, self.register_y, access)
             }
-            "SRE" => {
-                self.lsr(&op.addr_mode);
-                self.eor(&op.addr_mode);
-                self.program_counter = self.program_counter.wrapping_add((op.size - 1) as u16);
+            AddressingMode::IndirectX => {
+                let base = self.fetch();
+                self.read(base as u16);
 
-                0
+                let ptr = base.wrapping_add(self.register_x);
+                let lo = self.read(ptr as u16);
+                let hi = self.read(ptr.wrapping_add(1) as u16);
+                (hi as u16) << 8 | lo as u16
             }
-            "TAX" => {
-                self.tax();
-
-                0
+            AddressingMode::IndirectY => {
+                let ptr = self.fetch();
+                let lo = self.read(ptr as u16);
+                let hi = self.read(ptr.wrapping_add(1) as u16);
+                let base = (hi as u16) << 8 | lo as u16;
+                self.indexed_address(base, self.register_y, access)
             }
-            "TAY" => {
-                self.tay();
+            _ => panic!("mode {:?} is not supported", mode),
+        }
+    }
 
-                0
-            }
-            "TSX" => {
-                self.tsx();
+    // NOTE: インデックス加算で上位バイトを補正する前のアドレスを一度読む。
+    //       読み込み命令はページをまたいだときだけ、書き込み系は常に発生する
+    fn indexed_address(&mut self, base: u16, index: u8, access: Access) -> u16 {
+        let addr = base.wrapping_add(index as u16);
+        let is_crossed_page = self.check_page_crossed(base, addr);
 
-                0
-            }
-            "TXA" => {
-                self.txa();
+        if is_crossed_page || access != Access::Read {
+            self.read((base & 0xFF00) | (addr & 0x00FF));
+        }
 
-                0
-            }
-            "TXS" => {
-                self.txs();
+        addr
+    }
 
-                0
+    // NOTE: SHY/SHX/AHX/TAS は書き込む値にベースアドレスの上位バイト + 1 が AND される。
+    //       ページをまたいだときは上位バイトが書き込む値に置き換わる
+    fn unstable_store(&mut self, mode: &AddressingMode, value: u8) {
+        let (base, index) = match mode {
+            AddressingMode::AbsoluteX => (self.fetch_u16(), self.register_x),
+            AddressingMode::AbsoluteY => (self.fetch_u16(), self.register_y),
+            AddressingMode::IndirectY => {
+                let ptr = self.fetch();
+                let lo = self.read(ptr as u16);
+                let hi = self.read(ptr.wrapping_add(1) as u16);
+                ((hi as u16) << 8 | lo as u16, self.register_y)
             }
-            "TYA" => {
-                self.tya();
+            _ => panic!("mode {:?} is not supported", mode),
+        };
 
-                0
-            }
-            _ => todo!(),
+        let addr = self.indexed_address(base, index, Access::Write);
+        let value = value & ((base >> 8) as u8).wrapping_add(1);
+        let addr = if self.check_page_crossed(base, addr) {
+            (value as u16) << 8 | (addr & 0x00FF)
+        } else {
+            addr
         };
 
-        self.bus.tick(op.cycles + additional_cycle);
+        self.write(addr, value);
     }
 
-    fn adc(&mut self, mode: &AddressingMode) -> bool {
-        let (addr, is_crossed_page) = self.get_operand_address(mode);
-        let value = self.mem_read(addr);
-
+    fn adc(&mut self, value: u8) {
         let (result, overflow1) = self.register_a.overflowing_add(value);
         let (result, overflow2) =
             result.overflowing_add(if self.status.contains(ProcessorStatus::CARRY) {
@@ -507,45 +459,49 @@ impl<M: Mem + Bus> CPU<M> {
         self.status.set_carry(carry);
         self.status.set_overflow(overflow);
         self.update_zero_and_negative_flags(result);
-
-        is_crossed_page
     }
 
-    fn and(&mut self, mode: &AddressingMode) -> bool {
-        let (addr, is_crossed_cycle) = self.get_operand_address(mode);
-        let value = self.mem_read(addr);
-
+    fn and(&mut self, value: u8) {
         self.register_a &= value;
         self.update_zero_and_negative_flags(self.register_a);
-
-        is_crossed_cycle
     }
 
-    fn asl(&mut self, mode: &AddressingMode) {
-        let value = if *mode == AddressingMode::NoneAddressing {
-            self.register_a
-        } else {
-            let (addr, _) = self.get_operand_address(mode);
-            self.mem_read(addr)
-        };
+    // NOTE: AND してから ROR。C はビット 6、V はビット 6 と 5 の XOR になる
+    fn arr(&mut self, value: u8) {
+        self.register_a &= value;
+        self.register_a = (self.register_a >> 1)
+            | if self.status.contains(ProcessorStatus::CARRY) {
+                0x80
+            } else {
+                0
+            };
+
+        let result = self.register_a;
+        self.status.set_carry(result & 0x40 != 0);
+        self.status
+            .set_overflow(((result >> 6) ^ (result >> 5)) & 0x01 != 0);
+        self.update_zero_and_negative_flags(result);
+    }
 
+    fn asl(&mut self, value: u8) -> u8 {
         let result = value << 1;
         let carry = value & 0x80 != 0;
 
-        if *mode == AddressingMode::NoneAddressing {
-            self.register_a = result;
-        } else {
-            let (addr, _) = self.get_operand_address(mode);
-            self.mem_write(addr, result);
-        }
         self.status.set_carry(carry);
         self.update_zero_and_negative_flags(result);
+
+        result
     }
 
-    fn bit(&mut self, mode: &AddressingMode) {
-        let (addr, _) = self.get_operand_address(mode);
-        let value = self.mem_read(addr);
+    // NOTE: X = (A & X) - value。CMP と同じくボローなしで C が立ち、V は変わらない
+    fn axs(&mut self, value: u8) {
+        let register = self.register_a & self.register_x;
+        self.register_x = register.wrapping_sub(value);
+        self.status.set_carry(register >= value);
+        self.update_zero_and_negative_flags(self.register_x);
+    }
 
+    fn bit(&mut self, value: u8) {
         let result = self.register_a & value;
         self.status.set_zero(result == 0);
         self.status.set_overflow(value & 0x40 != 0);
@@ -568,24 +524,16 @@ impl<M: Mem + Bus> CPU<M> {
         self.status.set_overflow(false);
     }
 
-    fn cmp(&mut self, register: u8, mode: &AddressingMode) -> bool {
-        let (addr, is_crossed_page) = self.get_operand_address(mode);
-        let value = self.mem_read(addr);
-
+    fn cmp(&mut self, register: u8, value: u8) {
         let result = register.wrapping_sub(value);
         self.status.set_carry(register >= value);
         self.update_zero_and_negative_flags(result);
-
-        is_crossed_page
     }
 
-    fn dec(&mut self, mode: &AddressingMode) {
-        let (addr, _) = self.get_operand_address(mode);
-        let value = self.mem_read(addr);
-
+    fn dec(&mut self, value: u8) -> u8 {
         let result = value.wrapping_sub(1);
-        self.mem_write(addr, result);
         self.update_zero_and_negative_flags(result);
+        result
     }
 
     fn dex(&mut self) {
@@ -598,23 +546,15 @@ impl<M: Mem + Bus> CPU<M> {
         self.update_zero_and_negative_flags(self.register_y);
     }
 
-    fn eor(&mut self, mode: &AddressingMode) -> bool {
-        let (addr, is_crossed_page) = self.get_operand_address(mode);
-        let value = self.mem_read(addr);
-
+    fn eor(&mut self, value: u8) {
         self.register_a ^= value;
         self.update_zero_and_negative_flags(self.register_a);
-
-        is_crossed_page
     }
 
-    fn inc(&mut self, mode: &AddressingMode) {
-        let (addr, _) = self.get_operand_address(mode);
-        let value = self.mem_read(addr);
-
+    fn inc(&mut self, value: u8) -> u8 {
         let result = value.wrapping_add(1);
-        self.mem_write(addr, result);
         self.update_zero_and_negative_flags(result);
+        result
     }
 
     fn inx(&mut self) {
@@ -630,14 +570,14 @@ impl<M: Mem + Bus> CPU<M> {
     fn jmp(&mut self, mode: &AddressingMode) {
         match mode {
             AddressingMode::Absolute => {
-                let (addr, _) = self.get_operand_address(mode);
-                self.program_counter = addr;
+                self.program_counter = self.fetch_u16();
             }
             AddressingMode::Indirect => {
-                let ptr = self.mem_read_u16(self.program_counter);
+                let ptr = self.fetch_u16();
 
-                let lo = self.mem_read(ptr);
-                let hi = self.mem_read(if ptr & 0xFF == 0xFF {
+                // NOTE: ポインタがページ境界にあると上位バイトは同じページの先頭から読まれる
+                let lo = self.read(ptr);
+                let hi = self.read(if ptr & 0xFF == 0xFF {
                     ptr & 0xFF00
                 } else {
                     ptr + 1
@@ -650,71 +590,43 @@ impl<M: Mem + Bus> CPU<M> {
         }
     }
 
-    fn jsr(&mut self, mode: &AddressingMode) {
-        let (addr, _) = self.get_operand_address(mode);
-        self.stack_push_u16(self.program_counter + 2 - 1);
-        self.program_counter = addr;
-    }
+    fn jsr(&mut self) {
+        let lo = self.fetch() as u16;
+        self.read(0x100 | self.stack_pointer as u16);
+        self.stack_push_u16(self.program_counter);
+        let hi = self.read(self.program_counter) as u16;
 
-    fn lda(&mut self, mode: &AddressingMode) -> bool {
-        let (addr, is_crossed_page) = self.get_operand_address(mode);
-        let value = self.mem_read(addr);
+        self.program_counter = (hi << 8) | lo;
+    }
 
+    fn lda(&mut self, value: u8) {
         self.register_a = value;
         self.update_zero_and_negative_flags(value);
-
-        is_crossed_page
     }
 
-    fn ldx(&mut self, mode: &AddressingMode) -> bool {
-        let (addr, is_crossed_page) = self.get_operand_address(mode);
-        let value = self.mem_read(addr);
-
+    fn ldx(&mut self, value: u8) {
         self.register_x = value;
         self.update_zero_and_negative_flags(value);
-
-        is_crossed_page
     }
 
-    fn ldy(&mut self, mode: &AddressingMode) -> bool {
-        let (addr, is_crossed_page) = self.get_operand_address(mode);
-        let value = self.mem_read(addr);
-
+    fn ldy(&mut self, value: u8) {
         self.register_y = value;
         self.update_zero_and_negative_flags(value);
-
-        is_crossed_page
     }
 
-    fn lsr(&mut self, mode: &AddressingMode) {
-        let value = if *mode == AddressingMode::NoneAddressing {
-            self.register_a
-        } else {
-            let (addr, _) = self.get_operand_address(mode);
-            self.mem_read(addr)
-        };
-
+    fn lsr(&mut self, value: u8) -> u8 {
         let result = value >> 1;
         let carry = value & 0x01 != 0;
 
-        if *mode == AddressingMode::NoneAddressing {
-            self.register_a = result;
-        } else {
-            let (addr, _) = self.get_operand_address(mode);
-            self.mem_write(addr, result);
-        }
         self.status.set_carry(carry);
         self.update_zero_and_negative_flags(result);
-    }
 
-    fn ora(&mut self, mode: &AddressingMode) -> bool {
-        let (addr, is_crossed_page) = self.get_operand_address(mode);
-        let value = self.mem_read(addr);
+        result
+    }
 
+    fn ora(&mut self, value: u8) {
         self.register_a |= value;
         self.update_zero_and_negative_flags(self.register_a);
-
-        is_crossed_page
     }
 
     fn pha(&mut self) {
@@ -722,17 +634,19 @@ impl<M: Mem + Bus> CPU<M> {
     }
 
     fn php(&mut self) {
-        let mut flag = self.status.clone();
+        let mut flag = self.status;
         flag.set_break_command(true);
         self.stack_push(flag.bits());
     }
 
     fn pla(&mut self) {
+        self.read(0x100 | self.stack_pointer as u16);
         self.register_a = self.stack_pop();
         self.update_zero_and_negative_flags(self.register_a);
     }
 
     fn plp(&mut self) {
+        self.read(0x100 | self.stack_pointer as u16);
         let mut flag = ProcessorStatus::from_bits_truncate(self.stack_pop());
         flag.set_break_command(false);
         flag.set_break2_command(true);
@@ -740,14 +654,7 @@ impl<M: Mem + Bus> CPU<M> {
         self.status = flag;
     }
 
-    fn rol(&mut self, mode: &AddressingMode) {
-        let value = if *mode == AddressingMode::NoneAddressing {
-            self.register_a
-        } else {
-            let (addr, _) = self.get_operand_address(mode);
-            self.mem_read(addr)
-        };
-
+    fn rol(&mut self, value: u8) -> u8 {
         let result = (value << 1)
             | if self.status.contains(ProcessorStatus::CARRY) {
                 1
@@ -756,24 +663,13 @@ impl<M: Mem + Bus> CPU<M> {
             };
         let carry = value & 0x80 != 0;
 
-        if *mode == AddressingMode::NoneAddressing {
-            self.register_a = result;
-        } else {
-            let (addr, _) = self.get_operand_address(mode);
-            self.mem_write(addr, result);
-        }
         self.status.set_carry(carry);
         self.update_zero_and_negative_flags(result);
-    }
 
-    fn ror(&mut self, mode: &AddressingMode) {
-        let value = if *mode == AddressingMode::NoneAddressing {
-            self.register_a
-        } else {
-            let (addr, _) = self.get_operand_address(mode);
-            self.mem_read(addr)
-        };
+        result
+    }
 
+    fn ror(&mut self, value: u8) -> u8 {
         let result = (value >> 1)
             | if self.status.contains(ProcessorStatus::CARRY) {
                 0x80
@@ -782,17 +678,14 @@ impl<M: Mem + Bus> CPU<M> {
             };
         let carry = value & 0x01 != 0;
 
-        if *mode == AddressingMode::NoneAddressing {
-            self.register_a = result;
-        } else {
-            let (addr, _) = self.get_operand_address(mode);
-            self.mem_write(addr, result);
-        }
         self.status.set_carry(carry);
         self.update_zero_and_negative_flags(result);
+
+        result
     }
 
     fn rti(&mut self) {
+        self.read(0x100 | self.stack_pointer as u16);
         let mut flag = ProcessorStatus::from_bits_truncate(self.stack_pop());
         flag.set_break_command(false);
         flag.set_break2_command(true);
@@ -802,19 +695,13 @@ impl<M: Mem + Bus> CPU<M> {
     }
 
     fn rts(&mut self) {
-        self.program_counter = self.stack_pop_u16() + 1;
-    }
-
-    fn sax(&mut self, mode: &AddressingMode) {
-        let (addr, _) = self.get_operand_address(mode);
-        let value = self.register_a & self.register_x;
-        self.mem_write(addr, value);
+        self.read(0x100 | self.stack_pointer as u16);
+        let addr = self.stack_pop_u16();
+        self.read(addr);
+        self.program_counter = addr.wrapping_add(1);
     }
 
-    fn sbc(&mut self, mode: &AddressingMode) -> bool {
-        let (addr, is_crossed_page) = self.get_operand_address(mode);
-        let value = self.mem_read(addr);
-
+    fn sbc(&mut self, value: u8) {
         let (result, overflow1) = self.register_a.overflowing_sub(value);
         let (result, overflow2) =
             result.overflowing_sub(if self.status.contains(ProcessorStatus::CARRY) {
@@ -830,8 +717,6 @@ impl<M: Mem + Bus> CPU<M> {
         self.status.set_carry(carry);
         self.status.set_overflow(overflow);
         self.update_zero_and_negative_flags(result);
-
-        is_crossed_page
     }
 
     fn sec(&mut self) {
@@ -846,21 +731,6 @@ impl<M: Mem + Bus> CPU<M> {
         self.status.set_interrupt(true);
     }
 
-    fn sta(&mut self, mode: &AddressingMode) {
-        let (addr, _) = self.get_operand_address(mode);
-        self.mem_write(addr, self.register_a);
-    }
-
-    fn stx(&mut self, mode: &AddressingMode) {
-        let (addr, _) = self.get_operand_address(mode);
-        self.mem_write(addr, self.register_x);
-    }
-
-    fn sty(&mut self, mode: &AddressingMode) {
-        let (addr, _) = self.get_operand_address(mode);
-        self.mem_write(addr, self.register_y);
-    }
-
     fn tax(&mut self) {
         self.register_x = self.register_a;
         self.update_zero_and_negative_flags(self.register_x);
@@ -890,6 +760,12 @@ impl<M: Mem + Bus> CPU<M> {
         self.update_zero_and_negative_flags(self.register_a);
     }
 
+    // NOTE: 実機ではアナログ的に不安定な命令。よく使われる定数 0xEE を採用する
+    fn xaa(&mut self, value: u8) {
+        self.register_a = (self.register_a | 0xEE) & self.register_x & value;
+        self.update_zero_and_negative_flags(self.register_a);
+    }
+
     fn update_zero_and_negative_flags(&mut self, value: u8) {
         self.status.set_zero(value == 0);
         self.status.set_negative(value & 0x80 != 0);
@@ -897,7 +773,7 @@ impl<M: Mem + Bus> CPU<M> {
 
     fn stack_push(&mut self, value: u8) {
         let addr = 0x100 | self.stack_pointer as u16;
-        self.mem_write(addr, value);
+        self.write(addr, value);
         self.stack_pointer = self.stack_pointer.wrapping_sub(1);
     }
 
@@ -911,7 +787,7 @@ impl<M: Mem + Bus> CPU<M> {
     fn stack_pop(&mut self) -> u8 {
         self.stack_pointer = self.stack_pointer.wrapping_add(1);
         let addr = 0x100 | self.stack_pointer as u16;
-        self.mem_read(addr)
+        self.read(addr)
     }
 
     fn stack_pop_u16(&mut self) -> u16 {
@@ -920,91 +796,38 @@ impl<M: Mem + Bus> CPU<M> {
         (high_byte << 8) | low_byte
     }
 
-    fn get_operand_address(&mut self, mode: &AddressingMode) -> (u16, bool) {
-        match mode {
-            AddressingMode::Immediate => (self.program_counter, false),
-            _ => self.get_absolute_address(mode, self.program_counter),
+    // NOTE: BRK 以外は命令のフェッチ後に割り込むので、フェッチ分の 2 サイクルを読み捨てで消費する
+    fn interrupt(&mut self, interrupt: Interrupt) {
+        if interrupt.is_hardware() {
+            self.read(self.program_counter);
+            self.read(self.program_counter);
         }
-    }
 
-    fn interrupt(&mut self, interrupt: Interrupt) -> u8 {
         self.stack_push_u16(self.program_counter);
 
         let mask = interrupt.get_break_mask();
         self.stack_push(self.status.bits() & mask);
         self.status.set_interrupt(true);
 
-        self.program_counter = self.mem_read_u16(interrupt.get_address());
-
-        interrupt.get_cycles()
-    }
-
-    pub(super) fn get_absolute_address(&mut self, mode: &AddressingMode, addr: u16) -> (u16, bool) {
-        match mode {
-            AddressingMode::ZeroPage => (self.mem_read(addr) as u16, false),
-            AddressingMode::ZeroPageX => {
-                let address = self.mem_read(addr) as u16;
-                (address.wrapping_add(self.register_x as u16) & 0xFF, false)
-            }
-            AddressingMode::ZeroPageY => {
-                let address = self.mem_read(addr) as u16;
-                (address.wrapping_add(self.register_y as u16) & 0xFF, false)
-            }
-            AddressingMode::Absolute => (self.mem_read_u16(addr), false),
-            AddressingMode::AbsoluteX => {
-                let base = self.mem_read_u16(addr);
-                let addr = base.wrapping_add(self.register_x as u16);
-
-                (addr, self.check_page_crossed(base, addr))
-            }
-            AddressingMode::AbsoluteY => {
-                let base = self.mem_read_u16(addr);
-                let addr = base.wrapping_add(self.register_y as u16);
-
-                (addr, self.check_page_crossed(base, addr))
-            }
-            AddressingMode::Indirect => {
-                let ptr = self.mem_read_u16(addr);
-                (self.mem_read_u16(ptr), false)
-            }
-            AddressingMode::IndirectX => {
-                let base = self.mem_read(addr);
-
-                let ptr = (base as u16).wrapping_add(self.register_x as u16) & 0xFF;
-
-                let lo = self.mem_read(ptr);
-                let hi = self.mem_read(ptr.wrapping_add(1) & 0xFF);
-                ((hi as u16) << 8 | lo as u16, false)
-            }
-            AddressingMode::IndirectY => {
-                let base = self.mem_read(addr);
-
-                let lo = self.mem_read(base as u16);
-                let hi = self.mem_read((base.wrapping_add(1)) as u16);
-                let deref_base = (hi as u16) << 8 | lo as u16;
-                let addr = deref_base.wrapping_add(self.register_y as u16);
-
-                (addr, self.check_page_crossed(deref_base, addr))
-            }
-            _ => panic!("mode {:?} is not supported", mode),
-        }
+        let lo = self.read(interrupt.get_address()) as u16;
+        let hi = self.read(interrupt.get_address().wrapping_add(1)) as u16;
+        self.program_counter = (hi << 8) | lo;
     }
 
-    fn branch(&mut self, cond: bool) -> u8 {
-        let offset = self.mem_read(self.program_counter) as i8;
-        self.program_counter += 1;
+    // NOTE: 分岐成立で 1 サイクル、ページをまたぐとさらに 1 サイクル、次の命令位置を読み捨てる
+    fn branch(&mut self, cond: bool) {
+        let offset = self.fetch() as i8;
 
         if !cond {
-            return 0;
+            return;
         }
 
         let old_pc = self.program_counter;
-        self.program_counter = self.program_counter.wrapping_add(offset as u16);
+        self.read(old_pc);
+        self.program_counter = old_pc.wrapping_add(offset as u16);
 
         if self.check_page_crossed(old_pc, self.program_counter) {
-            2
-        } else {
-            1
+            self.read((old_pc & 0xFF00) | (self.program_counter & 0x00FF));
         }
     }
 
@@ -1,23 +1,188 @@
-use std::{collections::HashMap, fmt::Display};
-
-use once_cell::sync::Lazy;
+use std::fmt::Display;
 
 use super::addressing_mode::AddressingMode;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mnemonic {
+    Adc,
+    Ahx,
+    Alr,
+    Anc,
+    And,
+    Arr,
+    Asl,
+    Axs,
+    Bcc,
+    Bcs,
+    Beq,
+    Bit,
+    Bmi,
+    Bne,
+    Bpl,
+    Brk,
+    Bvc,
+    Bvs,
+    Clc,
+    Cld,
+    Cli,
+    Clv,
+    Cmp,
+    Cpx,
+    Cpy,
+    Dcp,
+    Dec,
+    Dex,
+    Dey,
+    Eor,
+    Inc,
+    Inx,
+    Iny,
+    Isb,
+    Jmp,
+    Jsr,
+    Kil,
+    Las,
+    Lax,
+    Lda,
+    Ldx,
+    Ldy,
+    Lsr,
+    Nop,
+    Ora,
+    Pha,
+    Php,
+    Pla,
+    Plp,
+    Rla,
+    Rol,
+    Ror,
+    Rra,
+    Rti,
+    Rts,
+    Sax,
+    Sbc,
+    Sec,
+    Sed,
+    Sei,
+    Shx,
+    Shy,
+    Slo,
+    Sre,
+    Sta,
+    Stx,
+    Sty,
+    Tas,
+    Tax,
+    Tay,
+    Tsx,
+    Txa,
+    Txs,
+    Tya,
+    Xaa,
+}
+
+impl Mnemonic {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Mnemonic::Adc => "ADC",
+            Mnemonic::Ahx => "AHX",
+            Mnemonic::Alr => "ALR",
+            Mnemonic::Anc => "ANC",
+            Mnemonic::And => "AND",
+            Mnemonic::Arr => "ARR",
+            Mnemonic::Asl => "ASL",
+            Mnemonic::Axs => "AXS",
+            Mnemonic::Bcc => "BCC",
+            Mnemonic::Bcs => "BCS",
+            Mnemonic::Beq => "BEQ",
+            Mnemonic::Bit => "BIT",
+            Mnemonic::Bmi => "BMI",
+            Mnemonic::Bne => "BNE",
+            Mnemonic::Bpl => "BPL",
+            Mnemonic::Brk => "BRK",
+            Mnemonic::Bvc => "BVC",
+            Mnemonic::Bvs => "BVS",
+            Mnemonic::Clc => "CLC",
+            Mnemonic::Cld => "CLD",
+            Mnemonic::Cli => "CLI",
+            Mnemonic::Clv => "CLV",
+            Mnemonic::Cmp => "CMP",
+            Mnemonic::Cpx => "CPX",
+            Mnemonic::Cpy => "CPY",
+            Mnemonic::Dcp => "DCP",
+            Mnemonic::Dec => "DEC",
+            Mnemonic::Dex => "DEX",
+            Mnemonic::Dey => "DEY",
+            Mnemonic::Eor => "EOR",
+            Mnemonic::Inc => "INC",
+            Mnemonic::Inx => "INX",
+            Mnemonic::Iny => "INY",
+            Mnemonic::Isb => "ISB",
+            Mnemonic::Jmp => "JMP",
+            Mnemonic::Jsr => "JSR",
+            Mnemonic::Kil => "KIL",
+            Mnemonic::Las => "LAS",
+            Mnemonic::Lax => "LAX",
+            Mnemonic::Lda => "LDA",
+            Mnemonic::Ldx => "LDX",
+            Mnemonic::Ldy => "LDY",
+            Mnemonic::Lsr => "LSR",
+            Mnemonic::Nop => "NOP",
+            Mnemonic::Ora => "ORA",
+            Mnemonic::Pha => "PHA",
+            Mnemonic::Php => "PHP",
+            Mnemonic::Pla => "PLA",
+            Mnemonic::Plp => "PLP",
+            Mnemonic::Rla => "RLA",
+            Mnemonic::Rol => "ROL",
+            Mnemonic::Ror => "ROR",
+            Mnemonic::Rra => "RRA",
+            Mnemonic::Rti => "RTI",
+            Mnemonic::Rts => "RTS",
+            Mnemonic::Sax => "SAX",
+            Mnemonic::Sbc => "SBC",
+            Mnemonic::Sec => "SEC",
+            Mnemonic::Sed => "SED",
+            Mnemonic::Sei => "SEI",
+            Mnemonic::Shx => "SHX",
+            Mnemonic::Shy => "SHY",
+            Mnemonic::Slo => "SLO",
+            Mnemonic::Sre => "SRE",
+            Mnemonic::Sta => "STA",
+            Mnemonic::Stx => "STX",
+            Mnemonic::Sty => "STY",
+            Mnemonic::Tas => "TAS",
+            Mnemonic::Tax => "TAX",
+            Mnemonic::Tay => "TAY",
+            Mnemonic::Tsx => "TSX",
+            Mnemonic::Txa => "TXA",
+            Mnemonic::Txs => "TXS",
+            Mnemonic::Tya => "TYA",
+            Mnemonic::Xaa => "XAA",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct OpCode {
     pub code: u8,
-    pub name: &'static str,
+    pub mnemonic: Mnemonic,
     pub size: u8,
     pub cycles: u8,
     pub addr_mode: AddressingMode,
 }
 
 impl OpCode {
-    fn new(code: u8, name: &'static str, size: u8, cycles: u8, addr_mode: AddressingMode) -> Self {
+    const fn new(
+        code: u8,
+        mnemonic: Mnemonic,
+        size: u8,
+        cycles: u8,
+        addr_mode: AddressingMode,
+    ) -> Self {
         Self {
             code,
-            name,
+            mnemonic,
             size,
             cycles,
             addr_mode,
@@ -31,7 +196,7 @@ impl Display for OpCode {
             f,
             "{:#04X} NAME:{} SIZE:{} CYC:{} ADDR:{:>14}",
             self.code,
-            self.name,
+            self.mnemonic.name(),
             self.size,
             self.cycles,
             format!("{:?}", self.addr_mode)
@@ -39,262 +204,300 @@ impl Display for OpCode {
     }
 }
 
-pub static CPU_OPCODE: Lazy<Vec<OpCode>> = Lazy::new(|| {
-    vec![
-        OpCode::new(0x69, "ADC", 2, 2, AddressingMode::Immediate),
-        OpCode::new(0x65, "ADC", 2, 3, AddressingMode::ZeroPage),
-        OpCode::new(0x75, "ADC", 2, 4, AddressingMode::ZeroPageX),
-        OpCode::new(0x6D, "ADC", 3, 4, AddressingMode::Absolute),
-        OpCode::new(0x7D, "ADC", 3, 4, AddressingMode::AbsoluteX),
-        OpCode::new(0x79, "ADC", 3, 4, AddressingMode::AbsoluteY),
-        OpCode::new(0x61, "ADC", 2, 6, AddressingMode::IndirectX),
-        OpCode::new(0x71, "ADC", 2, 5, AddressingMode::IndirectY),
-        OpCode::new(0x29, "AND", 2, 2, AddressingMode::Immediate),
-        OpCode::new(0x25, "AND", 2, 3, AddressingMode::ZeroPage),
-        OpCode::new(0x35, "AND", 2, 4, AddressingMode::ZeroPageX),
-        OpCode::new(0x2D, "AND", 3, 4, AddressingMode::Absolute),
-        OpCode::new(0x3D, "AND", 3, 4, AddressingMode::AbsoluteX),
-        OpCode::new(0x39, "AND", 3, 4, AddressingMode::AbsoluteY),
-        OpCode::new(0x21, "AND", 2, 6, AddressingMode::IndirectX),
-        OpCode::new(0x31, "AND", 2, 5, AddressingMode::IndirectY),
-        OpCode::new(0x0A, "ASL", 1, 2, AddressingMode::NoneAddressing),
-        OpCode::new(0x06, "ASL", 2, 5, AddressingMode::ZeroPage),
-        OpCode::new(0x16, "ASL", 2, 6, AddressingMode::ZeroPageX),
-        OpCode::new(0x0E, "ASL", 3, 6, AddressingMode::Absolute),
-        OpCode::new(0x1E, "ASL", 3, 7, AddressingMode::AbsoluteX),
-        OpCode::new(0x90, "BCC", 2, 2, AddressingMode::NoneAddressing),
-        OpCode::new(0xB0, "BCS", 2, 2, AddressingMode::NoneAddressing),
-        OpCode::new(0xF0, "BEQ", 2, 2, AddressingMode::NoneAddressing),
-        OpCode::new(0x24, "BIT", 2, 3, AddressingMode::ZeroPage),
-        OpCode::new(0x2C, "BIT", 3, 4, AddressingMode::Absolute),
-        OpCode::new(0x30, "BMI", 2, 2, AddressingMode::NoneAddressing),
-        OpCode::new(0xD0, "BNE", 2, 2, AddressingMode::NoneAddressing),
-        OpCode::new(0x10, "BPL", 2, 2, AddressingMode::NoneAddressing),
-        OpCode::new(0x00, "BRK", 1, 7, AddressingMode::NoneAddressing),
-        OpCode::new(0x50, "BVC", 2, 2, AddressingMode::NoneAddressing),
-        OpCode::new(0x70, "BVS", 2, 2, AddressingMode::NoneAddressing),
-        OpCode::new(0x18, "CLC", 1, 2, AddressingMode::NoneAddressing),
-        OpCode::new(0xD8, "CLD", 1, 2, AddressingMode::NoneAddressing),
-        OpCode::new(0x58, "CLI", 1, 2, AddressingMode::NoneAddressing),
-        OpCode::new(0xB8, "CLV", 1, 2, AddressingMode::NoneAddressing),
-        OpCode::new(0xC9, "CMP", 2, 2, AddressingMode::Immediate),
-        OpCode::new(0xC5, "CMP", 2, 3, AddressingMode::ZeroPage),
-        OpCode::new(0xD5, "CMP", 2, 4, AddressingMode::ZeroPageX),
-        OpCode::new(0xCD, "CMP", 3, 4, AddressingMode::Absolute),
-        OpCode::new(0xDD, "CMP", 3, 4, AddressingMode::AbsoluteX),
-        OpCode::new(0xD9, "CMP", 3, 4, AddressingMode::AbsoluteY),
-        OpCode::new(0xC1, "CMP", 2, 6, AddressingMode::IndirectX),
-        OpCode::new(0xD1, "CMP", 2, 5, AddressingMode::IndirectY),
-        OpCode::new(0xE0, "CPX", 2, 2, AddressingMode::Immediate),
-        OpCode::new(0xE4, "CPX", 2, 3, AddressingMode::ZeroPage),
-        OpCode::new(0xEC, "CPX", 3, 4, AddressingMode::Absolute),
-        OpCode::new(0xC0, "CPY", 2, 2, AddressingMode::Immediate),
-        OpCode::new(0xC4, "CPY", 2, 3, AddressingMode::ZeroPage),
-        OpCode::new(0xCC, "CPY", 3, 4, AddressingMode::Absolute),
-        OpCode::new(0xC6, "DEC", 2, 5, AddressingMode::ZeroPage),
-        OpCode::new(0xD6, "DEC", 2, 6, AddressingMode::ZeroPageX),
-        OpCode::new(0xCE, "DEC", 3, 6, AddressingMode::Absolute),
-        OpCode::new(0xDE, "DEC", 3, 7, AddressingMode::AbsoluteX),
-        OpCode::new(0xCA, "DEX", 1, 2, AddressingMode::NoneAddressing),
-        OpCode::new(0x88, "DEY", 1, 2, AddressingMode::NoneAddressing),
-        OpCode::new(0x49, "EOR", 2, 2, AddressingMode::Immediate),
-        OpCode::new(0x45, "EOR", 2, 3, AddressingMode::ZeroPage),
-        OpCode::new(0x55, "EOR", 2, 4, AddressingMode::ZeroPageX),
-        OpCode::new(0x4D, "EOR", 3, 4, AddressingMode::Absolute),
-        OpCode::new(0x5D, "EOR", 3, 4, AddressingMode::AbsoluteX),
-        OpCode::new(0x59, "EOR", 3, 4, AddressingMode::AbsoluteY),
-        OpCode::new(0x41, "EOR", 2, 6, AddressingMode::IndirectX),
-        OpCode::new(0x51, "EOR", 2, 5, AddressingMode::IndirectY),
-        OpCode::new(0xE6, "INC", 2, 5, AddressingMode::ZeroPage),
-        OpCode::new(0xF6, "INC", 2, 6, AddressingMode::ZeroPageX),
-        OpCode::new(0xEE, "INC", 3, 6, AddressingMode::Absolute),
-        OpCode::new(0xFE, "INC", 3, 7, AddressingMode::AbsoluteX),
-        OpCode::new(0xE8, "INX", 1, 2, AddressingMode::NoneAddressing),
-        OpCode::new(0xC8, "INY", 1, 2, AddressingMode::NoneAddressing),
-        OpCode::new(0x4C, "JMP", 3, 3, AddressingMode::Absolute),
-        OpCode::new(0x6C, "JMP", 3, 5, AddressingMode::Indirect),
-        OpCode::new(0x20, "JSR", 3, 6, AddressingMode::Absolute),
-        OpCode::new(0xA9, "LDA", 2, 2, AddressingMode::Immediate),
-        OpCode::new(0xA5, "LDA", 2, 3, AddressingMode::ZeroPage),
-        OpCode::new(0xB5, "LDA", 2, 4, AddressingMode::ZeroPageX),
-        OpCode::new(0xAD, "LDA", 3, 4, AddressingMode::Absolute),
-        OpCode::new(0xBD, "LDA", 3, 4, AddressingMode::AbsoluteX),
-        OpCode::new(0xB9, "LDA", 3, 4, AddressingMode::AbsoluteY),
-        OpCode::new(0xA1, "LDA", 2, 6, AddressingMode::IndirectX),
-        OpCode::new(0xB1, "LDA", 2, 5, AddressingMode::IndirectY),
-        OpCode::new(0xA2, "LDX", 2, 2, AddressingMode::Immediate),
-        OpCode::new(0xA6, "LDX", 2, 3, AddressingMode::ZeroPage),
-        OpCode::new(0xB6, "LDX", 2, 4, AddressingMode::ZeroPageY),
-        OpCode::new(0xAE, "LDX", 3, 4, AddressingMode::Absolute),
-        OpCode::new(0xBE, "LDX", 3, 4, AddressingMode::AbsoluteY),
-        OpCode::new(0xA0, "LDY", 2, 2, AddressingMode::Immediate),
-        OpCode::new(0xA4, "LDY", 2, 3, AddressingMode::ZeroPage),
-        OpCode::new(0xB4, "LDY", 2, 4, AddressingMode::ZeroPageX),
-        OpCode::new(0xAC, "LDY", 3, 4, AddressingMode::Absolute),
-        OpCode::new(0xBC, "LDY", 3, 4, AddressingMode::AbsoluteX),
-        OpCode::new(0x4A, "LSR", 1, 2, AddressingMode::NoneAddressing),
-        OpCode::new(0x46, "LSR", 2, 5, AddressingMode::ZeroPage),
-        OpCode::new(0x56, "LSR", 2, 6, AddressingMode::ZeroPageX),
-        OpCode::new(0x4E, "LSR", 3, 6, AddressingMode::Absolute),
-        OpCode::new(0x5E, "LSR", 3, 7, AddressingMode::AbsoluteX),
-        OpCode::new(0xEA, "NOP", 1, 2, AddressingMode::NoneAddressing),
-        OpCode::new(0x09, "ORA", 2, 2, AddressingMode::Immediate),
-        OpCode::new(0x05, "ORA", 2, 3, AddressingMode::ZeroPage),
-        OpCode::new(0x15, "ORA", 2, 4, AddressingMode::ZeroPageX),
-        OpCode::new(0x0D, "ORA", 3, 4, AddressingMode::Absolute),
-        OpCode::new(0x1D, "ORA", 3, 4, AddressingMode::AbsoluteX),
-        OpCode::new(0x19, "ORA", 3, 4, AddressingMode::AbsoluteY),
-        OpCode::new(0x01, "ORA", 2, 6, AddressingMode::IndirectX),
-        OpCode::new(0x11, "ORA", 2, 5, AddressingMode::IndirectY),
-        OpCode::new(0x48, "PHA", 1, 3, AddressingMode::NoneAddressing),
-        OpCode::new(0x08, "PHP", 1, 3, AddressingMode::NoneAddressing),
-        OpCode::new(0x68, "PLA", 1, 4, AddressingMode::NoneAddressing),
-        OpCode::new(0x28, "PLP", 1, 4, AddressingMode::NoneAddressing),
-        OpCode::new(0x2A, "ROL", 1, 2, AddressingMode::NoneAddressing),
-        OpCode::new(0x26, "ROL", 2, 5, AddressingMode::ZeroPage),
-        OpCode::new(0x36, "ROL", 2, 6, AddressingMode::ZeroPageX),
-        OpCode::new(0x2E, "ROL", 3, 6, AddressingMode::Absolute),
-        OpCode::new(0x3E, "ROL", 3, 7, AddressingMode::AbsoluteX),
-        OpCode::new(0x6A, "ROR", 1, 2, AddressingMode::NoneAddressing),
-        OpCode::new(0x66, "ROR", 2, 5, AddressingMode::ZeroPage),
-        OpCode::new(0x76, "ROR", 2, 6, AddressingMode::ZeroPageX),
-        OpCode::new(0x6E, "ROR", 3, 6, AddressingMode::Absolute),
-        OpCode::new(0x7E, "ROR", 3, 7, AddressingMode::AbsoluteX),
-        OpCode::new(0x40, "RTI", 1, 6, AddressingMode::NoneAddressing),
-        OpCode::new(0x60, "RTS", 1, 6, AddressingMode::NoneAddressing),
-        OpCode::new(0xE9, "SBC", 2, 2, AddressingMode::Immediate),
-        OpCode::new(0xE5, "SBC", 2, 3, AddressingMode::ZeroPage),
-        OpCode::new(0xF5, "SBC", 2, 4, AddressingMode::ZeroPageX),
-        OpCode::new(0xED, "SBC", 3, 4, AddressingMode::Absolute),
-        OpCode::new(0xFD, "SBC", 3, 4, AddressingMode::AbsoluteX),
-        OpCode::new(0xF9, "SBC", 3, 4, AddressingMode::AbsoluteY),
-        OpCode::new(0xE1, "SBC", 2, 6, AddressingMode::IndirectX),
-        OpCode::new(0xF1, "SBC", 2, 5, AddressingMode::IndirectY),
-        OpCode::new(0x38, "SEC", 1, 2, AddressingMode::NoneAddressing),
-        OpCode::new(0xF8, "SED", 1, 2, AddressingMode::NoneAddressing),
-        OpCode::new(0x78, "SEI", 1, 2, AddressingMode::NoneAddressing),
-        OpCode::new(0x85, "STA", 2, 3, AddressingMode::ZeroPage),
-        OpCode::new(0x95, "STA", 2, 4, AddressingMode::ZeroPageX),
-        OpCode::new(0x8D, "STA", 3, 4, AddressingMode::Absolute),
-        OpCode::new(0x9D, "STA", 3, 5, AddressingMode::AbsoluteX),
-        OpCode::new(0x99, "STA", 3, 5, AddressingMode::AbsoluteY),
-        OpCode::new(0x81, "STA", 2, 6, AddressingMode::IndirectX),
-        OpCode::new(0x91, "STA", 2, 6, AddressingMode::IndirectY),
-        OpCode::new(0x86, "STX", 2, 3, AddressingMode::ZeroPage),
-        OpCode::new(0x96, "STX", 2, 4, AddressingMode::ZeroPageY),
-        OpCode::new(0x8E, "STX", 3, 4, AddressingMode::Absolute),
-        OpCode::new(0x84, "STY", 2, 3, AddressingMode::ZeroPage),
-        OpCode::new(0x94, "STY", 2, 4, AddressingMode::ZeroPageX),
-        OpCode::new(0x8C, "STY", 3, 4, AddressingMode::Absolute),
-        OpCode::new(0xAA, "TAX", 1, 2, AddressingMode::NoneAddressing),
-        OpCode::new(0xA8, "TAY", 1, 2, AddressingMode::NoneAddressing),
-        OpCode::new(0xBA, "TSX", 1, 2, AddressingMode::NoneAddressing),
-        OpCode::new(0x8A, "TXA", 1, 2, AddressingMode::NoneAddressing),
-        OpCode::new(0x9A, "TXS", 1, 2, AddressingMode::NoneAddressing),
-        OpCode::new(0x98, "TYA", 1, 2, AddressingMode::NoneAddressing),
-    ]
-});
+pub const CPU_OPCODE: [OpCode; 151] = [
+    OpCode::new(0x69, Mnemonic::Adc, 2, 2, AddressingMode::Immediate),
+    OpCode::new(0x65, Mnemonic::Adc, 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0x75, Mnemonic::Adc, 2, 4, AddressingMode::ZeroPageX),
+    OpCode::new(0x6D, Mnemonic::Adc, 3, 4, AddressingMode::Absolute),
+    OpCode::new(0x7D, Mnemonic::Adc, 3, 4, AddressingMode::AbsoluteX),
+    OpCode::new(0x79, Mnemonic::Adc, 3, 4, AddressingMode::AbsoluteY),
+    OpCode::new(0x61, Mnemonic::Adc, 2, 6, AddressingMode::IndirectX),
+    OpCode::new(0x71, Mnemonic::Adc, 2, 5, AddressingMode::IndirectY),
+    OpCode::new(0x29, Mnemonic::And, 2, 2, AddressingMode::Immediate),
+    OpCode::new(0x25, Mnemonic::And, 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0x35, Mnemonic::And, 2, 4, AddressingMode::ZeroPageX),
+    OpCode::new(0x2D, Mnemonic::And, 3, 4, AddressingMode::Absolute),
+    OpCode::new(0x3D, Mnemonic::And, 3, 4, AddressingMode::AbsoluteX),
+    OpCode::new(0x39, Mnemonic::And, 3, 4, AddressingMode::AbsoluteY),
+    OpCode::new(0x21, Mnemonic::And, 2, 6, AddressingMode::IndirectX),
+    OpCode::new(0x31, Mnemonic::And, 2, 5, AddressingMode::IndirectY),
+    OpCode::new(0x0A, Mnemonic::Asl, 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x06, Mnemonic::Asl, 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0x16, Mnemonic::Asl, 2, 6, AddressingMode::ZeroPageX),
+    OpCode::new(0x0E, Mnemonic::Asl, 3, 6, AddressingMode::Absolute),
+    OpCode::new(0x1E, Mnemonic::Asl, 3, 7, AddressingMode::AbsoluteX),
+    OpCode::new(0x90, Mnemonic::Bcc, 2, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0xB0, Mnemonic::Bcs, 2, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0xF0, Mnemonic::Beq, 2, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x24, Mnemonic::Bit, 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0x2C, Mnemonic::Bit, 3, 4, AddressingMode::Absolute),
+    OpCode::new(0x30, Mnemonic::Bmi, 2, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0xD0, Mnemonic::Bne, 2, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x10, Mnemonic::Bpl, 2, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x00, Mnemonic::Brk, 1, 7, AddressingMode::NoneAddressing),
+    OpCode::new(0x50, Mnemonic::Bvc, 2, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x70, Mnemonic::Bvs, 2, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x18, Mnemonic::Clc, 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0xD8, Mnemonic::Cld, 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x58, Mnemonic::Cli, 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0xB8, Mnemonic::Clv, 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0xC9, Mnemonic::Cmp, 2, 2, AddressingMode::Immediate),
+    OpCode::new(0xC5, Mnemonic::Cmp, 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0xD5, Mnemonic::Cmp, 2, 4, AddressingMode::ZeroPageX),
+    OpCode::new(0xCD, Mnemonic::Cmp, 3, 4, AddressingMode::Absolute),
+    OpCode::new(0xDD, Mnemonic::Cmp, 3, 4, AddressingMode::AbsoluteX),
+    OpCode::new(0xD9, Mnemonic::Cmp, 3, 4, AddressingMode::AbsoluteY),
+    OpCode::new(0xC1, Mnemonic::Cmp, 2, 6, AddressingMode::IndirectX),
+    OpCode::new(0xD1, Mnemonic::Cmp, 2, 5, AddressingMode::IndirectY),
+    OpCode::new(0xE0, Mnemonic::Cpx, 2, 2, AddressingMode::Immediate),
+    OpCode::new(0xE4, Mnemonic::Cpx, 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0xEC, Mnemonic::Cpx, 3, 4, AddressingMode::Absolute),
+    OpCode::new(0xC0, Mnemonic::Cpy, 2, 2, AddressingMode::Immediate),
+    OpCode::new(0xC4, Mnemonic::Cpy, 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0xCC, Mnemonic::Cpy, 3, 4, AddressingMode::Absolute),
+    OpCode::new(0xC6, Mnemonic::Dec, 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0xD6, Mnemonic::Dec, 2, 6, AddressingMode::ZeroPageX),
+    OpCode::new(0xCE, Mnemonic::Dec, 3, 6, AddressingMode::Absolute),
+    OpCode::new(0xDE, Mnemonic::Dec, 3, 7, AddressingMode::AbsoluteX),
+    OpCode::new(0xCA, Mnemonic::Dex, 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x88, Mnemonic::Dey, 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x49, Mnemonic::Eor, 2, 2, AddressingMode::Immediate),
+    OpCode::new(0x45, Mnemonic::Eor, 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0x55, Mnemonic::Eor, 2, 4, AddressingMode::ZeroPageX),
+    OpCode::new(0x4D, Mnemonic::Eor, 3, 4, AddressingMode::Absolute),
+    OpCode::new(0x5D, Mnemonic::Eor, 3, 4, AddressingMode::AbsoluteX),
+    OpCode::new(0x59, Mnemonic::Eor, 3, 4, AddressingMode::AbsoluteY),
+    OpCode::new(0x41, Mnemonic::Eor, 2, 6, AddressingMode::IndirectX),
+    OpCode::new(0x51, Mnemonic::Eor, 2, 5, AddressingMode::IndirectY),
+    OpCode::new(0xE6, Mnemonic::Inc, 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0xF6, Mnemonic::Inc, 2, 6, AddressingMode::ZeroPageX),
+    OpCode::new(0xEE, Mnemonic::Inc, 3, 6, AddressingMode::Absolute),
+    OpCode::new(0xFE, Mnemonic::Inc, 3, 7, AddressingMode::AbsoluteX),
+    OpCode::new(0xE8, Mnemonic::Inx, 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0xC8, Mnemonic::Iny, 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x4C, Mnemonic::Jmp, 3, 3, AddressingMode::Absolute),
+    OpCode::new(0x6C, Mnemonic::Jmp, 3, 5, AddressingMode::Indirect),
+    OpCode::new(0x20, Mnemonic::Jsr, 3, 6, AddressingMode::Absolute),
+    OpCode::new(0xA9, Mnemonic::Lda, 2, 2, AddressingMode::Immediate),
+    OpCode::new(0xA5, Mnemonic::Lda, 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0xB5, Mnemonic::Lda, 2, 4, AddressingMode::ZeroPageX),
+    OpCode::new(0xAD, Mnemonic::Lda, 3, 4, AddressingMode::Absolute),
+    OpCode::new(0xBD, Mnemonic::Lda, 3, 4, AddressingMode::AbsoluteX),
+    OpCode::new(0xB9, Mnemonic::Lda, 3, 4, AddressingMode::AbsoluteY),
+    OpCode::new(0xA1, Mnemonic::Lda, 2, 6, AddressingMode::IndirectX),
+    OpCode::new(0xB1, Mnemonic::Lda, 2, 5, AddressingMode::IndirectY),
+    OpCode::new(0xA2, Mnemonic::Ldx, 2, 2, AddressingMode::Immediate),
+    OpCode::new(0xA6, Mnemonic::Ldx, 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0xB6, Mnemonic::Ldx, 2, 4, AddressingMode::ZeroPageY),
+    OpCode::new(0xAE, Mnemonic::Ldx, 3, 4, AddressingMode::Absolute),
+    OpCode::new(0xBE, Mnemonic::Ldx, 3, 4, AddressingMode::AbsoluteY),
+    OpCode::new(0xA0, Mnemonic::Ldy, 2, 2, AddressingMode::Immediate),
+    OpCode::new(0xA4, Mnemonic::Ldy, 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0xB4, Mnemonic::Ldy, 2, 4, AddressingMode::ZeroPageX),
+    OpCode::new(0xAC, Mnemonic::Ldy, 3, 4, AddressingMode::Absolute),
+    OpCode::new(0xBC, Mnemonic::Ldy, 3, 4, AddressingMode::AbsoluteX),
+    OpCode::new(0x4A, Mnemonic::Lsr, 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x46, Mnemonic::Lsr, 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0x56, Mnemonic::Lsr, 2, 6, AddressingMode::ZeroPageX),
+    OpCode::new(0x4E, Mnemonic::Lsr, 3, 6, AddressingMode::Absolute),
+    OpCode::new(0x5E, Mnemonic::Lsr, 3, 7, AddressingMode::AbsoluteX),
+    OpCode::new(0xEA, Mnemonic::Nop, 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x09, Mnemonic::Ora, 2, 2, AddressingMode::Immediate),
+    OpCode::new(0x05, Mnemonic::Ora, 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0x15, Mnemonic::Ora, 2, 4, AddressingMode::ZeroPageX),
+    OpCode::new(0x0D, Mnemonic::Ora, 3, 4, AddressingMode::Absolute),
+    OpCode::new(0x1D, Mnemonic::Ora, 3, 4, AddressingMode::AbsoluteX),
+    OpCode::new(0x19, Mnemonic::Ora, 3, 4, AddressingMode::AbsoluteY),
+    OpCode::new(0x01, Mnemonic::Ora, 2, 6, AddressingMode::IndirectX),
+    OpCode::new(0x11, Mnemonic::Ora, 2, 5, AddressingMode::IndirectY),
+    OpCode::new(0x48, Mnemonic::Pha, 1, 3, AddressingMode::NoneAddressing),
+    OpCode::new(0x08, Mnemonic::Php, 1, 3, AddressingMode::NoneAddressing),
+    OpCode::new(0x68, Mnemonic::Pla, 1, 4, AddressingMode::NoneAddressing),
+    OpCode::new(0x28, Mnemonic::Plp, 1, 4, AddressingMode::NoneAddressing),
+    OpCode::new(0x2A, Mnemonic::Rol, 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x26, Mnemonic::Rol, 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0x36, Mnemonic::Rol, 2, 6, AddressingMode::ZeroPageX),
+    OpCode::new(0x2E, Mnemonic::Rol, 3, 6, AddressingMode::Absolute),
+    OpCode::new(0x3E, Mnemonic::Rol, 3, 7, AddressingMode::AbsoluteX),
+    OpCode::new(0x6A, Mnemonic::Ror, 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x66, Mnemonic::Ror, 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0x76, Mnemonic::Ror, 2, 6, AddressingMode::ZeroPageX),
+    OpCode::new(0x6E, Mnemonic::Ror, 3, 6, AddressingMode::Absolute),
+    OpCode::new(0x7E, Mnemonic::Ror, 3, 7, AddressingMode::AbsoluteX),
+    OpCode::new(0x40, Mnemonic::Rti, 1, 6, AddressingMode::NoneAddressing),
+    OpCode::new(0x60, Mnemonic::Rts, 1, 6, AddressingMode::NoneAddressing),
+    OpCode::new(0xE9, Mnemonic::Sbc, 2, 2, AddressingMode::Immediate),
+    OpCode::new(0xE5, Mnemonic::Sbc, 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0xF5, Mnemonic::Sbc, 2, 4, AddressingMode::ZeroPageX),
+    OpCode::new(0xED, Mnemonic::Sbc, 3, 4, AddressingMode::Absolute),
+    OpCode::new(0xFD, Mnemonic::Sbc, 3, 4, AddressingMode::AbsoluteX),
+    OpCode::new(0xF9, Mnemonic::Sbc, 3, 4, AddressingMode::AbsoluteY),
+    OpCode::new(0xE1, Mnemonic::Sbc, 2, 6, AddressingMode::IndirectX),
+    OpCode::new(0xF1, Mnemonic::Sbc, 2, 5, AddressingMode::IndirectY),
+    OpCode::new(0x38, Mnemonic::Sec, 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0xF8, Mnemonic::Sed, 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x78, Mnemonic::Sei, 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x85, Mnemonic::Sta, 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0x95, Mnemonic::Sta, 2, 4, AddressingMode::ZeroPageX),
+    OpCode::new(0x8D, Mnemonic::Sta, 3, 4, AddressingMode::Absolute),
+    OpCode::new(0x9D, Mnemonic::Sta, 3, 5, AddressingMode::AbsoluteX),
+    OpCode::new(0x99, Mnemonic::Sta, 3, 5, AddressingMode::AbsoluteY),
+    OpCode::new(0x81, Mnemonic::Sta, 2, 6, AddressingMode::IndirectX),
+    OpCode::new(0x91, Mnemonic::Sta, 2, 6, AddressingMode::IndirectY),
+    OpCode::new(0x86, Mnemonic::Stx, 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0x96, Mnemonic::Stx, 2, 4, AddressingMode::ZeroPageY),
+    OpCode::new(0x8E, Mnemonic::Stx, 3, 4, AddressingMode::Absolute),
+    OpCode::new(0x84, Mnemonic::Sty, 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0x94, Mnemonic::Sty, 2, 4, AddressingMode::ZeroPageX),
+    OpCode::new(0x8C, Mnemonic::Sty, 3, 4, AddressingMode::Absolute),
+    OpCode::new(0xAA, Mnemonic::Tax, 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0xA8, Mnemonic::Tay, 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0xBA, Mnemonic::Tsx, 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x8A, Mnemonic::Txa, 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x9A, Mnemonic::Txs, 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x98, Mnemonic::Tya, 1, 2, AddressingMode::NoneAddressing),
+];
+
+pub const UNOFFICIAL_OPCODE: [OpCode; 105] = [
+    // NOP
+    OpCode::new(0x1A, Mnemonic::Nop, 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x3A, Mnemonic::Nop, 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x5A, Mnemonic::Nop, 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x7A, Mnemonic::Nop, 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0xDA, Mnemonic::Nop, 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0xFA, Mnemonic::Nop, 1, 2, AddressingMode::NoneAddressing),
+    // SKB
+    OpCode::new(0x80, Mnemonic::Nop, 2, 2, AddressingMode::Immediate),
+    OpCode::new(0x82, Mnemonic::Nop, 2, 2, AddressingMode::Immediate),
+    OpCode::new(0x89, Mnemonic::Nop, 2, 2, AddressingMode::Immediate),
+    OpCode::new(0xC2, Mnemonic::Nop, 2, 2, AddressingMode::Immediate),
+    OpCode::new(0xE2, Mnemonic::Nop, 2, 2, AddressingMode::Immediate),
+    // ING
+    OpCode::new(0x0C, Mnemonic::Nop, 3, 4, AddressingMode::Absolute),
+    OpCode::new(0x1C, Mnemonic::Nop, 3, 4, AddressingMode::AbsoluteX),
+    OpCode::new(0x3C, Mnemonic::Nop, 3, 4, AddressingMode::AbsoluteX),
+    OpCode::new(0x5C, Mnemonic::Nop, 3, 4, AddressingMode::AbsoluteX),
+    OpCode::new(0x7C, Mnemonic::Nop, 3, 4, AddressingMode::AbsoluteX),
+    OpCode::new(0xDC, Mnemonic::Nop, 3, 4, AddressingMode::AbsoluteX),
+    OpCode::new(0xFC, Mnemonic::Nop, 3, 4, AddressingMode::AbsoluteX),
+    OpCode::new(0x04, Mnemonic::Nop, 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0x44, Mnemonic::Nop, 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0x64, Mnemonic::Nop, 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0x14, Mnemonic::Nop, 2, 4, AddressingMode::ZeroPageX),
+    OpCode::new(0x34, Mnemonic::Nop, 2, 4, AddressingMode::ZeroPageX),
+    OpCode::new(0x54, Mnemonic::Nop, 2, 4, AddressingMode::ZeroPageX),
+    OpCode::new(0x74, Mnemonic::Nop, 2, 4, AddressingMode::ZeroPageX),
+    OpCode::new(0xD4, Mnemonic::Nop, 2, 4, AddressingMode::ZeroPageX),
+    OpCode::new(0xF4, Mnemonic::Nop, 2, 4, AddressingMode::ZeroPageX),
+    //
+    OpCode::new(0xA7, Mnemonic::Lax, 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0xB7, Mnemonic::Lax, 2, 4, AddressingMode::ZeroPageY),
+    OpCode::new(0xAF, Mnemonic::Lax, 3, 4, AddressingMode::Absolute),
+    OpCode::new(0xBF, Mnemonic::Lax, 3, 4, AddressingMode::AbsoluteY),
+    OpCode::new(0xA3, Mnemonic::Lax, 2, 6, AddressingMode::IndirectX),
+    OpCode::new(0xB3, Mnemonic::Lax, 2, 5, AddressingMode::IndirectY),
+    OpCode::new(0x87, Mnemonic::Sax, 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0x97, Mnemonic::Sax, 2, 4, AddressingMode::ZeroPageY),
+    OpCode::new(0x8F, Mnemonic::Sax, 3, 4, AddressingMode::Absolute),
+    OpCode::new(0x83, Mnemonic::Sax, 2, 6, AddressingMode::IndirectX),
+    OpCode::new(0xEB, Mnemonic::Sbc, 2, 2, AddressingMode::Immediate),
+    OpCode::new(0xC3, Mnemonic::Dcp, 2, 8, AddressingMode::IndirectX),
+    OpCode::new(0xC7, Mnemonic::Dcp, 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0xCF, Mnemonic::Dcp, 3, 6, AddressingMode::Absolute),
+    OpCode::new(0xD3, Mnemonic::Dcp, 2, 8, AddressingMode::IndirectY),
+    OpCode::new(0xD7, Mnemonic::Dcp, 2, 6, AddressingMode::ZeroPageX),
+    OpCode::new(0xDB, Mnemonic::Dcp, 3, 7, AddressingMode::AbsoluteY),
+    OpCode::new(0xDF, Mnemonic::Dcp, 3, 7, AddressingMode::AbsoluteX),
+    OpCode::new(0xE3, Mnemonic::Isb, 2, 8, AddressingMode::IndirectX),
+    OpCode::new(0xE7, Mnemonic::Isb, 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0xEF, Mnemonic::Isb, 3, 6, AddressingMode::Absolute),
+    OpCode::new(0xF3, Mnemonic::Isb, 2, 8, AddressingMode::IndirectY),
+    OpCode::new(0xF7, Mnemonic::Isb, 2, 6, AddressingMode::ZeroPageX),
+    OpCode::new(0xFB, Mnemonic::Isb, 3, 7, AddressingMode::AbsoluteY),
+    OpCode::new(0xFF, Mnemonic::Isb, 3, 7, AddressingMode::AbsoluteX),
+    OpCode::new(0x03, Mnemonic::Slo, 2, 8, AddressingMode::IndirectX),
+    OpCode::new(0x07, Mnemonic::Slo, 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0x0F, Mnemonic::Slo, 3, 6, AddressingMode::Absolute),
+    OpCode::new(0x13, Mnemonic::Slo, 2, 8, AddressingMode::IndirectY),
+    OpCode::new(0x17, Mnemonic::Slo, 2, 6, AddressingMode::ZeroPageX),
+    OpCode::new(0x1B, Mnemonic::Slo, 3, 7, AddressingMode::AbsoluteY),
+    OpCode::new(0x1F, Mnemonic::Slo, 3, 7, AddressingMode::AbsoluteX),
+    OpCode::new(0x23, Mnemonic::Rla, 2, 8, AddressingMode::IndirectX),
+    OpCode::new(0x27, Mnemonic::Rla, 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0x2F, Mnemonic::Rla, 3, 6, AddressingMode::Absolute),
+    OpCode::new(0x33, Mnemonic::Rla, 2, 8, AddressingMode::IndirectY),
+    OpCode::new(0x37, Mnemonic::Rla, 2, 6, AddressingMode::ZeroPageX),
+    OpCode::new(0x3B, Mnemonic::Rla, 3, 7, AddressingMode::AbsoluteY),
+    OpCode::new(0x3F, Mnemonic::Rla, 3, 7, AddressingMode::AbsoluteX),
+    OpCode::new(0x43, Mnemonic::Sre, 2, 8, AddressingMode::IndirectX),
+    OpCode::new(0x47, Mnemonic::Sre, 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0x4F, Mnemonic::Sre, 3, 6, AddressingMode::Absolute),
+    OpCode::new(0x53, Mnemonic::Sre, 2, 8, AddressingMode::IndirectY),
+    OpCode::new(0x57, Mnemonic::Sre, 2, 6, AddressingMode::ZeroPageX),
+    OpCode::new(0x5B, Mnemonic::Sre, 3, 7, AddressingMode::AbsoluteY),
+    OpCode::new(0x5F, Mnemonic::Sre, 3, 7, AddressingMode::AbsoluteX),
+    OpCode::new(0x63, Mnemonic::Rra, 2, 8, AddressingMode::IndirectX),
+    OpCode::new(0x67, Mnemonic::Rra, 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0x6F, Mnemonic::Rra, 3, 6, AddressingMode::Absolute),
+    OpCode::new(0x73, Mnemonic::Rra, 2, 8, AddressingMode::IndirectY),
+    OpCode::new(0x77, Mnemonic::Rra, 2, 6, AddressingMode::ZeroPageX),
+    OpCode::new(0x7B, Mnemonic::Rra, 3, 7, AddressingMode::AbsoluteY),
+    OpCode::new(0x7F, Mnemonic::Rra, 3, 7, AddressingMode::AbsoluteX),
+    OpCode::new(0x0B, Mnemonic::Anc, 2, 2, AddressingMode::Immediate),
+    OpCode::new(0x2B, Mnemonic::Anc, 2, 2, AddressingMode::Immediate),
+    OpCode::new(0x4B, Mnemonic::Alr, 2, 2, AddressingMode::Immediate),
+    OpCode::new(0x6B, Mnemonic::Arr, 2, 2, AddressingMode::Immediate),
+    OpCode::new(0xCB, Mnemonic::Axs, 2, 2, AddressingMode::Immediate),
+    OpCode::new(0xAB, Mnemonic::Lax, 2, 2, AddressingMode::Immediate),
+    OpCode::new(0x8B, Mnemonic::Xaa, 2, 2, AddressingMode::Immediate),
+    OpCode::new(0xBB, Mnemonic::Las, 3, 4, AddressingMode::AbsoluteY),
+    // NOTE: 書き込む値にアドレスの上位バイト + 1 が AND される不安定な命令
+    OpCode::new(0x9C, Mnemonic::Shy, 3, 5, AddressingMode::AbsoluteX),
+    OpCode::new(0x9E, Mnemonic::Shx, 3, 5, AddressingMode::AbsoluteY),
+    OpCode::new(0x93, Mnemonic::Ahx, 2, 6, AddressingMode::IndirectY),
+    OpCode::new(0x9F, Mnemonic::Ahx, 3, 5, AddressingMode::AbsoluteY),
+    OpCode::new(0x9B, Mnemonic::Tas, 3, 5, AddressingMode::AbsoluteY),
+    // KIL (JAM)
+    OpCode::new(0x02, Mnemonic::Kil, 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x12, Mnemonic::Kil, 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x22, Mnemonic::Kil, 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x32, Mnemonic::Kil, 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x42, Mnemonic::Kil, 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x52, Mnemonic::Kil, 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x62, Mnemonic::Kil, 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x72, Mnemonic::Kil, 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x92, Mnemonic::Kil, 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0xB2, Mnemonic::Kil, 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0xD2, Mnemonic::Kil, 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0xF2, Mnemonic::Kil, 1, 2, AddressingMode::NoneAddressing),
+];
+
+// NOTE: オペコードの 1 バイトをそのまま添字にして引くテーブル。256 個すべて埋まっていなければコンパイルエラーになる
+pub static OPCODE_TABLE: [OpCode; 256] = build_table();
 
-pub static UNOFFICIAL_OPCODE: Lazy<Vec<OpCode>> = Lazy::new(|| {
-    vec![
-        // NOP
-        OpCode::new(0x1A, "NOP", 1, 2, AddressingMode::NoneAddressing),
-        OpCode::new(0x3A, "NOP", 1, 2, AddressingMode::NoneAddressing),
-        OpCode::new(0x5A, "NOP", 1, 2, AddressingMode::NoneAddressing),
-        OpCode::new(0x7A, "NOP", 1, 2, AddressingMode::NoneAddressing),
-        OpCode::new(0xDA, "NOP", 1, 2, AddressingMode::NoneAddressing),
-        OpCode::new(0xFA, "NOP", 1, 2, AddressingMode::NoneAddressing),
-        // SKB
-        OpCode::new(0x80, "NOP", 2, 2, AddressingMode::Immediate),
-        OpCode::new(0x82, "NOP", 2, 2, AddressingMode::Immediate),
-        OpCode::new(0x89, "NOP", 2, 2, AddressingMode::Immediate),
-        OpCode::new(0xC2, "NOP", 2, 2, AddressingMode::Immediate),
-        OpCode::new(0xE2, "NOP", 2, 2, AddressingMode::Immediate),
-        // ING
-        OpCode::new(0x0C, "NOP", 3, 4, AddressingMode::Absolute),
-        OpCode::new(0x1C, "NOP", 3, 4, AddressingMode::AbsoluteX),
-        OpCode::new(0x3C, "NOP", 3, 4, AddressingMode::AbsoluteX),
-        OpCode::new(0x5C, "NOP", 3, 4, AddressingMode::AbsoluteX),
-        OpCode::new(0x7C, "NOP", 3, 4, AddressingMode::AbsoluteX),
-        OpCode::new(0xDC, "NOP", 3, 4, AddressingMode::AbsoluteX),
-        OpCode::new(0xFC, "NOP", 3, 4, AddressingMode::AbsoluteX),
-        OpCode::new(0x04, "NOP", 2, 3, AddressingMode::ZeroPage),
-        OpCode::new(0x44, "NOP", 2, 3, AddressingMode::ZeroPage),
-        OpCode::new(0x64, "NOP", 2, 3, AddressingMode::ZeroPage),
-        OpCode::new(0x0C, "NOP", 3, 4, AddressingMode::Absolute),
-        OpCode::new(0x14, "NOP", 2, 4, AddressingMode::ZeroPageX),
-        OpCode::new(0x34, "NOP", 2, 4, AddressingMode::ZeroPageX),
-        OpCode::new(0x54, "NOP", 2, 4, AddressingMode::ZeroPageX),
-        OpCode::new(0x74, "NOP", 2, 4, AddressingMode::ZeroPageX),
-        OpCode::new(0xD4, "NOP", 2, 4, AddressingMode::ZeroPageX),
-        OpCode::new(0xF4, "NOP", 2, 4, AddressingMode::ZeroPageX),
-        //
-        OpCode::new(0xA7, "LAX", 2, 3, AddressingMode::ZeroPage),
-        OpCode::new(0xB7, "LAX", 2, 4, AddressingMode::ZeroPageY),
-        OpCode::new(0xAF, "LAX", 3, 4, AddressingMode::Absolute),
-        OpCode::new(0xBF, "LAX", 3, 4, AddressingMode::AbsoluteY),
-        OpCode::new(0xA3, "LAX", 2, 6, AddressingMode::IndirectX),
-        OpCode::new(0xB3, "LAX", 2, 5, AddressingMode::IndirectY),
-        OpCode::new(0x87, "SAX", 2, 3, AddressingMode::ZeroPage),
-        OpCode::new(0x97, "SAX", 2, 4, AddressingMode::ZeroPageY),
-        OpCode::new(0x8F, "SAX", 3, 4, AddressingMode::Absolute),
-        OpCode::new(0x83, "SAX", 2, 6, AddressingMode::IndirectX),
-        OpCode::new(0xEB, "SBC", 2, 2, AddressingMode::Immediate),
-        OpCode::new(0xC3, "DCP", 2, 8, AddressingMode::IndirectX),
-        OpCode::new(0xC7, "DCP", 2, 5, AddressingMode::ZeroPage),
-        OpCode::new(0xCF, "DCP", 3, 6, AddressingMode::Absolute),
-        OpCode::new(0xD3, "DCP", 2, 8, AddressingMode::IndirectY),
-        OpCode::new(0xD7, "DCP", 2, 6, AddressingMode::ZeroPageX),
-        OpCode::new(0xDB, "DCP", 3, 7, AddressingMode::AbsoluteY),
-        OpCode::new(0xDF, "DCP", 3, 7, AddressingMode::AbsoluteX),
-        OpCode::new(0xE3, "ISB", 2, 8, AddressingMode::IndirectX),
-        OpCode::new(0xE7, "ISB", 2, 5, AddressingMode::ZeroPage),
-        OpCode::new(0xEF, "ISB", 3, 6, AddressingMode::Absolute),
-        OpCode::new(0xF3, "ISB", 2, 8, AddressingMode::IndirectY),
-        OpCode::new(0xF7, "ISB", 2, 6, AddressingMode::ZeroPageX),
-        OpCode::new(0xFB, "ISB", 3, 7, AddressingMode::AbsoluteY),
-        OpCode::new(0xFF, "ISB", 3, 7, AddressingMode::AbsoluteX),
-        OpCode::new(0x03, "SLO", 2, 8, AddressingMode::IndirectX),
-        OpCode::new(0x07, "SLO", 2, 5, AddressingMode::ZeroPage),
-        OpCode::new(0x0F, "SLO", 3, 6, AddressingMode::Absolute),
-        OpCode::new(0x13, "SLO", 2, 8, AddressingMode::IndirectY),
-        OpCode::new(0x17, "SLO", 2, 6, AddressingMode::ZeroPageX),
-        OpCode::new(0x1B, "SLO", 3, 7, AddressingMode::AbsoluteY),
-        OpCode::new(0x1F, "SLO", 3, 7, AddressingMode::AbsoluteX),
-        OpCode::new(0x23, "RLA", 2, 8, AddressingMode::IndirectX),
-        OpCode::new(0x27, "RLA", 2, 5, AddressingMode::ZeroPage),
-        OpCode::new(0x2F, "RLA", 3, 6, AddressingMode::Absolute),
-        OpCode::new(0x33, "RLA", 2, 8, AddressingMode::IndirectY),
-        OpCode::new(0x37, "RLA", 2, 6, AddressingMode::ZeroPageX),
-        OpCode::new(0x3B, "RLA", 3, 7, AddressingMode::AbsoluteY),
-        OpCode::new(0x3F, "RLA", 3, 7, AddressingMode::AbsoluteX),
-        OpCode::new(0x43, "SRE", 2, 8, AddressingMode::IndirectX),
-        OpCode::new(0x47, "SRE", 2, 5, AddressingMode::ZeroPage),
-        OpCode::new(0x4F, "SRE", 3, 6, AddressingMode::Absolute),
-        OpCode::new(0x53, "SRE", 2, 8, AddressingMode::IndirectY),
-        OpCode::new(0x57, "SRE", 2, 6, AddressingMode::ZeroPageX),
-        OpCode::new(0x5B, "SRE", 3, 7, AddressingMode::AbsoluteY),
-        OpCode::new(0x5F, "SRE", 3, 7, AddressingMode::AbsoluteX),
-        OpCode::new(0x63, "RRA", 2, 8, AddressingMode::IndirectX),
-        OpCode::new(0x67, "RRA", 2, 5, AddressingMode::ZeroPage),
-        OpCode::new(0x6F, "RRA", 3, 6, AddressingMode::Absolute),
-        OpCode::new(0x73, "RRA", 2, 8, AddressingMode::IndirectY),
-        OpCode::new(0x77, "RRA", 2, 6, AddressingMode::ZeroPageX),
-        OpCode::new(0x7B, "RRA", 3, 7, AddressingMode::AbsoluteY),
-        OpCode::new(0x7F, "RRA", 3, 7, AddressingMode::AbsoluteX),
-    ]
-});
+const fn build_table() -> [OpCode; 256] {
+    let mut table = [CPU_OPCODE[0]; 256];
+    let mut filled = [false; 256];
 
-pub static OPCODE_MAP: Lazy<HashMap<u8, &'static OpCode>> = Lazy::new(|| {
-    let mut map = HashMap::new();
-    for op in CPU_OPCODE.iter() {
-        map.insert(op.code, op);
+    let mut i = 0;
+    while i < CPU_OPCODE.len() {
+        table[CPU_OPCODE[i].code as usize] = CPU_OPCODE[i];
+        filled[CPU_OPCODE[i].code as usize] = true;
+        i += 1;
     }
 
-    let unofficial = UNOFFICIAL_OPCODE.iter();
-    for op in unofficial {
-        map.insert(op.code, op);
+    let mut i = 0;
+    while i < UNOFFICIAL_OPCODE.len() {
+        table[UNOFFICIAL_OPCODE[i].code as usize] = UNOFFICIAL_OPCODE[i];
+        filled[UNOFFICIAL_OPCODE[i].code as usize] = true;
+        i += 1;
     }
 
-    map
-});
+    let mut code = 0;
+    while code < filled.len() {
+        assert!(filled[code], "missing opcode in OPCODE_TABLE");
+        code += 1;
+    }
+
+    table
+}
@@ -0,0 +1,35 @@
+use super::{get_opecode, CPUTest, TestCPU};
+use crate::cpu::addressing_mode::AddressingMode;
+use crate::cpu::status::ProcessorStatus;
+use test_case::test_case;
+
+type ResultType = (u8, bool, bool, bool);
+
+fn assert(cpu: &mut TestCPU) -> ResultType {
+    (
+        cpu.register_a,
+        cpu.status.contains(ProcessorStatus::ZERO),
+        cpu.status.contains(ProcessorStatus::NEGATIVE),
+        cpu.status.contains(ProcessorStatus::CARRY),
+    )
+}
+
+#[test_case(
+    vec![get_opecode("ALR", AddressingMode::Immediate), 0x0F, 0x00],
+    |cpu| { cpu.register_a = 0xFF },
+    assert => (0x07, false, false, true);
+    "carry"
+)]
+#[test_case(
+    vec![get_opecode("ALR", AddressingMode::Immediate), 0xF0, 0x00],
+    |cpu| { cpu.register_a = 0x0F },
+    assert => (0x00, true, false, false);
+    "zero"
+)]
+fn test_alr(
+    code: Vec<u8>,
+    init: fn(cpu: &mut TestCPU) -> (),
+    assert: fn(&mut TestCPU) -> ResultType,
+) -> ResultType {
+    CPUTest::new(code, init, assert).run()
+}
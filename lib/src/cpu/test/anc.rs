@@ -0,0 +1,44 @@
+use super::{get_opecode, CPUTest, TestCPU};
+use crate::cpu::addressing_mode::AddressingMode;
+use crate::cpu::status::ProcessorStatus;
+use test_case::test_case;
+
+type ResultType = (u8, bool, bool, bool);
+
+fn assert(cpu: &mut TestCPU) -> ResultType {
+    (
+        cpu.register_a,
+        cpu.status.contains(ProcessorStatus::ZERO),
+        cpu.status.contains(ProcessorStatus::NEGATIVE),
+        cpu.status.contains(ProcessorStatus::CARRY),
+    )
+}
+
+#[test_case(
+    vec![0x0B, 0x0F, 0x00],
+    |cpu| { cpu.register_a = 0x3C },
+    assert => (0x0C, false, false, false);
+    "positive"
+)]
+#[test_case(
+    vec![0x2B, 0xF0, 0x00],
+    |cpu| { cpu.register_a = 0x81 },
+    assert => (0x80, false, true, true);
+    "negative_sets_carry"
+)]
+#[test_case(
+    vec![get_opecode("ANC", AddressingMode::Immediate), 0x00, 0x00],
+    |cpu| {
+        cpu.register_a = 0xFF;
+        cpu.status.set_carry(true);
+    },
+    assert => (0x00, true, false, false);
+    "zero_clears_carry"
+)]
+fn test_anc(
+    code: Vec<u8>,
+    init: fn(cpu: &mut TestCPU) -> (),
+    assert: fn(&mut TestCPU) -> ResultType,
+) -> ResultType {
+    CPUTest::new(code, init, assert).run()
+}
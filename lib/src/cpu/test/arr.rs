@@ -0,0 +1,45 @@
+use super::{get_opecode, CPUTest, TestCPU};
+use crate::cpu::addressing_mode::AddressingMode;
+use crate::cpu::status::ProcessorStatus;
+use test_case::test_case;
+
+type ResultType = (u8, bool, bool, bool, bool);
+
+fn assert(cpu: &mut TestCPU) -> ResultType {
+    (
+        cpu.register_a,
+        cpu.status.contains(ProcessorStatus::ZERO),
+        cpu.status.contains(ProcessorStatus::NEGATIVE),
+        cpu.status.contains(ProcessorStatus::CARRY),
+        cpu.status.contains(ProcessorStatus::OVERFLOW),
+    )
+}
+
+#[test_case(
+    vec![get_opecode("ARR", AddressingMode::Immediate), 0xFF, 0x00],
+    |cpu| { cpu.register_a = 0xC0 },
+    assert => (0x60, false, false, true, false);
+    "carry_from_bit6"
+)]
+#[test_case(
+    vec![get_opecode("ARR", AddressingMode::Immediate), 0xFF, 0x00],
+    |cpu| { cpu.register_a = 0x80 },
+    assert => (0x40, false, false, true, true);
+    "overflow_from_bit6_xor_bit5"
+)]
+#[test_case(
+    vec![get_opecode("ARR", AddressingMode::Immediate), 0x01, 0x00],
+    |cpu| {
+        cpu.register_a = 0x01;
+        cpu.status.set_carry(true);
+    },
+    assert => (0x80, false, true, false, false);
+    "rotate_in_carry"
+)]
+fn test_arr(
+    code: Vec<u8>,
+    init: fn(cpu: &mut TestCPU) -> (),
+    assert: fn(&mut TestCPU) -> ResultType,
+) -> ResultType {
+    CPUTest::new(code, init, assert).run()
+}
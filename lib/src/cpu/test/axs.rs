@@ -0,0 +1,53 @@
+use super::{get_opecode, CPUTest, TestCPU};
+use crate::cpu::addressing_mode::AddressingMode;
+use crate::cpu::status::ProcessorStatus;
+use test_case::test_case;
+
+type ResultType = (u8, bool, bool, bool, bool);
+
+fn assert(cpu: &mut TestCPU) -> ResultType {
+    (
+        cpu.register_x,
+        cpu.status.contains(ProcessorStatus::ZERO),
+        cpu.status.contains(ProcessorStatus::NEGATIVE),
+        cpu.status.contains(ProcessorStatus::CARRY),
+        cpu.status.contains(ProcessorStatus::OVERFLOW),
+    )
+}
+
+#[test_case(
+    vec![get_opecode("AXS", AddressingMode::Immediate), 0x02, 0x00],
+    |cpu| {
+        cpu.register_a = 0x0F;
+        cpu.register_x = 0xFC;
+    },
+    assert => (0x0A, false, false, true, false);
+    "no_borrow"
+)]
+#[test_case(
+    vec![get_opecode("AXS", AddressingMode::Immediate), 0x10, 0x00],
+    |cpu| {
+        cpu.register_a = 0x0F;
+        cpu.register_x = 0xFF;
+        cpu.status.set_carry(true);
+        cpu.status.set_overflow(true);
+    },
+    assert => (0xFF, false, true, false, true);
+    "borrow_keeps_overflow"
+)]
+#[test_case(
+    vec![get_opecode("AXS", AddressingMode::Immediate), 0x03, 0x00],
+    |cpu| {
+        cpu.register_a = 0x03;
+        cpu.register_x = 0x07;
+    },
+    assert => (0x00, true, false, true, false);
+    "zero"
+)]
+fn test_axs(
+    code: Vec<u8>,
+    init: fn(cpu: &mut TestCPU) -> (),
+    assert: fn(&mut TestCPU) -> ResultType,
+) -> ResultType {
+    CPUTest::new(code, init, assert).run()
+}
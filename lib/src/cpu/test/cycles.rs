@@ -0,0 +1,99 @@
+use super::{get_opecode, TestBus, TestCPU};
+use crate::{
+    bus::Mem,
+    cpu::{
+        addressing_mode::AddressingMode,
+        opecode::{Mnemonic, OPCODE_TABLE},
+        CPU,
+    },
+};
+use test_case::test_case;
+
+fn run_step(code: &[u8], initialize: fn(&mut TestCPU)) -> usize {
+    let mut cpu = CPU::new(TestBus::new(code));
+    cpu.program_counter = 0x8000;
+    initialize(&mut cpu);
+
+    cpu.step();
+
+    cpu.bus.cycles
+}
+
+#[test]
+fn test_opcode_table_cycles() {
+    // NOTE: メモリとインデックスレジスタが 0 ならページをまたがないので、表のサイクル数ちょうどになる
+    for op in OPCODE_TABLE.iter() {
+        if matches!(
+            op.mnemonic,
+            Mnemonic::Bcc
+                | Mnemonic::Bcs
+                | Mnemonic::Beq
+                | Mnemonic::Bmi
+                | Mnemonic::Bne
+                | Mnemonic::Bpl
+                | Mnemonic::Bvc
+                | Mnemonic::Bvs
+        ) {
+            continue;
+        }
+
+        let cycles = run_step(&[op.code, 0x00, 0x00], |_| {});
+        assert_eq!(
+            cycles,
+            op.cycles as usize,
+            "{} {:?} ({:#04X})",
+            op.mnemonic.name(),
+            op.addr_mode,
+            op.code
+        );
+    }
+}
+
+#[test_case(
+    vec![get_opecode("LDA", AddressingMode::AbsoluteX), 0xFF, 0x80],
+    |cpu| cpu.register_x = 0x01
+    => 5;
+    "read_page_crossed"
+)]
+#[test_case(
+    vec![get_opecode("LDA", AddressingMode::IndirectY), 0x10],
+    |cpu| {
+        cpu.mem_write_u16(0x10, 0x80FF);
+        cpu.register_y = 0x01;
+    }
+    => 6;
+    "indirect_y_page_crossed"
+)]
+#[test_case(
+    vec![get_opecode("STA", AddressingMode::AbsoluteX), 0x00, 0x02],
+    |cpu| cpu.register_x = 0x01
+    => 5;
+    "write_always_penalty"
+)]
+#[test_case(
+    vec![get_opecode("INC", AddressingMode::AbsoluteX), 0xFF, 0x02],
+    |cpu| cpu.register_x = 0x01
+    => 7;
+    "modify_page_crossed"
+)]
+#[test_case(
+    vec![get_opecode("BNE", AddressingMode::NoneAddressing), 0x10],
+    |cpu| cpu.status.set_zero(true)
+    => 2;
+    "branch_not_taken"
+)]
+#[test_case(
+    vec![get_opecode("BNE", AddressingMode::NoneAddressing), 0x10],
+    |cpu| cpu.status.set_zero(false)
+    => 3;
+    "branch_taken"
+)]
+#[test_case(
+    vec![get_opecode("BNE", AddressingMode::NoneAddressing), 0xF0],
+    |cpu| cpu.status.set_zero(false)
+    => 4;
+    "branch_page_crossed"
+)]
+fn test_cycles(code: Vec<u8>, initialize: fn(&mut TestCPU)) -> usize {
+    run_step(&code, initialize)
+}
@@ -28,8 +28,7 @@ fn assert(cpu: &mut TestCPU) -> TestResult {
         0x00,
     ],
     |cpu| {
-        cpu.mem_write_u16(0x1000, 0x2000);
-        cpu.mem_write_u16(0x2000, 0x8004);
+        cpu.mem_write_u16(0x1000, 0x8004);
     },
     assert => 0x01;
     "jump indirect"
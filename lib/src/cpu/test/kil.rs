@@ -0,0 +1,51 @@
+use super::{get_opecode, CPUTest, TestCPU};
+use crate::cpu::addressing_mode::AddressingMode;
+use test_case::test_case;
+
+type TestResult = (bool, u8, u16);
+
+fn assert(cpu: &mut TestCPU) -> TestResult {
+    (cpu.is_halted(), cpu.register_a, cpu.program_counter)
+}
+
+#[test_case(
+    vec![
+        get_opecode("KIL", AddressingMode::NoneAddressing),
+        get_opecode("LDA", AddressingMode::Immediate), 0x10,
+        0x00
+    ],
+    |_| {},
+    assert => (true, 0x00, 0x8001);
+    "halts"
+)]
+#[test_case(
+    vec![0xF2, 0x00],
+    |_| {},
+    assert => (true, 0x00, 0x8001);
+    "f2"
+)]
+fn test_kil(
+    code: Vec<u8>,
+    initialize: fn(&mut TestCPU),
+    assert: fn(&mut TestCPU) -> TestResult,
+) -> TestResult {
+    CPUTest::new(code, initialize, assert).run()
+}
+
+#[test]
+fn test_halted_cpu_keeps_bus_running() {
+    let mut cpu = super::TestCPU::new(super::TestBus::new(&[0x02]));
+    cpu.program_counter = 0x8000;
+
+    cpu.step();
+    let cycles = cpu.bus.cycles;
+    cpu.step();
+    cpu.step();
+
+    assert!(cpu.is_halted());
+    assert_eq!(cpu.program_counter, 0x8001);
+    assert_eq!(cpu.bus.cycles, cycles + 2);
+
+    cpu.reset();
+    assert!(!cpu.is_halted());
+}
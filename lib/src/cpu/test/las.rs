@@ -0,0 +1,51 @@
+use super::{get_opecode, CPUTest, TestCPU};
+use crate::bus::Mem;
+use crate::cpu::addressing_mode::AddressingMode;
+use test_case::test_case;
+
+type ResultType = (u8, u8, u8);
+
+fn assert(cpu: &mut TestCPU) -> ResultType {
+    (cpu.register_a, cpu.register_x, cpu.stack_pointer)
+}
+
+#[test_case(
+    vec![get_opecode("LAS", AddressingMode::AbsoluteY), 0x00, 0x02, 0x00],
+    |cpu| {
+        cpu.mem_write(0x0201, 0xF3);
+        cpu.register_y = 0x01;
+        cpu.stack_pointer = 0x3F;
+    },
+    assert => (0x33, 0x33, 0x33);
+    "absolute_y"
+)]
+fn test_las(
+    code: Vec<u8>,
+    init: fn(cpu: &mut TestCPU) -> (),
+    assert: fn(&mut TestCPU) -> ResultType,
+) -> ResultType {
+    CPUTest::new(code, init, assert).run()
+}
+
+#[test_case(
+    vec![get_opecode("LAX", AddressingMode::Immediate), 0x5A, 0x00],
+    |cpu| { cpu.register_a = 0x00 },
+    assert => (0x5A, 0x5A, 0xFD);
+    "lax_immediate"
+)]
+#[test_case(
+    vec![get_opecode("XAA", AddressingMode::Immediate), 0xFF, 0x00],
+    |cpu| {
+        cpu.register_a = 0x00;
+        cpu.register_x = 0x3C;
+    },
+    assert => (0x2C, 0x3C, 0xFD);
+    "xaa_immediate"
+)]
+fn test_unstable_immediate(
+    code: Vec<u8>,
+    init: fn(cpu: &mut TestCPU) -> (),
+    assert: fn(&mut TestCPU) -> ResultType,
+) -> ResultType {
+    CPUTest::new(code, init, assert).run()
+}
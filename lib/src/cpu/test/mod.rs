@@ -1,6 +1,10 @@
 mod adc;
+mod alr;
+mod anc;
 mod and;
+mod arr;
 mod asl;
+mod axs;
 mod bcc;
 mod bcs;
 mod beq;
@@ -11,20 +15,25 @@ mod bpl;
 mod bvc;
 mod bvs;
 mod cmp;
+mod cycles;
 mod dec;
 mod eor;
 mod inc;
 mod jmp;
 mod jsr;
+mod kil;
+mod las;
 mod lda;
 mod ldx;
 mod ldy;
 mod lsr;
 mod ora;
+mod reset;
 mod rol;
 mod ror;
 mod rti;
 mod sbc;
+mod sh;
 mod stack;
 mod status;
 mod store;
@@ -35,10 +44,11 @@ use crate::{
     cpu::CPU,
 };
 
-use super::{addressing_mode::AddressingMode, opecode::CPU_OPCODE};
+use super::{addressing_mode::AddressingMode, opecode::OPCODE_TABLE};
 
 struct TestBus {
     mem: [u8; 0x10000],
+    cycles: usize,
 }
 
 impl TestBus {
@@ -46,7 +56,7 @@ impl TestBus {
         let mut mem = [0u8; 0x10000];
         mem[0x8000..(0x8000 + code.len())].copy_from_slice(&code[..]);
 
-        TestBus { mem }
+        TestBus { mem, cycles: 0 }
     }
 }
 
@@ -79,8 +89,8 @@ impl Mem for TestBus {
 }
 
 impl Bus for TestBus {
-    fn tick(&mut self, _cycles: u8) {
-        // NOTE: 何もしない
+    fn tick(&mut self, cycles: u8) {
+        self.cycles += cycles as usize;
     }
 
     fn poll_nmi_status(&mut self) -> Option<bool> {
@@ -89,7 +99,7 @@ impl Bus for TestBus {
     }
 
     fn get_cycles(&self) -> (usize, usize) {
-        (0, 0)
+        (self.cycles, 0)
     }
 
     fn get_scanline(&self) -> u16 {
@@ -130,16 +140,23 @@ where
 
         (self.initialize)(&mut cpu);
 
-        cpu.run_with_callback(|_| {});
+        // NOTE: テスト用のプログラムは BRK で終わる。PC は BRK の次を指した状態で止める
+        while !cpu.is_halted() {
+            if cpu.mem_read(cpu.program_counter) == 0x00 {
+                cpu.program_counter = cpu.program_counter.wrapping_add(1);
+                break;
+            }
+            cpu.step();
+        }
 
         (self.assert)(&mut cpu)
     }
 }
 
 pub(super) fn get_opecode(name: &str, mode: AddressingMode) -> u8 {
-    CPU_OPCODE
+    OPCODE_TABLE
         .iter()
-        .find(|x| x.name == name && x.addr_mode == mode)
+        .find(|x| x.mnemonic.name() == name && x.addr_mode == mode)
         .take()
         .unwrap()
         .code
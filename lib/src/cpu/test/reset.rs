@@ -0,0 +1,19 @@
+use super::{TestBus, TestCPU};
+use crate::cpu::status::ProcessorStatus;
+
+// NOTE: 実機の RESET はスタックを読むだけで書き込まない。SP が 3 減って 0xFD になり、I フラグが立つ
+#[test]
+fn test_reset_does_not_write_stack() {
+    let mut cpu = TestCPU::new(TestBus::new(&[0x00]));
+    cpu.bus.mem[0x0100..0x0200].fill(0x55);
+    cpu.stack_pointer = 0x10;
+    cpu.status = ProcessorStatus::empty();
+    cpu.program_counter = 0x1234;
+
+    cpu.reset();
+
+    assert_eq!(cpu.stack_pointer, 0xFD);
+    assert!(cpu.bus.mem[0x0100..0x0200].iter().all(|&data| data == 0x55));
+    assert!(cpu.status.contains(ProcessorStatus::INTERRUPT));
+    assert_eq!(cpu.program_counter, 0x8000);
+}
@@ -19,7 +19,7 @@ fn assert(cpu: &mut TestCPU) -> TestResult {
         cpu.mem_write(0x01FD, 0b00110000);
         cpu.mem_write_u16(0x01FE, 0x1234);
     },
-    assert => (0b00110000, 0x1235);
+    assert => (0b00100000, 0x1235);
     "rti"
 )]
 fn test_rti(
@@ -0,0 +1,84 @@
+use super::{get_opecode, CPUTest, TestCPU};
+use crate::bus::Mem;
+use crate::cpu::addressing_mode::AddressingMode;
+use test_case::test_case;
+
+type TestResult = (u8, u8);
+
+fn assert(cpu: &mut TestCPU) -> TestResult {
+    (cpu.mem_read(0x0210), cpu.mem_read(0x0010))
+}
+
+#[test_case(
+    vec![get_opecode("SHY", AddressingMode::AbsoluteX), 0x00, 0x02, 0x00],
+    |cpu| {
+        cpu.register_x = 0x10;
+        cpu.register_y = 0xFF;
+    },
+    assert => (0x03, 0x00);
+    "shy"
+)]
+#[test_case(
+    vec![get_opecode("SHX", AddressingMode::AbsoluteY), 0x00, 0x02, 0x00],
+    |cpu| {
+        cpu.register_x = 0x1F;
+        cpu.register_y = 0x10;
+    },
+    assert => (0x03, 0x00);
+    "shx"
+)]
+#[test_case(
+    vec![get_opecode("AHX", AddressingMode::AbsoluteY), 0x00, 0x02, 0x00],
+    |cpu| {
+        cpu.register_a = 0x0E;
+        cpu.register_x = 0x07;
+        cpu.register_y = 0x10;
+    },
+    assert => (0x02, 0x00);
+    "ahx_absolute_y"
+)]
+#[test_case(
+    vec![get_opecode("AHX", AddressingMode::IndirectY), 0x20, 0x00],
+    |cpu| {
+        cpu.mem_write_u16(0x20, 0x0200);
+        cpu.register_a = 0xFF;
+        cpu.register_x = 0xFF;
+        cpu.register_y = 0x10;
+    },
+    assert => (0x03, 0x00);
+    "ahx_indirect_y"
+)]
+#[test_case(
+    vec![get_opecode("SHY", AddressingMode::AbsoluteX), 0xF0, 0x02, 0x00],
+    |cpu| {
+        cpu.register_x = 0x20;
+        cpu.register_y = 0x01;
+    },
+    |cpu| (cpu.mem_read(0x0110), cpu.mem_read(0x0310)) => (0x01, 0x00);
+    "page_crossed_replaces_high_byte"
+)]
+fn test_unstable_store(
+    code: Vec<u8>,
+    initialize: fn(&mut TestCPU),
+    assert: fn(&mut TestCPU) -> TestResult,
+) -> TestResult {
+    CPUTest::new(code, initialize, assert).run()
+}
+
+#[test_case(
+    vec![get_opecode("TAS", AddressingMode::AbsoluteY), 0x00, 0x02, 0x00],
+    |cpu| {
+        cpu.register_a = 0xF5;
+        cpu.register_x = 0x3E;
+        cpu.register_y = 0x10;
+    },
+    |cpu| (cpu.stack_pointer, cpu.mem_read(0x0210)) => (0x34, 0x00);
+    "tas"
+)]
+fn test_tas(
+    code: Vec<u8>,
+    initialize: fn(&mut TestCPU),
+    assert: fn(&mut TestCPU) -> TestResult,
+) -> TestResult {
+    CPUTest::new(code, initialize, assert).run()
+}
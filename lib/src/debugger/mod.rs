@@ -0,0 +1,196 @@
+use std::collections::BTreeSet;
+
+use crate::disasm::Symbols;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Registers {
+    pub pc: u16,
+    pub sp: u8,
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub status: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flag {
+    Carry,
+    Zero,
+    Interrupt,
+    Decimal,
+    Break,
+    Overflow,
+    Negative,
+}
+
+impl Flag {
+    pub fn mask(&self) -> u8 {
+        match self {
+            Flag::Carry => 0b0000_0001,
+            Flag::Zero => 0b0000_0010,
+            Flag::Interrupt => 0b0000_0100,
+            Flag::Decimal => 0b0000_1000,
+            Flag::Break => 0b0001_0000,
+            Flag::Overflow => 0b0100_0000,
+            Flag::Negative => 0b1000_0000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressSpace {
+    Cpu,
+    Ppu,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchKind {
+    Read,
+    Write,
+    Access,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub space: AddressSpace,
+    pub start: u16,
+    pub end: u16,
+    pub kind: WatchKind,
+}
+
+impl Watchpoint {
+    pub fn new(space: AddressSpace, addr: u16, kind: WatchKind) -> Self {
+        Self {
+            space,
+            start: addr,
+            end: addr,
+            kind,
+        }
+    }
+
+    fn matches(&self, space: AddressSpace, addr: u16, is_write: bool) -> bool {
+        let kind = match self.kind {
+            WatchKind::Read => !is_write,
+            WatchKind::Write => is_write,
+            WatchKind::Access => true,
+        };
+        kind && self.space == space && (self.start..=self.end).contains(&addr)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchHit {
+    pub space: AddressSpace,
+    pub addr: u16,
+    pub value: u8,
+    pub is_write: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunMode {
+    StepInto,
+    // NOTE: JSR ならサブルーチンから戻るまで実行する
+    StepOver,
+    // NOTE: 今のサブルーチンから RTS / RTI で抜けるまで実行する
+    StepOut,
+    Continue,
+    ToScanline(u16),
+    ToFrame,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    Step,
+    Breakpoint(u16),
+    Watchpoint(WatchHit),
+    Scanline(u16),
+    Frame,
+    Halted,
+    // NOTE: 命令数の上限に達した。無限ループでもフロントエンドに制御を返すため
+    Limit,
+}
+
+#[derive(Debug, Default)]
+pub struct Debugger {
+    breakpoints: BTreeSet<u16>,
+    watchpoints: Vec<Watchpoint>,
+    hit: Option<WatchHit>,
+    symbols: Symbols,
+}
+
+impl Debugger {
+    pub fn add_breakpoint(&mut self, pc: u16) {
+        self.breakpoints.insert(pc);
+    }
+
+    pub fn remove_breakpoint(&mut self, pc: u16) -> bool {
+        self.breakpoints.remove(&pc)
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = u16> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    pub fn has_breakpoint(&self, pc: u16) -> bool {
+        self.breakpoints.contains(&pc)
+    }
+
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) {
+        if !self.watchpoints.contains(&watchpoint) {
+            self.watchpoints.push(watchpoint);
+        }
+    }
+
+    pub fn remove_watchpoint(&mut self, watchpoint: &Watchpoint) -> bool {
+        let len = self.watchpoints.len();
+        self.watchpoints.retain(|w| w != watchpoint);
+        self.watchpoints.len() != len
+    }
+
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    pub fn set_symbols(&mut self, symbols: Symbols) {
+        self.symbols = symbols;
+    }
+
+    pub fn symbols(&self) -> &Symbols {
+        &self.symbols
+    }
+
+    pub fn clear(&mut self) {
+        self.breakpoints.clear();
+        self.watchpoints.clear();
+        self.hit = None;
+    }
+
+    pub(crate) fn check_access(
+        &mut self,
+        space: AddressSpace,
+        addr: u16,
+        value: u8,
+        is_write: bool,
+    ) {
+        if self.watchpoints.is_empty() {
+            return;
+        }
+
+        if self
+            .watchpoints
+            .iter()
+            .any(|w| w.matches(space, addr, is_write))
+        {
+            self.hit = Some(WatchHit {
+                space,
+                addr,
+                value,
+                is_write,
+            });
+        }
+    }
+
+    pub(crate) fn take_hit(&mut self) -> Option<WatchHit> {
+        self.hit.take()
+    }
+}
@@ -0,0 +1,128 @@
+use std::{cell::RefCell, collections::BTreeMap, fmt::Display, rc::Rc};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Category {
+    Bus,
+    Ppu,
+    Apu,
+}
+
+impl Display for Category {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Category::Bus => "bus",
+            Category::Ppu => "ppu",
+            Category::Apu => "apu",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub category: Category,
+    pub feature: &'static str,
+    pub message: String,
+    pub count: usize,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] {} (x{})", self.category, self.message, self.count)
+    }
+}
+
+pub trait DiagnosticsSink {
+    fn report(&mut self, diagnostic: &Diagnostic);
+}
+
+#[derive(Default)]
+struct Inner {
+    sink: Option<Box<dyn DiagnosticsSink>>,
+    counters: BTreeMap<(Category, &'static str), usize>,
+}
+
+// NOTE: 未実装の機能や無視したアクセスを数える。バス・PPU・APU で同じものを共有する
+#[derive(Clone, Default)]
+pub struct Diagnostics {
+    inner: Rc<RefCell<Inner>>,
+}
+
+impl Diagnostics {
+    pub fn set_sink(&self, sink: Option<Box<dyn DiagnosticsSink>>) {
+        self.inner.borrow_mut().sink = sink;
+    }
+
+    pub fn counters(&self) -> Vec<(Category, &'static str, usize)> {
+        self.inner
+            .borrow()
+            .counters
+            .iter()
+            .map(|(&(category, feature), &count)| (category, feature, count))
+            .collect()
+    }
+
+    pub fn clear(&self) {
+        self.inner.borrow_mut().counters.clear();
+    }
+
+    // NOTE: 毎フレーム起きるようなものでシンクが溢れないよう、1, 2, 4, 8... 回目だけ通知する
+    pub(crate) fn report<F>(&self, category: Category, feature: &'static str, message: F)
+    where
+        F: FnOnce() -> String,
+    {
+        let mut inner = self.inner.borrow_mut();
+
+        let count = inner.counters.entry((category, feature)).or_insert(0);
+        *count += 1;
+        let count = *count;
+
+        if !count.is_power_of_two() {
+            return;
+        }
+        if let Some(sink) = inner.sink.as_mut() {
+            sink.report(&Diagnostic {
+                category,
+                feature,
+                message: message(),
+                count,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::{Category, Diagnostic, Diagnostics, DiagnosticsSink};
+
+    struct RecordSink(Rc<RefCell<Vec<usize>>>);
+
+    impl DiagnosticsSink for RecordSink {
+        fn report(&mut self, diagnostic: &Diagnostic) {
+            self.0.borrow_mut().push(diagnostic.count);
+        }
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let reported = Rc::new(RefCell::new(vec![]));
+        let diagnostics = Diagnostics::default();
+        diagnostics.set_sink(Some(Box::new(RecordSink(reported.clone()))));
+
+        for _ in 0..10 {
+            diagnostics.report(Category::Apu, "dmc", || String::from("DMC"));
+        }
+        diagnostics.report(Category::Ppu, "chr-rom-write", || String::from("CHR"));
+
+        assert_eq!(*reported.borrow(), vec![1, 2, 4, 8, 1]);
+        assert_eq!(
+            diagnostics.counters(),
+            vec![
+                (Category::Ppu, "chr-rom-write", 1),
+                (Category::Apu, "dmc", 10)
+            ]
+        );
+    }
+}
@@ -0,0 +1,243 @@
+use std::fmt::Display;
+
+use crate::cpu::{
+    addressing_mode::AddressingMode,
+    opecode::{Mnemonic, OPCODE_TABLE, UNOFFICIAL_OPCODE},
+};
+
+pub use symbols::Symbols;
+
+mod symbols;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    pub addr: u16,
+    pub bytes: Vec<u8>,
+    pub mnemonic: &'static str,
+    pub operand: String,
+    // NOTE: オペランドが指すアドレス (分岐先やジャンプ先を含む)。即値や間接参照の先は含まない
+    pub target: Option<u16>,
+    pub is_unofficial: bool,
+    pub label: Option<String>,
+    pub comment: Option<String>,
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let bytes = self
+            .bytes
+            .iter()
+            .map(|b| format!("{:02X}", b))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let name = if self.is_unofficial {
+            format!("*{}", self.mnemonic)
+        } else {
+            self.mnemonic.to_string()
+        };
+
+        let text = format!(
+            "{:04X}  {:8} {:>4} {}",
+            self.addr, bytes, name, self.operand
+        );
+        match &self.comment {
+            Some(comment) => write!(f, "{:32} ; {}", text, comment),
+            None => write!(f, "{}", text.trim_end()),
+        }
+    }
+}
+
+pub fn disassemble(bytes: &[u8], origin: u16) -> Vec<Instruction> {
+    disassemble_with_symbols(bytes, origin, &Symbols::default())
+}
+
+pub fn disassemble_with_symbols(bytes: &[u8], origin: u16, symbols: &Symbols) -> Vec<Instruction> {
+    let mut instructions = vec![];
+
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let addr = origin.wrapping_add(offset as u16);
+        let instruction = decode(&bytes[offset..], addr, symbols);
+        offset += instruction.bytes.len();
+        instructions.push(instruction);
+    }
+
+    instructions
+}
+
+fn decode(bytes: &[u8], addr: u16, symbols: &Symbols) -> Instruction {
+    let label = symbols.get(addr).map(|s| s.to_string());
+
+    let op = OPCODE_TABLE[bytes[0] as usize];
+    // NOTE: 末尾で命令が途切れている場合はデータとして出す
+    if bytes.len() < op.size as usize {
+        return Instruction {
+            addr,
+            bytes: vec![bytes[0]],
+            mnemonic: ".byte",
+            operand: format!("${:02X}", bytes[0]),
+            target: None,
+            is_unofficial: false,
+            label,
+            comment: None,
+        };
+    }
+
+    let operand_bytes = &bytes[1..op.size as usize];
+    let value = match operand_bytes {
+        [lo] => *lo as u16,
+        [lo, hi] => (*hi as u16) << 8 | *lo as u16,
+        _ => 0,
+    };
+
+    let target = match op.addr_mode {
+        AddressingMode::ZeroPage
+        | AddressingMode::ZeroPageX
+        | AddressingMode::ZeroPageY
+        | AddressingMode::Absolute
+        | AddressingMode::AbsoluteX
+        | AddressingMode::AbsoluteY
+        | AddressingMode::Indirect => Some(value),
+        AddressingMode::NoneAddressing if is_branch(op.mnemonic) => {
+            Some(addr.wrapping_add(2).wrapping_add(value as u8 as i8 as u16))
+        }
+        _ => None,
+    };
+
+    let name = |width: usize| {
+        let addr = target.unwrap_or(value);
+        match symbols.get(addr) {
+            Some(label) => label.to_string(),
+            None if width == 2 => format!("${:02X}", addr),
+            None => format!("${:04X}", addr),
+        }
+    };
+    let operand = match op.addr_mode {
+        AddressingMode::Immediate => format!("#${:02X}", value),
+        AddressingMode::ZeroPage => name(2),
+        AddressingMode::ZeroPageX => format!("{},X", name(2)),
+        AddressingMode::ZeroPageY => format!("{},Y", name(2)),
+        AddressingMode::Absolute => name(4),
+        AddressingMode::AbsoluteX => format!("{},X", name(4)),
+        AddressingMode::AbsoluteY => format!("{},Y", name(4)),
+        AddressingMode::Indirect => format!("({})", name(4)),
+        AddressingMode::IndirectX => format!("(${:02X},X)", value),
+        AddressingMode::IndirectY => format!("(${:02X}),Y", value),
+        AddressingMode::NoneAddressing => match (target, op.code) {
+            (Some(_), _) => name(4),
+            // NOTE: アキュムレータを対象にするシフト・ローテート
+            (None, 0x0A | 0x2A | 0x4A | 0x6A) => String::from("A"),
+            (None, _) => String::new(),
+        },
+    };
+
+    Instruction {
+        addr,
+        bytes: bytes[..op.size as usize].to_vec(),
+        mnemonic: op.mnemonic.name(),
+        operand,
+        target,
+        is_unofficial: UNOFFICIAL_OPCODE.iter().any(|o| o.code == op.code),
+        label,
+        comment: target.and_then(register_name).map(|s| s.to_string()),
+    }
+}
+
+fn is_branch(mnemonic: Mnemonic) -> bool {
+    matches!(
+        mnemonic,
+        Mnemonic::Bcc
+            | Mnemonic::Bcs
+            | Mnemonic::Beq
+            | Mnemonic::Bmi
+            | Mnemonic::Bne
+            | Mnemonic::Bpl
+            | Mnemonic::Bvc
+            | Mnemonic::Bvs
+    )
+}
+
+pub fn register_name(addr: u16) -> Option<&'static str> {
+    let name = match addr {
+        0x2000 => "PPUCTRL",
+        0x2001 => "PPUMASK",
+        0x2002 => "PPUSTATUS",
+        0x2003 => "OAMADDR",
+        0x2004 => "OAMDATA",
+        0x2005 => "PPUSCROLL",
+        0x2006 => "PPUADDR",
+        0x2007 => "PPUDATA",
+        0x4000 => "SQ1_VOL",
+        0x4001 => "SQ1_SWEEP",
+        0x4002 => "SQ1_LO",
+        0x4003 => "SQ1_HI",
+        0x4004 => "SQ2_VOL",
+        0x4005 => "SQ2_SWEEP",
+        0x4006 => "SQ2_LO",
+        0x4007 => "SQ2_HI",
+        0x4008 => "TRI_LINEAR",
+        0x400A => "TRI_LO",
+        0x400B => "TRI_HI",
+        0x400C => "NOISE_VOL",
+        0x400E => "NOISE_LO",
+        0x400F => "NOISE_HI",
+        0x4010 => "DMC_FREQ",
+        0x4011 => "DMC_RAW",
+        0x4012 => "DMC_START",
+        0x4013 => "DMC_LEN",
+        0x4014 => "OAMDMA",
+        0x4015 => "SND_CHN",
+        0x4016 => "JOY1",
+        0x4017 => "JOY2",
+        _ => return None,
+    };
+    Some(name)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{disassemble, disassemble_with_symbols, Symbols};
+
+    #[test]
+    fn test_disassemble() {
+        let code = [
+            0xA9, 0x10, // LDA #$10
+            0x8D, 0x00, 0x20, // STA $2000
+            0xB1, 0x20, // LDA ($20),Y
+            0x0A, // ASL A
+            0xD0, 0xF6, // BNE $8000
+            0xA7, 0x10, // *LAX $10
+            0x20, 0x00, // 途切れた JSR
+        ];
+        let lines = disassemble(&code, 0x8000)
+            .iter()
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            lines,
+            vec![
+                "8000  A9 10     LDA #$10",
+                "8002  8D 00 20  STA $2000        ; PPUCTRL",
+                "8005  B1 20     LDA ($20),Y",
+                "8007  0A        ASL A",
+                "8008  D0 F6     BNE $8000",
+                "800A  A7 10    *LAX $10",
+                "800C  20       .byte $20",
+                "800D  00        BRK",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_symbols() {
+        let symbols = Symbols::parse("game.nl", "$8000#Reset#\n$0010#counter#\n").unwrap();
+        let code = [0xE6, 0x10, 0x4C, 0x00, 0x80];
+        let instructions = disassemble_with_symbols(&code, 0x8000, &symbols);
+
+        assert_eq!(instructions[0].label.as_deref(), Some("Reset"));
+        assert_eq!(instructions[0].operand, "counter");
+        assert_eq!(instructions[1].operand, "Reset");
+        assert_eq!(instructions[1].target, Some(0x8000));
+    }
+}
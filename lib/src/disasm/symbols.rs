@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Default)]
+pub struct Symbols {
+    labels: HashMap<u16, String>,
+}
+
+impl Symbols {
+    // NOTE: 拡張子で形式を判定する (.nl: FCEUX / asm6, .mlb: Mesen, .dbg: ca65)
+    pub fn parse(name: &str, text: &str) -> Result<Self, String> {
+        let extension = name.rsplit('.').next().unwrap_or("").to_ascii_lowercase();
+        match extension.as_str() {
+            "nl" => Self::from_nl(text),
+            "mlb" => Self::from_mlb(text),
+            "dbg" => Self::from_dbg(text),
+            _ => Err(format!("Unknown symbol file: {}", name)),
+        }
+    }
+
+    // NOTE: `$8000#Reset#コメント`
+    pub fn from_nl(text: &str) -> Result<Self, String> {
+        let mut symbols = Self::default();
+        for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            let mut fields = line.split('#');
+            let addr = fields.next().unwrap_or("");
+            let label = fields.next().unwrap_or("");
+
+            let addr = parse_hex(addr.trim_start_matches('$'))
+                .ok_or_else(|| format!("Invalid .nl line: {}", line))?;
+            symbols.insert(addr, label);
+        }
+        Ok(symbols)
+    }
+
+    // NOTE: `P:0000:Reset:コメント`。P は PRG-ROM 内のオフセット、R は RAM、G はレジスタ
+    pub fn from_mlb(text: &str) -> Result<Self, String> {
+        let mut symbols = Self::default();
+        for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            let fields = line.split(':').collect::<Vec<_>>();
+            let (kind, addr, label) = match fields[..] {
+                [kind, addr, label, ..] => (kind, addr, label),
+                _ => return Err(format!("Invalid .mlb line: {}", line)),
+            };
+
+            // NOTE: 範囲指定 (`0010-0017`) は先頭だけ使う
+            let addr = addr.split('-').next().unwrap_or("");
+            let addr = parse_hex(addr).ok_or_else(|| format!("Invalid .mlb line: {}", line))?;
+            let addr = match kind {
+                "P" | "NesPrgRom" => 0x8000 | (addr & 0x7FFF),
+                "R" | "NesInternalRam" => addr & 0x07FF,
+                "S" | "NesSaveRam" | "W" | "NesWorkRam" => 0x6000 | (addr & 0x1FFF),
+                "G" | "NesMemory" | "NesRegister" => addr,
+                _ => continue,
+            };
+            symbols.insert(addr, label);
+        }
+        Ok(symbols)
+    }
+
+    // NOTE: ca65 のデバッグ情報のうち `sym` 行のラベルだけを使う
+    //       sym	id=0,name="reset",addrsize=absolute,scope=0,def=1,val=0x8000,seg=0,type=lab
+    pub fn from_dbg(text: &str) -> Result<Self, String> {
+        let mut symbols = Self::default();
+        for line in text.lines() {
+            let Some(attrs) = line.strip_prefix("sym\t") else {
+                continue;
+            };
+
+            let mut name = None;
+            let mut value = None;
+            let mut is_label = false;
+            for attr in attrs.split(',') {
+                match attr.split_once('=') {
+                    Some(("name", v)) => name = Some(v.trim_matches('"')),
+                    Some(("val", v)) => value = parse_hex(v.trim_start_matches("0x")),
+                    Some(("type", v)) => is_label = v == "lab",
+                    _ => {}
+                }
+            }
+
+            match (name, value, is_label) {
+                (Some(name), Some(value), true) => symbols.insert(value, name),
+                (_, _, true) => return Err(format!("Invalid .dbg line: {}", line)),
+                _ => {}
+            }
+        }
+        Ok(symbols)
+    }
+
+    pub fn insert(&mut self, addr: u16, label: &str) {
+        if !label.is_empty() {
+            self.labels.insert(addr, label.to_string());
+        }
+    }
+
+    pub fn get(&self, addr: u16) -> Option<&str> {
+        self.labels.get(&addr).map(|s| s.as_str())
+    }
+
+    pub fn merge(&mut self, other: Symbols) {
+        self.labels.extend(other.labels);
+    }
+
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+}
+
+fn parse_hex(s: &str) -> Option<u16> {
+    u32::from_str_radix(s.trim(), 16)
+        .ok()
+        .filter(|&v| v <= 0xFFFF)
+        .map(|v| v as u16)
+}
+
+#[cfg(test)]
+mod test {
+    use super::Symbols;
+
+    #[test]
+    fn test_from_mlb() {
+        let symbols = Symbols::parse(
+            "game.mlb",
+            "P:0000:Reset:entry point\nR:0010-0011:ptr\nG:2002:Status\nX:0000:ignored\n",
+        )
+        .unwrap();
+
+        assert_eq!(symbols.get(0x8000), Some("Reset"));
+        assert_eq!(symbols.get(0x0010), Some("ptr"));
+        assert_eq!(symbols.get(0x2002), Some("Status"));
+        assert_eq!(symbols.len(), 3);
+    }
+
+    #[test]
+    fn test_from_dbg() {
+        let text = [
+            "version\tmajor=2,minor=0",
+            "sym\tid=0,name=\"reset\",addrsize=absolute,scope=0,def=1,val=0x8000,seg=0,type=lab",
+            "sym\tid=1,name=\"SIZE\",addrsize=zeropage,scope=0,def=2,val=0x10,type=equ",
+            "sym\tid=2,name=\"nmi\",addrsize=absolute,scope=0,def=3,val=0xC123,seg=0,type=lab",
+        ]
+        .join("\n");
+        let symbols = Symbols::parse("game.dbg", &text).unwrap();
+
+        assert_eq!(symbols.get(0x8000), Some("reset"));
+        assert_eq!(symbols.get(0xC123), Some("nmi"));
+        assert_eq!(symbols.get(0x0010), None);
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(Symbols::parse("game.sym", "").is_err());
+        assert!(Symbols::parse("game.nl", "zzzz#label#").is_err());
+    }
+}
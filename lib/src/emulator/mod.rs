@@ -1,5 +1,22 @@
 use crate::{
-    bus::NESBus, cpu::CPU, joypad::JoypadHandler, render::Renderer, rom::Rom, speaker::Speaker,
+    bus::{Bus, NESBus},
+    cpu::{status::ProcessorStatus, CPU},
+    debugger::{Debugger, Flag, Registers, RunMode, StopReason},
+    diagnostics::Diagnostics,
+    disasm::{disassemble_with_symbols, Instruction},
+    fds::Fds,
+    joypad::JoypadHandler,
+    mapper::Cartridge,
+    record::Recorder,
+    region::Region,
+    render::{
+        filter::Filter,
+        utils::{frame::Frame, palette::Palette},
+        Renderer,
+    },
+    rom::Rom,
+    speaker::Speaker,
+    trace::{TraceLogger, TraceState},
 };
 
 pub struct Emulator<S, J, R>
@@ -9,6 +26,10 @@ where
     R: Renderer,
 {
     cpu: CPU<NESBus<S, J, R>>,
+    trace_logger: Option<TraceLogger>,
+    recorder: Option<Recorder>,
+    audio_dump: Option<Recorder>,
+    recorded_frame: usize,
 }
 
 impl<S, J, R> Emulator<S, J, R>
@@ -20,17 +41,785 @@ where
     pub fn new(rom_data: Vec<u8>, speaker: S, handler: J, renderer: R) -> Self {
         let rom = Rom::new(&rom_data).unwrap();
 
-        let bus = NESBus::new(rom, speaker, handler, renderer);
+        Self::with_cartridge(rom.into(), speaker, handler, renderer)
+    }
+
+    // NOTE: patch は前回までにディスクへ書き込んだ内容 (sidecar の IPS)
+    pub fn new_fds(
+        image: Vec<u8>,
+        patch: Option<Vec<u8>>,
+        bios: Vec<u8>,
+        speaker: S,
+        handler: J,
+        renderer: R,
+    ) -> Result<Self, String> {
+        let fds = Fds::new(image, patch.as_deref(), bios)?;
+        let cartridge = Cartridge::fds(fds);
+        Ok(Self::with_cartridge(cartridge, speaker, handler, renderer))
+    }
+
+    fn with_cartridge(cartridge: Cartridge, speaker: S, handler: J, renderer: R) -> Self {
+        let bus = NESBus::new(cartridge, speaker, handler, renderer);
         let cpu = CPU::new(bus);
 
-        Self { cpu }
+        Self {
+            cpu,
+            trace_logger: None,
+            recorder: None,
+            audio_dump: None,
+            recorded_frame: 0,
+        }
     }
 
     pub fn reset(&mut self) {
         self.cpu.reset();
     }
 
+    // NOTE: リセットベクタを無視して pc から始める。nestest の自動実行モード用
+    pub fn reset_with_pc(&mut self, pc: u16) {
+        self.cpu.reset_with_pc(pc);
+    }
+
     pub fn step(&mut self) {
+        self.trace();
         self.cpu.step();
+        self.record();
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.cpu.is_halted()
+    }
+
+    pub fn set_region(&mut self, region: Region) {
+        self.cpu.bus.set_region(region);
+    }
+
+    pub fn region(&self) -> Region {
+        self.cpu.bus.get_region()
+    }
+
+    pub fn frame_rate(&self) -> f32 {
+        self.region().frame_rate()
+    }
+
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.cpu.bus.set_palette(palette);
+    }
+
+    // NOTE: FDS 以外では 0
+    pub fn disk_sides(&self) -> usize {
+        self.cpu
+            .bus
+            .mapper()
+            .fds()
+            .map_or(0, |fds| fds.side_count())
+    }
+
+    pub fn disk_side(&self) -> Option<usize> {
+        self.cpu.bus.mapper().fds().and_then(|fds| fds.side())
+    }
+
+    // NOTE: None でディスクを取り出す
+    pub fn insert_disk(&mut self, side: Option<usize>) {
+        if let Some(fds) = self.cpu.bus.mapper_mut().fds_mut() {
+            fds.insert(side);
+        }
+    }
+
+    pub fn switch_disk_side(&mut self) {
+        if let Some(fds) = self.cpu.bus.mapper_mut().fds_mut() {
+            fds.switch_side();
+        }
+    }
+
+    // NOTE: ディスクに書き込まれていれば、読み込んだイメージからの差分 (IPS) を返す
+    pub fn disk_patch(&self) -> Option<Vec<u8>> {
+        self.cpu.bus.mapper().fds().and_then(|fds| fds.patch())
+    }
+
+    pub fn diagnostics(&self) -> &Diagnostics {
+        self.cpu.bus.diagnostics()
+    }
+
+    pub fn renderer_mut(&mut self) -> &mut R {
+        self.cpu.bus.renderer_mut()
+    }
+
+    // NOTE: 最後にレンダラーへ渡したフレーム
+    pub fn frame(&self) -> &Frame {
+        self.cpu.bus.frame()
+    }
+
+    pub fn screenshot(&self, filter: Filter) -> Vec<u8> {
+        self.frame().to_png(filter)
+    }
+
+    // NOTE: 次に描画されたフレームから記録を始める。音声は recorder のサンプリングレートで APU から取り出す
+    pub fn start_recording(&mut self, recorder: Recorder) -> Result<(), String> {
+        check_sample_rate(&recorder, self.audio_dump.as_ref())?;
+        self.stop_recording();
+        self.recorded_frame = self.frame_count();
+        self.recorder = Some(recorder);
+        self.update_audio_capture();
+        Ok(())
+    }
+
+    pub fn stop_recording(&mut self) -> Option<Result<(), String>> {
+        self.recorder.as_ref()?;
+        self.flush_audio();
+        let recorder = self.recorder.take()?;
+        self.update_audio_capture();
+        Some(recorder.finish())
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    pub fn recorder(&self) -> Option<&Recorder> {
+        self.recorder.as_ref()
+    }
+
+    // NOTE: 映像の録画とは別に、音声だけを書き出す。APU から取り出す PCM は 1 つなので、録画とはサンプリングレートを揃える
+    pub fn start_audio_dump(&mut self, recorder: Recorder) -> Result<(), String> {
+        check_sample_rate(&recorder, self.recorder.as_ref())?;
+        self.stop_audio_dump();
+        self.audio_dump = Some(recorder);
+        self.update_audio_capture();
+        Ok(())
+    }
+
+    pub fn stop_audio_dump(&mut self) -> Option<Result<(), String>> {
+        self.audio_dump.as_ref()?;
+        self.flush_audio();
+        let recorder = self.audio_dump.take()?;
+        self.update_audio_capture();
+        Some(recorder.finish())
+    }
+
+    pub fn set_trace_logger(&mut self, logger: Option<TraceLogger>) {
+        self.trace_logger = logger;
+    }
+
+    pub fn trace_logger(&self) -> Option<&TraceLogger> {
+        self.trace_logger.as_ref()
+    }
+
+    pub fn trace_logger_mut(&mut self) -> Option<&mut TraceLogger> {
+        self.trace_logger.as_mut()
+    }
+
+    pub fn debugger(&self) -> &Debugger {
+        self.cpu.bus.debugger()
+    }
+
+    pub fn debugger_mut(&mut self) -> &mut Debugger {
+        self.cpu.bus.debugger_mut()
+    }
+
+    pub fn registers(&self) -> Registers {
+        Registers {
+            pc: self.cpu.program_counter,
+            sp: self.cpu.stack_pointer,
+            a: self.cpu.register_a,
+            x: self.cpu.register_x,
+            y: self.cpu.register_y,
+            status: self.cpu.status.bits(),
+        }
+    }
+
+    pub fn set_registers(&mut self, registers: Registers) {
+        self.cpu.program_counter = registers.pc;
+        self.cpu.stack_pointer = registers.sp;
+        self.cpu.register_a = registers.a;
+        self.cpu.register_x = registers.x;
+        self.cpu.register_y = registers.y;
+        self.cpu.status = ProcessorStatus::from_bits_truncate(registers.status);
+    }
+
+    pub fn flag(&self, flag: Flag) -> bool {
+        self.cpu.status.bits() & flag.mask() != 0
+    }
+
+    pub fn set_flag(&mut self, flag: Flag, value: bool) {
+        let mut registers = self.registers();
+        if value {
+            registers.status |= flag.mask();
+        } else {
+            registers.status &= !flag.mask();
+        }
+        self.set_registers(registers);
+    }
+
+    pub fn scanline(&self) -> u16 {
+        self.cpu.bus.get_scanline()
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.cpu.bus.get_frame_count()
+    }
+
+    // NOTE: 副作用なしで CPU のアドレス空間を読む。I/O レジスタはオープンバスの値になる
+    pub fn peek(&self, addr: u16) -> u8 {
+        self.cpu.bus.peek(addr)
+    }
+
+    // NOTE: mode の条件を満たすか、ブレークポイント・ウォッチポイントに当たるか、limit 命令実行したら戻る。
+    //       開始位置のブレークポイントでは止まらないので、同じ位置から続けて呼べる
+    pub fn run(&mut self, mode: RunMode, limit: usize) -> StopReason {
+        let start_sp = self.cpu.stack_pointer;
+        let start_frame = self.cpu.bus.get_frame_count();
+        let mut scanline = self.cpu.bus.get_scanline();
+
+        let return_addr = match mode {
+            RunMode::StepOver if self.peek_opcode() == JSR => {
+                Some(self.cpu.program_counter.wrapping_add(3))
+            }
+            _ => None,
+        };
+
+        for n in 0..limit {
+            let pc = self.cpu.program_counter;
+            if n > 0 && self.debugger().has_breakpoint(pc) {
+                return StopReason::Breakpoint(pc);
+            }
+
+            let opcode = self.peek_opcode();
+            self.step();
+
+            if let Some(hit) = self.debugger_mut().take_hit() {
+                return StopReason::Watchpoint(hit);
+            }
+            if self.cpu.is_halted() {
+                return StopReason::Halted;
+            }
+
+            match mode {
+                RunMode::StepInto => return StopReason::Step,
+                RunMode::StepOver => match return_addr {
+                    Some(addr) => {
+                        if self.cpu.program_counter == addr && self.cpu.stack_pointer == start_sp {
+                            return StopReason::Step;
+                        }
+                    }
+                    None => return StopReason::Step,
+                },
+                RunMode::StepOut => {
+                    if (opcode == RTS || opcode == RTI) && self.cpu.stack_pointer > start_sp {
+                        return StopReason::Step;
+                    }
+                }
+                RunMode::Continue => {}
+                RunMode::ToScanline(line) => {
+                    let current = self.cpu.bus.get_scanline();
+                    if current == line && scanline != line {
+                        return StopReason::Scanline(line);
+                    }
+                    scanline = current;
+                }
+                RunMode::ToFrame => {
+                    if self.cpu.bus.get_frame_count() != start_frame {
+                        return StopReason::Frame;
+                    }
+                }
+            }
+        }
+
+        StopReason::Limit
+    }
+
+    pub fn disassemble(&self, addr: u16, count: usize) -> Vec<Instruction> {
+        let bytes = (0..count * 3)
+            .map(|i| self.cpu.bus.peek(addr.wrapping_add(i as u16)))
+            .collect::<Vec<_>>();
+
+        let mut instructions = disassemble_with_symbols(&bytes, addr, self.debugger().symbols());
+        instructions.truncate(count);
+        instructions
+    }
+
+    fn trace(&mut self) {
+        let Some(logger) = &mut self.trace_logger else {
+            return;
+        };
+
+        let bus = &self.cpu.bus;
+        let frame = bus.get_frame_count();
+        if self.cpu.is_halted() || !logger.is_enabled(self.cpu.program_counter, frame) {
+            return;
+        }
+
+        let (cycles, dot) = bus.get_cycles();
+        let state = TraceState {
+            registers: Registers {
+                pc: self.cpu.program_counter,
+                sp: self.cpu.stack_pointer,
+                a: self.cpu.register_a,
+                x: self.cpu.register_x,
+                y: self.cpu.register_y,
+                status: self.cpu.status.bits(),
+            },
+            cycles,
+            scanline: bus.get_scanline(),
+            dot,
+            frame,
+        };
+        logger.log(&state, bus.debugger().symbols(), |addr| bus.peek(addr));
+    }
+
+    fn record(&mut self) {
+        if self.recorder.is_none() && self.audio_dump.is_none() {
+            return;
+        }
+
+        let frame_count = self.cpu.bus.get_frame_count();
+        if frame_count == self.recorded_frame {
+            return;
+        }
+        self.recorded_frame = frame_count;
+
+        let Some(recorder) = &mut self.recorder else {
+            self.flush_audio();
+            return;
+        };
+
+        recorder.push_frame(self.cpu.bus.frame());
+        self.flush_audio();
+    }
+
+    fn flush_audio(&mut self) {
+        let Some(audio) = self.cpu.bus.take_audio() else {
+            return;
+        };
+        for recorder in self.recorder.iter_mut().chain(self.audio_dump.iter_mut()) {
+            recorder.push_audio(&audio);
+        }
+    }
+
+    fn update_audio_capture(&mut self) {
+        let sample_rate = self
+            .recorder
+            .iter()
+            .chain(self.audio_dump.iter())
+            .find(|recorder| recorder.has_audio())
+            .map(|recorder| recorder.sample_rate());
+        if sample_rate != self.cpu.bus.audio_sample_rate() {
+            self.cpu.bus.set_audio_capture(sample_rate);
+        }
+    }
+
+    fn peek_opcode(&self) -> u8 {
+        self.cpu.bus.peek(self.cpu.program_counter)
+    }
+}
+
+fn check_sample_rate(recorder: &Recorder, other: Option<&Recorder>) -> Result<(), String> {
+    match other {
+        Some(other)
+            if recorder.has_audio()
+                && other.has_audio()
+                && recorder.sample_rate() != other.sample_rate() =>
+        {
+            Err(format!(
+                "Sample rate {} Hz does not match the {} Hz already being captured",
+                recorder.sample_rate(),
+                other.sample_rate()
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+const JSR: u8 = 0x20;
+const RTI: u8 = 0x40;
+const RTS: u8 = 0x60;
+
+#[cfg(test)]
+mod test {
+    use std::{
+        cell::RefCell,
+        io::{Cursor, Seek, SeekFrom, Write},
+        rc::Rc,
+    };
+
+    use crate::{
+        audio::Channel,
+        debugger::{AddressSpace, Flag, RunMode, StopReason, WatchHit, WatchKind, Watchpoint},
+        disasm::Symbols,
+        record::Recorder,
+        testrom::{NullJoypadHandler, NullRenderer, NullSpeaker},
+        trace::{TraceFormat, TraceLogger},
+    };
+
+    use super::Emulator;
+
+    type TestEmulator = Emulator<NullSpeaker, NullJoypadHandler, NullRenderer>;
+
+    fn test_emulator() -> TestEmulator {
+        #[rustfmt::skip]
+        let program = [
+            0xA2, 0x00,       // $8000: LDX #$00
+            0x20, 0x10, 0x80, // $8002: JSR $8010
+            0xE8,             // $8005: INX
+            0x8E, 0x00, 0x02, // $8006: STX $0200
+            0x4C, 0x02, 0x80, // $8009: JMP $8002
+        ];
+        #[rustfmt::skip]
+        let subroutine = [
+            0xA9, 0x42,       // $8010: LDA #$42
+            0xEA,             // $8012: NOP
+            0x60,             // $8013: RTS
+        ];
+
+        let mut prg_rom = vec![0xEA; 0x4000];
+        prg_rom[..program.len()].copy_from_slice(&program);
+        prg_rom[0x10..0x10 + subroutine.len()].copy_from_slice(&subroutine);
+        nrom_emulator(prg_rom)
+    }
+
+    fn nrom_emulator(mut prg_rom: Vec<u8>) -> TestEmulator {
+        let mut raw = vec![0x4E, 0x45, 0x53, 0x1A, 0x01, 0x00, 0x00, 0x00];
+        raw.resize(16, 0x00);
+        prg_rom[0x3FFC] = 0x00;
+        prg_rom[0x3FFD] = 0x80;
+        raw.extend(prg_rom);
+
+        let mut emulator = Emulator::new(raw, NullSpeaker, NullJoypadHandler, NullRenderer);
+        emulator.reset();
+        emulator
+    }
+
+    #[test]
+    fn test_step() {
+        let mut emulator = test_emulator();
+
+        assert_eq!(emulator.run(RunMode::StepInto, 100), StopReason::Step);
+        assert_eq!(emulator.registers().pc, 0x8002);
+
+        // NOTE: JSR をまたいで次の命令で止まる
+        assert_eq!(emulator.run(RunMode::StepOver, 100), StopReason::Step);
+        assert_eq!(emulator.registers().pc, 0x8005);
+        assert_eq!(emulator.registers().a, 0x42);
+
+        emulator.run(RunMode::StepOver, 100);
+        emulator.run(RunMode::StepOver, 100);
+        emulator.run(RunMode::StepOver, 100);
+        assert_eq!(emulator.registers().pc, 0x8002);
+
+        // NOTE: サブルーチンに入ってから抜ける
+        emulator.run(RunMode::StepInto, 100);
+        assert_eq!(emulator.registers().pc, 0x8010);
+        assert_eq!(emulator.run(RunMode::StepOut, 100), StopReason::Step);
+        assert_eq!(emulator.registers().pc, 0x8005);
+    }
+
+    #[test]
+    fn test_breakpoint() {
+        let mut emulator = test_emulator();
+        emulator.debugger_mut().add_breakpoint(0x8009);
+
+        assert_eq!(
+            emulator.run(RunMode::Continue, 1000),
+            StopReason::Breakpoint(0x8009)
+        );
+        assert_eq!(emulator.registers().x, 1);
+
+        assert_eq!(
+            emulator.run(RunMode::Continue, 1000),
+            StopReason::Breakpoint(0x8009)
+        );
+        assert_eq!(emulator.registers().x, 2);
+
+        emulator.debugger_mut().remove_breakpoint(0x8009);
+        assert_eq!(emulator.run(RunMode::Continue, 1000), StopReason::Limit);
+    }
+
+    #[test]
+    fn test_watchpoint() {
+        let mut emulator = test_emulator();
+        emulator.debugger_mut().add_watchpoint(Watchpoint::new(
+            AddressSpace::Cpu,
+            0x0200,
+            WatchKind::Write,
+        ));
+
+        assert_eq!(
+            emulator.run(RunMode::Continue, 1000),
+            StopReason::Watchpoint(WatchHit {
+                space: AddressSpace::Cpu,
+                addr: 0x0200,
+                value: 0x01,
+                is_write: true,
+            })
+        );
+        assert_eq!(emulator.registers().pc, 0x8009);
+    }
+
+    #[test]
+    fn test_run_to_scanline_and_frame() {
+        let mut emulator = test_emulator();
+
+        assert_eq!(
+            emulator.run(RunMode::ToScanline(100), 100_000),
+            StopReason::Scanline(100)
+        );
+        assert_eq!(emulator.scanline(), 100);
+
+        assert_eq!(emulator.run(RunMode::ToFrame, 100_000), StopReason::Frame);
+        assert_eq!(emulator.scanline(), 0);
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let mut emulator = test_emulator();
+        let mut symbols = Symbols::default();
+        symbols.insert(0x8010, "sub");
+        emulator.debugger_mut().set_symbols(symbols);
+
+        let lines = emulator
+            .disassemble(0x8000, 3)
+            .iter()
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![
+                "8000  A2 00     LDX #$00",
+                "8002  20 10 80  JSR sub",
+                "8005  E8        INX",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_registers() {
+        let mut emulator = test_emulator();
+
+        let mut registers = emulator.registers();
+        registers.a = 0x12;
+        registers.pc = 0x8010;
+        emulator.set_registers(registers);
+        emulator.set_flag(Flag::Carry, true);
+
+        assert_eq!(emulator.registers().a, 0x12);
+        assert_eq!(emulator.registers().pc, 0x8010);
+        assert!(emulator.flag(Flag::Carry));
+        assert!(!emulator.flag(Flag::Zero));
+    }
+
+    #[test]
+    fn test_trace_logger() {
+        let mut emulator = test_emulator();
+        let mut logger = TraceLogger::new(TraceFormat::Nestest);
+        logger.set_capacity(3);
+        logger.set_pc_range(Some(0x8000..=0x800F));
+        emulator.set_trace_logger(Some(logger));
+
+        for _ in 0..8 {
+            emulator.step();
+        }
+
+        // NOTE: サブルーチン内の命令は範囲外なので残らない
+        let history = emulator
+            .trace_logger()
+            .unwrap()
+            .history()
+            .map(|l| l[..19].to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            history,
+            vec![
+                "8005  E8        INX",
+                "8006  8E 00 02  STX",
+                "8009  4C 02 80  JMP",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_trace_logger_symbols() {
+        let mut emulator = test_emulator();
+        let mut symbols = Symbols::default();
+        symbols.insert(0x8010, "sub");
+        emulator.debugger_mut().set_symbols(symbols);
+        emulator.set_trace_logger(Some(TraceLogger::new(TraceFormat::Nestest)));
+
+        for _ in 0..3 {
+            emulator.step();
+        }
+
+        let history = emulator
+            .trace_logger()
+            .unwrap()
+            .history()
+            .map(|l| l[..30].trim_end().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            history,
+            vec![
+                "8000  A2 00     LDX #$00",
+                "8002  20 10 80  JSR sub",
+                "8010  A9 42    sub: LDA #$42",
+            ]
+        );
+    }
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Rc<RefCell<Cursor<Vec<u8>>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Seek for SharedBuffer {
+        fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+            self.0.borrow_mut().seek(pos)
+        }
+    }
+
+    #[test]
+    fn test_recording() {
+        let mut emulator = test_emulator();
+        let video = SharedBuffer::default();
+        let audio = SharedBuffer::default();
+
+        let mut recorder = Recorder::new(60.0, 48_000);
+        recorder.set_video(Box::new(video.clone())).unwrap();
+        recorder.set_audio(Box::new(audio.clone())).unwrap();
+        emulator.start_recording(recorder).unwrap();
+        assert!(emulator.is_recording());
+
+        for _ in 0..3 {
+            emulator.run(RunMode::ToFrame, 100_000);
+        }
+        assert_eq!(emulator.recorder().unwrap().frames(), 3);
+        assert_eq!(emulator.stop_recording(), Some(Ok(())));
+        assert!(!emulator.is_recording());
+        assert_eq!(emulator.stop_recording(), None);
+
+        let video = video.0.borrow().get_ref().clone();
+        let header = b"YUV4MPEG2 W256 H240 F60000:1000 Ip A1:1 C444\n";
+        assert!(video.starts_with(header));
+        assert_eq!(video.len(), header.len() + 3 * (6 + 256 * 240 * 3));
+
+        // NOTE: NTSC の 3 フレームぶん (約 0.05 秒) のサンプル
+        let audio = audio.0.borrow().get_ref().clone();
+        let samples = (audio.len() - 44) / 2;
+        assert!((2350..2450).contains(&samples), "{}", samples);
+    }
+
+    #[test]
+    fn test_audio_dump() {
+        let mut emulator = test_emulator();
+        let dump = SharedBuffer::default();
+        let stem = SharedBuffer::default();
+        let video = SharedBuffer::default();
+        let audio = SharedBuffer::default();
+
+        let mut recorder = Recorder::new(60.0, 48_000);
+        recorder.set_audio(Box::new(dump.clone())).unwrap();
+        recorder
+            .add_stem(Channel::Dmc, Box::new(stem.clone()))
+            .unwrap();
+        emulator.start_audio_dump(recorder).unwrap();
+        emulator.run(RunMode::ToFrame, 100_000);
+
+        // NOTE: 音声ダンプは録画の開始・終了とは関係なく続く
+        let mut recorder = Recorder::new(60.0, 48_000);
+        recorder.set_video(Box::new(video.clone())).unwrap();
+        recorder.set_audio(Box::new(audio.clone())).unwrap();
+        emulator.start_recording(recorder).unwrap();
+        emulator.run(RunMode::ToFrame, 100_000);
+        emulator.run(RunMode::ToFrame, 100_000);
+        assert_eq!(emulator.stop_recording(), Some(Ok(())));
+        emulator.run(RunMode::ToFrame, 100_000);
+        assert_eq!(emulator.stop_audio_dump(), Some(Ok(())));
+
+        let samples = |buffer: &SharedBuffer| (buffer.0.borrow().get_ref().len() - 44) / 2;
+        assert!((3150..3250).contains(&samples(&dump)), "{}", samples(&dump));
+        assert_eq!(samples(&stem), samples(&dump));
+        assert!(
+            (1550..1650).contains(&samples(&audio)),
+            "{}",
+            samples(&audio)
+        );
+    }
+
+    #[test]
+    fn test_recording_matches_audio_dump() {
+        #[rustfmt::skip]
+        let program = [
+            0xA9, 0x01,       // $8000: LDA #$01
+            0x8D, 0x15, 0x40, // $8002: STA $4015
+            0xA9, 0x9F,       // $8005: LDA #$9F
+            0x8D, 0x00, 0x40, // $8007: STA $4000
+            0xA9, 0xFF,       // $800A: LDA #$FF
+            0x8D, 0x02, 0x40, // $800C: STA $4002
+            0xA9, 0x08,       // $800F: LDA #$08
+            0x8D, 0x03, 0x40, // $8011: STA $4003
+            0x4C, 0x14, 0x80, // $8014: JMP $8014
+        ];
+        let mut prg_rom = vec![0xEA; 0x4000];
+        prg_rom[..program.len()].copy_from_slice(&program);
+        let mut emulator = nrom_emulator(prg_rom);
+
+        let dump = SharedBuffer::default();
+        let audio = SharedBuffer::default();
+        let mut recorder = Recorder::new(60.0, 48_000);
+        recorder.set_audio(Box::new(dump.clone())).unwrap();
+        emulator.start_audio_dump(recorder).unwrap();
+        let mut recorder = Recorder::new(60.0, 48_000);
+        recorder
+            .set_video(Box::new(SharedBuffer::default()))
+            .unwrap();
+        recorder.set_audio(Box::new(audio.clone())).unwrap();
+        emulator.start_recording(recorder).unwrap();
+
+        for _ in 0..3 {
+            emulator.run(RunMode::ToFrame, 100_000);
+        }
+        assert_eq!(emulator.stop_recording(), Some(Ok(())));
+        assert_eq!(emulator.stop_audio_dump(), Some(Ok(())));
+
+        // NOTE: 録画の音声も音声ダンプも、同じ APU の出力から作られる
+        let dump = dump.0.borrow().get_ref().clone();
+        let audio = audio.0.borrow().get_ref().clone();
+        assert_eq!(audio, dump);
+        assert!(audio[44..].chunks(2).any(|s| s != [0, 0]));
+    }
+
+    #[test]
+    fn test_sample_rate_mismatch() {
+        let mut emulator = test_emulator();
+        let mut recorder = Recorder::new(60.0, 48_000);
+        recorder
+            .set_audio(Box::new(SharedBuffer::default()))
+            .unwrap();
+        emulator.start_audio_dump(recorder).unwrap();
+
+        let mut recorder = Recorder::new(60.0, 44_100);
+        recorder
+            .set_audio(Box::new(SharedBuffer::default()))
+            .unwrap();
+        assert_eq!(
+            emulator.start_recording(recorder),
+            Err(String::from(
+                "Sample rate 44100 Hz does not match the 48000 Hz already being captured"
+            ))
+        );
+        assert!(!emulator.is_recording());
+
+        // NOTE: 映像だけなら音声のサンプリングレートは関係ない
+        let mut recorder = Recorder::new(60.0, 44_100);
+        recorder
+            .set_video(Box::new(SharedBuffer::default()))
+            .unwrap();
+        assert_eq!(emulator.start_recording(recorder), Ok(()));
     }
 }
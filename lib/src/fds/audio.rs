@@ -0,0 +1,358 @@
+use crate::apu::expansion::ExpansionAudio;
+
+const WAVE_TABLE: u16 = 0x4040;
+const WAVE_TABLE_END: u16 = 0x407F;
+const VOLUME_ENVELOPE: u16 = 0x4080;
+const WAVE_FREQUENCY_LO: u16 = 0x4082;
+const WAVE_FREQUENCY_HI: u16 = 0x4083;
+const MOD_ENVELOPE: u16 = 0x4084;
+const MOD_COUNTER: u16 = 0x4085;
+const MOD_FREQUENCY_LO: u16 = 0x4086;
+const MOD_FREQUENCY_HI: u16 = 0x4087;
+const MOD_TABLE: u16 = 0x4088;
+const MASTER_VOLUME: u16 = 0x4089;
+const ENVELOPE_SPEED: u16 = 0x408A;
+const VOLUME_GAIN: u16 = 0x4090;
+const MOD_GAIN: u16 = 0x4092;
+
+// NOTE: $4089 の下位 2bit で 2/2, 2/3, 2/4, 2/5 に絞る
+const MASTER_VOLUMES: [u32; 4] = [36, 24, 17, 14];
+// NOTE: 変調テーブルの値ごとのカウンタの増分。4 はカウンタを 0 に戻す
+const MOD_STEPS: [i8; 8] = [0, 1, 2, 4, 0, -4, -2, -1];
+const MOD_RESET: u8 = 4;
+
+#[derive(Default)]
+struct Envelope {
+    speed: u8,
+    increase: bool,
+    disabled: bool,
+    gain: u8,
+    timer: u32,
+    frequency: u16,
+}
+
+impl Envelope {
+    fn write_control(&mut self, data: u8, master_speed: u8) {
+        self.speed = data & 0x3F;
+        self.increase = data & 0x40 != 0;
+        self.disabled = data & 0x80 != 0;
+        self.reset_timer(master_speed);
+        if self.disabled {
+            self.gain = self.speed;
+        }
+    }
+
+    fn reset_timer(&mut self, master_speed: u8) {
+        self.timer = 8 * (self.speed as u32 + 1) * master_speed as u32;
+    }
+
+    fn tick(&mut self, master_speed: u8) -> bool {
+        if self.disabled || master_speed == 0 {
+            return false;
+        }
+
+        self.timer = self.timer.saturating_sub(1);
+        if self.timer > 0 {
+            return false;
+        }
+        self.reset_timer(master_speed);
+        if self.increase && self.gain < 32 {
+            self.gain += 1;
+        } else if !self.increase && self.gain > 0 {
+            self.gain -= 1;
+        }
+        true
+    }
+}
+
+// NOTE: 64 サンプル 6bit の波形メモリ音源。周波数は変調ユニットのカウンタとゲインで揺らす
+pub(crate) struct FdsAudio {
+    wave_table: [u8; 64],
+    wave_write: bool,
+    wave_halted: bool,
+    envelopes_halted: bool,
+    wave_position: u8,
+    wave_accumulator: u32,
+    volume: Envelope,
+    modulator: Envelope,
+    mod_table: [u8; 64],
+    mod_position: u8,
+    mod_counter: i8,
+    mod_accumulator: u16,
+    mod_halted: bool,
+    mod_output: i32,
+    master_volume: u8,
+    master_speed: u8,
+    output: u8,
+}
+
+impl FdsAudio {
+    pub(crate) fn new() -> Self {
+        Self {
+            wave_table: [0; 64],
+            wave_write: false,
+            wave_halted: true,
+            envelopes_halted: false,
+            wave_position: 0,
+            wave_accumulator: 0,
+            volume: Envelope::default(),
+            modulator: Envelope::default(),
+            mod_table: [0; 64],
+            mod_position: 0,
+            mod_counter: 0,
+            mod_accumulator: 0,
+            mod_halted: true,
+            mod_output: 0,
+            master_volume: 0,
+            master_speed: 0xE8,
+            output: 0,
+        }
+    }
+
+    fn tick_modulator(&mut self) -> bool {
+        if self.mod_halted || self.modulator.frequency == 0 {
+            return false;
+        }
+
+        let (accumulator, overflow) = self
+            .mod_accumulator
+            .overflowing_add(self.modulator.frequency);
+        self.mod_accumulator = accumulator;
+        if !overflow {
+            return false;
+        }
+
+        let value = self.mod_table[self.mod_position as usize];
+        let counter = if value == MOD_RESET {
+            0
+        } else {
+            self.mod_counter + MOD_STEPS[value as usize]
+        };
+        self.set_mod_counter(counter as u8);
+        self.mod_position = (self.mod_position + 1) & 0x3F;
+        true
+    }
+
+    // NOTE: 7bit の符号付きで折り返す
+    fn set_mod_counter(&mut self, value: u8) {
+        self.mod_counter = ((value << 1) as i8) >> 1;
+    }
+
+    // NOTE: カウンタ × ゲインを丸めてから周波数に掛ける。丸め方は実機の回路に合わせる
+    fn update_mod_output(&mut self, frequency: u16) {
+        let mut temp = self.mod_counter as i32 * self.modulator.gain as i32;
+        let remainder = temp & 0x0F;
+        temp >>= 4;
+        if remainder > 0 && temp & 0x80 == 0 {
+            temp += if self.mod_counter < 0 { -1 } else { 2 };
+        }
+        if temp >= 192 {
+            temp -= 256;
+        } else if temp < -64 {
+            temp += 256;
+        }
+
+        temp *= frequency as i32;
+        let remainder = temp & 0x3F;
+        temp >>= 6;
+        if remainder >= 32 {
+            temp += 1;
+        }
+        self.mod_output = temp;
+    }
+
+    fn update_output(&mut self) {
+        let level = self.volume.gain.min(32) as u32 * MASTER_VOLUMES[self.master_volume as usize];
+        self.output = (self.wave_table[self.wave_position as usize] as u32 * level / 1152) as u8;
+    }
+}
+
+impl ExpansionAudio for FdsAudio {
+    fn read(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            WAVE_TABLE..=WAVE_TABLE_END => {
+                let index = if self.wave_write {
+                    (addr - WAVE_TABLE) as u8
+                } else {
+                    self.wave_position
+                };
+                Some(self.wave_table[index as usize] | 0x40)
+            }
+            VOLUME_GAIN => Some(self.volume.gain | 0x40),
+            MOD_GAIN => Some(self.modulator.gain | 0x40),
+            _ => None,
+        }
+    }
+
+    fn write(&mut self, addr: u16, data: u8) {
+        match addr {
+            WAVE_TABLE..=WAVE_TABLE_END if self.wave_write => {
+                self.wave_table[(addr - WAVE_TABLE) as usize] = data & 0x3F;
+            }
+            VOLUME_ENVELOPE => self.volume.write_control(data, self.master_speed),
+            WAVE_FREQUENCY_LO => {
+                self.volume.frequency = (self.volume.frequency & 0x0F00) | data as u16;
+            }
+            WAVE_FREQUENCY_HI => {
+                self.volume.frequency =
+                    (self.volume.frequency & 0x00FF) | ((data as u16 & 0x0F) << 8);
+                self.wave_halted = data & 0x80 != 0;
+                self.envelopes_halted = data & 0x40 != 0;
+                if self.wave_halted {
+                    self.wave_position = 0;
+                    self.wave_accumulator = 0;
+                }
+                if self.envelopes_halted {
+                    self.volume.reset_timer(self.master_speed);
+                    self.modulator.reset_timer(self.master_speed);
+                }
+            }
+            MOD_ENVELOPE => self.modulator.write_control(data, self.master_speed),
+            MOD_COUNTER => self.set_mod_counter(data & 0x7F),
+            MOD_FREQUENCY_LO => {
+                self.modulator.frequency = (self.modulator.frequency & 0x0F00) | data as u16;
+            }
+            MOD_FREQUENCY_HI => {
+                self.modulator.frequency =
+                    (self.modulator.frequency & 0x00FF) | ((data as u16 & 0x0F) << 8);
+                self.mod_halted = data & 0x80 != 0;
+                if self.mod_halted {
+                    self.mod_accumulator = 0;
+                }
+            }
+            // NOTE: 変調を止めている間だけ書ける。1 回の書き込みで 2 つ埋まる
+            MOD_TABLE if self.mod_halted => {
+                let value = data & 0x07;
+                self.mod_table[self.mod_position as usize] = value;
+                self.mod_table[(self.mod_position as usize + 1) & 0x3F] = value;
+                self.mod_position = (self.mod_position + 2) & 0x3F;
+            }
+            MASTER_VOLUME => {
+                self.master_volume = data & 0x03;
+                self.wave_write = data & 0x80 != 0;
+            }
+            ENVELOPE_SPEED => self.master_speed = data,
+            _ => {}
+        }
+    }
+
+    fn tick(&mut self) {
+        let frequency = self.volume.frequency;
+        if !self.wave_halted && !self.envelopes_halted {
+            self.volume.tick(self.master_speed);
+            if self.modulator.tick(self.master_speed) {
+                self.update_mod_output(frequency);
+            }
+        }
+        if self.tick_modulator() {
+            self.update_mod_output(frequency);
+        }
+
+        self.update_output();
+        if self.wave_halted || self.wave_write {
+            return;
+        }
+        let pitch = frequency as i32 + self.mod_output;
+        if pitch > 0 {
+            self.wave_accumulator += pitch as u32;
+            if self.wave_accumulator > 0xFFFF {
+                self.wave_accumulator -= 0x10000;
+                self.wave_position = (self.wave_position + 1) & 0x3F;
+            }
+        }
+    }
+
+    // NOTE: 0.0..=1.0。DMC と同じく無音を 0 にする
+    fn output(&self) -> f32 {
+        self.output as f32 / 63.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ExpansionAudio, FdsAudio};
+
+    fn saw_audio() -> FdsAudio {
+        let mut audio = FdsAudio::new();
+        audio.write(0x4089, 0x80);
+        for i in 0..64 {
+            audio.write(0x4040 + i, i as u8);
+        }
+        audio.write(0x4089, 0x00);
+        // NOTE: エンベロープなしでゲイン 32
+        audio.write(0x4080, 0xA0);
+        audio
+    }
+
+    #[test]
+    fn test_wave_table() {
+        let mut audio = saw_audio();
+        assert_eq!(audio.read(0x4041), Some(0x40));
+
+        // NOTE: 周波数 0x400 なら 64 サイクルで 1 サンプル進む
+        audio.write(0x4082, 0x00);
+        audio.write(0x4083, 0x04);
+        for _ in 0..64 * 10 {
+            audio.tick();
+        }
+        audio.tick();
+        assert_eq!(audio.read(0x4040), Some(0x40 | 10));
+        assert_eq!(audio.output(), 10.0 / 63.0);
+
+        // NOTE: 停止すると先頭に戻る
+        audio.write(0x4083, 0x80);
+        audio.tick();
+        assert_eq!(audio.output(), 0.0);
+    }
+
+    #[test]
+    fn test_master_volume() {
+        let mut audio = saw_audio();
+        audio.write(0x4082, 0x00);
+        audio.write(0x4083, 0x04);
+        for _ in 0..64 * 63 + 1 {
+            audio.tick();
+        }
+        assert_eq!(audio.output(), 1.0);
+
+        audio.write(0x4089, 0x03);
+        audio.tick();
+        assert_eq!(audio.output(), (63 * 32 * 14 / 1152) as f32 / 63.0);
+    }
+
+    #[test]
+    fn test_volume_envelope() {
+        let mut audio = FdsAudio::new();
+        audio.write(0x408A, 0x01);
+        // NOTE: 増加、速さ 0 なら 8 サイクルごとに 1 上がる
+        audio.write(0x4080, 0x40);
+        audio.write(0x4083, 0x00);
+        for _ in 0..8 * 5 {
+            audio.tick();
+        }
+        assert_eq!(audio.read(0x4090), Some(0x40 | 5));
+    }
+
+    #[test]
+    fn test_mod_counter() {
+        let mut audio = FdsAudio::new();
+        audio.write(0x4087, 0x80);
+        // NOTE: 32 個書き込むと読み出し位置が先頭に戻る
+        for value in [1, 3, 4, 7].into_iter().chain([0; 28]) {
+            audio.write(0x4088, value);
+        }
+        audio.write(0x4085, 0x3F);
+        // NOTE: 周波数 0x800 なら 32 サイクルごとに変調テーブルを 1 つ進める
+        audio.write(0x4086, 0x00);
+        audio.write(0x4087, 0x08);
+
+        let mut counters = vec![];
+        for _ in 0..8 {
+            for _ in 0..32 {
+                audio.tick();
+            }
+            counters.push(audio.mod_counter);
+        }
+        assert_eq!(counters, vec![-64, -63, -59, -55, 0, 0, -1, -2]);
+    }
+}
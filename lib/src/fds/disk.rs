@@ -0,0 +1,231 @@
+use super::ips;
+
+pub(crate) const SIDE_SIZE: usize = 65500;
+const HEADER_TAG: &[u8] = b"FDS\x1A";
+const HEADER_SIZE: usize = 16;
+const DISK_INFO_TAG: &[u8] = b"\x01*NINTENDO-HVC*";
+
+// NOTE: 先頭の 28300bit と各ブロックの後ろの 976bit のギャップ
+const LEAD_IN_GAP: usize = 28300 / 8;
+const BLOCK_GAP: usize = 976 / 8;
+const BLOCK_START: u8 = 0x80;
+// NOTE: .fds には CRC が入っていないので、読み込み時は適当な値で埋める
+const DUMMY_CRC: [u8; 2] = [0x4D, 0x62];
+
+// NOTE: .fds の各面を、ギャップとブロックの開始マーク・CRC を入れた、ドライブから見える並びに変換して持つ。
+//       書き込まれた面は保存時に .fds の並びに戻し、元のイメージとの差分を作る
+pub(crate) struct FdsDisk {
+    original: Vec<u8>,
+    patched: Vec<u8>,
+    offset: usize,
+    sides: Vec<Vec<u8>>,
+    modified: Vec<bool>,
+}
+
+impl FdsDisk {
+    pub(crate) fn is_fds(raw: &[u8]) -> bool {
+        raw.starts_with(HEADER_TAG) || raw.starts_with(DISK_INFO_TAG)
+    }
+
+    // NOTE: patch は前回までの書き込みの差分 (IPS)
+    pub(crate) fn new(image: Vec<u8>, patch: Option<&[u8]>) -> Result<Self, String> {
+        let patched = match patch {
+            Some(patch) => ips::apply(&image, patch)?,
+            None => image.clone(),
+        };
+
+        let offset = if patched.starts_with(HEADER_TAG) {
+            HEADER_SIZE
+        } else {
+            0
+        };
+        let data = patched.get(offset..).unwrap_or_default();
+
+        let sides = data
+            .chunks_exact(SIDE_SIZE)
+            .map(add_gaps)
+            .collect::<Vec<_>>();
+        if sides.is_empty() || !data.starts_with(DISK_INFO_TAG) {
+            return Err("Invalid FDS image".to_string());
+        }
+
+        Ok(Self {
+            original: image,
+            offset,
+            modified: vec![false; sides.len()],
+            sides,
+            patched,
+        })
+    }
+
+    pub(crate) fn side_count(&self) -> usize {
+        self.sides.len()
+    }
+
+    pub(crate) fn side_len(&self, side: usize) -> usize {
+        self.sides[side].len()
+    }
+
+    pub(crate) fn read(&self, side: usize, pos: usize) -> u8 {
+        self.sides[side][pos]
+    }
+
+    pub(crate) fn write(&mut self, side: usize, pos: usize, data: u8) {
+        if self.sides[side][pos] != data {
+            self.sides[side][pos] = data;
+            self.modified[side] = true;
+        }
+    }
+
+    pub(crate) fn is_modified(&self) -> bool {
+        self.modified.iter().any(|&modified| modified)
+    }
+
+    // NOTE: 書き込まれた面だけ .fds の並びに戻す
+    pub(crate) fn image(&self) -> Vec<u8> {
+        let mut image = self.patched.clone();
+        for (i, side) in self.sides.iter().enumerate() {
+            if !self.modified[i] {
+                continue;
+            }
+            let start = self.offset + i * SIDE_SIZE;
+            remove_gaps(side, &mut image[start..start + SIDE_SIZE]);
+        }
+        image
+    }
+
+    // NOTE: 読み込んだときの (パッチを当てる前の) イメージからの差分
+    pub(crate) fn patch(&self) -> Vec<u8> {
+        ips::create(&self.original, &self.image())
+    }
+}
+
+fn block_len(side: &[u8], pos: usize, file_size: usize) -> Option<usize> {
+    match side.get(pos)? {
+        1 => Some(56),
+        2 => Some(2),
+        3 => Some(16),
+        4 => Some(1 + file_size),
+        _ => None,
+    }
+}
+
+// NOTE: ファイルヘッダー (ブロック 3) の 13-14 バイト目が続くファイルデータ (ブロック 4) の大きさ
+fn file_size(block: &[u8]) -> usize {
+    if block[0] == 3 {
+        u16::from_le_bytes([block[13], block[14]]) as usize
+    } else {
+        0
+    }
+}
+
+fn add_gaps(side: &[u8]) -> Vec<u8> {
+    let mut raw = vec![0; LEAD_IN_GAP];
+
+    let mut pos = 0;
+    let mut size = 0;
+    while let Some(len) = block_len(side, pos, size) {
+        let Some(block) = side.get(pos..pos + len) else {
+            break;
+        };
+        size = file_size(block);
+
+        raw.push(BLOCK_START);
+        raw.extend(block);
+        raw.extend(DUMMY_CRC);
+        raw.extend([0; BLOCK_GAP]);
+        pos += len;
+    }
+
+    // NOTE: ブロックの後ろの空き領域も書き込めるよう、元の大きさ分の余白を足しておく
+    raw.resize(raw.len().max(LEAD_IN_GAP + SIDE_SIZE), 0);
+    raw
+}
+
+// NOTE: ギャップを読み飛ばして開始マークの後ろのブロックを詰めて並べる。out の残りはそのままにする
+fn remove_gaps(raw: &[u8], out: &mut [u8]) {
+    let mut pos = 0;
+    let mut out_pos = 0;
+    let mut size = 0;
+    loop {
+        let Some(start) = raw[pos..].iter().position(|&b| b == BLOCK_START) else {
+            return;
+        };
+        pos += start + 1;
+
+        let Some(len) = block_len(raw, pos, size) else {
+            return;
+        };
+        let Some(block) = raw.get(pos..pos + len) else {
+            return;
+        };
+        let Some(dest) = out.get_mut(out_pos..out_pos + len) else {
+            return;
+        };
+        dest.copy_from_slice(block);
+        size = file_size(block);
+
+        out_pos += len;
+        pos += len + DUMMY_CRC.len();
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::{FdsDisk, BLOCK_GAP, LEAD_IN_GAP, SIDE_SIZE};
+
+    // NOTE: ディスク情報、ファイル数 1、8 バイトのファイル 1 つの面
+    pub(crate) fn test_side() -> Vec<u8> {
+        let mut side = vec![0x01];
+        side.extend(b"*NINTENDO-HVC*");
+        side.resize(56, 0x00);
+        side.extend([0x02, 0x01]);
+        side.extend([0x03, 0x00, 0x00]);
+        side.extend(b"FILE    ");
+        side.extend([0x00, 0x60, 0x08, 0x00, 0x00]);
+        side.push(0x04);
+        side.extend(1..=8);
+        side.resize(SIDE_SIZE, 0x00);
+        side
+    }
+
+    #[test]
+    fn test_gaps() {
+        let mut image = b"FDS\x1A\x01".to_vec();
+        image.resize(16, 0);
+        image.extend(test_side());
+
+        let mut disk = FdsDisk::new(image.clone(), None).unwrap();
+        assert_eq!(disk.side_count(), 1);
+        assert_eq!(disk.read(0, LEAD_IN_GAP - 1), 0x00);
+        assert_eq!(disk.read(0, LEAD_IN_GAP), 0x80);
+        assert_eq!(disk.read(0, LEAD_IN_GAP + 1), 0x01);
+        let file_count = LEAD_IN_GAP + 1 + 56 + 2 + BLOCK_GAP;
+        assert_eq!(disk.read(0, file_count), 0x80);
+        assert_eq!(disk.read(0, file_count + 1), 0x02);
+
+        assert!(!disk.is_modified());
+        assert_eq!(disk.image(), image);
+        assert_eq!(disk.patch(), b"PATCHEOF");
+
+        // NOTE: ファイルデータの 1 バイト目を書き換える
+        let data = file_count + 1 + 2 + 2 + BLOCK_GAP + 1 + 16 + 2 + BLOCK_GAP + 1 + 1;
+        assert_eq!(disk.read(0, data), 0x01);
+        disk.write(0, data, 0xEE);
+        assert!(disk.is_modified());
+
+        let patch = disk.patch();
+        let mut expected = image.clone();
+        expected[16 + 56 + 2 + 16 + 1] = 0xEE;
+        assert_eq!(disk.image(), expected);
+
+        let disk = FdsDisk::new(image, Some(&patch)).unwrap();
+        assert_eq!(disk.read(0, data), 0xEE);
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(FdsDisk::new(vec![0; 16], None).is_err());
+        assert!(FdsDisk::new(vec![0; SIDE_SIZE], None).is_err());
+    }
+}
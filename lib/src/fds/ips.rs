@@ -0,0 +1,103 @@
+const HEADER: &[u8] = b"PATCH";
+const FOOTER: &[u8] = b"EOF";
+// NOTE: オフセットがこの値だと終端の "EOF" と区別できない
+const EOF_OFFSET: usize = 0x454F46;
+const MAX_RECORD: usize = 0xFFFF;
+
+// NOTE: 同じ長さのデータの差分を IPS にする
+pub(crate) fn create(original: &[u8], modified: &[u8]) -> Vec<u8> {
+    let mut patch = HEADER.to_vec();
+
+    let mut pos = 0;
+    while pos < modified.len() {
+        if original.get(pos) == Some(&modified[pos]) {
+            pos += 1;
+            continue;
+        }
+
+        let start = if pos == EOF_OFFSET { pos - 1 } else { pos };
+        let mut end = pos;
+        while end < modified.len()
+            && end - start < MAX_RECORD
+            && original.get(end) != Some(&modified[end])
+        {
+            end += 1;
+        }
+
+        patch.extend(&(start as u32).to_be_bytes()[1..]);
+        patch.extend(((end - start) as u16).to_be_bytes());
+        patch.extend(&modified[start..end]);
+        pos = end;
+    }
+
+    patch.extend(FOOTER);
+    patch
+}
+
+pub(crate) fn apply(data: &[u8], patch: &[u8]) -> Result<Vec<u8>, String> {
+    if !patch.starts_with(HEADER) {
+        return Err("Invalid IPS patch".to_string());
+    }
+
+    let truncated = || "Truncated IPS patch".to_string();
+    let mut out = data.to_vec();
+    let mut pos = HEADER.len();
+    loop {
+        let record = patch.get(pos..pos + 3).ok_or_else(truncated)?;
+        if record == FOOTER {
+            break;
+        }
+        let offset = u32::from_be_bytes([0, record[0], record[1], record[2]]) as usize;
+        let size = patch.get(pos + 3..pos + 5).ok_or_else(truncated)?;
+        let size = u16::from_be_bytes([size[0], size[1]]) as usize;
+        pos += 5;
+
+        // NOTE: サイズ 0 は RLE で、続く 2 バイトが繰り返す長さ、その次が値
+        let bytes = if size == 0 {
+            let rle = patch.get(pos..pos + 3).ok_or_else(truncated)?;
+            pos += 3;
+            vec![rle[2]; u16::from_be_bytes([rle[0], rle[1]]) as usize]
+        } else {
+            let bytes = patch.get(pos..pos + size).ok_or_else(truncated)?.to_vec();
+            pos += size;
+            bytes
+        };
+
+        if out.len() < offset + bytes.len() {
+            out.resize(offset + bytes.len(), 0);
+        }
+        out[offset..offset + bytes.len()].copy_from_slice(&bytes);
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{apply, create};
+
+    #[test]
+    fn test_roundtrip() {
+        let original = vec![0u8; 0x20];
+        let mut modified = original.clone();
+        modified[1] = 0xAA;
+        modified[2] = 0xBB;
+        modified[0x1F] = 0xCC;
+
+        let patch = create(&original, &modified);
+        assert_eq!(
+            patch,
+            b"PATCH\x00\x00\x01\x00\x02\xAA\xBB\x00\x00\x1F\x00\x01\xCCEOF".to_vec()
+        );
+        assert_eq!(apply(&original, &patch).unwrap(), modified);
+        assert_eq!(create(&original, &original), b"PATCHEOF".to_vec());
+    }
+
+    #[test]
+    fn test_rle() {
+        let patch = b"PATCH\x00\x00\x02\x00\x00\x00\x03\x7FEOF";
+        assert_eq!(apply(&[0; 4], patch).unwrap(), vec![0, 0, 0x7F, 0x7F, 0x7F]);
+        assert!(apply(&[0; 4], b"PATCH\x00\x00").is_err());
+        assert!(apply(&[0; 4], b"NOPE").is_err());
+    }
+}
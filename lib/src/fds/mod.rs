@@ -0,0 +1,613 @@
+use std::path::{Path, PathBuf};
+
+pub(crate) use audio::FdsAudio;
+use disk::FdsDisk;
+
+use crate::{
+    apu::expansion::ExpansionAudio,
+    diagnostics::{Category, Diagnostics},
+    mapper::Mapper,
+    rom::Mirroring,
+};
+
+mod audio;
+mod disk;
+mod ips;
+
+pub const BIOS_SIZE: usize = 0x2000;
+
+const TIMER_RELOAD_LO: u16 = 0x4020;
+const TIMER_RELOAD_HI: u16 = 0x4021;
+const TIMER_CONTROL: u16 = 0x4022;
+const MASTER_IO_ENABLE: u16 = 0x4023;
+const WRITE_DATA: u16 = 0x4024;
+const DISK_CONTROL: u16 = 0x4025;
+const DISK_STATUS: u16 = 0x4030;
+const READ_DATA: u16 = 0x4031;
+const DRIVE_STATUS: u16 = 0x4032;
+const EXTERNAL_CONNECTOR: u16 = 0x4033;
+const SOUND: u16 = 0x4040;
+const SOUND_END: u16 = 0x4097;
+const RAM: u16 = 0x6000;
+const RAM_END: u16 = 0xDFFF;
+const BIOS: u16 = 0xE000;
+const BIOS_END: u16 = 0xFFFF;
+
+// NOTE: モーターを回してから最初のバイトが読めるまでと、1 バイトごとにかかる CPU サイクル
+const HEAD_DELAY: u32 = 50000;
+const BYTE_DELAY: u32 = 150;
+// NOTE: 面を入れ替えるとき、BIOS が取り出されたことに気づけるよう 1 秒ほど空ける
+const INSERT_DELAY: u32 = 1_800_000;
+
+pub fn is_fds_image(raw: &[u8]) -> bool {
+    FdsDisk::is_fds(raw)
+}
+
+// NOTE: ディスクへの書き込みは元のイメージを書き換えず、隣の game.fds.ips に差分として保存する
+pub fn sidecar_path(path: &Path) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".ips");
+    PathBuf::from(path)
+}
+
+// NOTE: RAM アダプタ。32KB の RAM・BIOS・タイマー IRQ・ディスクドライブ・波形メモリ音源を持つ
+pub(crate) struct Fds {
+    ram: Vec<u8>,
+    bios: Vec<u8>,
+    disk: FdsDisk,
+    audio: FdsAudio,
+    side: Option<usize>,
+    next_side: Option<usize>,
+    insert_delay: u32,
+
+    disk_regs_enabled: bool,
+    sound_regs_enabled: bool,
+    timer_reload: u16,
+    timer_counter: u16,
+    timer_repeat: bool,
+    timer_enabled: bool,
+    timer_irq: bool,
+
+    motor_on: bool,
+    reset_transfer: bool,
+    read_mode: bool,
+    mirroring: Mirroring,
+    crc_control: bool,
+    disk_ready: bool,
+    disk_irq_enabled: bool,
+    disk_irq: bool,
+
+    write_data: u8,
+    read_data: u8,
+    transfer_complete: bool,
+    end_of_head: bool,
+    scanning: bool,
+    gap_ended: bool,
+    position: usize,
+    delay: u32,
+    previous_crc_control: bool,
+    crc: u16,
+
+    diagnostics: Diagnostics,
+}
+
+impl Fds {
+    pub(crate) fn new(image: Vec<u8>, patch: Option<&[u8]>, bios: Vec<u8>) -> Result<Self, String> {
+        if bios.len() != BIOS_SIZE {
+            return Err("Invalid FDS BIOS".to_string());
+        }
+        let disk = FdsDisk::new(image, patch)?;
+
+        Ok(Self {
+            ram: vec![0; (RAM_END - RAM + 1) as usize],
+            bios,
+            disk,
+            audio: FdsAudio::new(),
+            side: Some(0),
+            next_side: None,
+            insert_delay: 0,
+            disk_regs_enabled: false,
+            sound_regs_enabled: false,
+            timer_reload: 0,
+            timer_counter: 0,
+            timer_repeat: false,
+            timer_enabled: false,
+            timer_irq: false,
+            motor_on: false,
+            reset_transfer: false,
+            read_mode: false,
+            mirroring: Mirroring::Horizontal,
+            crc_control: false,
+            disk_ready: false,
+            disk_irq_enabled: false,
+            disk_irq: false,
+            write_data: 0,
+            read_data: 0,
+            transfer_complete: false,
+            end_of_head: true,
+            scanning: false,
+            gap_ended: false,
+            position: 0,
+            delay: 0,
+            previous_crc_control: false,
+            crc: 0,
+            diagnostics: Diagnostics::default(),
+        })
+    }
+
+    pub(crate) fn side_count(&self) -> usize {
+        self.disk.side_count()
+    }
+
+    pub(crate) fn side(&self) -> Option<usize> {
+        self.side
+    }
+
+    // NOTE: None で取り出す。入れる面は少し待ってから入る
+    pub(crate) fn insert(&mut self, side: Option<usize>) {
+        self.side = None;
+        self.next_side = side.filter(|&side| side < self.side_count());
+        self.insert_delay = INSERT_DELAY;
+    }
+
+    pub(crate) fn switch_side(&mut self) {
+        let side = match self.side.or(self.next_side) {
+            Some(side) => (side + 1) % self.side_count(),
+            None => 0,
+        };
+        self.insert(Some(side));
+    }
+
+    // NOTE: 書き込まれていなければ None
+    pub(crate) fn patch(&self) -> Option<Vec<u8>> {
+        self.disk.is_modified().then(|| self.disk.patch())
+    }
+
+    fn read_register(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            DISK_STATUS if self.disk_regs_enabled => {
+                let mut status = 0;
+                if self.timer_irq {
+                    status |= 0x01;
+                }
+                if self.transfer_complete {
+                    status |= 0x02;
+                }
+                if self.end_of_head {
+                    status |= 0x40;
+                }
+                self.timer_irq = false;
+                self.disk_irq = false;
+                self.transfer_complete = false;
+                Some(status)
+            }
+            READ_DATA if self.disk_regs_enabled => {
+                self.transfer_complete = false;
+                self.disk_irq = false;
+                Some(self.read_data)
+            }
+            DRIVE_STATUS if self.disk_regs_enabled => {
+                let inserted = self.side.is_some();
+                let mut status = 0x40;
+                if !inserted {
+                    status |= 0x05;
+                }
+                if !inserted || !self.scanning {
+                    status |= 0x02;
+                }
+                Some(status)
+            }
+            // NOTE: バッテリーが十分あるときは bit7 が立つ
+            EXTERNAL_CONNECTOR if self.disk_regs_enabled => Some(0x80),
+            SOUND..=SOUND_END if self.sound_regs_enabled => self.audio.read(addr),
+            _ => None,
+        }
+    }
+
+    fn write_register(&mut self, addr: u16, data: u8) {
+        match addr {
+            TIMER_RELOAD_LO => self.timer_reload = (self.timer_reload & 0xFF00) | data as u16,
+            TIMER_RELOAD_HI => {
+                self.timer_reload = (self.timer_reload & 0x00FF) | ((data as u16) << 8)
+            }
+            TIMER_CONTROL => {
+                self.timer_repeat = data & 0x01 != 0;
+                self.timer_enabled = data & 0x02 != 0 && self.disk_regs_enabled;
+                if self.timer_enabled {
+                    self.timer_counter = self.timer_reload;
+                } else {
+                    self.timer_irq = false;
+                }
+            }
+            MASTER_IO_ENABLE => {
+                self.disk_regs_enabled = data & 0x01 != 0;
+                self.sound_regs_enabled = data & 0x02 != 0;
+                if !self.disk_regs_enabled {
+                    self.timer_enabled = false;
+                    self.timer_irq = false;
+                    self.disk_irq = false;
+                }
+            }
+            WRITE_DATA if self.disk_regs_enabled => {
+                self.write_data = data;
+                self.transfer_complete = false;
+                self.disk_irq = false;
+            }
+            DISK_CONTROL if self.disk_regs_enabled => {
+                self.motor_on = data & 0x01 != 0;
+                self.reset_transfer = data & 0x02 != 0;
+                self.read_mode = data & 0x04 != 0;
+                self.mirroring = if data & 0x08 != 0 {
+                    Mirroring::Horizontal
+                } else {
+                    Mirroring::Vertical
+                };
+                self.crc_control = data & 0x10 != 0;
+                self.disk_ready = data & 0x40 != 0;
+                self.disk_irq_enabled = data & 0x80 != 0;
+                self.disk_irq = false;
+            }
+            SOUND..=SOUND_END if self.sound_regs_enabled => self.audio.write(addr, data),
+            _ => {}
+        }
+    }
+
+    fn tick_timer(&mut self) {
+        if !self.timer_enabled {
+            return;
+        }
+        if self.timer_counter == 0 {
+            self.timer_irq = true;
+            self.timer_counter = self.timer_reload;
+            if !self.timer_repeat {
+                self.timer_enabled = false;
+            }
+        } else {
+            self.timer_counter -= 1;
+        }
+    }
+
+    fn tick_insert(&mut self) {
+        if self.insert_delay == 0 {
+            return;
+        }
+        self.insert_delay -= 1;
+        if self.insert_delay == 0 {
+            self.side = self.next_side.take();
+        }
+    }
+
+    // NOTE: ヘッドがディスクの先頭に戻ってから、150 サイクルごとに 1 バイト読み書きする
+    fn tick_drive(&mut self) {
+        let Some(side) = self.side.filter(|_| self.motor_on) else {
+            self.end_of_head = true;
+            self.scanning = false;
+            return;
+        };
+
+        if self.reset_transfer && !self.scanning {
+            return;
+        }
+
+        if self.end_of_head {
+            self.delay = HEAD_DELAY;
+            self.end_of_head = false;
+            self.position = 0;
+            self.gap_ended = false;
+            return;
+        }
+
+        if self.delay > 0 {
+            self.delay -= 1;
+            return;
+        }
+
+        self.scanning = true;
+        if self.read_mode {
+            self.read_byte(side);
+        } else {
+            self.write_byte(side);
+        }
+        self.previous_crc_control = self.crc_control;
+
+        self.position += 1;
+        if self.position >= self.disk.side_len(side) {
+            self.motor_on = false;
+        } else {
+            self.delay = BYTE_DELAY;
+        }
+    }
+
+    fn read_byte(&mut self, side: usize) {
+        let data = self.disk.read(side, self.position);
+        let mut need_irq = self.disk_irq_enabled;
+
+        if !self.previous_crc_control {
+            self.update_crc(data);
+        }
+
+        // NOTE: ギャップの後の最初の 0 以外のバイト (ブロックの開始マーク) から読み始める
+        if !self.disk_ready {
+            self.gap_ended = false;
+            self.crc = 0;
+        } else if data != 0 && !self.gap_ended {
+            self.gap_ended = true;
+            need_irq = false;
+        }
+
+        if self.gap_ended {
+            self.transfer_complete = true;
+            self.read_data = data;
+            if need_irq {
+                self.disk_irq = true;
+            }
+        }
+    }
+
+    fn write_byte(&mut self, side: usize) {
+        let mut data = 0;
+        if !self.crc_control {
+            self.transfer_complete = true;
+            data = self.write_data;
+            if self.disk_irq_enabled {
+                self.disk_irq = true;
+            }
+        }
+
+        if !self.disk_ready {
+            data = 0;
+        }
+
+        if !self.crc_control {
+            self.update_crc(data);
+        } else {
+            if !self.previous_crc_control {
+                self.update_crc(0);
+                self.update_crc(0);
+            }
+            data = self.crc as u8;
+            self.crc >>= 8;
+        }
+
+        self.disk.write(side, self.position, data);
+        self.gap_ended = false;
+    }
+
+    fn update_crc(&mut self, data: u8) {
+        for bit in 0..8 {
+            let carry = self.crc & 0x01 != 0;
+            self.crc >>= 1;
+            if carry {
+                self.crc ^= 0x8408;
+            }
+            if data & (1 << bit) != 0 {
+                self.crc ^= 0x8000;
+            }
+        }
+    }
+}
+
+impl Mapper for Fds {
+    fn read(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            RAM..=BIOS_END => self.peek(addr),
+            _ => self.read_register(addr),
+        }
+    }
+
+    fn peek(&self, addr: u16) -> Option<u8> {
+        match addr {
+            RAM..=RAM_END => Some(self.ram[(addr - RAM) as usize]),
+            BIOS..=BIOS_END => Some(self.bios[(addr - BIOS) as usize]),
+            _ => None,
+        }
+    }
+
+    fn write(&mut self, addr: u16, data: u8) {
+        match addr {
+            RAM..=RAM_END => self.ram[(addr - RAM) as usize] = data,
+            BIOS..=BIOS_END => {
+                self.diagnostics.report(Category::Bus, "rom-write", || {
+                    format!("Ignoring write to ROM at {:#06X}", addr)
+                });
+            }
+            _ => self.write_register(addr, data),
+        }
+    }
+
+    fn set_diagnostics(&mut self, diagnostics: Diagnostics) {
+        self.diagnostics = diagnostics;
+    }
+
+    fn mirroring(&self) -> Option<Mirroring> {
+        Some(self.mirroring)
+    }
+
+    fn tick(&mut self, cycles: u8) {
+        for _ in 0..cycles {
+            self.tick_timer();
+            self.tick_insert();
+            self.tick_drive();
+            self.audio.tick();
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.timer_irq || self.disk_irq
+    }
+
+    fn audio_output(&self) -> Option<f32> {
+        Some(self.audio.output())
+    }
+
+    fn fds(&self) -> Option<&Fds> {
+        Some(self)
+    }
+
+    fn fds_mut(&mut self) -> Option<&mut Fds> {
+        Some(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::{disk::test::test_side, sidecar_path, Fds, BIOS_SIZE};
+    use crate::{
+        debugger::RunMode,
+        emulator::Emulator,
+        mapper::Mapper,
+        speaker::{test::ExpansionSpeaker, EXPANSION_CH},
+        testrom::{NullJoypadHandler, NullRenderer},
+    };
+
+    fn fds() -> Fds {
+        Fds::new(test_side(), None, vec![0; BIOS_SIZE]).unwrap()
+    }
+
+    #[test]
+    fn test_sidecar_path() {
+        assert_eq!(
+            sidecar_path(Path::new("roms/game.fds")),
+            Path::new("roms/game.fds.ips")
+        );
+    }
+
+    #[test]
+    fn test_invalid_bios() {
+        assert!(Fds::new(test_side(), None, vec![0; 0x1000]).is_err());
+    }
+
+    #[test]
+    fn test_timer_irq() {
+        let mut fds = fds();
+        fds.write(0x4023, 0x01);
+        fds.write(0x4020, 0x10);
+        fds.write(0x4021, 0x00);
+        fds.write(0x4022, 0x03);
+
+        fds.tick(0x10);
+        assert!(!fds.irq());
+        fds.tick(1);
+        assert!(fds.irq());
+
+        assert_eq!(fds.read(0x4030).unwrap() & 0x01, 0x01);
+        assert!(!fds.irq());
+
+        // NOTE: リピートなら再びカウントする
+        fds.tick(0x11);
+        assert!(fds.irq());
+
+        // NOTE: ディスクレジスタを無効にするとタイマーも止まる
+        fds.write(0x4023, 0x00);
+        assert!(!fds.irq());
+        assert_eq!(fds.read(0x4030), None);
+    }
+
+    #[test]
+    fn test_read_disk() {
+        let mut fds = fds();
+        fds.write(0x4023, 0x01);
+        assert_eq!(fds.read(0x4032), Some(0x42));
+
+        // NOTE: モーター ON、読み込みモード、IRQ 有効
+        fds.write(0x4025, 0xC5);
+        fds.tick(1);
+        for _ in 0..=super::HEAD_DELAY / 255 {
+            fds.tick(255);
+        }
+
+        let mut data = vec![];
+        while data.len() < 16 {
+            fds.tick(1);
+            if fds.irq() {
+                data.push(fds.read(0x4031).unwrap());
+                assert!(!fds.irq());
+            }
+        }
+        assert_eq!(fds.read(0x4032), Some(0x40));
+        assert_eq!(&data[..15], b"\x01*NINTENDO-HVC*");
+    }
+
+    #[test]
+    fn test_insert() {
+        let mut fds = fds();
+        fds.write(0x4023, 0x01);
+        assert_eq!(fds.side(), Some(0));
+
+        fds.switch_side();
+        assert_eq!(fds.side(), None);
+        assert_eq!(fds.read(0x4032).unwrap() & 0x07, 0x07);
+        for _ in 0..=super::INSERT_DELAY / 255 {
+            fds.tick(255);
+        }
+        assert_eq!(fds.side(), Some(0));
+    }
+
+    #[test]
+    fn test_mirroring_and_ram() {
+        let mut fds = fds();
+        fds.write(0x4023, 0x01);
+        fds.write(0x4025, 0x00);
+        assert_eq!(fds.mirroring(), Some(crate::rom::Mirroring::Vertical));
+        fds.write(0x4025, 0x08);
+        assert_eq!(fds.mirroring(), Some(crate::rom::Mirroring::Horizontal));
+
+        fds.write(0xDFFF, 0x12);
+        assert_eq!(fds.peek(0xDFFF), Some(0x12));
+        fds.write(0xE000, 0x34);
+        assert_eq!(fds.peek(0xE000), Some(0x00));
+    }
+
+    #[test]
+    fn test_audio_reaches_speaker() {
+        #[rustfmt::skip]
+        let program = [
+            0xA9, 0x03,       // $E000: LDA #$03
+            0x8D, 0x23, 0x40, // $E002: STA $4023
+            0xA9, 0x80,       // $E005: LDA #$80
+            0x8D, 0x89, 0x40, // $E007: STA $4089
+            0xA2, 0x3F,       // $E00A: LDX #$3F
+            0xA9, 0x3F,       // $E00C: LDA #$3F
+            0x9D, 0x40, 0x40, // $E00E: STA $4040,X
+            0xCA,             // $E011: DEX
+            0x10, 0xFA,       // $E012: BPL $E00E
+            0xA9, 0x00,       // $E014: LDA #$00
+            0x8D, 0x89, 0x40, // $E016: STA $4089
+            0xA9, 0xA0,       // $E019: LDA #$A0
+            0x8D, 0x80, 0x40, // $E01B: STA $4080
+            0xA9, 0xFF,       // $E01E: LDA #$FF
+            0x8D, 0x82, 0x40, // $E020: STA $4082
+            0xA9, 0x0F,       // $E023: LDA #$0F
+            0x8D, 0x83, 0x40, // $E025: STA $4083
+            0x4C, 0x28, 0xE0, // $E028: JMP $E028
+        ];
+        let mut bios = vec![0xEA; BIOS_SIZE];
+        bios[..program.len()].copy_from_slice(&program);
+        bios[0x1FFC] = 0x00;
+        bios[0x1FFD] = 0xE0;
+
+        let speaker = ExpansionSpeaker::default();
+        let mut emulator = Emulator::new_fds(
+            test_side(),
+            None,
+            bios,
+            speaker.clone(),
+            NullJoypadHandler,
+            NullRenderer,
+        )
+        .unwrap();
+        emulator.reset();
+        for _ in 0..2 {
+            emulator.run(RunMode::ToFrame, 100_000);
+        }
+
+        // NOTE: 波形メモリがすべて最大値なので、鳴り始めてからは一定の出力になる
+        let sent = speaker.sent();
+        assert!(!sent.is_empty());
+        assert!(sent.iter().all(|(ch, _)| *ch == EXPANSION_CH));
+        let (_, samples) = sent.last().unwrap();
+        assert!(samples.iter().all(|&v| v > 0.0));
+    }
+}
@@ -1,9 +1,20 @@
 mod apu;
+pub mod audio;
 mod bus;
 mod cpu;
+pub mod debugger;
+pub mod diagnostics;
+pub mod disasm;
 pub mod emulator;
+pub mod fds;
 pub mod joypad;
+mod mapper;
+pub mod nsf;
 mod ppu;
+pub mod record;
+pub mod region;
 pub mod render;
 mod rom;
 pub mod speaker;
+pub mod testrom;
+pub mod trace;
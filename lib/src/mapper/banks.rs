@@ -0,0 +1,79 @@
+const PRG_ROM: u16 = 0x8000;
+pub(super) const PRG_BANK_SIZE: usize = 0x2000;
+pub(super) const CHR_BANK_SIZE: usize = 0x400;
+
+// NOTE: $8000-$FFFF を 8KB ずつ 4 つに分けて PRG-ROM のバンクを割り当てる。
+//       最初は先頭の 2 バンクと末尾の 2 バンクを並べておく
+pub(super) struct PrgBanks {
+    rom: Vec<u8>,
+    banks: [usize; 4],
+}
+
+impl PrgBanks {
+    pub(super) fn new(rom: Vec<u8>) -> Self {
+        let mut prg = Self { rom, banks: [0; 4] };
+        let last = prg.last();
+        prg.set(0, 0);
+        prg.set(1, 1);
+        prg.set(2, last.saturating_sub(1));
+        prg.set(3, last);
+        prg
+    }
+
+    pub(super) fn last(&self) -> usize {
+        (self.rom.len() / PRG_BANK_SIZE).max(1) - 1
+    }
+
+    // NOTE: バンク数を超えた番号は折り返す
+    pub(super) fn set(&mut self, slot: usize, bank: usize) {
+        self.banks[slot] = bank % (self.last() + 1);
+    }
+
+    // NOTE: 16KB 単位のバンクを slot と slot + 1 に割り当てる
+    pub(super) fn set_16k(&mut self, slot: usize, bank: usize) {
+        self.set(slot, bank * 2);
+        self.set(slot + 1, bank * 2 + 1);
+    }
+
+    pub(super) fn read(&self, addr: u16) -> u8 {
+        let offset = (addr - PRG_ROM) as usize;
+        self.read_bank(self.banks[offset / PRG_BANK_SIZE], offset)
+    }
+
+    // NOTE: スロットを通さずに 8KB のバンクから読む。$6000-$7FFF に ROM を置くマッパー用
+    pub(super) fn read_bank(&self, bank: usize, offset: usize) -> u8 {
+        self.rom[(bank * PRG_BANK_SIZE + offset % PRG_BANK_SIZE) % self.rom.len()]
+    }
+}
+
+// NOTE: 1KB 単位のバンク番号を PPU に渡すオフセットにする
+pub(super) fn chr_offsets(banks: [usize; 8]) -> [usize; 8] {
+    banks.map(|bank| bank * CHR_BANK_SIZE)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{chr_offsets, PrgBanks};
+
+    #[test]
+    fn test_prg_banks() {
+        let rom = (0..8).flat_map(|bank| vec![bank as u8; 0x2000]).collect();
+        let mut prg = PrgBanks::new(rom);
+        assert_eq!(
+            [0x8000, 0xA000, 0xC000, 0xE000].map(|addr| prg.read(addr)),
+            [0, 1, 6, 7]
+        );
+
+        prg.set(0, 9);
+        prg.set_16k(2, 2);
+        assert_eq!(
+            [0x8000, 0xA000, 0xC000, 0xFFFF].map(|addr| prg.read(addr)),
+            [1, 1, 4, 5]
+        );
+    }
+
+    #[test]
+    fn test_chr_offsets() {
+        assert_eq!(chr_offsets([0, 1, 2, 3, 4, 5, 6, 7])[7], 0x1C00);
+    }
+}
@@ -0,0 +1,226 @@
+use crate::{
+    apu::expansion::{ExpansionAudio, Sunsoft5bAudio},
+    rom::Mirroring,
+};
+
+use super::{
+    banks::{chr_offsets, PrgBanks},
+    Mapper,
+};
+
+const PRG_RAM: u16 = 0x6000;
+const PRG_RAM_END: u16 = 0x7FFF;
+const ROM: u16 = 0x8000;
+const ROM_END: u16 = 0xFFFF;
+const COMMAND: u16 = 0x8000;
+const COMMAND_END: u16 = 0x9FFF;
+const PARAMETER: u16 = 0xA000;
+const PARAMETER_END: u16 = 0xBFFF;
+// NOTE: 5B の音源は $C000 でレジスタを選んで $E000 に書く
+const AUDIO: u16 = 0xC000;
+
+const RAM_SELECT: u8 = 0b0100_0000;
+const RAM_ENABLE: u8 = 0b1000_0000;
+
+// NOTE: マッパー 69 (Sunsoft FME-7 / 5B)。$8000 でコマンドを選び、$A000 に引数を書く。
+//       $6000-$7FFF には ROM か RAM のバンクを置ける。$E000-$FFFF は末尾のバンクに固定
+pub(super) struct Fme7 {
+    prg: PrgBanks,
+    prg_ram: [u8; 0x2000],
+    command: u8,
+    ram_bank: u8,
+    chr_banks: [u8; 8],
+    mirroring: Mirroring,
+    irq_counter: u16,
+    irq_enabled: bool,
+    counter_enabled: bool,
+    irq: bool,
+    audio: Sunsoft5bAudio,
+}
+
+impl Fme7 {
+    pub(super) fn new(prg_rom: Vec<u8>, mirroring: Mirroring) -> Self {
+        Self {
+            prg: PrgBanks::new(prg_rom),
+            prg_ram: [0; 0x2000],
+            command: 0,
+            ram_bank: 0,
+            chr_banks: [0, 1, 2, 3, 4, 5, 6, 7],
+            mirroring,
+            irq_counter: 0,
+            irq_enabled: false,
+            counter_enabled: false,
+            irq: false,
+            audio: Sunsoft5bAudio::new(),
+        }
+    }
+
+    fn is_ram_enabled(&self) -> bool {
+        self.ram_bank & RAM_SELECT != 0 && self.ram_bank & RAM_ENABLE != 0
+    }
+
+    fn write_parameter(&mut self, data: u8) {
+        match self.command {
+            0x0..=0x7 => self.chr_banks[self.command as usize] = data,
+            0x8 => self.ram_bank = data,
+            0x9..=0xB => self
+                .prg
+                .set((self.command - 0x9) as usize, (data & 0x3F) as usize),
+            0xC => {
+                self.mirroring = match data & 0x03 {
+                    0 => Mirroring::Vertical,
+                    1 => Mirroring::Horizontal,
+                    2 => Mirroring::SingleScreenLower,
+                    _ => Mirroring::SingleScreenUpper,
+                }
+            }
+            // NOTE: 書き込むと IRQ も取り下げる
+            0xD => {
+                self.irq_enabled = data & 0x01 != 0;
+                self.counter_enabled = data & 0x80 != 0;
+                self.irq = false;
+            }
+            0xE => self.irq_counter = (self.irq_counter & 0xFF00) | data as u16,
+            _ => self.irq_counter = (self.irq_counter & 0x00FF) | (data as u16) << 8,
+        }
+    }
+}
+
+impl Mapper for Fme7 {
+    fn read(&mut self, addr: u16) -> Option<u8> {
+        self.peek(addr)
+    }
+
+    // NOTE: RAM を選んで無効にしているときはオープンバス
+    fn peek(&self, addr: u16) -> Option<u8> {
+        match addr {
+            PRG_RAM..=PRG_RAM_END if self.ram_bank & RAM_SELECT == 0 => Some(
+                self.prg
+                    .read_bank((self.ram_bank & 0x3F) as usize, (addr - PRG_RAM) as usize),
+            ),
+            PRG_RAM..=PRG_RAM_END if self.is_ram_enabled() => {
+                Some(self.prg_ram[(addr - PRG_RAM) as usize])
+            }
+            ROM..=ROM_END => Some(self.prg.read(addr)),
+            _ => None,
+        }
+    }
+
+    fn write(&mut self, addr: u16, data: u8) {
+        match addr {
+            PRG_RAM..=PRG_RAM_END if self.is_ram_enabled() => {
+                self.prg_ram[(addr - PRG_RAM) as usize] = data
+            }
+            COMMAND..=COMMAND_END => self.command = data & 0x0F,
+            PARAMETER..=PARAMETER_END => self.write_parameter(data),
+            AUDIO..=ROM_END => self.audio.write(addr, data),
+            _ => {}
+        }
+    }
+
+    fn mirroring(&self) -> Option<Mirroring> {
+        Some(self.mirroring)
+    }
+
+    fn chr_banks(&self) -> Option<[usize; 8]> {
+        Some(chr_offsets(self.chr_banks.map(|bank| bank as usize)))
+    }
+
+    // NOTE: カウンタは CPU の 1 サイクルごとに減り、0 から $FFFF に戻るときに IRQ を出す
+    fn tick(&mut self, cycles: u8) {
+        for _ in 0..cycles {
+            if self.counter_enabled {
+                self.irq_counter = self.irq_counter.wrapping_sub(1);
+                if self.irq_counter == 0xFFFF && self.irq_enabled {
+                    self.irq = true;
+                }
+            }
+            self.audio.tick();
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.irq
+    }
+
+    fn audio_output(&self) -> Option<f32> {
+        Some(self.audio.output())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Fme7;
+    use crate::{mapper::Mapper, rom::Mirroring};
+
+    fn prg_rom() -> Vec<u8> {
+        (0..16).flat_map(|bank| vec![bank as u8; 0x2000]).collect()
+    }
+
+    fn command(fme7: &mut Fme7, command: u8, data: u8) {
+        fme7.write(0x8000, command);
+        fme7.write(0xA000, data);
+    }
+
+    #[test]
+    fn test_banks() {
+        let mut fme7 = Fme7::new(prg_rom(), Mirroring::Vertical);
+        command(&mut fme7, 0x9, 0x03);
+        command(&mut fme7, 0xA, 0x05);
+        command(&mut fme7, 0xB, 0x07);
+        assert_eq!(
+            [0x8000, 0xA000, 0xC000, 0xE000].map(|addr| fme7.peek(addr).unwrap()),
+            [3, 5, 7, 15]
+        );
+
+        command(&mut fme7, 0x6, 0x21);
+        assert_eq!(fme7.chr_banks().unwrap()[6], 0x21 * 0x400);
+
+        command(&mut fme7, 0xC, 0x03);
+        assert_eq!(fme7.mirroring(), Some(Mirroring::SingleScreenUpper));
+    }
+
+    #[test]
+    fn test_prg_ram() {
+        let mut fme7 = Fme7::new(prg_rom(), Mirroring::Vertical);
+        command(&mut fme7, 0x8, 0x09);
+        assert_eq!(fme7.peek(0x6000), Some(9));
+
+        // NOTE: RAM を選んでも有効にするまでは読み書きできない
+        command(&mut fme7, 0x8, 0x40);
+        fme7.write(0x6000, 0x12);
+        assert_eq!(fme7.peek(0x6000), None);
+
+        command(&mut fme7, 0x8, 0xC0);
+        fme7.write(0x6000, 0x12);
+        assert_eq!(fme7.peek(0x6000), Some(0x12));
+    }
+
+    #[test]
+    fn test_irq() {
+        let mut fme7 = Fme7::new(prg_rom(), Mirroring::Vertical);
+        command(&mut fme7, 0xE, 0x01);
+        command(&mut fme7, 0xF, 0x00);
+        command(&mut fme7, 0xD, 0x81);
+        fme7.tick(1);
+        assert!(!fme7.irq());
+        fme7.tick(1);
+        assert!(fme7.irq());
+
+        command(&mut fme7, 0xD, 0x00);
+        assert!(!fme7.irq());
+    }
+
+    #[test]
+    fn test_audio() {
+        let mut fme7 = Fme7::new(prg_rom(), Mirroring::Vertical);
+        assert_eq!(fme7.audio_output(), Some(0.0));
+        // NOTE: トーンもノイズも無効にしたチャンネル A を音量 15 で鳴らす
+        fme7.write(0xC000, 0x07);
+        fme7.write(0xE000, 0x3F);
+        fme7.write(0xC000, 0x08);
+        fme7.write(0xE000, 0x0F);
+        fme7.tick(1);
+        assert!(fme7.audio_output().unwrap() > 0.0);
+    }
+}
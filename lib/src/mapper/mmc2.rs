@@ -0,0 +1,180 @@
+use crate::rom::Mirroring;
+
+use super::{
+    banks::{chr_offsets, PrgBanks},
+    Mapper,
+};
+
+const PRG_RAM: u16 = 0x6000;
+const PRG_RAM_END: u16 = 0x7FFF;
+const ROM: u16 = 0x8000;
+const ROM_END: u16 = 0xFFFF;
+
+const FD: usize = 0;
+const FE: usize = 1;
+
+// NOTE: マッパー 9 (MMC2, パンチアウト!!) と 10 (MMC4, ファイアーエムブレム)。
+//       $0000 と $1000 の 4KB ごとに FD 用と FE 用の 2 つのバンクを持ち、PPU がタイル $FD/$FE の
+//       上位プレーンを読んだ時点でラッチを切り替える。切り替わるのはそのタイルを読み終わってから
+pub(super) struct Mmc2 {
+    prg: PrgBanks,
+    prg_ram: [u8; 0x2000],
+    is_mmc4: bool,
+    chr_banks: [[u8; 2]; 2],
+    latches: [usize; 2],
+    mirroring: Mirroring,
+}
+
+impl Mmc2 {
+    pub(super) fn new(prg_rom: Vec<u8>, mapper: u8, mirroring: Mirroring) -> Self {
+        let mut mmc2 = Self {
+            prg: PrgBanks::new(prg_rom),
+            prg_ram: [0; 0x2000],
+            is_mmc4: mapper == 10,
+            chr_banks: [[0, 0], [1, 1]],
+            latches: [FE, FE],
+            mirroring,
+        };
+        mmc2.set_prg_bank(0);
+        mmc2
+    }
+
+    // NOTE: MMC2 は $8000 の 8KB だけを切り替えて残りは末尾の 3 バンク、MMC4 は 16KB 単位で $C000 が末尾に固定
+    fn set_prg_bank(&mut self, bank: usize) {
+        let last = self.prg.last();
+        if self.is_mmc4 {
+            self.prg.set_16k(0, bank);
+            self.prg.set_16k(2, last / 2);
+        } else {
+            self.prg.set(0, bank);
+            self.prg.set(1, last.saturating_sub(2));
+            self.prg.set(2, last.saturating_sub(1));
+            self.prg.set(3, last);
+        }
+    }
+}
+
+impl Mapper for Mmc2 {
+    fn read(&mut self, addr: u16) -> Option<u8> {
+        self.peek(addr)
+    }
+
+    fn peek(&self, addr: u16) -> Option<u8> {
+        match addr {
+            PRG_RAM..=PRG_RAM_END => Some(self.prg_ram[(addr - PRG_RAM) as usize]),
+            ROM..=ROM_END => Some(self.prg.read(addr)),
+            _ => None,
+        }
+    }
+
+    fn write(&mut self, addr: u16, data: u8) {
+        match addr {
+            PRG_RAM..=PRG_RAM_END => self.prg_ram[(addr - PRG_RAM) as usize] = data,
+            0xA000..=0xAFFF => self.set_prg_bank((data & 0x0F) as usize),
+            0xB000..=0xBFFF => self.chr_banks[0][FD] = data & 0x1F,
+            0xC000..=0xCFFF => self.chr_banks[0][FE] = data & 0x1F,
+            0xD000..=0xDFFF => self.chr_banks[1][FD] = data & 0x1F,
+            0xE000..=0xEFFF => self.chr_banks[1][FE] = data & 0x1F,
+            0xF000..=0xFFFF => {
+                self.mirroring = if data & 0x01 == 0 {
+                    Mirroring::Vertical
+                } else {
+                    Mirroring::Horizontal
+                };
+            }
+            _ => {}
+        }
+    }
+
+    fn mirroring(&self) -> Option<Mirroring> {
+        Some(self.mirroring)
+    }
+
+    fn chr_banks(&self) -> Option<[usize; 8]> {
+        let banks = std::array::from_fn(|slot| {
+            let half = slot / 4;
+            self.chr_banks[half][self.latches[half]] as usize * 4 + slot % 4
+        });
+        Some(chr_offsets(banks))
+    }
+
+    // NOTE: MMC2 の $0000 側だけは $0FD8 / $0FE8 ちょうど (タイルの 1 行目) でしか反応しない
+    fn observe_pattern_fetch(&mut self, addr: u16) -> bool {
+        let half = (addr >> 12) as usize & 0x01;
+        let latch = match addr & 0x0FF8 {
+            0x0FD8 => FD,
+            0x0FE8 => FE,
+            _ => return false,
+        };
+        if half == 0 && !self.is_mmc4 && addr & 0x07 != 0 {
+            return false;
+        }
+
+        let changed = self.latches[half] != latch;
+        self.latches[half] = latch;
+        changed
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use test_case::test_case;
+
+    use super::Mmc2;
+    use crate::{mapper::Mapper, rom::Mirroring};
+
+    fn prg_rom() -> Vec<u8> {
+        (0..16).flat_map(|bank| vec![bank as u8; 0x2000]).collect()
+    }
+
+    #[test_case(9, [5, 13, 14, 15] ; "mmc2")]
+    #[test_case(10, [10, 11, 14, 15] ; "mmc4")]
+    fn test_prg_banks(mapper: u8, expected: [u8; 4]) {
+        let mut mmc = Mmc2::new(prg_rom(), mapper, Mirroring::Vertical);
+        mmc.write(0xA000, 0x05);
+        assert_eq!(
+            [0x8000, 0xA000, 0xC000, 0xE000].map(|addr| mmc.peek(addr).unwrap()),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_latch() {
+        let mut mmc = Mmc2::new(prg_rom(), 9, Mirroring::Vertical);
+        mmc.write(0xB000, 0x02);
+        mmc.write(0xC000, 0x03);
+        mmc.write(0xD000, 0x04);
+        mmc.write(0xE000, 0x05);
+        let banks = |mmc: &Mmc2| {
+            let banks = mmc.chr_banks().unwrap();
+            (banks[0] / 0x1000, banks[4] / 0x1000)
+        };
+        assert_eq!(banks(&mmc), (3, 5));
+
+        // NOTE: 下位プレーンでは切り替わらない
+        assert!(!mmc.observe_pattern_fetch(0x0FD0));
+        assert!(mmc.observe_pattern_fetch(0x0FD8));
+        assert_eq!(banks(&mmc), (2, 5));
+        assert!(!mmc.observe_pattern_fetch(0x0FD8));
+
+        assert!(mmc.observe_pattern_fetch(0x1FDF));
+        assert_eq!(banks(&mmc), (2, 4));
+        assert!(mmc.observe_pattern_fetch(0x1FE8));
+        assert!(mmc.observe_pattern_fetch(0x0FE8));
+        assert_eq!(banks(&mmc), (3, 5));
+    }
+
+    #[test_case(9, false ; "mmc2")]
+    #[test_case(10, true ; "mmc4")]
+    fn test_latch_range(mapper: u8, switched: bool) {
+        let mut mmc = Mmc2::new(prg_rom(), mapper, Mirroring::Vertical);
+        assert_eq!(mmc.observe_pattern_fetch(0x0FDB), switched);
+    }
+
+    #[test]
+    fn test_mirroring() {
+        let mut mmc = Mmc2::new(prg_rom(), 9, Mirroring::Vertical);
+        mmc.write(0xF000, 0x01);
+        assert_eq!(mmc.mirroring(), Some(Mirroring::Horizontal));
+    }
+}
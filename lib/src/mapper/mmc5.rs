@@ -0,0 +1,313 @@
+use crate::{
+    apu::expansion::{ExpansionAudio, Mmc5Audio},
+    rom::Mirroring,
+};
+
+use super::{
+    banks::{chr_offsets, PrgBanks, PRG_BANK_SIZE},
+    Mapper,
+};
+
+const AUDIO: u16 = 0x5000;
+const AUDIO_END: u16 = 0x5015;
+const PRG_MODE: u16 = 0x5100;
+const CHR_MODE: u16 = 0x5101;
+const NAMETABLE_MAPPING: u16 = 0x5105;
+const PRG_RAM_BANK: u16 = 0x5113;
+const PRG_BANKS: u16 = 0x5114;
+const PRG_BANKS_END: u16 = 0x5117;
+const SPRITE_CHR_BANKS: u16 = 0x5120;
+const SPRITE_CHR_BANKS_END: u16 = 0x5127;
+const BG_CHR_BANKS: u16 = 0x5128;
+const BG_CHR_BANKS_END: u16 = 0x512B;
+const CHR_UPPER_BITS: u16 = 0x5130;
+const IRQ_STATUS: u16 = 0x5204;
+const MULTIPLICAND: u16 = 0x5205;
+const MULTIPLIER: u16 = 0x5206;
+const EXRAM: u16 = 0x5C00;
+const EXRAM_END: u16 = 0x5FFF;
+const PRG_RAM: u16 = 0x6000;
+const PRG_RAM_END: u16 = 0x7FFF;
+const ROM: u16 = 0x8000;
+const ROM_END: u16 = 0xFFFF;
+
+const PRG_RAM_SIZE: usize = 0x10000;
+// NOTE: $5114-$5116 の bit7 が 0 なら RAM。$5117 はいつも ROM
+const ROM_SELECT: u8 = 0b1000_0000;
+
+// NOTE: マッパー 5 (MMC5)。PRG と CHR のバンク、乗算器、拡張 RAM、音源だけを扱う。
+//       スキャンライン IRQ、拡張 RAM を使った属性やネームテーブル、縦分割、PCM の読み込みモード、
+//       PRG-RAM の書き込み保護は未実装。8x16 スプライトでも最後に書いた側の CHR バンクだけを使う
+pub(super) struct Mmc5 {
+    prg: PrgBanks,
+    prg_ram: Vec<u8>,
+    prg_mode: u8,
+    prg_ram_bank: u8,
+    prg_banks: [u8; 4],
+    chr_mode: u8,
+    sprite_chr_banks: [u16; 8],
+    bg_chr_banks: [u16; 4],
+    chr_upper_bits: u16,
+    use_bg_chr: bool,
+    mirroring: Mirroring,
+    multiplicand: u8,
+    multiplier: u8,
+    exram: [u8; 0x400],
+    audio: Mmc5Audio,
+}
+
+impl Mmc5 {
+    pub(super) fn new(prg_rom: Vec<u8>, mirroring: Mirroring) -> Self {
+        Self {
+            prg: PrgBanks::new(prg_rom),
+            prg_ram: vec![0; PRG_RAM_SIZE],
+            prg_mode: 3,
+            prg_ram_bank: 0,
+            prg_banks: [0, 0, 0, 0xFF],
+            chr_mode: 0,
+            sprite_chr_banks: [0; 8],
+            bg_chr_banks: [0; 4],
+            chr_upper_bits: 0,
+            use_bg_chr: false,
+            mirroring,
+            multiplicand: 0xFF,
+            multiplier: 0xFF,
+            exram: [0; 0x400],
+            audio: Mmc5Audio::new(),
+        }
+    }
+
+    // NOTE: $8000-$FFFF の 8KB ごとに、(ROM か, 8KB 単位のバンク番号) を返す
+    fn prg_bank(&self, slot: usize) -> (bool, usize) {
+        let (register, size) = match self.prg_mode {
+            0 => (3, 4),
+            1 | 2 if slot < 2 => (1, 2),
+            1 => (3, 2),
+            _ => (slot, 1),
+        };
+        let value = self.prg_banks[register];
+        let is_rom = register == 3 || value & ROM_SELECT != 0;
+        let bank = ((value & !ROM_SELECT) as usize & !(size - 1)) + slot % size;
+        (is_rom, bank)
+    }
+
+    fn prg_ram_index(bank: usize, addr: u16) -> usize {
+        (bank * PRG_BANK_SIZE + addr as usize % PRG_BANK_SIZE) % PRG_RAM_SIZE
+    }
+}
+
+impl Mapper for Mmc5 {
+    fn read(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            AUDIO..=AUDIO_END => self.audio.read(addr),
+            _ => self.peek(addr),
+        }
+    }
+
+    fn peek(&self, addr: u16) -> Option<u8> {
+        match addr {
+            IRQ_STATUS => Some(0x00),
+            MULTIPLICAND => Some((self.multiplicand as u16 * self.multiplier as u16) as u8),
+            MULTIPLIER => Some(((self.multiplicand as u16 * self.multiplier as u16) >> 8) as u8),
+            EXRAM..=EXRAM_END => Some(self.exram[(addr - EXRAM) as usize]),
+            PRG_RAM..=PRG_RAM_END => {
+                Some(self.prg_ram[Self::prg_ram_index((self.prg_ram_bank & 0x07) as usize, addr)])
+            }
+            ROM..=ROM_END => {
+                let offset = (addr - ROM) as usize;
+                match self.prg_bank(offset / PRG_BANK_SIZE) {
+                    (true, bank) => Some(self.prg.read_bank(bank, offset)),
+                    (false, bank) => Some(self.prg_ram[Self::prg_ram_index(bank & 0x07, addr)]),
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn write(&mut self, addr: u16, data: u8) {
+        match addr {
+            AUDIO..=AUDIO_END => self.audio.write(addr, data),
+            PRG_MODE => self.prg_mode = data & 0x03,
+            CHR_MODE => self.chr_mode = data & 0x03,
+            // NOTE: 2 画面のどちらかを使う並びだけを PPU のミラーリングにする
+            NAMETABLE_MAPPING => {
+                self.mirroring = match data {
+                    0x00 => Mirroring::SingleScreenLower,
+                    0x44 => Mirroring::Vertical,
+                    0x50 => Mirroring::Horizontal,
+                    0x55 => Mirroring::SingleScreenUpper,
+                    _ => self.mirroring,
+                }
+            }
+            PRG_RAM_BANK => self.prg_ram_bank = data,
+            PRG_BANKS..=PRG_BANKS_END => self.prg_banks[(addr - PRG_BANKS) as usize] = data,
+            SPRITE_CHR_BANKS..=SPRITE_CHR_BANKS_END => {
+                self.sprite_chr_banks[(addr - SPRITE_CHR_BANKS) as usize] =
+                    self.chr_upper_bits | data as u16;
+                self.use_bg_chr = false;
+            }
+            BG_CHR_BANKS..=BG_CHR_BANKS_END => {
+                self.bg_chr_banks[(addr - BG_CHR_BANKS) as usize] =
+                    self.chr_upper_bits | data as u16;
+                self.use_bg_chr = true;
+            }
+            CHR_UPPER_BITS => self.chr_upper_bits = ((data & 0x03) as u16) << 8,
+            MULTIPLICAND => self.multiplicand = data,
+            MULTIPLIER => self.multiplier = data,
+            EXRAM..=EXRAM_END => self.exram[(addr - EXRAM) as usize] = data,
+            PRG_RAM..=PRG_RAM_END => {
+                let index = Self::prg_ram_index((self.prg_ram_bank & 0x07) as usize, addr);
+                self.prg_ram[index] = data;
+            }
+            ROM..=ROM_END => {
+                if let (false, bank) = self.prg_bank((addr - ROM) as usize / PRG_BANK_SIZE) {
+                    self.prg_ram[Self::prg_ram_index(bank & 0x07, addr)] = data;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn mirroring(&self) -> Option<Mirroring> {
+        Some(self.mirroring)
+    }
+
+    // NOTE: バンク番号はモードの大きさ (8KB / 4KB / 2KB / 1KB) 単位。BG 側の 4 つは $0000 と $1000 の両方に置く
+    fn chr_banks(&self) -> Option<[usize; 8]> {
+        let banks: [usize; 8] = if self.use_bg_chr {
+            let r = self.bg_chr_banks.map(|bank| bank as usize);
+            std::array::from_fn(|slot| match self.chr_mode {
+                0 => r[3] * 8 + slot,
+                1 => r[3] * 4 + slot % 4,
+                2 => r[slot % 4 / 2 * 2 + 1] * 2 + slot % 2,
+                _ => r[slot % 4],
+            })
+        } else {
+            let r = self.sprite_chr_banks.map(|bank| bank as usize);
+            std::array::from_fn(|slot| match self.chr_mode {
+                0 => r[7] * 8 + slot,
+                1 => r[slot / 4 * 4 + 3] * 4 + slot % 4,
+                2 => r[slot / 2 * 2 + 1] * 2 + slot % 2,
+                _ => r[slot],
+            })
+        };
+        Some(chr_offsets(banks))
+    }
+
+    fn tick(&mut self, cycles: u8) {
+        for _ in 0..cycles {
+            self.audio.tick();
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.audio.irq()
+    }
+
+    fn audio_output(&self) -> Option<f32> {
+        Some(self.audio.output())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use test_case::test_case;
+
+    use super::Mmc5;
+    use crate::{mapper::Mapper, rom::Mirroring};
+
+    fn prg_rom() -> Vec<u8> {
+        (0..16).flat_map(|bank| vec![bank as u8; 0x2000]).collect()
+    }
+
+    #[test_case(0, [12, 13, 14, 15] ; "32k")]
+    #[test_case(1, [4, 5, 14, 15] ; "16k")]
+    #[test_case(2, [4, 5, 6, 15] ; "16k 8k")]
+    #[test_case(3, [3, 5, 6, 15] ; "8k")]
+    fn test_prg_modes(mode: u8, expected: [u8; 4]) {
+        let mut mmc5 = Mmc5::new(prg_rom(), Mirroring::Vertical);
+        mmc5.write(0x5100, mode);
+        for (addr, bank) in [
+            (0x5114, 0x83),
+            (0x5115, 0x85),
+            (0x5116, 0x86),
+            (0x5117, 0xFF),
+        ] {
+            mmc5.write(addr, bank);
+        }
+        assert_eq!(
+            [0x8000, 0xA000, 0xC000, 0xE000].map(|addr| mmc5.peek(addr).unwrap()),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_prg_ram() {
+        let mut mmc5 = Mmc5::new(prg_rom(), Mirroring::Vertical);
+        mmc5.write(0x5113, 0x02);
+        mmc5.write(0x6000, 0x12);
+
+        // NOTE: 同じ RAM のバンクを $8000 にも置ける
+        mmc5.write(0x5114, 0x02);
+        assert_eq!(mmc5.peek(0x8000), Some(0x12));
+        mmc5.write(0x8001, 0x34);
+        assert_eq!(mmc5.peek(0x6001), Some(0x34));
+
+        mmc5.write(0x5113, 0x03);
+        assert_eq!(mmc5.peek(0x6000), Some(0x00));
+    }
+
+    #[test_case(0, [0x38, 0x39, 0x3A, 0x3B, 0x3C, 0x3D, 0x3E, 0x3F] ; "8k")]
+    #[test_case(1, [0x0C, 0x0D, 0x0E, 0x0F, 0x1C, 0x1D, 0x1E, 0x1F] ; "4k")]
+    #[test_case(2, [0x02, 0x03, 0x06, 0x07, 0x0A, 0x0B, 0x0E, 0x0F] ; "2k")]
+    #[test_case(3, [0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07] ; "1k")]
+    fn test_chr_modes(mode: u8, expected: [usize; 8]) {
+        let mut mmc5 = Mmc5::new(prg_rom(), Mirroring::Vertical);
+        mmc5.write(0x5101, mode);
+        for i in 0..8 {
+            mmc5.write(0x5120 + i, i as u8);
+        }
+        assert_eq!(mmc5.chr_banks().unwrap(), expected.map(|bank| bank * 0x400));
+    }
+
+    #[test]
+    fn test_bg_chr_banks() {
+        let mut mmc5 = Mmc5::new(prg_rom(), Mirroring::Vertical);
+        mmc5.write(0x5101, 0x03);
+        mmc5.write(0x5130, 0x01);
+        for i in 0..4 {
+            mmc5.write(0x5128 + i, 0x10 + i as u8);
+        }
+        let expected = [0x110, 0x111, 0x112, 0x113, 0x110, 0x111, 0x112, 0x113];
+        assert_eq!(mmc5.chr_banks().unwrap(), expected.map(|bank| bank * 0x400));
+    }
+
+    #[test]
+    fn test_multiplier_and_mirroring() {
+        let mut mmc5 = Mmc5::new(prg_rom(), Mirroring::Vertical);
+        mmc5.write(0x5205, 0x12);
+        mmc5.write(0x5206, 0x34);
+        assert_eq!(
+            [mmc5.peek(0x5205), mmc5.peek(0x5206)],
+            [Some(0xA8), Some(0x03)]
+        );
+
+        mmc5.write(0x5105, 0x50);
+        assert_eq!(mmc5.mirroring(), Some(Mirroring::Horizontal));
+    }
+
+    #[test]
+    fn test_audio() {
+        let mut mmc5 = Mmc5::new(prg_rom(), Mirroring::Vertical);
+        assert_eq!(mmc5.audio_output(), Some(0.0));
+        mmc5.write(0x5011, 0x80);
+        assert!(mmc5.audio_output().unwrap() > 0.0);
+
+        // NOTE: PCM に 0 を書くと IRQ が立つ
+        mmc5.write(0x5010, 0x80);
+        mmc5.write(0x5011, 0x00);
+        assert!(mmc5.irq());
+        mmc5.read(0x5010);
+        assert!(!mmc5.irq());
+    }
+}
@@ -0,0 +1,122 @@
+use fme7::Fme7;
+use mmc2::Mmc2;
+use mmc5::Mmc5;
+use n163::N163;
+use nrom::Nrom;
+use vrc1::Vrc1;
+use vrc3::Vrc3;
+use vrc4::Vrc4;
+use vrc6::Vrc6;
+use vrc7::Vrc7;
+
+use crate::{
+    diagnostics::Diagnostics,
+    fds::Fds,
+    region::Region,
+    rom::{Mirroring, Rom},
+};
+
+mod banks;
+mod fme7;
+mod mmc2;
+mod mmc5;
+mod n163;
+mod nrom;
+mod vrc;
+mod vrc1;
+mod vrc3;
+mod vrc4;
+mod vrc6;
+mod vrc7;
+
+// NOTE: カートリッジ側 ($4020-$FFFF) のハードウェア。読み込みで None を返したアドレスはオープンバスになる
+pub(crate) trait Mapper {
+    fn read(&mut self, addr: u16) -> Option<u8>;
+    // NOTE: 副作用なしで読む。レジスタなど読むと状態が変わるところは None を返す
+    fn peek(&self, addr: u16) -> Option<u8>;
+    fn write(&mut self, addr: u16, data: u8);
+
+    fn set_diagnostics(&mut self, _diagnostics: Diagnostics) {}
+
+    // NOTE: 実行中にミラーリングを切り替えるものだけが Some を返す
+    fn mirroring(&self) -> Option<Mirroring> {
+        None
+    }
+
+    // NOTE: PPU の $0000-$1FFF を 1KB ずつ割り当てる CHR のオフセット。切り替えるものだけが Some を返す
+    fn chr_banks(&self) -> Option<[usize; 8]> {
+        None
+    }
+
+    // NOTE: レンダリング中に PPU がパターンテーブルを読んだアドレス (フェッチ順)。CHR のバンクを切り替えたら true を返す
+    fn observe_pattern_fetch(&mut self, _addr: u16) -> bool {
+        false
+    }
+
+    fn tick(&mut self, _cycles: u8) {}
+
+    fn irq(&self) -> bool {
+        false
+    }
+
+    // NOTE: 拡張音源の今の出力。大きさは ExpansionAudio::output と同じ
+    fn audio_output(&self) -> Option<f32> {
+        None
+    }
+
+    fn fds(&self) -> Option<&Fds> {
+        None
+    }
+
+    fn fds_mut(&mut self) -> Option<&mut Fds> {
+        None
+    }
+}
+
+pub(crate) struct Cartridge {
+    pub(crate) mapper: Box<dyn Mapper>,
+    pub(crate) chr_rom: Vec<u8>,
+    pub(crate) is_chr_ram: bool,
+    pub(crate) mirroring: Mirroring,
+    pub(crate) region: Option<Region>,
+}
+
+impl Cartridge {
+    pub(crate) fn fds(fds: Fds) -> Self {
+        Self {
+            mapper: Box::new(fds),
+            chr_rom: vec![0; 0x2000],
+            is_chr_ram: true,
+            mirroring: Mirroring::Horizontal,
+            region: Some(Region::Ntsc),
+        }
+    }
+}
+
+impl From<Rom> for Cartridge {
+    fn from(rom: Rom) -> Self {
+        let mirroring = rom.screen_mirroring;
+        let mapper: Box<dyn Mapper> = match rom.mapper {
+            5 => Box::new(Mmc5::new(rom.prg_rom, mirroring)),
+            9 | 10 => Box::new(Mmc2::new(rom.prg_rom, rom.mapper, mirroring)),
+            21 | 22 | 23 | 25 => {
+                Box::new(Vrc4::new(rom.prg_rom, rom.mapper, rom.submapper, mirroring))
+            }
+            19 => Box::new(N163::new(rom.prg_rom)),
+            24 | 26 => Box::new(Vrc6::new(rom.prg_rom, rom.mapper, mirroring)),
+            69 => Box::new(Fme7::new(rom.prg_rom, mirroring)),
+            73 => Box::new(Vrc3::new(rom.prg_rom)),
+            75 => Box::new(Vrc1::new(rom.prg_rom, mirroring)),
+            85 => Box::new(Vrc7::new(rom.prg_rom, rom.submapper, mirroring)),
+            _ => Box::new(Nrom::new(rom.prg_rom)),
+        };
+
+        Self {
+            mapper,
+            chr_rom: rom.chr_rom,
+            is_chr_ram: rom.is_chr_ram,
+            mirroring: rom.screen_mirroring,
+            region: rom.region,
+        }
+    }
+}
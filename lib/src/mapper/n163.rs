@@ -0,0 +1,190 @@
+use crate::apu::expansion::{ExpansionAudio, N163Audio};
+
+use super::{
+    banks::{chr_offsets, PrgBanks},
+    Mapper,
+};
+
+const AUDIO_DATA: u16 = 0x4800;
+const AUDIO_DATA_END: u16 = 0x4FFF;
+const IRQ_LOW: u16 = 0x5000;
+const IRQ_LOW_END: u16 = 0x57FF;
+const IRQ_HIGH: u16 = 0x5800;
+const IRQ_HIGH_END: u16 = 0x5FFF;
+const PRG_RAM: u16 = 0x6000;
+const PRG_RAM_END: u16 = 0x7FFF;
+const ROM: u16 = 0x8000;
+const ROM_END: u16 = 0xFFFF;
+const AUDIO_ADDRESS: u16 = 0xF800;
+
+// NOTE: 15bit のカウンタが CPU の 1 サイクルごとに増え、$7FFF で止まって IRQ を出す
+const IRQ_COUNTER_MAX: u16 = 0x7FFF;
+
+// NOTE: マッパー 19 (ナムコ 163)。$E0 以上の CHR バンクで内蔵 VRAM を選ぶ機能と、
+//       $C000-$DFFF のネームテーブルの切り替え、PRG-RAM の書き込み保護は未実装
+pub(super) struct N163 {
+    prg: PrgBanks,
+    prg_ram: [u8; 0x2000],
+    chr_banks: [u8; 8],
+    irq_counter: u16,
+    irq_enabled: bool,
+    irq: bool,
+    silenced: bool,
+    audio: N163Audio,
+}
+
+impl N163 {
+    pub(super) fn new(prg_rom: Vec<u8>) -> Self {
+        Self {
+            prg: PrgBanks::new(prg_rom),
+            prg_ram: [0; 0x2000],
+            chr_banks: [0, 1, 2, 3, 4, 5, 6, 7],
+            irq_counter: 0,
+            irq_enabled: false,
+            irq: false,
+            silenced: false,
+            audio: N163Audio::new(),
+        }
+    }
+}
+
+impl Mapper for N163 {
+    // NOTE: 音源のデータポートは読むとアドレスが進むことがある
+    fn read(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            AUDIO_DATA..=AUDIO_DATA_END => self.audio.read(addr),
+            _ => self.peek(addr),
+        }
+    }
+
+    fn peek(&self, addr: u16) -> Option<u8> {
+        match addr {
+            IRQ_LOW..=IRQ_LOW_END => Some(self.irq_counter as u8),
+            IRQ_HIGH..=IRQ_HIGH_END => {
+                Some((self.irq_counter >> 8) as u8 | (self.irq_enabled as u8) << 7)
+            }
+            PRG_RAM..=PRG_RAM_END => Some(self.prg_ram[(addr - PRG_RAM) as usize]),
+            ROM..=ROM_END => Some(self.prg.read(addr)),
+            _ => None,
+        }
+    }
+
+    fn write(&mut self, addr: u16, data: u8) {
+        match addr {
+            AUDIO_DATA..=AUDIO_DATA_END => self.audio.write(addr, data),
+            IRQ_LOW..=IRQ_LOW_END => {
+                self.irq_counter = (self.irq_counter & 0x7F00) | data as u16;
+                self.irq = false;
+            }
+            IRQ_HIGH..=IRQ_HIGH_END => {
+                self.irq_counter = (self.irq_counter & 0x00FF) | ((data & 0x7F) as u16) << 8;
+                self.irq_enabled = data & 0x80 != 0;
+                self.irq = false;
+            }
+            PRG_RAM..=PRG_RAM_END => self.prg_ram[(addr - PRG_RAM) as usize] = data,
+            0x8000..=0xBFFF => self.chr_banks[((addr - 0x8000) / 0x800) as usize] = data,
+            0xE000..=0xE7FF => {
+                self.prg.set(0, (data & 0x3F) as usize);
+                self.silenced = data & 0x40 != 0;
+            }
+            0xE800..=0xEFFF => self.prg.set(1, (data & 0x3F) as usize),
+            0xF000..=0xF7FF => self.prg.set(2, (data & 0x3F) as usize),
+            AUDIO_ADDRESS..=ROM_END => self.audio.write(addr, data),
+            _ => {}
+        }
+    }
+
+    fn chr_banks(&self) -> Option<[usize; 8]> {
+        Some(chr_offsets(self.chr_banks.map(|bank| bank as usize)))
+    }
+
+    fn tick(&mut self, cycles: u8) {
+        for _ in 0..cycles {
+            if self.irq_enabled && self.irq_counter < IRQ_COUNTER_MAX {
+                self.irq_counter += 1;
+                if self.irq_counter == IRQ_COUNTER_MAX {
+                    self.irq = true;
+                }
+            }
+            self.audio.tick();
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.irq
+    }
+
+    // NOTE: $E000 の bit6 で音源を止める
+    fn audio_output(&self) -> Option<f32> {
+        if self.silenced {
+            Some(0.0)
+        } else {
+            Some(self.audio.output())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::N163;
+    use crate::mapper::Mapper;
+
+    fn prg_rom() -> Vec<u8> {
+        (0..16).flat_map(|bank| vec![bank as u8; 0x2000]).collect()
+    }
+
+    #[test]
+    fn test_prg_and_chr_banks() {
+        let mut n163 = N163::new(prg_rom());
+        n163.write(0xE000, 0x03);
+        n163.write(0xE800, 0x05);
+        n163.write(0xF000, 0x07);
+        assert_eq!(
+            [0x8000, 0xA000, 0xC000, 0xE000].map(|addr| n163.peek(addr).unwrap()),
+            [3, 5, 7, 15]
+        );
+
+        n163.write(0x8800, 0x21);
+        n163.write(0xB800, 0x42);
+        let banks = n163.chr_banks().unwrap();
+        assert_eq!([banks[1], banks[7]], [0x21 * 0x400, 0x42 * 0x400]);
+    }
+
+    #[test]
+    fn test_irq() {
+        let mut n163 = N163::new(prg_rom());
+        n163.write(0x5000, 0xFD);
+        n163.write(0x5800, 0xFF);
+        n163.tick(1);
+        assert!(!n163.irq());
+        n163.tick(1);
+        assert!(n163.irq());
+        assert_eq!(n163.peek(0x5000), Some(0xFF));
+
+        // NOTE: $7FFF で止まる
+        n163.tick(10);
+        assert_eq!(n163.peek(0x5800), Some(0xFF));
+        n163.write(0x5800, 0x00);
+        assert!(!n163.irq());
+    }
+
+    #[test]
+    fn test_audio() {
+        let mut n163 = N163::new(prg_rom());
+        // NOTE: 波形 F, F をアドレス 0 に置いて、チャンネル 8 を音量 15 で鳴らす
+        n163.write(0xF800, 0x80);
+        n163.write(0x4800, 0xFF);
+        n163.write(0xF800, 0x80 | 0x7C);
+        for data in [0xFC, 0x00, 0x00, 0x0F] {
+            n163.write(0x4800, data);
+        }
+        n163.tick(15);
+        assert!(n163.audio_output().unwrap() > 0.0);
+
+        n163.write(0xF800, 0x00);
+        assert_eq!(n163.read(0x4800), Some(0xFF));
+
+        n163.write(0xE000, 0x40);
+        assert_eq!(n163.audio_output(), Some(0.0));
+    }
+}
@@ -0,0 +1,65 @@
+use crate::diagnostics::{Category, Diagnostics};
+
+use super::Mapper;
+
+const PRG_RAM: u16 = 0x6000;
+const PRG_RAM_END: u16 = 0x7FFF;
+const ROM: u16 = 0x8000;
+const ROM_END: u16 = 0xFFFF;
+
+// NOTE: バンク切り替えなし。PRG-ROM が 16KB なら $C000-$FFFF は $8000-$BFFF のミラー
+pub(super) struct Nrom {
+    prg_rom: Vec<u8>,
+    prg_ram: [u8; 0x2000],
+    diagnostics: Diagnostics,
+}
+
+impl Nrom {
+    pub(super) fn new(prg_rom: Vec<u8>) -> Self {
+        Self {
+            prg_rom,
+            prg_ram: [0; 0x2000],
+            diagnostics: Diagnostics::default(),
+        }
+    }
+
+    fn read_prg_rom(&self, addr: u16) -> u8 {
+        let prog_addr = addr - ROM;
+        let prog_addr = if self.prg_rom.len() == 0x4000 && prog_addr >= 0x4000 {
+            prog_addr % 0x4000
+        } else {
+            prog_addr
+        };
+        self.prg_rom[prog_addr as usize]
+    }
+}
+
+impl Mapper for Nrom {
+    fn read(&mut self, addr: u16) -> Option<u8> {
+        self.peek(addr)
+    }
+
+    fn peek(&self, addr: u16) -> Option<u8> {
+        match addr {
+            PRG_RAM..=PRG_RAM_END => Some(self.prg_ram[(addr - PRG_RAM) as usize]),
+            ROM..=ROM_END => Some(self.read_prg_rom(addr)),
+            _ => None,
+        }
+    }
+
+    fn write(&mut self, addr: u16, data: u8) {
+        match addr {
+            PRG_RAM..=PRG_RAM_END => self.prg_ram[(addr - PRG_RAM) as usize] = data,
+            ROM..=ROM_END => {
+                self.diagnostics.report(Category::Bus, "rom-write", || {
+                    format!("Ignoring write to ROM at {:#06X}", addr)
+                });
+            }
+            _ => {}
+        }
+    }
+
+    fn set_diagnostics(&mut self, diagnostics: Diagnostics) {
+        self.diagnostics = diagnostics;
+    }
+}
@@ -0,0 +1,148 @@
+use crate::rom::Mirroring;
+
+// NOTE: スキャンラインモードでは CPU 1 サイクルごとに 3 減らし、341 (PPU の 1 ライン) を下回るたびにカウントする
+const PRESCALER_RELOAD: i16 = 341;
+const PRESCALER_STEP: i16 = 3;
+
+// NOTE: VRC4 / VRC6 / VRC7 共通の 8bit の IRQ カウンター。0xFF から溢れたらラッチの値に戻して IRQ を出す
+#[derive(Default)]
+pub(super) struct VrcIrq {
+    latch: u8,
+    counter: u8,
+    prescaler: i16,
+    enabled: bool,
+    enable_after_ack: bool,
+    cycle_mode: bool,
+    pending: bool,
+}
+
+impl VrcIrq {
+    pub(super) fn write_latch(&mut self, data: u8) {
+        self.latch = data;
+    }
+
+    // NOTE: VRC4 はラッチを 4bit ずつ書く
+    pub(super) fn write_latch_low(&mut self, data: u8) {
+        self.latch = (self.latch & 0xF0) | (data & 0x0F);
+    }
+
+    pub(super) fn write_latch_high(&mut self, data: u8) {
+        self.latch = (self.latch & 0x0F) | (data << 4);
+    }
+
+    pub(super) fn write_control(&mut self, data: u8) {
+        self.enable_after_ack = data & 0x01 != 0;
+        self.enabled = data & 0x02 != 0;
+        self.cycle_mode = data & 0x04 != 0;
+        if self.enabled {
+            self.counter = self.latch;
+            self.prescaler = PRESCALER_RELOAD;
+        }
+        self.pending = false;
+    }
+
+    pub(super) fn acknowledge(&mut self) {
+        self.enabled = self.enable_after_ack;
+        self.pending = false;
+    }
+
+    pub(super) fn tick(&mut self) {
+        if !self.enabled {
+            return;
+        }
+        if self.cycle_mode {
+            self.clock();
+            return;
+        }
+
+        self.prescaler -= PRESCALER_STEP;
+        if self.prescaler <= 0 {
+            self.prescaler += PRESCALER_RELOAD;
+            self.clock();
+        }
+    }
+
+    pub(super) fn pending(&self) -> bool {
+        self.pending
+    }
+
+    fn clock(&mut self) {
+        if self.counter == 0xFF {
+            self.counter = self.latch;
+            self.pending = true;
+        } else {
+            self.counter += 1;
+        }
+    }
+}
+
+// NOTE: VRC4 / VRC6 / VRC7 のミラーリングのレジスタ (下位 2bit)
+pub(super) fn mirroring(data: u8) -> Mirroring {
+    match data & 0x03 {
+        0 => Mirroring::Vertical,
+        1 => Mirroring::Horizontal,
+        2 => Mirroring::SingleScreenLower,
+        _ => Mirroring::SingleScreenUpper,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::VrcIrq;
+
+    fn cycles_until_irq(irq: &mut VrcIrq) -> usize {
+        let mut cycles = 0;
+        while !irq.pending() {
+            irq.tick();
+            cycles += 1;
+            assert!(cycles < 1_000_000);
+        }
+        cycles
+    }
+
+    #[test]
+    fn test_cycle_mode() {
+        let mut irq = VrcIrq::default();
+        irq.write_latch(0xFC);
+        irq.write_control(0x06);
+        // NOTE: FC -> FD -> FE -> FF -> 溢れる
+        assert_eq!(cycles_until_irq(&mut irq), 4);
+
+        // NOTE: ラッチから数え直す
+        irq.acknowledge();
+        assert!(!irq.pending());
+        irq.write_control(0x06);
+        assert_eq!(cycles_until_irq(&mut irq), 4);
+    }
+
+    #[test]
+    fn test_scanline_mode() {
+        let mut irq = VrcIrq::default();
+        irq.write_latch_low(0x0E);
+        irq.write_latch_high(0x0F);
+        irq.write_control(0x02);
+        // NOTE: 2 ライン = 682 PPU サイクル = 約 227 CPU サイクル
+        let cycles = cycles_until_irq(&mut irq);
+        assert!((227..=228).contains(&cycles), "{}", cycles);
+    }
+
+    #[test]
+    fn test_acknowledge() {
+        let mut irq = VrcIrq::default();
+        irq.write_latch(0xFF);
+        // NOTE: A=0 なら ack で止まる
+        irq.write_control(0x06);
+        cycles_until_irq(&mut irq);
+        irq.acknowledge();
+        for _ in 0..1000 {
+            irq.tick();
+        }
+        assert!(!irq.pending());
+
+        // NOTE: A=1 なら ack のあとも続ける
+        irq.write_control(0x07);
+        cycles_until_irq(&mut irq);
+        irq.acknowledge();
+        assert_eq!(cycles_until_irq(&mut irq), 1);
+    }
+}
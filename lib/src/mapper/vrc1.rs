@@ -0,0 +1,98 @@
+use crate::rom::Mirroring;
+
+use super::{
+    banks::{chr_offsets, PrgBanks},
+    Mapper,
+};
+
+const ROM: u16 = 0x8000;
+const ROM_END: u16 = 0xFFFF;
+
+// NOTE: マッパー 75。8KB の PRG を 3 つと 4KB の CHR を 2 つ切り替えるだけで、PRG-RAM も IRQ もない
+pub(super) struct Vrc1 {
+    prg: PrgBanks,
+    chr_banks: [u8; 2],
+    mirroring: Mirroring,
+}
+
+impl Vrc1 {
+    pub(super) fn new(prg_rom: Vec<u8>, mirroring: Mirroring) -> Self {
+        let mut prg = PrgBanks::new(prg_rom);
+        prg.set(3, prg.last());
+
+        Self {
+            prg,
+            chr_banks: [0, 1],
+            mirroring,
+        }
+    }
+}
+
+impl Mapper for Vrc1 {
+    fn read(&mut self, addr: u16) -> Option<u8> {
+        self.peek(addr)
+    }
+
+    fn peek(&self, addr: u16) -> Option<u8> {
+        match addr {
+            ROM..=ROM_END => Some(self.prg.read(addr)),
+            _ => None,
+        }
+    }
+
+    fn write(&mut self, addr: u16, data: u8) {
+        match addr & 0xF000 {
+            0x8000 => self.prg.set(0, (data & 0x0F) as usize),
+            // NOTE: CHR バンクの最上位ビットはここにある
+            0x9000 => {
+                self.mirroring = if data & 0x01 == 0 {
+                    Mirroring::Vertical
+                } else {
+                    Mirroring::Horizontal
+                };
+                self.chr_banks[0] = (self.chr_banks[0] & 0x0F) | (data & 0x02) << 3;
+                self.chr_banks[1] = (self.chr_banks[1] & 0x0F) | (data & 0x04) << 2;
+            }
+            0xA000 => self.prg.set(1, (data & 0x0F) as usize),
+            0xC000 => self.prg.set(2, (data & 0x0F) as usize),
+            0xE000 => self.chr_banks[0] = (self.chr_banks[0] & 0x10) | (data & 0x0F),
+            0xF000 => self.chr_banks[1] = (self.chr_banks[1] & 0x10) | (data & 0x0F),
+            _ => {}
+        }
+    }
+
+    fn mirroring(&self) -> Option<Mirroring> {
+        Some(self.mirroring)
+    }
+
+    fn chr_banks(&self) -> Option<[usize; 8]> {
+        let banks = std::array::from_fn(|slot| self.chr_banks[slot / 4] as usize * 4 + slot % 4);
+        Some(chr_offsets(banks))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Vrc1;
+    use crate::{mapper::Mapper, rom::Mirroring};
+
+    #[test]
+    fn test_banks() {
+        let prg_rom = (0..16).flat_map(|bank| vec![bank as u8; 0x2000]).collect();
+        let mut vrc = Vrc1::new(prg_rom, Mirroring::Vertical);
+        vrc.write(0x8000, 0x01);
+        vrc.write(0xA000, 0x02);
+        vrc.write(0xC000, 0x13);
+        assert_eq!(
+            [0x8000, 0xA000, 0xC000, 0xE000].map(|addr| vrc.peek(addr).unwrap()),
+            [1, 2, 3, 15]
+        );
+
+        vrc.write(0xE000, 0x03);
+        vrc.write(0xF000, 0x05);
+        vrc.write(0x9000, 0x05);
+        assert_eq!(vrc.mirroring(), Some(Mirroring::Horizontal));
+        let banks = vrc.chr_banks().unwrap();
+        assert_eq!((banks[0], banks[4]), (0x03 * 0x1000, 0x15 * 0x1000));
+    }
+}
@@ -0,0 +1,163 @@
+use super::{banks::PrgBanks, Mapper};
+
+const PRG_RAM: u16 = 0x6000;
+const PRG_RAM_END: u16 = 0x7FFF;
+const ROM: u16 = 0x8000;
+const ROM_END: u16 = 0xFFFF;
+
+// NOTE: VRC3 の IRQ は 16bit のカウンターを CPU サイクルごとに上げる。
+//       8bit モードでは下位バイトだけが溢れたら IRQ を出して、下位バイトだけ戻す
+#[derive(Default)]
+struct Vrc3Irq {
+    latch: u16,
+    counter: u16,
+    enabled: bool,
+    enable_after_ack: bool,
+    byte_mode: bool,
+    pending: bool,
+}
+
+impl Vrc3Irq {
+    // NOTE: $8000/$9000/$A000/$B000 でラッチを下から 4bit ずつ書く
+    fn write_latch(&mut self, nibble: u16, data: u8) {
+        let shift = nibble * 4;
+        self.latch = (self.latch & !(0x0F << shift)) | ((data as u16 & 0x0F) << shift);
+    }
+
+    fn write_control(&mut self, data: u8) {
+        self.enable_after_ack = data & 0x01 != 0;
+        self.enabled = data & 0x02 != 0;
+        self.byte_mode = data & 0x04 != 0;
+        if self.enabled {
+            self.counter = self.latch;
+        }
+        self.pending = false;
+    }
+
+    fn acknowledge(&mut self) {
+        self.enabled = self.enable_after_ack;
+        self.pending = false;
+    }
+
+    fn tick(&mut self) {
+        if !self.enabled {
+            return;
+        }
+        if self.byte_mode {
+            if self.counter & 0xFF == 0xFF {
+                self.counter = (self.counter & 0xFF00) | (self.latch & 0x00FF);
+                self.pending = true;
+            } else {
+                self.counter += 1;
+            }
+        } else if self.counter == 0xFFFF {
+            self.counter = self.latch;
+            self.pending = true;
+        } else {
+            self.counter += 1;
+        }
+    }
+}
+
+// NOTE: マッパー 73 (沙羅曼蛇)。$8000 の 16KB だけを切り替え、$C000 は末尾に固定
+pub(super) struct Vrc3 {
+    prg: PrgBanks,
+    prg_ram: [u8; 0x2000],
+    irq: Vrc3Irq,
+}
+
+impl Vrc3 {
+    pub(super) fn new(prg_rom: Vec<u8>) -> Self {
+        let mut prg = PrgBanks::new(prg_rom);
+        let last = prg.last() / 2;
+        prg.set_16k(0, 0);
+        prg.set_16k(2, last);
+
+        Self {
+            prg,
+            prg_ram: [0; 0x2000],
+            irq: Vrc3Irq::default(),
+        }
+    }
+}
+
+impl Mapper for Vrc3 {
+    fn read(&mut self, addr: u16) -> Option<u8> {
+        self.peek(addr)
+    }
+
+    fn peek(&self, addr: u16) -> Option<u8> {
+        match addr {
+            PRG_RAM..=PRG_RAM_END => Some(self.prg_ram[(addr - PRG_RAM) as usize]),
+            ROM..=ROM_END => Some(self.prg.read(addr)),
+            _ => None,
+        }
+    }
+
+    fn write(&mut self, addr: u16, data: u8) {
+        match addr {
+            PRG_RAM..=PRG_RAM_END => self.prg_ram[(addr - PRG_RAM) as usize] = data,
+            0x8000..=0xBFFF => self.irq.write_latch((addr - 0x8000) >> 12, data),
+            0xC000..=0xCFFF => self.irq.write_control(data),
+            0xD000..=0xDFFF => self.irq.acknowledge(),
+            0xF000..=0xFFFF => self.prg.set_16k(0, (data & 0x07) as usize),
+            _ => {}
+        }
+    }
+
+    fn tick(&mut self, cycles: u8) {
+        for _ in 0..cycles {
+            self.irq.tick();
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.irq.pending
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Vrc3;
+    use crate::mapper::Mapper;
+
+    #[test]
+    fn test_prg_banks() {
+        let prg_rom = (0..8).flat_map(|bank| vec![bank as u8; 0x2000]).collect();
+        let mut vrc = Vrc3::new(prg_rom);
+        vrc.write(0xF000, 0x02);
+        assert_eq!(
+            [0x8000, 0xA000, 0xC000, 0xE000].map(|addr| vrc.peek(addr).unwrap()),
+            [4, 5, 6, 7]
+        );
+    }
+
+    #[test]
+    fn test_irq() {
+        let mut vrc = Vrc3::new(vec![0; 0x4000]);
+        for (i, addr) in [0x8000, 0x9000, 0xA000, 0xB000].into_iter().enumerate() {
+            vrc.write(addr, [0x0E, 0x0F, 0x0F, 0x0F][i]);
+        }
+        vrc.write(0xC000, 0x02);
+        vrc.tick(1);
+        assert!(!vrc.irq());
+        vrc.tick(1);
+        assert!(vrc.irq());
+        vrc.write(0xD000, 0x00);
+        assert!(!vrc.irq());
+    }
+
+    #[test]
+    fn test_byte_mode() {
+        let mut vrc = Vrc3::new(vec![0; 0x4000]);
+        vrc.write(0x8000, 0x0F);
+        vrc.write(0x9000, 0x0F);
+        vrc.write(0xC000, 0x07);
+        // NOTE: 上位バイトが 0 でも下位バイトの 0xFF で溢れる
+        vrc.tick(1);
+        assert!(vrc.irq());
+        vrc.write(0xD000, 0x00);
+        vrc.tick(1);
+        assert!(vrc.irq());
+    }
+}
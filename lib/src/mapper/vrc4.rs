@@ -0,0 +1,250 @@
+use crate::rom::Mirroring;
+
+use super::{
+    banks::{chr_offsets, PrgBanks},
+    vrc::{self, VrcIrq},
+    Mapper,
+};
+
+const PRG_RAM: u16 = 0x6000;
+const PRG_RAM_END: u16 = 0x7FFF;
+const ROM: u16 = 0x8000;
+const ROM_END: u16 = 0xFFFF;
+
+// NOTE: レジスタ番号の bit0 と bit1 になるアドレス線。どちらの配線か分からないときは両方の OR にする
+struct AddressLines(u16, u16);
+
+impl AddressLines {
+    fn register(&self, addr: u16) -> u16 {
+        (addr & self.0 != 0) as u16 | ((addr & self.1 != 0) as u16) << 1
+    }
+}
+
+// NOTE: マッパー 21/22/23/25。VRC2 と VRC4 は同じ番号にまたがっていて、サブマッパーで区別する
+//       21: VRC4a (A1, A2) / VRC4c (A6, A7)
+//       22: VRC2a (A1, A0)
+//       23: VRC4f (A0, A1) / VRC4e (A2, A3) / VRC2b (A0, A1)
+//       25: VRC4b (A1, A0) / VRC4d (A3, A2) / VRC2c (A1, A0)
+pub(super) struct Vrc4 {
+    prg: PrgBanks,
+    prg_ram: [u8; 0x2000],
+    is_vrc2: bool,
+    lines: AddressLines,
+    // NOTE: VRC2a は CHR のバンク番号の最下位ビットを使わない
+    chr_shift: u8,
+    prg_banks: [u8; 2],
+    prg_swap: bool,
+    chr_banks: [u16; 8],
+    mirroring: Mirroring,
+    irq: VrcIrq,
+}
+
+impl Vrc4 {
+    pub(super) fn new(prg_rom: Vec<u8>, mapper: u8, submapper: u8, mirroring: Mirroring) -> Self {
+        let (lines, is_vrc2) = match (mapper, submapper) {
+            (21, 1) => (AddressLines(0x02, 0x04), false),
+            (21, 2) => (AddressLines(0x40, 0x80), false),
+            (21, _) => (AddressLines(0x42, 0x84), false),
+            (22, _) => (AddressLines(0x02, 0x01), true),
+            (23, 1) => (AddressLines(0x01, 0x02), false),
+            (23, 2) => (AddressLines(0x04, 0x08), false),
+            (23, 3) => (AddressLines(0x01, 0x02), true),
+            (23, _) => (AddressLines(0x05, 0x0A), false),
+            (25, 1) => (AddressLines(0x02, 0x01), false),
+            (25, 2) => (AddressLines(0x08, 0x04), false),
+            (25, 3) => (AddressLines(0x02, 0x01), true),
+            (_, _) => (AddressLines(0x0A, 0x05), false),
+        };
+
+        let mut vrc4 = Self {
+            prg: PrgBanks::new(prg_rom),
+            prg_ram: [0; 0x2000],
+            is_vrc2,
+            lines,
+            chr_shift: if mapper == 22 { 1 } else { 0 },
+            prg_banks: [0, 1],
+            prg_swap: false,
+            chr_banks: [0, 1, 2, 3, 4, 5, 6, 7],
+            mirroring,
+            irq: VrcIrq::default(),
+        };
+        vrc4.update_prg_banks();
+        vrc4
+    }
+
+    // NOTE: スワップモードでは $8000 と $C000 が入れ替わる ($C000 側が後ろから 2 番目に固定)
+    fn update_prg_banks(&mut self) {
+        let second_last = self.prg.last().saturating_sub(1);
+        let (first, third) = if self.prg_swap {
+            (second_last, self.prg_banks[0] as usize)
+        } else {
+            (self.prg_banks[0] as usize, second_last)
+        };
+        self.prg.set(0, first);
+        self.prg.set(1, self.prg_banks[1] as usize);
+        self.prg.set(2, third);
+        self.prg.set(3, self.prg.last());
+    }
+
+    // NOTE: $B000-$E003 の 1KB バンク。偶数レジスタが下位 4bit、奇数レジスタが上位 (VRC2 は 4bit、VRC4 は 5bit)
+    fn write_chr_bank(&mut self, addr: u16, reg: u16, data: u8) {
+        let index = ((addr >> 12) - 0xB) as usize * 2 + (reg >> 1) as usize;
+        let bank = &mut self.chr_banks[index];
+        if reg & 0x01 == 0 {
+            *bank = (*bank & 0x1F0) | (data as u16 & 0x0F);
+        } else {
+            let mask = if self.is_vrc2 { 0x0F } else { 0x1F };
+            *bank = (*bank & 0x0F) | ((data as u16 & mask) << 4);
+        }
+    }
+}
+
+impl Mapper for Vrc4 {
+    fn read(&mut self, addr: u16) -> Option<u8> {
+        self.peek(addr)
+    }
+
+    fn peek(&self, addr: u16) -> Option<u8> {
+        match addr {
+            PRG_RAM..=PRG_RAM_END => Some(self.prg_ram[(addr - PRG_RAM) as usize]),
+            ROM..=ROM_END => Some(self.prg.read(addr)),
+            _ => None,
+        }
+    }
+
+    fn write(&mut self, addr: u16, data: u8) {
+        if let PRG_RAM..=PRG_RAM_END = addr {
+            self.prg_ram[(addr - PRG_RAM) as usize] = data;
+            return;
+        }
+
+        let reg = self.lines.register(addr);
+        match (addr & 0xF000, reg) {
+            (0x8000, _) => {
+                self.prg_banks[0] = data & 0x1F;
+                self.update_prg_banks();
+            }
+            (0x9000, _) if self.is_vrc2 => {
+                self.mirroring = if data & 0x01 == 0 {
+                    Mirroring::Vertical
+                } else {
+                    Mirroring::Horizontal
+                };
+            }
+            (0x9000, 0 | 1) => self.mirroring = vrc::mirroring(data),
+            (0x9000, _) => {
+                self.prg_swap = data & 0x02 != 0;
+                self.update_prg_banks();
+            }
+            (0xA000, _) => {
+                self.prg_banks[1] = data & 0x1F;
+                self.update_prg_banks();
+            }
+            (0xB000..=0xE000, _) => self.write_chr_bank(addr, reg, data),
+            // NOTE: VRC2 には IRQ がない
+            (0xF000, _) if self.is_vrc2 => {}
+            (0xF000, 0) => self.irq.write_latch_low(data),
+            (0xF000, 1) => self.irq.write_latch_high(data),
+            (0xF000, 2) => self.irq.write_control(data),
+            (0xF000, _) => self.irq.acknowledge(),
+            _ => {}
+        }
+    }
+
+    fn mirroring(&self) -> Option<Mirroring> {
+        Some(self.mirroring)
+    }
+
+    fn chr_banks(&self) -> Option<[usize; 8]> {
+        Some(chr_offsets(
+            self.chr_banks.map(|bank| (bank >> self.chr_shift) as usize),
+        ))
+    }
+
+    fn tick(&mut self, cycles: u8) {
+        for _ in 0..cycles {
+            self.irq.tick();
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.irq.pending()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use test_case::test_case;
+
+    use super::Vrc4;
+    use crate::{mapper::Mapper, rom::Mirroring};
+
+    fn prg_rom() -> Vec<u8> {
+        (0..16).flat_map(|bank| vec![bank as u8; 0x2000]).collect()
+    }
+
+    // NOTE: CHR バンク 0 の上位 (レジスタ 1) を書き込むアドレス
+    #[test_case(21, 1, 0xB002 ; "vrc4a")]
+    #[test_case(21, 2, 0xB040 ; "vrc4c")]
+    #[test_case(21, 0, 0xB040 ; "vrc4a or vrc4c")]
+    #[test_case(22, 0, 0xB002 ; "vrc2a")]
+    #[test_case(23, 1, 0xB001 ; "vrc4f")]
+    #[test_case(23, 2, 0xB004 ; "vrc4e")]
+    #[test_case(25, 1, 0xB002 ; "vrc4b")]
+    #[test_case(25, 2, 0xB008 ; "vrc4d")]
+    #[test_case(25, 0, 0xB008 ; "vrc4b or vrc4d")]
+    fn test_address_lines(mapper: u8, submapper: u8, addr: u16) {
+        let mut vrc = Vrc4::new(prg_rom(), mapper, submapper, Mirroring::Vertical);
+        vrc.write(0xB000, 0x03);
+        vrc.write(addr, 0x01);
+        let bank = if mapper == 22 { 0x13 >> 1 } else { 0x13 };
+        assert_eq!(vrc.chr_banks().unwrap()[0], bank * 0x400);
+    }
+
+    #[test]
+    fn test_prg_swap() {
+        let mut vrc = Vrc4::new(prg_rom(), 21, 1, Mirroring::Vertical);
+        vrc.write(0x8000, 0x03);
+        vrc.write(0xA000, 0x05);
+        let read =
+            |vrc: &Vrc4| [0x8000, 0xA000, 0xC000, 0xE000].map(|addr| vrc.peek(addr).unwrap());
+        assert_eq!(read(&vrc), [3, 5, 14, 15]);
+
+        vrc.write(0x9004, 0x02);
+        assert_eq!(read(&vrc), [14, 5, 3, 15]);
+    }
+
+    #[test]
+    fn test_mirroring() {
+        let mut vrc = Vrc4::new(prg_rom(), 25, 1, Mirroring::Vertical);
+        vrc.write(0x9000, 0x03);
+        assert_eq!(vrc.mirroring(), Some(Mirroring::SingleScreenUpper));
+
+        // NOTE: VRC2 は bit0 だけ
+        let mut vrc = Vrc4::new(prg_rom(), 22, 0, Mirroring::Vertical);
+        vrc.write(0x9000, 0x03);
+        assert_eq!(vrc.mirroring(), Some(Mirroring::Horizontal));
+    }
+
+    #[test]
+    fn test_irq() {
+        let mut vrc = Vrc4::new(prg_rom(), 23, 1, Mirroring::Vertical);
+        vrc.write(0xF000, 0x0E);
+        vrc.write(0xF001, 0x0F);
+        vrc.write(0xF002, 0x06);
+        vrc.tick(1);
+        assert!(!vrc.irq());
+        vrc.tick(1);
+        assert!(vrc.irq());
+        vrc.write(0xF003, 0x00);
+        assert!(!vrc.irq());
+
+        // NOTE: VRC2 には IRQ がない
+        let mut vrc = Vrc4::new(prg_rom(), 23, 3, Mirroring::Vertical);
+        vrc.write(0xF000, 0x0F);
+        vrc.write(0xF001, 0x0F);
+        vrc.write(0xF002, 0x06);
+        vrc.tick(10);
+        assert!(!vrc.irq());
+    }
+}
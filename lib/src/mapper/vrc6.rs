@@ -0,0 +1,197 @@
+use crate::{
+    apu::expansion::{ExpansionAudio, Vrc6Audio},
+    rom::Mirroring,
+};
+
+use super::{
+    banks::{chr_offsets, PrgBanks},
+    vrc::{self, VrcIrq},
+    Mapper,
+};
+
+const PRG_RAM: u16 = 0x6000;
+const PRG_RAM_END: u16 = 0x7FFF;
+const ROM: u16 = 0x8000;
+const ROM_END: u16 = 0xFFFF;
+
+// NOTE: マッパー 24 (VRC6a) と 26 (VRC6b)。26 は A0 と A1 が入れ替わっているので、24 の並びに直してから扱う
+pub(super) struct Vrc6 {
+    prg: PrgBanks,
+    prg_ram: [u8; 0x2000],
+    swap_lines: bool,
+    chr_registers: [u8; 8],
+    chr_mode: u8,
+    mirroring: Mirroring,
+    irq: VrcIrq,
+    audio: Vrc6Audio,
+}
+
+impl Vrc6 {
+    pub(super) fn new(prg_rom: Vec<u8>, mapper: u8, mirroring: Mirroring) -> Self {
+        Self {
+            prg: PrgBanks::new(prg_rom),
+            prg_ram: [0; 0x2000],
+            swap_lines: mapper == 26,
+            chr_registers: [0, 1, 2, 3, 4, 5, 6, 7],
+            chr_mode: 0,
+            mirroring,
+            irq: VrcIrq::default(),
+            audio: Vrc6Audio::new(),
+        }
+    }
+
+    fn canonical_addr(&self, addr: u16) -> u16 {
+        let reg = if self.swap_lines {
+            (addr & 0x01) << 1 | (addr & 0x02) >> 1
+        } else {
+            addr & 0x03
+        };
+        (addr & 0xF000) | reg
+    }
+}
+
+impl Mapper for Vrc6 {
+    fn read(&mut self, addr: u16) -> Option<u8> {
+        self.peek(addr)
+    }
+
+    fn peek(&self, addr: u16) -> Option<u8> {
+        match addr {
+            PRG_RAM..=PRG_RAM_END => Some(self.prg_ram[(addr - PRG_RAM) as usize]),
+            ROM..=ROM_END => Some(self.prg.read(addr)),
+            _ => None,
+        }
+    }
+
+    fn write(&mut self, addr: u16, data: u8) {
+        if let PRG_RAM..=PRG_RAM_END = addr {
+            self.prg_ram[(addr - PRG_RAM) as usize] = data;
+            return;
+        }
+
+        match self.canonical_addr(addr) {
+            0x8000..=0x8003 => self.prg.set_16k(0, (data & 0x0F) as usize),
+            0xB003 => {
+                self.chr_mode = data & 0x03;
+                self.mirroring = vrc::mirroring(data >> 2);
+            }
+            addr @ 0x9000..=0xB002 => self.audio.write(addr, data),
+            0xC000..=0xC003 => self.prg.set(2, (data & 0x1F) as usize),
+            addr @ (0xD000..=0xD003 | 0xE000..=0xE003) => {
+                let index = ((addr >> 12) - 0xD) * 4 + (addr & 0x03);
+                self.chr_registers[index as usize] = data;
+            }
+            0xF000 => self.irq.write_latch(data),
+            0xF001 => self.irq.write_control(data),
+            0xF002 => self.irq.acknowledge(),
+            _ => {}
+        }
+    }
+
+    fn mirroring(&self) -> Option<Mirroring> {
+        Some(self.mirroring)
+    }
+
+    // NOTE: 2KB のバンクではレジスタの最下位ビットの代わりに PPU の A10 を使う
+    fn chr_banks(&self) -> Option<[usize; 8]> {
+        let r = self.chr_registers.map(|bank| bank as usize);
+        let banks = match self.chr_mode {
+            0 => r,
+            1 => std::array::from_fn(|slot| (r[slot / 2] & !1) | (slot & 1)),
+            _ => std::array::from_fn(|slot| match slot {
+                0..=3 => r[slot],
+                _ => (r[4 + (slot - 4) / 2] & !1) | (slot & 1),
+            }),
+        };
+        Some(chr_offsets(banks))
+    }
+
+    fn tick(&mut self, cycles: u8) {
+        for _ in 0..cycles {
+            self.irq.tick();
+            self.audio.tick();
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.irq.pending()
+    }
+
+    fn audio_output(&self) -> Option<f32> {
+        Some(self.audio.output())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use test_case::test_case;
+
+    use super::Vrc6;
+    use crate::{mapper::Mapper, rom::Mirroring};
+
+    fn prg_rom() -> Vec<u8> {
+        (0..16).flat_map(|bank| vec![bank as u8; 0x2000]).collect()
+    }
+
+    #[test]
+    fn test_prg_banks() {
+        let mut vrc = Vrc6::new(prg_rom(), 24, Mirroring::Vertical);
+        vrc.write(0x8000, 0x02);
+        vrc.write(0xC000, 0x09);
+        assert_eq!(
+            [0x8000, 0xA000, 0xC000, 0xE000].map(|addr| vrc.peek(addr).unwrap()),
+            [4, 5, 9, 15]
+        );
+    }
+
+    // NOTE: CHR レジスタ 1 ($D001) と $B003 の書き込み先
+    #[test_case(24, 0xD001, 0xB003 ; "vrc6a")]
+    #[test_case(26, 0xD002, 0xB003 ; "vrc6b")]
+    fn test_address_lines(mapper: u8, chr: u16, control: u16) {
+        let mut vrc = Vrc6::new(prg_rom(), mapper, Mirroring::Vertical);
+        vrc.write(chr, 0x21);
+        vrc.write(control, 0x04);
+        assert_eq!(vrc.chr_banks().unwrap()[1], 0x21 * 0x400);
+        assert_eq!(vrc.mirroring(), Some(Mirroring::Horizontal));
+    }
+
+    #[test_case(0x00, [8, 9, 10, 11, 12, 13, 14, 15] ; "1k")]
+    #[test_case(0x01, [8, 9, 10, 11, 12, 13, 14, 15] ; "2k")]
+    #[test_case(0x02, [8, 9, 10, 11, 12, 13, 14, 15] ; "mixed")]
+    fn test_chr_modes(mode: u8, expected: [usize; 8]) {
+        let mut vrc = Vrc6::new(prg_rom(), 24, Mirroring::Vertical);
+        vrc.write(0xB003, mode);
+        let registers: &[u8] = match mode {
+            0 => &[8, 9, 10, 11, 12, 13, 14, 15],
+            1 => &[8, 10, 12, 14, 0, 0, 0, 0],
+            _ => &[8, 9, 10, 11, 12, 14, 0, 0],
+        };
+        for (i, bank) in registers.iter().enumerate() {
+            let addr = 0xD000 + (i as u16 / 4) * 0x1000 + i as u16 % 4;
+            vrc.write(addr, *bank);
+        }
+        assert_eq!(vrc.chr_banks().unwrap(), expected.map(|bank| bank * 0x400));
+    }
+
+    #[test]
+    fn test_irq() {
+        let mut vrc = Vrc6::new(prg_rom(), 26, Mirroring::Vertical);
+        vrc.write(0xF000, 0xFF);
+        vrc.write(0xF002, 0x07);
+        vrc.tick(1);
+        assert!(vrc.irq());
+        vrc.write(0xF001, 0x00);
+        assert!(!vrc.irq());
+    }
+
+    #[test]
+    fn test_audio() {
+        let mut vrc = Vrc6::new(prg_rom(), 24, Mirroring::Vertical);
+        assert_eq!(vrc.audio_output(), Some(0.0));
+        // NOTE: デューティ 100% (モード bit) で音量 15 なら周期に関係なく最大
+        vrc.write(0x9000, 0x8F);
+        vrc.write(0x9002, 0x80);
+        vrc.tick(1);
+        assert!(vrc.audio_output().unwrap() > 0.0);
+    }
+}
@@ -0,0 +1,202 @@
+use crate::{
+    apu::expansion::{ExpansionAudio, Vrc7Audio},
+    rom::Mirroring,
+};
+
+use super::{
+    banks::{chr_offsets, PrgBanks},
+    vrc::{self, VrcIrq},
+    Mapper,
+};
+
+const PRG_RAM: u16 = 0x6000;
+const PRG_RAM_END: u16 = 0x7FFF;
+const ROM: u16 = 0x8000;
+const ROM_END: u16 = 0xFFFF;
+
+// NOTE: 音源のレジスタは A4 と A5 で選ぶ ($9010 と $9030)
+const AUDIO_MASK: u16 = 0xF030;
+const AUDIO_SELECT: u16 = 0x9010;
+const AUDIO_WRITE: u16 = 0x9030;
+
+// NOTE: マッパー 85。各 $x000 の 2 つ目のレジスタが VRC7a は A4、VRC7b は A3 につながっている
+pub(super) struct Vrc7 {
+    prg: PrgBanks,
+    prg_ram: [u8; 0x2000],
+    high_line: u16,
+    chr_banks: [u8; 8],
+    mirroring: Mirroring,
+    silenced: bool,
+    irq: VrcIrq,
+    audio: Vrc7Audio,
+}
+
+impl Vrc7 {
+    pub(super) fn new(prg_rom: Vec<u8>, submapper: u8, mirroring: Mirroring) -> Self {
+        let high_line = match submapper {
+            1 => 0x08,
+            2 => 0x10,
+            _ => 0x18,
+        };
+        let mut prg = PrgBanks::new(prg_rom);
+        prg.set(3, prg.last());
+
+        Self {
+            prg,
+            prg_ram: [0; 0x2000],
+            high_line,
+            chr_banks: [0, 1, 2, 3, 4, 5, 6, 7],
+            mirroring,
+            silenced: false,
+            irq: VrcIrq::default(),
+            audio: Vrc7Audio::new(),
+        }
+    }
+}
+
+impl Mapper for Vrc7 {
+    fn read(&mut self, addr: u16) -> Option<u8> {
+        self.peek(addr)
+    }
+
+    fn peek(&self, addr: u16) -> Option<u8> {
+        match addr {
+            PRG_RAM..=PRG_RAM_END => Some(self.prg_ram[(addr - PRG_RAM) as usize]),
+            ROM..=ROM_END => Some(self.prg.read(addr)),
+            _ => None,
+        }
+    }
+
+    fn write(&mut self, addr: u16, data: u8) {
+        if let PRG_RAM..=PRG_RAM_END = addr {
+            self.prg_ram[(addr - PRG_RAM) as usize] = data;
+            return;
+        }
+        if let AUDIO_SELECT | AUDIO_WRITE = addr & AUDIO_MASK {
+            self.audio.write(addr & AUDIO_MASK, data);
+            return;
+        }
+
+        let high = addr & self.high_line != 0;
+        match (addr & 0xF000, high) {
+            (0x8000, false) => self.prg.set(0, (data & 0x3F) as usize),
+            (0x8000, true) => self.prg.set(1, (data & 0x3F) as usize),
+            (0x9000, false) => self.prg.set(2, (data & 0x3F) as usize),
+            (0xA000..=0xD000, _) => {
+                let index = ((addr >> 12) - 0xA) * 2 + high as u16;
+                self.chr_banks[index as usize] = data;
+            }
+            (0xE000, false) => {
+                self.mirroring = vrc::mirroring(data);
+                self.silenced = data & 0x40 != 0;
+            }
+            (0xE000, true) => self.irq.write_latch(data),
+            (0xF000, false) => self.irq.write_control(data),
+            (0xF000, true) => self.irq.acknowledge(),
+            _ => {}
+        }
+    }
+
+    fn mirroring(&self) -> Option<Mirroring> {
+        Some(self.mirroring)
+    }
+
+    fn chr_banks(&self) -> Option<[usize; 8]> {
+        Some(chr_offsets(self.chr_banks.map(|bank| bank as usize)))
+    }
+
+    fn tick(&mut self, cycles: u8) {
+        for _ in 0..cycles {
+            self.irq.tick();
+            self.audio.tick();
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.irq.pending()
+    }
+
+    // NOTE: $E000 の bit6 で音源をリセットして黙らせる
+    fn audio_output(&self) -> Option<f32> {
+        if self.silenced {
+            Some(0.0)
+        } else {
+            Some(self.audio.output())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use test_case::test_case;
+
+    use super::Vrc7;
+    use crate::{mapper::Mapper, rom::Mirroring};
+
+    fn prg_rom() -> Vec<u8> {
+        (0..16).flat_map(|bank| vec![bank as u8; 0x2000]).collect()
+    }
+
+    #[test_case(1, 0x8008 ; "vrc7b")]
+    #[test_case(2, 0x8010 ; "vrc7a")]
+    #[test_case(0, 0x8010 ; "either")]
+    fn test_prg_banks(submapper: u8, prg1: u16) {
+        let mut vrc = Vrc7::new(prg_rom(), submapper, Mirroring::Vertical);
+        vrc.write(0x8000, 0x03);
+        vrc.write(prg1, 0x05);
+        vrc.write(0x9000, 0x07);
+        assert_eq!(
+            [0x8000, 0xA000, 0xC000, 0xE000].map(|addr| vrc.peek(addr).unwrap()),
+            [3, 5, 7, 15]
+        );
+    }
+
+    #[test]
+    fn test_chr_banks() {
+        let mut vrc = Vrc7::new(prg_rom(), 2, Mirroring::Vertical);
+        for (i, addr) in [
+            0xA000, 0xA010, 0xB000, 0xB010, 0xC000, 0xC010, 0xD000, 0xD010,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            vrc.write(addr, 0x10 + i as u8);
+        }
+        assert_eq!(
+            vrc.chr_banks().unwrap(),
+            [16, 17, 18, 19, 20, 21, 22, 23].map(|bank| bank * 0x400)
+        );
+    }
+
+    #[test]
+    fn test_mirroring_and_irq() {
+        let mut vrc = Vrc7::new(prg_rom(), 2, Mirroring::Vertical);
+        vrc.write(0xE000, 0x02);
+        assert_eq!(vrc.mirroring(), Some(Mirroring::SingleScreenLower));
+
+        vrc.write(0xE010, 0xFF);
+        vrc.write(0xF000, 0x06);
+        vrc.tick(1);
+        assert!(vrc.irq());
+        vrc.write(0xF010, 0x00);
+        assert!(!vrc.irq());
+    }
+
+    #[test]
+    fn test_silence() {
+        let mut vrc = Vrc7::new(prg_rom(), 2, Mirroring::Vertical);
+        // NOTE: 内蔵音色 1 で最大音量のまま鳴らす
+        vrc.write(0x9010, 0x30);
+        vrc.write(0x9030, 0x10);
+        vrc.write(0x9010, 0x10);
+        vrc.write(0x9030, 0x80);
+        vrc.write(0x9010, 0x20);
+        vrc.write(0x9030, 0x1C);
+        vrc.tick(255);
+        vrc.tick(255);
+        assert_ne!(vrc.audio_output(), Some(0.0));
+
+        vrc.write(0xE000, 0x40);
+        assert_eq!(vrc.audio_output(), Some(0.0));
+    }
+}
@@ -0,0 +1,362 @@
+use crate::{
+    apu::{
+        expansion::{ExpansionAudio, Mmc5Audio, N163Audio, Sunsoft5bAudio, Vrc6Audio, Vrc7Audio},
+        APU,
+    },
+    bus::{Bus, Mem},
+    diagnostics::{Category, Diagnostics},
+    fds::FdsAudio,
+    region::Region,
+    speaker::Speaker,
+};
+
+use super::Nsf;
+
+const BANK_SIZE: usize = 0x1000;
+
+const RAM: u16 = 0x0000;
+const RAM_MIRRORS_END: u16 = 0x1FFF;
+const APU: u16 = 0x4000;
+const APU_END: u16 = 0x4017;
+const JOYPAD1_REGISTERS: u16 = 0x4016;
+const BANK_REGISTERS: u16 = 0x5FF8;
+const BANK_REGISTERS_END: u16 = 0x5FFF;
+const PRG_RAM: u16 = 0x6000;
+const PRG_RAM_END: u16 = 0x7FFF;
+const ROM: u16 = 0x8000;
+const ROM_END: u16 = 0xFFFF;
+// NOTE: FDS の NSF は $8000-$DFFF も RAM として書き込める
+const FDS_RAM_END: u16 = 0xDFFF;
+
+// NOTE: ヘッダーの拡張音源のビット
+const VRC6: u8 = 0x01;
+const VRC7: u8 = 0x02;
+const FDS: u8 = 0x04;
+const MMC5: u8 = 0x08;
+const N163: u8 = 0x10;
+const SUNSOFT_5B: u8 = 0x20;
+pub(super) const SUPPORTED_EXPANSIONS: u8 = VRC6 | VRC7 | FDS | MMC5 | N163 | SUNSOFT_5B;
+
+// NOTE: PPU もコントローラーもない、NSF を鳴らすためだけのバス。
+//       $8000-$FFFF は 4KB のバンク 8 つで、バンク切り替えがない NSF は 0-7 を並べたものとして扱う
+pub(crate) struct NsfBus<S: Speaker> {
+    ram: [u8; 0x0800],
+    prg_ram: [u8; 0x2000],
+    prg: Vec<u8>,
+    initial_banks: [u8; 8],
+    banks: [u8; 8],
+    apu: APU<S>,
+    expansion: Vec<Box<dyn ExpansionAudio>>,
+    fds: bool,
+    cycles: usize,
+    diagnostics: Diagnostics,
+}
+
+impl<S: Speaker> NsfBus<S> {
+    pub(crate) fn new(nsf: &Nsf, speaker: S) -> Self {
+        let diagnostics = Diagnostics::default();
+        let mut apu = APU::new(speaker);
+        apu.set_diagnostics(diagnostics.clone());
+
+        let mut bus = Self {
+            ram: [0; 0x0800],
+            prg_ram: [0; 0x2000],
+            prg: vec![],
+            initial_banks: [0, 1, 2, 3, 4, 5, 6, 7],
+            banks: [0; 8],
+            apu,
+            expansion: expansion_chips(nsf.expansion),
+            fds: nsf.expansion & FDS != 0,
+            cycles: 0,
+            diagnostics,
+        };
+        bus.load(nsf);
+        bus
+    }
+
+    // NOTE: バンク切り替えありならデータはロードアドレスの下位 12bit だけずらして 4KB 単位に並べる。
+    //       なしならロードアドレスにそのまま置く ($8000 未満の分は PRG-RAM へ)
+    fn load(&mut self, nsf: &Nsf) {
+        if let Some(banks) = nsf.banks {
+            let padding = (nsf.load_addr as usize) & (BANK_SIZE - 1);
+            let mut prg = vec![0; padding];
+            prg.extend(&nsf.data);
+            prg.resize(prg.len().div_ceil(BANK_SIZE).max(1) * BANK_SIZE, 0);
+            self.prg = prg;
+            self.initial_banks = banks;
+        } else {
+            self.prg = vec![0; 8 * BANK_SIZE];
+            for (i, &value) in nsf.data.iter().enumerate() {
+                let addr = nsf.load_addr as usize + i;
+                match addr {
+                    0x6000..=0x7FFF => self.prg_ram[addr - 0x6000] = value,
+                    0x8000..=0xFFFF => self.prg[addr - 0x8000] = value,
+                    _ => break,
+                }
+            }
+        }
+    }
+
+    // NOTE: 曲の頭で呼ぶ。RAM を消してバンクを初期値に戻す
+    pub(crate) fn reset(&mut self) {
+        self.ram = [0; 0x0800];
+        self.prg_ram = [0; 0x2000];
+        self.banks = self.initial_banks;
+    }
+
+    pub(crate) fn apu_mut(&mut self) -> &mut APU<S> {
+        &mut self.apu
+    }
+
+    pub(crate) fn apu(&self) -> &APU<S> {
+        &self.apu
+    }
+
+    pub(crate) fn set_region(&mut self, region: Region) {
+        self.apu.set_region(region);
+    }
+
+    pub(crate) fn diagnostics(&self) -> &Diagnostics {
+        &self.diagnostics
+    }
+
+    pub(crate) fn cycles(&self) -> usize {
+        self.cycles
+    }
+
+    fn prg_index(&self, addr: u16) -> usize {
+        let slot = (addr - ROM) as usize / BANK_SIZE;
+        let bank_count = self.prg.len() / BANK_SIZE;
+        let bank = self.banks[slot] as usize % bank_count;
+        bank * BANK_SIZE + (addr as usize & (BANK_SIZE - 1))
+    }
+
+    fn read_prg(&self, addr: u16) -> u8 {
+        self.prg[self.prg_index(addr)]
+    }
+
+    fn write_prg(&mut self, addr: u16, data: u8) {
+        if self.fds && addr <= FDS_RAM_END {
+            let index = self.prg_index(addr);
+            self.prg[index] = data;
+        } else if self.expansion.is_empty() {
+            // NOTE: 拡張音源のレジスタは ROM と同じ番地にあることがある
+            self.diagnostics.report(Category::Bus, "rom-write", || {
+                format!("Ignoring write to ROM at {:#06X}", addr)
+            });
+        }
+    }
+}
+
+fn expansion_chips(expansion: u8) -> Vec<Box<dyn ExpansionAudio>> {
+    let mut chips: Vec<Box<dyn ExpansionAudio>> = vec![];
+    if expansion & VRC6 != 0 {
+        chips.push(Box::new(Vrc6Audio::new()));
+    }
+    if expansion & VRC7 != 0 {
+        chips.push(Box::new(Vrc7Audio::new()));
+    }
+    if expansion & FDS != 0 {
+        chips.push(Box::new(FdsAudio::new()));
+    }
+    if expansion & MMC5 != 0 {
+        chips.push(Box::new(Mmc5Audio::new()));
+    }
+    if expansion & N163 != 0 {
+        chips.push(Box::new(N163Audio::new()));
+    }
+    if expansion & SUNSOFT_5B != 0 {
+        chips.push(Box::new(Sunsoft5bAudio::new()));
+    }
+    chips
+}
+
+impl<S: Speaker> Mem for NsfBus<S> {
+    fn mem_read(&mut self, addr: u16) -> u8 {
+        if addr > APU_END {
+            if let Some(data) = self.expansion.iter_mut().find_map(|chip| chip.read(addr)) {
+                return data;
+            }
+        }
+
+        match addr {
+            RAM..=RAM_MIRRORS_END => self.ram[(addr & 0x07FF) as usize],
+            APU..=APU_END if addr != JOYPAD1_REGISTERS => self.apu.read(addr),
+            PRG_RAM..=PRG_RAM_END => self.prg_ram[(addr - PRG_RAM) as usize],
+            ROM..=ROM_END => self.read_prg(addr),
+            _ => 0,
+        }
+    }
+
+    fn mem_write(&mut self, addr: u16, data: u8) {
+        if addr > APU_END {
+            for chip in &mut self.expansion {
+                chip.write(addr, data);
+            }
+        }
+
+        match addr {
+            RAM..=RAM_MIRRORS_END => self.ram[(addr & 0x07FF) as usize] = data,
+            APU..=APU_END if addr != JOYPAD1_REGISTERS => self.apu.write(addr, data),
+            BANK_REGISTERS..=BANK_REGISTERS_END => {
+                self.banks[(addr - BANK_REGISTERS) as usize] = data;
+            }
+            PRG_RAM..=PRG_RAM_END => self.prg_ram[(addr - PRG_RAM) as usize] = data,
+            ROM..=ROM_END => self.write_prg(addr, data),
+            _ => {}
+        }
+    }
+
+    fn mem_read_u16(&mut self, addr: u16) -> u16 {
+        let low_byte = self.mem_read(addr) as u16;
+        let high_byte = self.mem_read(addr.wrapping_add(1)) as u16;
+        (high_byte << 8) | low_byte
+    }
+
+    fn mem_write_u16(&mut self, addr: u16, data: u16) {
+        let [low_byte, high_byte] = data.to_le_bytes();
+        self.mem_write(addr, low_byte);
+        self.mem_write(addr.wrapping_add(1), high_byte);
+    }
+}
+
+impl<S: Speaker> NsfBus<S> {
+    fn clock(&mut self, cycles: u8) {
+        self.cycles += cycles as usize;
+        if !self.expansion.is_empty() {
+            for _ in 0..cycles {
+                for chip in &mut self.expansion {
+                    chip.tick();
+                }
+            }
+            let output = self.expansion.iter().map(|chip| chip.output()).sum();
+            self.apu.set_expansion_output(Some(output));
+        }
+        self.apu.tick(cycles);
+    }
+}
+
+impl<S: Speaker> Bus for NsfBus<S> {
+    // NOTE: DMC がサンプルを読む間は CPU が 4 サイクル止まる。NSF には OAM DMA がないので、いつも 4 サイクル
+    fn tick(&mut self, cycles: u8) {
+        self.clock(cycles);
+        if let Some(addr) = self.apu.dmc_fetch_address() {
+            self.clock(4);
+            let data = self.read_prg(addr);
+            self.apu.dmc_fill(data);
+        }
+    }
+
+    fn poll_nmi_status(&mut self) -> Option<bool> {
+        None
+    }
+
+    fn get_cycles(&self) -> (usize, usize) {
+        (self.cycles, 0)
+    }
+
+    fn get_scanline(&self) -> u16 {
+        0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        bus::{Bus, Mem},
+        nsf::{test::nsf_header, Nsf},
+        speaker::{test::ExpansionSpeaker, EXPANSION_CH},
+        testrom::NullSpeaker,
+    };
+
+    use super::NsfBus;
+
+    #[test]
+    fn test_flat_load() {
+        let mut raw = nsf_header(0x8100, 0x8100, 0x8100, 1);
+        raw.extend([0x11, 0x22]);
+        let nsf = Nsf::new(&raw).unwrap();
+        let mut bus = NsfBus::new(&nsf, NullSpeaker);
+        bus.reset();
+
+        assert_eq!(bus.mem_read(0x80FF), 0x00);
+        assert_eq!(bus.mem_read(0x8100), 0x11);
+        assert_eq!(bus.mem_read(0x8101), 0x22);
+    }
+
+    #[test]
+    fn test_bankswitch() {
+        let mut raw = nsf_header(0x8010, 0x8010, 0x8010, 1);
+        raw[0x70..0x78].copy_from_slice(&[0, 1, 0, 0, 0, 0, 0, 0]);
+        let mut data = vec![0; 0x2000 - 0x10];
+        data[0] = 0xAA;
+        data[0x1000 - 0x10] = 0xBB;
+        raw.extend(data);
+        let nsf = Nsf::new(&raw).unwrap();
+        let mut bus = NsfBus::new(&nsf, NullSpeaker);
+        bus.reset();
+
+        assert_eq!(bus.mem_read(0x8010), 0xAA);
+        assert_eq!(bus.mem_read(0x9000), 0xBB);
+        assert_eq!(bus.mem_read(0xA010), 0xAA);
+
+        bus.mem_write(0x5FF8, 0x01);
+        assert_eq!(bus.mem_read(0x8000), 0xBB);
+        // NOTE: バンク数を超えた番号は折り返す
+        bus.mem_write(0x5FF8, 0x02);
+        assert_eq!(bus.mem_read(0x8010), 0xAA);
+
+        bus.reset();
+        assert_eq!(bus.mem_read(0x8000), 0x00);
+    }
+
+    #[test]
+    fn test_expansion() {
+        let mut raw = nsf_header(0x8000, 0x8000, 0x8000, 1);
+        raw[0x7B] = 0x14;
+        raw.extend([0x00; 0x100]);
+        let nsf = Nsf::new(&raw).unwrap();
+        let mut bus = NsfBus::new(&nsf, NullSpeaker);
+        bus.reset();
+
+        // NOTE: N163 の内部 RAM
+        bus.mem_write(0xF800, 0x80);
+        bus.mem_write(0x4800, 0x12);
+        bus.mem_write(0xF800, 0x00);
+        assert_eq!(bus.mem_read(0x4800), 0x12);
+
+        // NOTE: FDS の波形メモリと、RAM になる $8000-$DFFF
+        bus.mem_write(0x4089, 0x80);
+        bus.mem_write(0x4040, 0x3F);
+        assert_eq!(bus.mem_read(0x4040), 0x7F);
+        bus.mem_write(0x8000, 0xAB);
+        assert_eq!(bus.mem_read(0x8000), 0xAB);
+        bus.mem_write(0xE000, 0xCD);
+        assert_eq!(bus.mem_read(0xE000), 0x00);
+        assert!(bus.diagnostics().counters().is_empty());
+    }
+
+    #[test]
+    fn test_expansion_speaker() {
+        let mut raw = nsf_header(0x8000, 0x8000, 0x8000, 1);
+        raw[0x7B] = 0x01;
+        raw.extend([0x00; 0x100]);
+        let nsf = Nsf::new(&raw).unwrap();
+        let speaker = ExpansionSpeaker::default();
+        let mut bus = NsfBus::new(&nsf, speaker.clone());
+        bus.reset();
+
+        // NOTE: VRC6 の矩形波 1 を音量 15 で鳴らしっぱなしにする
+        bus.mem_write(0x9000, 0x8F);
+        bus.mem_write(0x9002, 0x80);
+        for _ in 0..300 {
+            bus.tick(100);
+        }
+
+        let sent = speaker.sent();
+        assert_eq!(sent.len(), 1);
+        let (ch, samples) = &sent[0];
+        assert_eq!(*ch, EXPANSION_CH);
+        assert!(samples.iter().all(|&v| v == 2.0));
+    }
+}
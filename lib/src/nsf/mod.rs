@@ -0,0 +1,286 @@
+use crate::region::Region;
+
+pub use player::NsfPlayer;
+
+mod bus;
+mod player;
+
+const NSF_TAG: &[u8] = b"NESM\x1A";
+const NSFE_TAG: &[u8] = b"NSFE";
+const NSF_HEADER_SIZE: usize = 0x80;
+
+pub struct Nsf {
+    pub songs: u8,
+    // NOTE: 0 始まり。NSF のヘッダーは 1 始まりなので読み込み時に直す
+    pub starting_song: u8,
+    pub load_addr: u16,
+    pub init_addr: u16,
+    pub play_addr: u16,
+    pub title: String,
+    pub artist: String,
+    pub copyright: String,
+    pub track_labels: Vec<String>,
+    pub ntsc_speed: u16,
+    pub pal_speed: u16,
+    pub banks: Option<[u8; 8]>,
+    pub region: Region,
+    pub expansion: u8,
+    pub data: Vec<u8>,
+}
+
+impl Nsf {
+    pub fn is_nsf(raw: &[u8]) -> bool {
+        raw.starts_with(NSF_TAG) || raw.starts_with(NSFE_TAG)
+    }
+
+    pub fn new(raw: &[u8]) -> Result<Self, String> {
+        if raw.starts_with(NSF_TAG) {
+            Self::parse_nsf(raw)
+        } else if raw.starts_with(NSFE_TAG) {
+            Self::parse_nsfe(raw)
+        } else {
+            Err("Invalid NSF file".to_string())
+        }
+    }
+
+    // NOTE: PLAY を呼ぶ間隔 (秒)。ヘッダーの値が 0 のときはフレームレートに合わせる
+    pub fn play_period(&self, region: Region) -> f32 {
+        let speed = match region {
+            Region::Ntsc => self.ntsc_speed,
+            Region::Pal | Region::Dendy => self.pal_speed,
+        };
+        if speed == 0 {
+            1.0 / region.frame_rate()
+        } else {
+            speed as f32 / 1_000_000.0
+        }
+    }
+
+    pub fn track_label(&self, song: u8) -> Option<&str> {
+        self.track_labels
+            .get(song as usize)
+            .map(|label| label.as_str())
+            .filter(|label| !label.is_empty())
+    }
+
+    fn parse_nsf(raw: &[u8]) -> Result<Self, String> {
+        if raw.len() < NSF_HEADER_SIZE {
+            return Err("Invalid NSF file".to_string());
+        }
+
+        let banks: [u8; 8] = raw[0x70..0x78].try_into().unwrap();
+        Ok(Self {
+            songs: raw[0x06],
+            starting_song: raw[0x07].saturating_sub(1),
+            load_addr: read_u16(raw, 0x08),
+            init_addr: read_u16(raw, 0x0A),
+            play_addr: read_u16(raw, 0x0C),
+            title: read_string(&raw[0x0E..0x2E]),
+            artist: read_string(&raw[0x2E..0x4E]),
+            copyright: read_string(&raw[0x4E..0x6E]),
+            track_labels: vec![],
+            ntsc_speed: read_u16(raw, 0x6E),
+            pal_speed: read_u16(raw, 0x78),
+            banks: banks.iter().any(|&bank| bank != 0).then_some(banks),
+            region: parse_region(raw[0x7A]),
+            expansion: raw[0x7B],
+            data: raw[NSF_HEADER_SIZE..].to_vec(),
+        })
+    }
+
+    // NOTE: "NSFE" の後に [長さ (4)][ID (4)][データ] のチャンクが NEND まで続く。
+    //       ID の先頭が大文字のチャンクは必須で、知らないものがあれば再生できない
+    fn parse_nsfe(raw: &[u8]) -> Result<Self, String> {
+        let mut nsf = Self {
+            songs: 1,
+            starting_song: 0,
+            load_addr: 0,
+            init_addr: 0,
+            play_addr: 0,
+            title: String::new(),
+            artist: String::new(),
+            copyright: String::new(),
+            track_labels: vec![],
+            ntsc_speed: 0,
+            pal_speed: 0,
+            banks: None,
+            region: Region::Ntsc,
+            expansion: 0,
+            data: vec![],
+        };
+        let mut has_info = false;
+
+        let mut pos = NSFE_TAG.len();
+        while pos + 8 <= raw.len() {
+            let len = u32::from_le_bytes(raw[pos..pos + 4].try_into().unwrap()) as usize;
+            let id = &raw[pos + 4..pos + 8];
+            let body = raw
+                .get(pos + 8..pos + 8 + len)
+                .ok_or_else(|| "Truncated NSFe chunk".to_string())?;
+            pos += 8 + len;
+
+            match id {
+                b"INFO" => {
+                    if body.len() < 8 {
+                        return Err("Invalid NSFe INFO chunk".to_string());
+                    }
+                    has_info = true;
+                    nsf.load_addr = read_u16(body, 0);
+                    nsf.init_addr = read_u16(body, 2);
+                    nsf.play_addr = read_u16(body, 4);
+                    nsf.region = parse_region(body[6]);
+                    nsf.expansion = body[7];
+                    nsf.songs = body.get(8).copied().unwrap_or(1);
+                    nsf.starting_song = body.get(9).copied().unwrap_or(0);
+                }
+                b"DATA" => nsf.data = body.to_vec(),
+                b"BANK" => {
+                    let mut banks = [0; 8];
+                    for (bank, &value) in banks.iter_mut().zip(body) {
+                        *bank = value;
+                    }
+                    nsf.banks = Some(banks);
+                }
+                b"RATE" => {
+                    if body.len() >= 2 {
+                        nsf.ntsc_speed = read_u16(body, 0);
+                    }
+                    if body.len() >= 4 {
+                        nsf.pal_speed = read_u16(body, 2);
+                    }
+                }
+                b"auth" => {
+                    let mut fields = body.split(|&b| b == 0).map(read_string);
+                    nsf.title = fields.next().unwrap_or_default();
+                    nsf.artist = fields.next().unwrap_or_default();
+                    nsf.copyright = fields.next().unwrap_or_default();
+                }
+                b"tlbl" => {
+                    nsf.track_labels = body
+                        .split(|&b| b == 0)
+                        .map(read_string)
+                        .take(nsf.songs as usize)
+                        .collect();
+                }
+                b"NEND" => break,
+                _ if id[0].is_ascii_uppercase() => {
+                    return Err(format!(
+                        "Unsupported NSFe chunk: {}",
+                        String::from_utf8_lossy(id)
+                    ));
+                }
+                _ => {}
+            }
+        }
+
+        if !has_info {
+            return Err("Missing NSFe INFO chunk".to_string());
+        }
+        Ok(nsf)
+    }
+}
+
+fn read_u16(raw: &[u8], pos: usize) -> u16 {
+    u16::from_le_bytes([raw[pos], raw[pos + 1]])
+}
+
+fn read_string(raw: &[u8]) -> String {
+    let end = raw.iter().position(|&b| b == 0).unwrap_or(raw.len());
+    String::from_utf8_lossy(&raw[..end]).trim().to_string()
+}
+
+// NOTE: bit0 が PAL、bit1 が両対応。両対応なら NTSC で鳴らす
+fn parse_region(flags: u8) -> Region {
+    if flags & 0x03 == 0x01 {
+        Region::Pal
+    } else {
+        Region::Ntsc
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use crate::region::Region;
+
+    use super::Nsf;
+
+    pub(crate) fn nsf_header(load: u16, init: u16, play: u16, songs: u8) -> Vec<u8> {
+        let mut raw = vec![0; 0x80];
+        raw[..5].copy_from_slice(b"NESM\x1A");
+        raw[0x05] = 1;
+        raw[0x06] = songs;
+        raw[0x07] = 1;
+        raw[0x08..0x0A].copy_from_slice(&load.to_le_bytes());
+        raw[0x0A..0x0C].copy_from_slice(&init.to_le_bytes());
+        raw[0x0C..0x0E].copy_from_slice(&play.to_le_bytes());
+        raw[0x0E..0x0E + 5].copy_from_slice(b"Title");
+        raw[0x2E..0x2E + 6].copy_from_slice(b"Artist");
+        raw[0x6E..0x70].copy_from_slice(&16_639u16.to_le_bytes());
+        raw[0x78..0x7A].copy_from_slice(&19_997u16.to_le_bytes());
+        raw
+    }
+
+    #[test]
+    fn test_nsf() {
+        let mut raw = nsf_header(0x8000, 0x8003, 0x8006, 3);
+        raw[0x07] = 2;
+        raw[0x72] = 1;
+        raw.extend([0xEA; 4]);
+
+        let nsf = Nsf::new(&raw).unwrap();
+        assert_eq!(nsf.songs, 3);
+        assert_eq!(nsf.starting_song, 1);
+        assert_eq!(
+            (nsf.load_addr, nsf.init_addr, nsf.play_addr),
+            (0x8000, 0x8003, 0x8006)
+        );
+        assert_eq!(nsf.title, "Title");
+        assert_eq!(nsf.artist, "Artist");
+        assert_eq!(nsf.copyright, "");
+        assert_eq!(nsf.banks, Some([0, 0, 1, 0, 0, 0, 0, 0]));
+        assert_eq!(nsf.region, Region::Ntsc);
+        assert_eq!(nsf.data, vec![0xEA; 4]);
+        assert!((nsf.play_period(Region::Ntsc) - 0.016639).abs() < 1e-6);
+        assert!((nsf.play_period(Region::Pal) - 0.019997).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_nsfe() {
+        let chunk = |id: &[u8], body: &[u8]| {
+            let mut chunk = (body.len() as u32).to_le_bytes().to_vec();
+            chunk.extend(id);
+            chunk.extend(body);
+            chunk
+        };
+
+        let mut raw = b"NSFE".to_vec();
+        raw.extend(chunk(
+            b"INFO",
+            &[0x00, 0x80, 0x03, 0x80, 0x06, 0x80, 0x01, 0x00, 0x02, 0x01],
+        ));
+        raw.extend(chunk(b"DATA", &[0x60]));
+        raw.extend(chunk(b"auth", b"Game\0Composer\0(C)\0Ripper\0"));
+        raw.extend(chunk(b"tlbl", b"Intro\0\0"));
+        raw.extend(chunk(b"text", b"ignored"));
+        raw.extend(chunk(b"NEND", &[]));
+
+        let nsf = Nsf::new(&raw).unwrap();
+        assert_eq!((nsf.songs, nsf.starting_song), (2, 1));
+        assert_eq!(nsf.play_addr, 0x8006);
+        assert_eq!(nsf.region, Region::Pal);
+        assert_eq!(nsf.title, "Game");
+        assert_eq!(nsf.artist, "Composer");
+        assert_eq!(nsf.copyright, "(C)");
+        assert_eq!(nsf.track_label(0), Some("Intro"));
+        assert_eq!(nsf.track_label(1), None);
+        assert_eq!(nsf.banks, None);
+        assert_eq!(nsf.data, vec![0x60]);
+
+        let mut unknown = b"NSFE".to_vec();
+        unknown.extend(chunk(b"XTRA", &[]));
+        assert_eq!(
+            Nsf::new(&unknown).err(),
+            Some("Unsupported NSFe chunk: XTRA".to_string())
+        );
+    }
+}
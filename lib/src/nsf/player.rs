@@ -0,0 +1,217 @@
+use crate::{
+    audio::AudioBuffer,
+    bus::{Bus, Mem},
+    cpu::CPU,
+    diagnostics::{Category, Diagnostics},
+    region::Region,
+    speaker::Speaker,
+};
+
+use super::{
+    bus::{NsfBus, SUPPORTED_EXPANSIONS},
+    Nsf,
+};
+
+// NOTE: INIT / PLAY から RTS で戻ってくる先。どちらのルーチンも実行しない場所にしておく
+const RETURN_ADDR: u16 = 0x4100;
+
+pub struct NsfPlayer<S: Speaker> {
+    cpu: CPU<NsfBus<S>>,
+    nsf: Nsf,
+    region: Region,
+    song: u8,
+    next_play: f64,
+}
+
+impl<S: Speaker> NsfPlayer<S> {
+    pub fn new(nsf: Nsf, speaker: S) -> Self {
+        let bus = NsfBus::new(&nsf, speaker);
+        let unsupported = nsf.expansion & !SUPPORTED_EXPANSIONS;
+        if unsupported != 0 {
+            bus.diagnostics()
+                .report(Category::Apu, "expansion-audio", || {
+                    format!("Expansion audio is not supported ({:#04X})", unsupported)
+                });
+        }
+
+        let region = nsf.region;
+        let mut player = Self {
+            cpu: CPU::new(bus),
+            nsf,
+            region,
+            song: 0,
+            next_play: 0.0,
+        };
+        player.cpu.bus.set_region(region);
+        player
+    }
+
+    pub fn nsf(&self) -> &Nsf {
+        &self.nsf
+    }
+
+    pub fn song(&self) -> u8 {
+        self.song
+    }
+
+    pub fn region(&self) -> Region {
+        self.region
+    }
+
+    // NOTE: 次の start から反映される
+    pub fn set_region(&mut self, region: Region) {
+        self.region = region;
+        self.cpu.bus.set_region(region);
+    }
+
+    pub fn play_period(&self) -> f32 {
+        self.nsf.play_period(self.region)
+    }
+
+    pub fn diagnostics(&self) -> &Diagnostics {
+        self.cpu.bus.diagnostics()
+    }
+
+    pub fn set_audio_capture(&mut self, sample_rate: Option<u32>) {
+        self.cpu.bus.apu_mut().set_audio_capture(sample_rate);
+    }
+
+    pub fn take_audio(&mut self) -> Option<AudioBuffer> {
+        self.cpu.bus.apu_mut().take_audio()
+    }
+
+    pub fn channel_levels(&self) -> [f32; 5] {
+        self.cpu.bus.apu().channel_levels()
+    }
+
+    // NOTE: song は 0 始まり。RAM と APU を初期化してから、A = 曲番号、X = NTSC (0) / PAL (1) で INIT を呼ぶ
+    pub fn start(&mut self, song: u8) {
+        self.song = song.min(self.nsf.songs.saturating_sub(1));
+
+        let bus = &mut self.cpu.bus;
+        bus.reset();
+        for addr in 0x4000..=0x4013 {
+            bus.mem_write(addr, 0x00);
+        }
+        bus.mem_write(0x4015, 0x00);
+        bus.mem_write(0x4015, 0x0F);
+        bus.mem_write(0x4017, 0x40);
+
+        self.cpu.reset_with_pc(self.nsf.init_addr);
+        self.cpu.register_a = self.song;
+        self.cpu.register_x = (self.region == Region::Pal) as u8;
+        self.call(self.nsf.init_addr, "init");
+
+        self.next_play = self.cpu.bus.cycles() as f64;
+    }
+
+    // NOTE: PLAY を 1 回呼び、次に呼ぶ時刻まで APU だけを進める
+    pub fn play(&mut self) {
+        self.call(self.nsf.play_addr, "play");
+
+        self.next_play += self.play_period() as f64 * self.region.cpu_clock() as f64;
+        while (self.cpu.bus.cycles() as f64) < self.next_play {
+            self.cpu.bus.tick(1);
+        }
+    }
+
+    fn call(&mut self, addr: u16, name: &'static str) {
+        let [lo, hi] = (RETURN_ADDR - 1).to_le_bytes();
+        self.push(hi);
+        self.push(lo);
+        self.cpu.program_counter = addr;
+
+        // NOTE: 戻ってこないルーチンで固まらないよう、1 秒分実行したら諦める
+        let limit = self.cpu.bus.cycles() + self.region.cpu_clock() as usize;
+        while self.cpu.program_counter != RETURN_ADDR {
+            if self.cpu.is_halted() || self.cpu.bus.cycles() >= limit {
+                self.cpu
+                    .bus
+                    .diagnostics()
+                    .report(Category::Bus, "nsf-timeout", || {
+                        format!("NSF {} routine at {:#06X} did not return", name, addr)
+                    });
+                self.cpu.reset_with_pc(RETURN_ADDR);
+                return;
+            }
+            self.cpu.step();
+        }
+    }
+
+    fn push(&mut self, data: u8) {
+        let addr = 0x0100 | self.cpu.stack_pointer as u16;
+        self.cpu.bus.mem_write(addr, data);
+        self.cpu.stack_pointer = self.cpu.stack_pointer.wrapping_sub(1);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        audio::Channel,
+        bus::Mem,
+        nsf::{test::nsf_header, Nsf},
+        testrom::NullSpeaker,
+    };
+
+    use super::NsfPlayer;
+
+    fn test_player() -> NsfPlayer<NullSpeaker> {
+        #[rustfmt::skip]
+        let init = [
+            0x85, 0x00,       // $8000: STA $00
+            0xA9, 0xBF,       // $8002: LDA #$BF
+            0x8D, 0x00, 0x40, // $8004: STA $4000
+            0xA9, 0xFF,       // $8007: LDA #$FF
+            0x8D, 0x02, 0x40, // $8009: STA $4002
+            0xA9, 0x08,       // $800C: LDA #$08
+            0x8D, 0x03, 0x40, // $800E: STA $4003
+            0x60,             // $8011: RTS
+        ];
+        #[rustfmt::skip]
+        let play = [
+            0xE6, 0x01,       // $8020: INC $01
+            0xA5, 0x01,       // $8022: LDA $01
+            0x8D, 0x11, 0x40, // $8024: STA $4011
+            0x60,             // $8027: RTS
+        ];
+
+        let mut raw = nsf_header(0x8000, 0x8000, 0x8020, 4);
+        let mut data = vec![0xEA; 0x30];
+        data[..init.len()].copy_from_slice(&init);
+        data[0x20..0x20 + play.len()].copy_from_slice(&play);
+        raw.extend(data);
+
+        NsfPlayer::new(Nsf::new(&raw).unwrap(), NullSpeaker)
+    }
+
+    #[test]
+    fn test_play() {
+        let mut player = test_player();
+        player.start(2);
+        assert_eq!(player.cpu.bus.mem_read(0x0000), 2);
+        assert_eq!(player.channel_levels()[0], 1.0);
+
+        player.set_audio_capture(Some(44_100));
+        for _ in 0..3 {
+            player.play();
+        }
+        assert_eq!(player.cpu.bus.mem_read(0x0001), 3);
+        assert_eq!(player.channel_levels()[4], 3.0 / 127.0);
+
+        // NOTE: PLAY は 16639us ごとなので、3 回で約 0.05 秒
+        let audio = player.take_audio().unwrap();
+        assert!((2190..2215).contains(&audio.len()), "{}", audio.len());
+        assert!(audio.channel(Channel::Pulse1).iter().any(|&v| v > 0.0));
+        assert!(player
+            .diagnostics()
+            .counters()
+            .iter()
+            .all(|c| c.1 != "nsf-timeout"));
+
+        // NOTE: 曲を変えると RAM が消える
+        player.start(9);
+        assert_eq!(player.song(), 3);
+        assert_eq!(player.cpu.bus.mem_read(0x0001), 0);
+    }
+}
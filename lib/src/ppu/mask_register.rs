@@ -25,4 +25,8 @@ impl MaskRegister {
     pub fn is_show_sprites(&self) -> bool {
         self.contains(Self::SHOW_SPRITES)
     }
+
+    pub fn emphasis(&self) -> u8 {
+        self.bits() >> 5
+    }
 }
@@ -5,7 +5,11 @@ use oam_register::OAMRegister;
 use scroll_register::ScrollRegister;
 use status_register::StatusRegister;
 
-use crate::rom::Mirroring;
+use crate::{
+    diagnostics::{Category, Diagnostics},
+    region::Region,
+    rom::Mirroring,
+};
 
 mod addr_register;
 mod ctrl_register;
@@ -14,9 +18,18 @@ mod oam_register;
 mod scroll_register;
 mod status_register;
 
+// NOTE: I/O ラッチの各ビットはリフレッシュされないと 600ms ほどで 0 に落ちる (NTSC で約 36 フレーム)
+const IO_LATCH_DECAY_FRAMES: usize = 36;
+const CHR_BANK_SIZE: usize = 0x400;
+
+// NOTE: レンダリング中にパターンテーブルを読んだアドレスを受け取る。読んだタイルで CHR のバンクを切り替える
+//       カートリッジ (MMC2/MMC4) は新しいバンクを返す
+pub type PatternFetchHook<'a> = dyn FnMut(u16) -> Option<[usize; 8]> + 'a;
+
 pub struct PPU {
     is_chr_ram: bool,
     chr_rom: Vec<u8>,
+    chr_banks: [usize; 8],
     palette_table: [u8; 32],
     vram: [u8; 2048],
     oam: OAMRegister,
@@ -30,6 +43,11 @@ pub struct PPU {
     scanline: u16,
     cycles: usize,
     nmi_interrupt: Option<bool>,
+    region: Region,
+    frame_count: usize,
+    io_latch: u8,
+    io_latch_refreshed: [usize; 8],
+    diagnostics: Diagnostics,
 }
 
 impl PPU {
@@ -37,6 +55,7 @@ impl PPU {
         Self {
             is_chr_ram,
             chr_rom,
+            chr_banks: std::array::from_fn(|i| i * CHR_BANK_SIZE),
             palette_table: [0; 32],
             vram: [0; 2048],
             mirroring,
@@ -50,9 +69,31 @@ impl PPU {
             scanline: 0,
             cycles: 0,
             nmi_interrupt: None,
+            region: Region::default(),
+            frame_count: 0,
+            io_latch: 0,
+            io_latch_refreshed: [0; 8],
+            diagnostics: Diagnostics::default(),
         }
     }
 
+    pub fn set_region(&mut self, region: Region) {
+        self.region = region;
+    }
+
+    pub fn set_mirroring(&mut self, mirroring: Mirroring) {
+        self.mirroring = mirroring;
+    }
+
+    // NOTE: $0000-$1FFF の 1KB ごとに、CHR のどこを見せるか (バイト単位のオフセット)
+    pub fn set_chr_banks(&mut self, banks: [usize; 8]) {
+        self.chr_banks = banks;
+    }
+
+    pub fn set_diagnostics(&mut self, diagnostics: Diagnostics) {
+        self.diagnostics = diagnostics;
+    }
+
     pub fn write_to_addr(&mut self, value: u8) {
         self.addr.update(value);
     }
@@ -83,31 +124,48 @@ impl PPU {
         self.oam.write(value);
     }
 
-    pub fn write_to_oam_dma(&mut self, data: &[u8; 256]) {
-        self.oam.write_dma(data);
-    }
-
-    pub fn read_oam_data(&self) -> u8 {
-        self.oam.read()
+    pub fn read_oam_data(&mut self) -> u8 {
+        let value = self.oam.read();
+        self.refresh_io_latch(value, 0xFF);
+        value
     }
 
+    // NOTE: 下位 5 ビットはステータスではなく I/O ラッチに残っている値が見える
     pub fn read_status(&mut self) -> u8 {
-        let status = self.status.bits();
+        let status = self.status.bits() & 0xE0;
         self.status.set_vblank_status(false);
         self.addr.reset_latch();
         self.scroll.reset_latch();
 
-        status
+        self.refresh_io_latch(status, 0xE0);
+        self.io_latch
+    }
+
+    // NOTE: 書き込み専用レジスタを読むと、最後に PPU とやり取りした値がそのまま返る
+    pub fn read_io_latch(&mut self) -> u8 {
+        self.decay_io_latch();
+        self.io_latch
+    }
+
+    pub fn write_to_io_latch(&mut self, value: u8) {
+        self.refresh_io_latch(value, 0xFF);
     }
 
     pub fn read_data(&mut self) -> u8 {
         let addr = self.addr.get();
         self.increment_vram_addr();
 
-        match addr {
+        // NOTE: パレットは 6 ビットしかないので、上位 2 ビットは I/O ラッチの値になる
+        if let 0x3F00..=0x3FFF = addr {
+            let value = self.palette_table[self.mirror_palette_addr(addr) as usize];
+            self.refresh_io_latch(value, 0x3F);
+            return self.io_latch;
+        }
+
+        let value = match addr {
             0..=0x1FFF => {
                 let result = self.internal_data_buf;
-                self.internal_data_buf = self.chr_rom[addr as usize];
+                self.internal_data_buf = self.chr_rom[self.chr_index(addr)];
                 result
             }
             0x2000..=0x2FFF => {
@@ -119,13 +177,11 @@ impl PPU {
                 "addr space 0x3000..0x3EFF is not expected to be used, requested = {:#04X} ",
                 addr
             ),
-            0x3F10 | 0x3F14 | 0x3F18 | 0x3F1C => {
-                let addr_mirror = addr - 0x10;
-                self.palette_table[(addr_mirror - 0x3F00) as usize]
-            }
-            0x3F00..=0x3FFF => self.palette_table[(addr - 0x3F00) as usize],
             _ => panic!("unexpected access to mirrored space {}", addr),
-        }
+        };
+
+        self.refresh_io_latch(value, 0xFF);
+        value
     }
 
     pub fn write_to_data(&mut self, value: u8) {
@@ -133,9 +189,12 @@ impl PPU {
         match addr {
             0..=0x1FFF => {
                 if self.is_chr_ram {
-                    self.chr_rom[addr as usize] = value;
+                    let index = self.chr_index(addr);
+                    self.chr_rom[index] = value;
                 } else {
-                    eprintln!("CHR-ROM is read-only");
+                    self.diagnostics.report(Category::Ppu, "chr-rom-write", || {
+                        format!("Ignoring write to CHR-ROM at {:#06X}", addr)
+                    });
                 }
             }
             0x2000..=0x2FFF => {
@@ -162,7 +221,7 @@ impl PPU {
             self.cycles -= 341;
             self.scanline += 1;
 
-            if self.scanline == 241 {
+            if self.scanline == self.region.vblank_scanline() {
                 self.status.set_vblank_status(true);
                 self.status.set_sprite_zero_hit(false);
                 if self.ctrl.generate_vblank_nmi() {
@@ -170,8 +229,9 @@ impl PPU {
                 }
             }
 
-            if self.scanline >= 262 {
+            if self.scanline >= self.region.scanlines_per_frame() {
                 self.scanline = 0;
+                self.frame_count += 1;
                 self.status.set_sprite_zero_hit(false);
                 self.status.set_vblank_status(false);
                 self.nmi_interrupt = None;
@@ -206,6 +266,22 @@ impl PPU {
         self.cycles
     }
 
+    pub fn get_frame_count(&self) -> usize {
+        self.frame_count
+    }
+
+    pub fn get_vram_addr(&self) -> u16 {
+        self.addr.get()
+    }
+
+    pub fn get_emphasis(&self) -> u8 {
+        self.mask.emphasis()
+    }
+
+    pub fn get_backdrop_color(&self) -> u8 {
+        self.palette_table[0]
+    }
+
     pub fn get_oam_data(&self) -> &[u8; 256] {
         &self.oam.data
     }
@@ -228,19 +304,37 @@ impl PPU {
             (Mirroring::Horizontal, 0x2800) | (Mirroring::Horizontal, 0x2C00) => {
                 (&self.vram[0x0400..0x0800], &self.vram[0x0000..0x0400])
             }
+            (Mirroring::SingleScreenLower, _) => {
+                (&self.vram[0x0000..0x0400], &self.vram[0x0000..0x0400])
+            }
+            (Mirroring::SingleScreenUpper, _) => {
+                (&self.vram[0x0400..0x0800], &self.vram[0x0400..0x0800])
+            }
             (_, _) => {
                 panic!("Not supported mirroring type {:?}", self.mirroring);
             }
         }
     }
 
-    pub fn get_bg_tile(&self, bank: u16, idx: usize) -> &[u8] {
-        let tile = self.vram[idx] as u16;
-        &self.chr_rom[(bank + tile * 16) as usize..=(bank + tile * 16 + 15) as usize]
+    // NOTE: タイルの 1 行を実機と同じく下位プレーン、上位プレーンの順に読み、読むたびにフックへ知らせる。
+    //       切り替えたバンクはその次の読み出しから使う
+    pub fn fetch_pattern_row(&mut self, addr: u16, on_fetch: &mut PatternFetchHook) -> (u8, u8) {
+        let low = self.fetch_pattern(addr, on_fetch);
+        let high = self.fetch_pattern(addr + 8, on_fetch);
+        (low, high)
     }
 
-    pub fn get_sprite_tile(&self, bank: u16, idx: u16) -> &[u8] {
-        &self.chr_rom[(bank + idx * 16) as usize..=(bank + idx * 16 + 15) as usize]
+    fn fetch_pattern(&mut self, addr: u16, on_fetch: &mut PatternFetchHook) -> u8 {
+        let data = self.chr_rom[self.chr_index(addr)];
+        if let Some(banks) = on_fetch(addr) {
+            self.chr_banks = banks;
+        }
+        data
+    }
+
+    fn chr_index(&self, addr: u16) -> usize {
+        let bank = self.chr_banks[addr as usize / CHR_BANK_SIZE];
+        (bank + (addr as usize % CHR_BANK_SIZE)) % self.chr_rom.len()
     }
 
     pub fn get_bg_palette(
@@ -290,6 +384,8 @@ impl PPU {
             (Mirroring::Horizontal, 2) => vram_index - 0x400,
             (Mirroring::Horizontal, 1) => vram_index - 0x400,
             (Mirroring::Horizontal, 3) => vram_index - 0x800,
+            (Mirroring::SingleScreenLower, _) => vram_index % 0x400,
+            (Mirroring::SingleScreenUpper, _) => vram_index % 0x400 + 0x400,
             _ => vram_index,
         }
     }
@@ -312,14 +408,51 @@ impl PPU {
     fn increment_vram_addr(&mut self) {
         self.addr.increment(self.ctrl.vram_addr_increment());
     }
+
+    fn refresh_io_latch(&mut self, value: u8, mask: u8) {
+        self.decay_io_latch();
+        self.io_latch = (self.io_latch & !mask) | (value & mask);
+        for (bit, refreshed) in self.io_latch_refreshed.iter_mut().enumerate() {
+            if mask & (1 << bit) != 0 {
+                *refreshed = self.frame_count;
+            }
+        }
+    }
+
+    fn decay_io_latch(&mut self) {
+        for (bit, refreshed) in self.io_latch_refreshed.iter().enumerate() {
+            if self.frame_count - refreshed >= IO_LATCH_DECAY_FRAMES {
+                self.io_latch &= !(1 << bit);
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::rom::Mirroring;
+    use crate::{region::Region, rom::Mirroring};
 
     use super::PPU;
 
+    fn run_to_vblank(ppu: &mut PPU) -> u16 {
+        while !ppu.status.is_in_vblank() {
+            ppu.tick(3);
+        }
+        ppu.get_scanline()
+    }
+
+    fn run_to_frame_end(ppu: &mut PPU) -> usize {
+        let mut lines = 0;
+        let mut scanline = ppu.get_scanline();
+        while !ppu.tick(3) {
+            if ppu.get_scanline() != scanline {
+                scanline = ppu.get_scanline();
+                lines += 1;
+            }
+        }
+        lines + 1
+    }
+
     #[test]
     fn test_read_data() {
         let chr_rom = vec![0; 0x2000];
@@ -334,4 +467,94 @@ mod test {
         let data = ppu.read_data();
         assert_eq!(data, 0x42);
     }
+
+    #[test]
+    fn test_chr_banks() {
+        let chr_rom = (0..0x4000).map(|i| (i / 0x400) as u8).collect();
+        let mut ppu = PPU::new(chr_rom, false, Mirroring::Horizontal);
+        assert_eq!(ppu.fetch_pattern_row(0x1000, &mut |_| None), (4, 4));
+
+        ppu.set_chr_banks([15 * 0x400, 0, 0, 0, 9 * 0x400, 0, 0, 0]);
+        assert_eq!(ppu.fetch_pattern_row(0x0000, &mut |_| None), (15, 15));
+        assert_eq!(ppu.fetch_pattern_row(0x13F7, &mut |_| None), (9, 9));
+
+        ppu.write_to_addr(0x00);
+        ppu.write_to_addr(0x10);
+        ppu.read_data();
+        assert_eq!(ppu.read_data(), 15);
+    }
+
+    #[test]
+    fn test_fetch_pattern_row() {
+        let chr_rom = (0..0x4000).map(|i| (i / 0x400) as u8).collect();
+        let mut ppu = PPU::new(chr_rom, false, Mirroring::Horizontal);
+
+        // NOTE: 下位プレーンを読んだところでバンクを切り替えると、上位プレーンは新しいバンクから読む
+        let mut fetched = vec![];
+        let mut on_fetch = |addr| {
+            fetched.push(addr);
+            (addr == 0x0010).then_some([12 * 0x400; 8])
+        };
+        assert_eq!(ppu.fetch_pattern_row(0x0010, &mut on_fetch), (0, 12));
+        assert_eq!(ppu.fetch_pattern_row(0x1000, &mut on_fetch), (12, 12));
+        assert_eq!(fetched, vec![0x0010, 0x0018, 0x1000, 0x1008]);
+    }
+
+    #[test]
+    fn test_single_screen_mirroring() {
+        let mut ppu = PPU::new(vec![0; 0x2000], false, Mirroring::SingleScreenUpper);
+        ppu.write_to_addr(0x2C);
+        ppu.write_to_addr(0x05);
+        ppu.write_to_data(0x42);
+        assert_eq!(ppu.vram[0x0405], 0x42);
+
+        let (main, sub) = ppu.get_name_table();
+        assert_eq!((main[5], sub[5]), (0x42, 0x42));
+
+        ppu.set_mirroring(Mirroring::SingleScreenLower);
+        assert_eq!(ppu.get_name_table().0[5], 0x00);
+    }
+
+    #[test]
+    fn test_io_latch() {
+        let mut ppu = PPU::new(vec![0; 0x2000], false, Mirroring::Horizontal);
+
+        // NOTE: ステータスの下位 5 ビットと書き込み専用レジスタには最後に書いた値が見える
+        ppu.write_to_io_latch(0x1F);
+        assert_eq!(ppu.read_status() & 0x1F, 0x1F);
+        assert_eq!(ppu.read_io_latch(), 0x1F);
+
+        ppu.palette_table[0] = 0x3F;
+        ppu.write_to_io_latch(0xC0);
+        ppu.write_to_addr(0x3F);
+        ppu.write_to_addr(0x00);
+        assert_eq!(ppu.read_data(), 0xFF);
+
+        for _ in 0..super::IO_LATCH_DECAY_FRAMES {
+            run_to_frame_end(&mut ppu);
+        }
+        assert_eq!(ppu.read_io_latch(), 0x00);
+    }
+
+    #[test]
+    fn test_region_timing() {
+        let cases = [
+            (Region::Ntsc, 241, 262),
+            (Region::Pal, 241, 312),
+            (Region::Dendy, 291, 312),
+        ];
+
+        for (region, vblank_scanline, scanlines) in cases {
+            let mut ppu = PPU::new(vec![0; 0x2000], false, Mirroring::Horizontal);
+            ppu.set_region(region);
+
+            assert_eq!(run_to_vblank(&mut ppu), vblank_scanline, "{}", region);
+            assert_eq!(
+                run_to_frame_end(&mut ppu) + vblank_scanline as usize,
+                scanlines,
+                "{}",
+                region
+            );
+        }
+    }
 }
@@ -11,13 +11,6 @@ impl OAMRegister {
         }
     }
 
-    pub fn write_dma(&mut self, data: &[u8; 256]) {
-        for x in data.iter() {
-            self.data[self.addr as usize] = *x;
-            self.addr = self.addr.wrapping_add(1);
-        }
-    }
-
     pub fn write(&mut self, data: u8) {
         self.data[self.addr as usize] = data;
         self.addr = self.addr.wrapping_add(1);
@@ -0,0 +1,118 @@
+use std::{collections::HashMap, io::Write};
+
+use crate::render::utils::frame::Frame;
+
+// NOTE: GIF の表示間隔は 1/100 秒単位なので、frame_step フレームごとに 1 枚にして、
+//       端数は次のフレームへ持ち越して平均の速度を合わせる
+pub struct GifWriter<W: Write> {
+    encoder: gif::Encoder<W>,
+    frame_rate: f32,
+    frame_step: usize,
+    frames: usize,
+    written_centis: u64,
+}
+
+impl<W: Write> GifWriter<W> {
+    pub fn new(writer: W, frame_rate: f32, frame_step: usize) -> Result<Self, String> {
+        let mut encoder = gif::Encoder::new(writer, Frame::WIDTH as u16, Frame::HEIGHT as u16, &[])
+            .map_err(|e| e.to_string())?;
+        encoder
+            .set_repeat(gif::Repeat::Infinite)
+            .map_err(|e| e.to_string())?;
+
+        Ok(Self {
+            encoder,
+            frame_rate,
+            frame_step: frame_step.max(1),
+            frames: 0,
+            written_centis: 0,
+        })
+    }
+
+    pub fn write_frame(&mut self, frame: &Frame) -> Result<(), String> {
+        self.frames += 1;
+        if !(self.frames - 1).is_multiple_of(self.frame_step) {
+            return Ok(());
+        }
+
+        let end = ((self.frames - 1 + self.frame_step) as f32 * 100.0 / self.frame_rate).round();
+        let delay = (end as u64).saturating_sub(self.written_centis).max(1);
+        self.written_centis += delay;
+
+        let (palette, pixels) = index_colors(frame);
+        let mut gif_frame = gif::Frame::from_palette_pixels(
+            Frame::WIDTH as u16,
+            Frame::HEIGHT as u16,
+            pixels,
+            palette,
+            None,
+        );
+        gif_frame.delay = delay as u16;
+        self.encoder
+            .write_frame(&gif_frame)
+            .map_err(|e| e.to_string())
+    }
+
+    pub fn finish(self) -> Result<W, String> {
+        self.encoder.into_inner().map_err(|e| e.to_string())
+    }
+}
+
+// NOTE: 1 フレームに出る色は多くても数十色なので、フレームごとのローカルパレットにする。
+//       256 色を超えた分は先頭の色で代用する
+fn index_colors(frame: &Frame) -> (Vec<u8>, Vec<u8>) {
+    let mut indices = HashMap::new();
+    let mut palette = vec![];
+
+    let pixels = frame
+        .palette_indices
+        .iter()
+        .map(|&index| {
+            let next = indices.len();
+            *indices.entry(index).or_insert_with(|| {
+                if next >= 256 {
+                    return 0;
+                }
+                let (r, g, b) = frame.palette().lookup(index);
+                palette.extend([r, g, b]);
+                next as u8
+            })
+        })
+        .collect();
+
+    (palette, pixels)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::render::utils::frame::Frame;
+
+    use super::{index_colors, GifWriter};
+
+    #[test]
+    fn test_index_colors() {
+        let mut frame = Frame::new();
+        frame.set_pixel(1, 0, 0x16, 0);
+        frame.set_pixel(2, 0, 0x16, 0);
+
+        let (palette, pixels) = index_colors(&frame);
+        assert_eq!(palette.len(), 6);
+        assert_eq!(&pixels[..4], &[0, 1, 1, 0]);
+    }
+
+    #[test]
+    fn test_write_gif() {
+        let mut writer = GifWriter::new(vec![], 60.0, 2).unwrap();
+        for _ in 0..4 {
+            writer.write_frame(&Frame::new()).unwrap();
+        }
+        let out = writer.finish().unwrap();
+
+        let mut decoder = gif::DecodeOptions::new().read_info(&out[..]).unwrap();
+        let mut delays = vec![];
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            delays.push(frame.delay);
+        }
+        assert_eq!(delays, vec![3, 4]);
+    }
+}
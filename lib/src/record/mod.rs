@@ -0,0 +1,194 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Seek, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    audio::{AudioBuffer, Channel},
+    render::utils::frame::Frame,
+};
+
+pub use self::gif::GifWriter;
+pub use wav::WavWriter;
+pub use y4m::Y4mWriter;
+
+mod gif;
+mod wav;
+mod y4m;
+
+pub trait WriteSeek: Write + Seek {}
+
+impl<T: Write + Seek> WriteSeek for T {}
+
+// NOTE: フレームと PCM を受け取って、設定された出力へ順に書き出す。
+//       書き込みに失敗したら以降は何もせず、エラーを error / finish で返す
+pub struct Recorder {
+    frame_rate: f32,
+    sample_rate: u32,
+    video: Option<Y4mWriter<Box<dyn Write>>>,
+    audio: Option<WavWriter<Box<dyn WriteSeek>>>,
+    gif: Option<GifWriter<Box<dyn Write>>>,
+    stems: Vec<(Channel, WavWriter<Box<dyn WriteSeek>>)>,
+    frames: usize,
+    error: Option<String>,
+}
+
+impl Recorder {
+    pub fn new(frame_rate: f32, sample_rate: u32) -> Self {
+        Self {
+            frame_rate,
+            sample_rate,
+            video: None,
+            audio: None,
+            gif: None,
+            stems: vec![],
+            frames: 0,
+            error: None,
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    pub fn has_audio(&self) -> bool {
+        self.audio.is_some() || !self.stems.is_empty()
+    }
+
+    pub fn set_video(&mut self, writer: Box<dyn Write>) -> Result<(), String> {
+        self.video = Some(Y4mWriter::new(writer, self.frame_rate).map_err(|e| e.to_string())?);
+        Ok(())
+    }
+
+    pub fn set_audio(&mut self, writer: Box<dyn WriteSeek>) -> Result<(), String> {
+        self.audio = Some(WavWriter::new(writer, self.sample_rate).map_err(|e| e.to_string())?);
+        Ok(())
+    }
+
+    // NOTE: ミックス前のチャンネル単体の出力を別の WAV に書き出す
+    pub fn add_stem(&mut self, channel: Channel, writer: Box<dyn WriteSeek>) -> Result<(), String> {
+        let writer = WavWriter::new(writer, self.sample_rate).map_err(|e| e.to_string())?;
+        self.stems.push((channel, writer));
+        Ok(())
+    }
+
+    pub fn set_gif(&mut self, writer: Box<dyn Write>, frame_step: usize) -> Result<(), String> {
+        self.gif = Some(GifWriter::new(writer, self.frame_rate, frame_step)?);
+        Ok(())
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    pub fn push_frame(&mut self, frame: &Frame) {
+        if self.error.is_some() {
+            return;
+        }
+        self.frames += 1;
+
+        let result = self
+            .video
+            .as_mut()
+            .map_or(Ok(()), |video| {
+                video.write_frame(frame).map_err(|e| e.to_string())
+            })
+            .and_then(|_| {
+                self.gif
+                    .as_mut()
+                    .map_or(Ok(()), |gif| gif.write_frame(frame))
+            });
+        if let Err(e) = result {
+            self.error = Some(e);
+        }
+    }
+
+    pub fn push_audio(&mut self, buffer: &AudioBuffer) {
+        if self.error.is_some() {
+            return;
+        }
+        let result = self
+            .audio
+            .iter_mut()
+            .map(|audio| audio.write_samples(&buffer.mixed))
+            .chain(
+                self.stems
+                    .iter_mut()
+                    .map(|(channel, stem)| stem.write_samples(buffer.channel(*channel))),
+            )
+            .collect::<Result<Vec<_>, _>>();
+        if let Err(e) = result {
+            self.error = Some(e.to_string());
+        }
+    }
+
+    pub fn finish(mut self) -> Result<(), String> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        if let Some(video) = self.video.take() {
+            video.finish().map_err(|e| e.to_string())?;
+        }
+        if let Some(audio) = self.audio.take() {
+            audio.finish().map_err(|e| e.to_string())?;
+        }
+        if let Some(gif) = self.gif.take() {
+            gif.finish()?;
+        }
+        for (_, stem) in self.stems.drain(..) {
+            stem.finish().map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+}
+
+// NOTE: path にミックスした音声を、stems なら "out.pulse1.wav" のようにチャンネルごとの音声も書き出す
+pub fn audio_dump(path: &Path, stems: bool, sample_rate: u32) -> Result<Recorder, String> {
+    let create = |path: &Path| {
+        File::create(path)
+            .map(BufWriter::new)
+            .map_err(|e| format!("{}: {}", path.display(), e))
+    };
+
+    // NOTE: 音声だけなのでフレームレートは使わない
+    let mut recorder = Recorder::new(60.0, sample_rate);
+    recorder.set_audio(Box::new(create(path)?))?;
+    if stems {
+        for channel in Channel::ALL {
+            recorder.add_stem(channel, Box::new(create(&stem_path(path, channel))?))?;
+        }
+    }
+    Ok(recorder)
+}
+
+pub fn stem_path(path: &Path, channel: Channel) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(ext) => format!("{}.{}.{}", stem, channel, ext.to_string_lossy()),
+        None => format!("{}.{}", stem, channel),
+    };
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use test_case::test_case;
+
+    use crate::audio::Channel;
+
+    use super::stem_path;
+
+    #[test_case("out.wav", Channel::Pulse1, "out.pulse1.wav")]
+    #[test_case("dir/out.wav", Channel::Dmc, "dir/out.dmc.wav")]
+    #[test_case("out", Channel::Noise, "out.noise")]
+    fn test_stem_path(path: &str, channel: Channel, expected: &str) {
+        assert_eq!(stem_path(Path::new(path), channel), Path::new(expected));
+    }
+}
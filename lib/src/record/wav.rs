@@ -0,0 +1,81 @@
+use std::io::{self, Seek, SeekFrom, Write};
+
+const HEADER_SIZE: u32 = 44;
+
+// NOTE: 16bit モノラルの PCM。サイズはヘッダーに仮の値を書いておき、finish で書き直す
+pub struct WavWriter<W: Write + Seek> {
+    writer: W,
+    samples: u32,
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    pub fn new(mut writer: W, sample_rate: u32) -> io::Result<Self> {
+        writer.write_all(&header(sample_rate, 0))?;
+        Ok(Self { writer, samples: 0 })
+    }
+
+    pub fn write_samples(&mut self, samples: &[f32]) -> io::Result<()> {
+        let bytes = samples
+            .iter()
+            .flat_map(|&s| ((s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16).to_le_bytes())
+            .collect::<Vec<_>>();
+        self.writer.write_all(&bytes)?;
+        self.samples += samples.len() as u32;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        let data_size = self.samples * 2;
+        self.writer.seek(SeekFrom::Start(4))?;
+        self.writer
+            .write_all(&(HEADER_SIZE - 8 + data_size).to_le_bytes())?;
+        self.writer.seek(SeekFrom::Start(40))?;
+        self.writer.write_all(&data_size.to_le_bytes())?;
+        self.writer.seek(SeekFrom::End(0))?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+fn header(sample_rate: u32, data_size: u32) -> Vec<u8> {
+    let mut header = Vec::with_capacity(HEADER_SIZE as usize);
+    header.extend(b"RIFF");
+    header.extend((HEADER_SIZE - 8 + data_size).to_le_bytes());
+    header.extend(b"WAVEfmt ");
+    header.extend(16u32.to_le_bytes());
+    // NOTE: PCM, 1ch
+    header.extend(1u16.to_le_bytes());
+    header.extend(1u16.to_le_bytes());
+    header.extend(sample_rate.to_le_bytes());
+    header.extend((sample_rate * 2).to_le_bytes());
+    header.extend(2u16.to_le_bytes());
+    header.extend(16u16.to_le_bytes());
+    header.extend(b"data");
+    header.extend(data_size.to_le_bytes());
+    header
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use super::WavWriter;
+
+    #[test]
+    fn test_wav() {
+        let mut writer = WavWriter::new(Cursor::new(vec![]), 44_100).unwrap();
+        writer.write_samples(&[0.0, 1.0, -1.0]).unwrap();
+        writer.write_samples(&[2.0]).unwrap();
+        let out = writer.finish().unwrap().into_inner();
+
+        assert_eq!(out.len(), 44 + 8);
+        assert_eq!(&out[0..4], b"RIFF");
+        assert_eq!(u32::from_le_bytes(out[4..8].try_into().unwrap()), 36 + 8);
+        assert_eq!(u32::from_le_bytes(out[24..28].try_into().unwrap()), 44_100);
+        assert_eq!(u32::from_le_bytes(out[40..44].try_into().unwrap()), 8);
+        assert_eq!(
+            &out[44..],
+            &[0x00, 0x00, 0xFF, 0x7F, 0x01, 0x80, 0xFF, 0x7F]
+        );
+    }
+}
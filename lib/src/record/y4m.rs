@@ -0,0 +1,83 @@
+use std::io::{self, Write};
+
+use crate::render::utils::frame::Frame;
+
+// NOTE: 非圧縮の YUV4MPEG2。色差を間引かない 4:4:4 で、BT.601 の limited range に変換する
+pub struct Y4mWriter<W: Write> {
+    writer: W,
+    rgb: Vec<u8>,
+    planes: Vec<u8>,
+}
+
+impl<W: Write> Y4mWriter<W> {
+    pub fn new(mut writer: W, frame_rate: f32) -> io::Result<Self> {
+        writeln!(
+            writer,
+            "YUV4MPEG2 W{} H{} F{}:1000 Ip A1:1 C444",
+            Frame::WIDTH,
+            Frame::HEIGHT,
+            (frame_rate * 1000.0).round() as u32
+        )?;
+
+        Ok(Self {
+            writer,
+            rgb: vec![0; Frame::WIDTH * Frame::HEIGHT * 3],
+            planes: vec![0; Frame::WIDTH * Frame::HEIGHT * 3],
+        })
+    }
+
+    pub fn write_frame(&mut self, frame: &Frame) -> io::Result<()> {
+        frame.to_rgb(&mut self.rgb);
+
+        let size = Frame::WIDTH * Frame::HEIGHT;
+        let (y_plane, rest) = self.planes.split_at_mut(size);
+        let (cb_plane, cr_plane) = rest.split_at_mut(size);
+        for (i, rgb) in self.rgb.chunks_exact(3).enumerate() {
+            let (y, cb, cr) = to_ycbcr(rgb[0], rgb[1], rgb[2]);
+            y_plane[i] = y;
+            cb_plane[i] = cb;
+            cr_plane[i] = cr;
+        }
+
+        self.writer.write_all(b"FRAME\n")?;
+        self.writer.write_all(&self.planes)
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+fn to_ycbcr(r: u8, g: u8, b: u8) -> (u8, u8, u8) {
+    let (r, g, b) = (r as f32, g as f32, b as f32);
+    let y = 16.0 + (65.738 * r + 129.057 * g + 25.064 * b) / 256.0;
+    let cb = 128.0 + (-37.945 * r - 74.494 * g + 112.439 * b) / 256.0;
+    let cr = 128.0 + (112.439 * r - 94.154 * g - 18.285 * b) / 256.0;
+    (y.round() as u8, cb.round() as u8, cr.round() as u8)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::render::utils::frame::Frame;
+
+    use super::{to_ycbcr, Y4mWriter};
+
+    #[test]
+    fn test_to_ycbcr() {
+        assert_eq!(to_ycbcr(0, 0, 0), (16, 128, 128));
+        assert_eq!(to_ycbcr(255, 255, 255), (235, 128, 128));
+    }
+
+    #[test]
+    fn test_write_frame() {
+        let mut writer = Y4mWriter::new(vec![], 60.0988).unwrap();
+        writer.write_frame(&Frame::new()).unwrap();
+        writer.write_frame(&Frame::new()).unwrap();
+        let out = writer.finish().unwrap();
+
+        let header = b"YUV4MPEG2 W256 H240 F60099:1000 Ip A1:1 C444\n";
+        assert!(out.starts_with(header));
+        assert_eq!(out.len(), header.len() + 2 * (6 + 256 * 240 * 3));
+    }
+}
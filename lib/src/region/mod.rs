@@ -0,0 +1,99 @@
+use std::{fmt::Display, str::FromStr};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Region {
+    #[default]
+    Ntsc,
+    Pal,
+    Dendy,
+}
+
+impl Region {
+    pub fn cpu_clock(&self) -> f32 {
+        match self {
+            // 1.789773 MHz
+            Region::Ntsc => 1_789_773.0,
+            // 1.662607 MHz
+            Region::Pal => 1_662_607.0,
+            // 1.773448 MHz
+            Region::Dendy => 1_773_448.0,
+        }
+    }
+
+    pub fn frame_rate(&self) -> f32 {
+        match self {
+            Region::Ntsc => 60.0988,
+            Region::Pal | Region::Dendy => 50.007,
+        }
+    }
+
+    pub(crate) fn scanlines_per_frame(&self) -> u16 {
+        match self {
+            Region::Ntsc => 262,
+            Region::Pal | Region::Dendy => 312,
+        }
+    }
+
+    pub(crate) fn vblank_scanline(&self) -> u16 {
+        match self {
+            Region::Ntsc | Region::Pal => 241,
+            // NOTE: Dendy は post-render line が 51 本あり、VBlank の長さは NTSC と同じ 20 本
+            Region::Dendy => 291,
+        }
+    }
+
+    pub(crate) fn ppu_clock_ratio(&self) -> (u16, u16) {
+        match self {
+            Region::Ntsc | Region::Dendy => (3, 1),
+            // NOTE: PAL は CPU 1 サイクルあたり 3.2 ドット
+            Region::Pal => (16, 5),
+        }
+    }
+}
+
+impl FromStr for Region {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "ntsc" => Ok(Region::Ntsc),
+            "pal" => Ok(Region::Pal),
+            "dendy" => Ok(Region::Dendy),
+            _ => Err(format!("Unknown region: {}", s)),
+        }
+    }
+}
+
+impl Display for Region {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Region::Ntsc => "NTSC",
+            Region::Pal => "PAL",
+            Region::Dendy => "Dendy",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Region;
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!("ntsc".parse::<Region>(), Ok(Region::Ntsc));
+        assert_eq!("PAL".parse::<Region>(), Ok(Region::Pal));
+        assert_eq!("Dendy".parse::<Region>(), Ok(Region::Dendy));
+        assert!("secam".parse::<Region>().is_err());
+    }
+
+    #[test]
+    fn test_vblank_length() {
+        let vblank_lines =
+            |region: Region| region.scanlines_per_frame() - 1 - region.vblank_scanline();
+
+        assert_eq!(vblank_lines(Region::Ntsc), 20);
+        assert_eq!(vblank_lines(Region::Pal), 70);
+        assert_eq!(vblank_lines(Region::Dendy), 20);
+    }
+}
@@ -0,0 +1,28 @@
+use super::{put, Image};
+
+// NOTE: 3 ライン目を暗くして走査線を、列ごとに RGB を強調してアパーチャグリルを表現する
+const SCANLINE: [f32; 3] = [1.0, 1.0, 0.55];
+const MASK: [(f32, f32, f32); 3] = [(1.0, 0.8, 0.8), (0.8, 1.0, 0.8), (0.8, 0.8, 1.0)];
+const BRIGHTNESS: f32 = 1.15;
+
+pub(super) fn crt3x(src: &Image, out: &mut [u8]) {
+    let width = src.width * 3;
+    let channel = |value: u8, scale: f32| (value as f32 * scale * BRIGHTNESS).min(255.0) as u8;
+
+    for y in 0..src.height {
+        for x in 0..src.width {
+            let (r, g, b) = src.get(x as isize, y as isize);
+
+            for (row, scanline) in SCANLINE.iter().enumerate() {
+                for (column, mask) in MASK.iter().enumerate() {
+                    let rgb = (
+                        channel(r, scanline * mask.0),
+                        channel(g, scanline * mask.1),
+                        channel(b, scanline * mask.2),
+                    );
+                    put(out, width, x * 3 + column, y * 3 + row, rgb);
+                }
+            }
+        }
+    }
+}
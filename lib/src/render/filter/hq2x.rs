@@ -0,0 +1,205 @@
+use super::{mix, put, to_yuv, Image, Rgb};
+
+// NOTE: hq2x の色の違いの判定。YUV の各成分の差がどれか閾値を超えたら違う色とみなす
+const THRESHOLD_Y: f32 = 48.0;
+const THRESHOLD_U: f32 = 7.0;
+const THRESHOLD_V: f32 = 6.0;
+
+// NOTE: 3x3 の近傍を左上から 0..=8 で番号付けする (4 が中央)。左上の出力ピクセルの規則を、
+//       近傍を左右・上下に反転させて右上・左下・右下にも使う
+const MIRRORS: [[usize; 9]; 4] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8],
+    [2, 1, 0, 5, 4, 3, 8, 7, 6],
+    [6, 7, 8, 3, 4, 5, 0, 1, 2],
+    [8, 7, 6, 5, 4, 3, 2, 1, 0],
+];
+
+fn is_different(a: Rgb, b: Rgb) -> bool {
+    if a == b {
+        return false;
+    }
+
+    let (ay, au, av) = to_yuv(a);
+    let (by, bu, bv) = to_yuv(b);
+    (ay - by).abs() > THRESHOLD_Y || (au - bu).abs() > THRESHOLD_U || (av - bv).abs() > THRESHOLD_V
+}
+
+// NOTE: 中央を除く 8 ピクセルのパターンのビット位置
+fn bit(n: usize) -> usize {
+    if n > 4 {
+        n - 1
+    } else {
+        n
+    }
+}
+
+// NOTE: hq2x の 256 パターンのテーブルのうち、左上の出力ピクセルに関わる部分を (マスク, 値) の組にまとめたもの。
+//       上から順に当てはめ、一部は実行時に辺の 2 ピクセル (左と上など) の違いも見る
+fn top_left(w: &[Rgb; 9], pattern: u8) -> Rgb {
+    let any = |patterns: &[(u8, u8)]| patterns.iter().any(|&(mask, bits)| pattern & mask == bits);
+    let [w0, w1, _, w3, w4, w5, _, w7, _] = *w;
+
+    if any(&[(0xBF, 0x37), (0xDB, 0x13)]) && is_different(w1, w5) {
+        return mix(&[(w4, 3), (w3, 1)]);
+    }
+    if any(&[(0xDB, 0x49), (0xEF, 0x6D)]) && is_different(w7, w3) {
+        return mix(&[(w4, 3), (w1, 1)]);
+    }
+    if any(&[(0x0B, 0x0B), (0xFE, 0x4A), (0xFE, 0x1A)]) && is_different(w3, w1) {
+        return w4;
+    }
+    if any(&[
+        (0x6F, 0x2A),
+        (0x5B, 0x0A),
+        (0xBF, 0x3A),
+        (0xDF, 0x5A),
+        (0x9F, 0x8A),
+        (0xCF, 0x8A),
+        (0xEF, 0x4E),
+        (0x3F, 0x0E),
+        (0xFB, 0x5A),
+        (0xBB, 0x8A),
+        (0x7F, 0x5A),
+        (0xAF, 0x8A),
+        (0xEB, 0x8A),
+    ]) && is_different(w3, w1)
+    {
+        return mix(&[(w4, 3), (w0, 1)]);
+    }
+    if any(&[(0x0B, 0x08)]) {
+        return mix(&[(w4, 2), (w0, 1), (w1, 1)]);
+    }
+    if any(&[(0x0B, 0x02)]) {
+        return mix(&[(w4, 2), (w0, 1), (w3, 1)]);
+    }
+    if any(&[(0x2F, 0x2F)]) {
+        return mix(&[(w4, 14), (w3, 1), (w1, 1)]);
+    }
+    if any(&[(0xBF, 0x37), (0xDB, 0x13)]) {
+        return mix(&[(w4, 5), (w1, 2), (w3, 1)]);
+    }
+    if any(&[(0xDB, 0x49), (0xEF, 0x6D)]) {
+        return mix(&[(w4, 5), (w3, 2), (w1, 1)]);
+    }
+    if any(&[(0x1B, 0x03), (0x4F, 0x43), (0x8B, 0x83), (0x6B, 0x43)]) {
+        return mix(&[(w4, 3), (w3, 1)]);
+    }
+    if any(&[(0x4B, 0x09), (0x8B, 0x89), (0x1F, 0x19), (0x3B, 0x19)]) {
+        return mix(&[(w4, 3), (w1, 1)]);
+    }
+    if any(&[(0x7E, 0x2A), (0xEF, 0xAB), (0xBF, 0x8F), (0x7E, 0x0E)]) {
+        return mix(&[(w4, 2), (w3, 3), (w1, 3)]);
+    }
+    if any(&[
+        (0xFB, 0x6A),
+        (0x6F, 0x6E),
+        (0x3F, 0x3E),
+        (0xFB, 0xFA),
+        (0xDF, 0xDE),
+        (0xDF, 0x1E),
+    ]) {
+        return mix(&[(w4, 3), (w0, 1)]);
+    }
+    if any(&[
+        (0x0A, 0x00),
+        (0x4F, 0x4B),
+        (0x9F, 0x1B),
+        (0x2F, 0x0B),
+        (0xBE, 0x0A),
+        (0xEE, 0x0A),
+        (0x7E, 0x0A),
+        (0xEB, 0x4B),
+        (0x3B, 0x1B),
+    ]) {
+        return mix(&[(w4, 2), (w3, 1), (w1, 1)]);
+    }
+    mix(&[(w4, 6), (w3, 1), (w1, 1)])
+}
+
+// NOTE: Maxim Stepin の hq2x。近傍 8 ピクセルが中央と違うかどうかの 256 パターンで補間方法を選ぶ
+pub(super) fn hq2x(src: &Image, out: &mut [u8]) {
+    let width = src.width * 2;
+
+    for y in 0..src.height {
+        for x in 0..src.width {
+            let (sx, sy) = (x as isize, y as isize);
+            let w: [Rgb; 9] =
+                std::array::from_fn(|n| src.get(sx + n as isize % 3 - 1, sy + n as isize / 3 - 1));
+            let pattern = (0..9)
+                .filter(|&n| n != 4 && is_different(w[n], w[4]))
+                .fold(0u8, |pattern, n| pattern | 1 << bit(n));
+
+            for (i, mirror) in MIRRORS.iter().enumerate() {
+                let mirrored_w = mirror.map(|n| w[n]);
+                let mirrored_pattern = (0..9).filter(|&n| n != 4).fold(0u8, |acc, n| {
+                    acc | (pattern >> bit(mirror[n]) & 1) << bit(n)
+                });
+
+                let rgb = top_left(&mirrored_w, mirrored_pattern);
+                put(out, width, x * 2 + i % 2, y * 2 + i / 2, rgb);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::render::utils::palette::Palette;
+
+    use super::{hq2x, mix, Image};
+
+    fn run(data: &[u16], width: usize, height: usize) -> impl Fn(usize, usize) -> (u8, u8, u8) {
+        let palette = Palette::default();
+        let src = Image::new(data, &palette, width, height);
+        let mut out = vec![0; width * height * 4 * 3];
+        hq2x(&src, &mut out);
+
+        move |x, y| {
+            let base = (y * width * 2 + x) * 3;
+            (out[base], out[base + 1], out[base + 2])
+        }
+    }
+
+    #[test]
+    fn test_lone_pixel() {
+        // NOTE: 周り 8 ピクセルがすべて違い、左と上は同じ色なので 14:1:1 で丸める
+        let palette = Palette::default();
+        let (w, b) = (palette.get(0x30, 0), palette.get(0x0D, 0));
+        #[rustfmt::skip]
+        let data = [
+            0x0D, 0x0D, 0x0D,
+            0x0D, 0x30, 0x0D,
+            0x0D, 0x0D, 0x0D,
+        ];
+        let pixel = run(&data, 3, 3);
+
+        let center = mix(&[(w, 14), (b, 1), (b, 1)]);
+        for (x, y) in [(2, 2), (3, 2), (2, 3), (3, 3)] {
+            assert_eq!(pixel(x, y), center, "({}, {})", x, y);
+        }
+        assert_eq!(pixel(0, 0), b);
+        assert_eq!(pixel(2, 1), b);
+    }
+
+    #[test]
+    fn test_diagonal_edge() {
+        // NOTE: 斜めの境界では、境界側の出力ピクセルだけが隣の色と混ざる
+        //   # # .
+        //   # . .
+        //   . . .
+        let palette = Palette::default();
+        let (w, b) = (palette.get(0x30, 0), palette.get(0x0D, 0));
+        #[rustfmt::skip]
+        let data = [
+            0x30, 0x30, 0x0D,
+            0x30, 0x0D, 0x0D,
+            0x0D, 0x0D, 0x0D,
+        ];
+        let pixel = run(&data, 3, 3);
+
+        // NOTE: 中央の左上は左上・上・左が白 (パターン 0x0B) で、左と上が同じ色なので 2:1:1
+        assert_eq!(pixel(2, 2), mix(&[(b, 2), (w, 1), (w, 1)]));
+        assert_eq!(pixel(3, 3), b);
+        assert_eq!(pixel(0, 0), w);
+    }
+}
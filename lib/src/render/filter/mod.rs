@@ -0,0 +1,175 @@
+use std::str::FromStr;
+
+use super::utils::{frame::Frame, palette::Palette};
+
+mod crt;
+mod hq2x;
+pub mod ntsc;
+mod scale;
+mod xbr;
+
+pub const FILTERS: [&str; 6] = ["none", "scale2x", "scale3x", "hq2x", "xbr", "crt"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Filter {
+    #[default]
+    None,
+    Scale2x,
+    Scale3x,
+    Hq2x,
+    Xbr,
+    Crt,
+}
+
+impl Filter {
+    pub fn scale(&self) -> usize {
+        match self {
+            Filter::None => 1,
+            Filter::Scale2x | Filter::Hq2x | Filter::Xbr => 2,
+            Filter::Scale3x | Filter::Crt => 3,
+        }
+    }
+
+    pub fn output_size(&self) -> (usize, usize) {
+        (Frame::WIDTH * self.scale(), Frame::HEIGHT * self.scale())
+    }
+
+    pub fn apply(&self, frame: &Frame, out: &mut Vec<u8>) {
+        let (width, height) = self.output_size();
+        out.resize(width * height * 3, 0);
+
+        let src = Image::new(
+            &frame.palette_indices,
+            frame.palette(),
+            Frame::WIDTH,
+            Frame::HEIGHT,
+        );
+        match self {
+            Filter::None => frame.to_rgb(out),
+            Filter::Scale2x => scale::scale2x(&src, out),
+            Filter::Scale3x => scale::scale3x(&src, out),
+            Filter::Hq2x => hq2x::hq2x(&src, out),
+            Filter::Xbr => xbr::xbr2x(&src, out),
+            Filter::Crt => crt::crt3x(&src, out),
+        }
+    }
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" => Ok(Filter::None),
+            "scale2x" => Ok(Filter::Scale2x),
+            "scale3x" => Ok(Filter::Scale3x),
+            "hq2x" => Ok(Filter::Hq2x),
+            "xbr" => Ok(Filter::Xbr),
+            "crt" => Ok(Filter::Crt),
+            _ => Err(format!(
+                "Unknown filter: {} (available: {})",
+                s,
+                FILTERS.join(", ")
+            )),
+        }
+    }
+}
+
+type Rgb = (u8, u8, u8);
+
+// NOTE: パレット番号のバッファを座標で RGB として読むためのラッパー。範囲外は端のピクセルを返す
+struct Image<'a> {
+    indices: &'a [u16],
+    palette: &'a Palette,
+    width: usize,
+    height: usize,
+}
+
+impl<'a> Image<'a> {
+    fn new(indices: &'a [u16], palette: &'a Palette, width: usize, height: usize) -> Self {
+        Self {
+            indices,
+            palette,
+            width,
+            height,
+        }
+    }
+
+    fn get(&self, x: isize, y: isize) -> Rgb {
+        let x = x.clamp(0, self.width as isize - 1) as usize;
+        let y = y.clamp(0, self.height as isize - 1) as usize;
+        self.palette.lookup(self.indices[y * self.width + x])
+    }
+}
+
+fn put(out: &mut [u8], width: usize, x: usize, y: usize, rgb: Rgb) {
+    let base = (y * width + x) * 3;
+    out[base] = rgb.0;
+    out[base + 1] = rgb.1;
+    out[base + 2] = rgb.2;
+}
+
+fn to_yuv(rgb: Rgb) -> (f32, f32, f32) {
+    let (r, g, b) = (rgb.0 as f32, rgb.1 as f32, rgb.2 as f32);
+    let y = 0.299 * r + 0.587 * g + 0.114 * b;
+    let u = -0.169 * r - 0.331 * g + 0.5 * b;
+    let v = 0.5 * r - 0.419 * g - 0.081 * b;
+    (y, u, v)
+}
+
+// NOTE: 重みの混合。weights の合計で割って丸める
+fn mix(colors: &[(Rgb, u32)]) -> Rgb {
+    let total = colors.iter().map(|(_, w)| w).sum::<u32>().max(1);
+    let channel = |f: fn(&Rgb) -> u8| {
+        let sum = colors.iter().map(|(c, w)| f(c) as u32 * w).sum::<u32>();
+        ((sum + total / 2) / total) as u8
+    };
+    (channel(|c| c.0), channel(|c| c.1), channel(|c| c.2))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::render::utils::frame::Frame;
+
+    use super::{Filter, FILTERS};
+
+    #[test]
+    fn test_from_str() {
+        for name in FILTERS {
+            assert!(name.parse::<Filter>().is_ok(), "{}", name);
+        }
+        assert_eq!("HQ2X".parse::<Filter>(), Ok(Filter::Hq2x));
+        assert!("bilinear".parse::<Filter>().is_err());
+    }
+
+    #[test]
+    fn test_solid_frame_keeps_color() {
+        let mut frame = Frame::new();
+        for y in 0..Frame::HEIGHT {
+            for x in 0..Frame::WIDTH {
+                frame.set_pixel(x, y, 0x21, 0);
+            }
+        }
+        let expected = frame.palette().get(0x21, 0);
+
+        for filter in [
+            Filter::None,
+            Filter::Scale2x,
+            Filter::Scale3x,
+            Filter::Hq2x,
+            Filter::Xbr,
+        ] {
+            let mut out = vec![];
+            filter.apply(&frame, &mut out);
+
+            let (width, height) = filter.output_size();
+            assert_eq!(out.len(), width * height * 3);
+            assert!(
+                out.chunks_exact(3)
+                    .all(|rgb| (rgb[0], rgb[1], rgb[2]) == expected),
+                "{:?}",
+                filter
+            );
+        }
+    }
+}
@@ -0,0 +1,241 @@
+use std::str::FromStr;
+
+use crate::render::utils::{
+    frame::Frame,
+    palette::{color_carrier, composite_signal, yiq_to_rgb},
+};
+
+// NOTE: PPU の 1 ドットはマスタークロック 8 つ分、カラーサブキャリアの 1 周期は 12 個分
+const SAMPLES_PER_PIXEL: usize = 8;
+const SAMPLES_PER_LINE: usize = Frame::WIDTH * SAMPLES_PER_PIXEL;
+const CARRIER_PERIOD: usize = 12;
+const SAMPLES_PER_OUTPUT: usize = SAMPLES_PER_PIXEL / 2;
+const SVIDEO_LUMA_WINDOW: usize = 4;
+const PIXEL_VARIANTS: usize = 512;
+
+// NOTE: 1 ライン (341 ドット) ごとに位相が 4 ずつ、奇数フレームでは 1 ドット欠けるので 2 フレーム周期でずれる
+const LINE_PHASE_STEP: usize = 4;
+const FRAME_PHASE_STEP: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NtscMode {
+    Composite,
+    SVideo,
+    Rgb,
+}
+
+impl FromStr for NtscMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "composite" => Ok(NtscMode::Composite),
+            "svideo" | "s-video" => Ok(NtscMode::SVideo),
+            "rgb" => Ok(NtscMode::Rgb),
+            _ => Err(format!("Unknown NTSC filter mode: {}", s)),
+        }
+    }
+}
+
+pub struct NtscFilter {
+    mode: NtscMode,
+    signal: Vec<[f32; CARRIER_PERIOD]>,
+    luma: Vec<f32>,
+    carrier: [(f32, f32); CARRIER_PERIOD],
+    composite_line: Vec<f32>,
+    luma_line: Vec<f32>,
+    frame_count: u64,
+}
+
+impl NtscFilter {
+    pub const WIDTH: usize = Frame::WIDTH * SAMPLES_PER_PIXEL / SAMPLES_PER_OUTPUT;
+    pub const HEIGHT: usize = Frame::HEIGHT;
+
+    pub fn new(mode: NtscMode) -> Self {
+        let signal = (0..PIXEL_VARIANTS as u16)
+            .map(|pixel| {
+                let mut levels = [0.0; CARRIER_PERIOD];
+                for (phase, level) in levels.iter_mut().enumerate() {
+                    *level = composite_signal(pixel, phase);
+                }
+                levels
+            })
+            .collect::<Vec<_>>();
+        let luma = signal
+            .iter()
+            .map(|levels| levels.iter().sum::<f32>() / CARRIER_PERIOD as f32)
+            .collect();
+
+        let mut carrier = [(0.0, 0.0); CARRIER_PERIOD];
+        for (phase, value) in carrier.iter_mut().enumerate() {
+            *value = color_carrier(phase as f32);
+        }
+
+        Self {
+            mode,
+            signal,
+            luma,
+            carrier,
+            composite_line: vec![0.0; SAMPLES_PER_LINE],
+            luma_line: vec![0.0; SAMPLES_PER_LINE],
+            frame_count: 0,
+        }
+    }
+
+    pub fn mode(&self) -> NtscMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: NtscMode) {
+        self.mode = mode;
+    }
+
+    pub fn apply(&mut self, frame: &Frame, out: &mut Vec<u8>) {
+        out.resize(Self::WIDTH * Self::HEIGHT * 3, 0);
+
+        let frame_phase = (self.frame_count % 2) as usize * FRAME_PHASE_STEP;
+        self.frame_count += 1;
+
+        for (y, line) in out.chunks_exact_mut(Self::WIDTH * 3).enumerate() {
+            match self.mode {
+                NtscMode::Rgb => {
+                    for (x, dst) in line.chunks_exact_mut(6).enumerate() {
+                        let (r, g, b) = frame.get_pixel(x, y);
+                        dst.copy_from_slice(&[r, g, b, r, g, b]);
+                    }
+                }
+                NtscMode::Composite | NtscMode::SVideo => {
+                    let phase = (y * LINE_PHASE_STEP + frame_phase) % CARRIER_PERIOD;
+                    let indices = &frame.palette_indices[y * Frame::WIDTH..][..Frame::WIDTH];
+                    self.modulate_line(indices, phase);
+                    self.demodulate_line(line, phase);
+                }
+            }
+        }
+    }
+
+    fn modulate_line(&mut self, indices: &[u16], phase: usize) {
+        for (x, &pixel) in indices.iter().enumerate() {
+            let pixel = pixel as usize % PIXEL_VARIANTS;
+            for sample in x * SAMPLES_PER_PIXEL..(x + 1) * SAMPLES_PER_PIXEL {
+                self.composite_line[sample] = self.signal[pixel][(sample + phase) % CARRIER_PERIOD];
+                self.luma_line[sample] = self.luma[pixel];
+            }
+        }
+    }
+
+    fn demodulate_line(&self, out: &mut [u8], phase: usize) {
+        for (x, rgb) in out.chunks_exact_mut(3).enumerate() {
+            let center = x * SAMPLES_PER_OUTPUT + SAMPLES_PER_OUTPUT / 2;
+            let window = center.saturating_sub(CARRIER_PERIOD / 2)
+                ..(center + CARRIER_PERIOD / 2).min(SAMPLES_PER_LINE);
+            let len = window.len() as f32;
+
+            let (mut y, mut i, mut q) = (0.0, 0.0, 0.0);
+            for sample in window {
+                let (cos, sin) = self.carrier[(sample + phase) % CARRIER_PERIOD];
+                let chroma = match self.mode {
+                    NtscMode::SVideo => self.composite_line[sample] - self.luma_line[sample],
+                    _ => self.composite_line[sample],
+                };
+
+                y += self.composite_line[sample];
+                i += chroma * cos;
+                q += chroma * sin;
+            }
+
+            let y = match self.mode {
+                NtscMode::SVideo => {
+                    let window = center.saturating_sub(SVIDEO_LUMA_WINDOW / 2)
+                        ..(center + SVIDEO_LUMA_WINDOW / 2).min(SAMPLES_PER_LINE);
+                    let len = window.len() as f32;
+                    self.luma_line[window].iter().sum::<f32>() / len
+                }
+                _ => y / len,
+            };
+
+            let (r, g, b) = yiq_to_rgb(y, i / len, q / len);
+            rgb.copy_from_slice(&[r, g, b]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::render::utils::{frame::Frame, palette::Palette};
+
+    use super::{NtscFilter, NtscMode};
+
+    fn solid_frame(color: u8) -> Frame {
+        let mut frame = Frame::new();
+        for y in 0..Frame::HEIGHT {
+            for x in 0..Frame::WIDTH {
+                frame.set_pixel(x, y, color, 0);
+            }
+        }
+        frame
+    }
+
+    fn striped_frame() -> Frame {
+        let mut frame = Frame::new();
+        for y in 0..Frame::HEIGHT {
+            for x in 0..Frame::WIDTH {
+                let color = if x % 2 == 0 { 0x30 } else { 0x0F };
+                frame.set_pixel(x, y, color, 0);
+            }
+        }
+        frame
+    }
+
+    #[test]
+    fn test_solid_color_matches_composite_palette() {
+        let expected = Palette::builtin("composite").unwrap().get(0x16, 0);
+
+        for mode in [NtscMode::Composite, NtscMode::SVideo] {
+            let mut filter = NtscFilter::new(mode);
+            let mut out = vec![];
+            filter.apply(&solid_frame(0x16), &mut out);
+
+            let center = (120 * NtscFilter::WIDTH + 256) * 3;
+            let actual = (out[center], out[center + 1], out[center + 2]);
+            assert!(
+                actual.0.abs_diff(expected.0) <= 1
+                    && actual.1.abs_diff(expected.1) <= 1
+                    && actual.2.abs_diff(expected.2) <= 1,
+                "{:?}: {:?} != {:?}",
+                mode,
+                actual,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_rgb_doubles_pixels() {
+        let frame = striped_frame();
+        let mut filter = NtscFilter::new(NtscMode::Rgb);
+        let mut out = vec![];
+        filter.apply(&frame, &mut out);
+
+        assert_eq!(out.len(), NtscFilter::WIDTH * NtscFilter::HEIGHT * 3);
+        assert_eq!(&out[0..6], &[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+        let (r, g, b) = frame.get_pixel(1, 0);
+        assert_eq!(&out[6..12], &[r, g, b, r, g, b]);
+    }
+
+    #[test]
+    fn test_dot_crawl() {
+        let frame = striped_frame();
+
+        let mut filter = NtscFilter::new(NtscMode::Composite);
+        let (mut even, mut odd) = (vec![], vec![]);
+        filter.apply(&frame, &mut even);
+        filter.apply(&frame, &mut odd);
+        assert_ne!(even, odd);
+
+        let mut filter = NtscFilter::new(NtscMode::Rgb);
+        filter.apply(&frame, &mut even);
+        filter.apply(&frame, &mut odd);
+        assert_eq!(even, odd);
+    }
+}
@@ -0,0 +1,123 @@
+use super::{put, Image};
+
+// NOTE: AdvMAME2x / AdvMAME3x (Scale2x / Scale3x) のルールそのまま
+//   A B C
+//   D E F
+//   G H I
+pub(super) fn scale2x(src: &Image, out: &mut [u8]) {
+    let width = src.width * 2;
+
+    for y in 0..src.height {
+        for x in 0..src.width {
+            let (sx, sy) = (x as isize, y as isize);
+            let b = src.get(sx, sy - 1);
+            let d = src.get(sx - 1, sy);
+            let e = src.get(sx, sy);
+            let f = src.get(sx + 1, sy);
+            let h = src.get(sx, sy + 1);
+
+            let pixels = if b != h && d != f {
+                [
+                    if d == b { d } else { e },
+                    if b == f { f } else { e },
+                    if d == h { d } else { e },
+                    if h == f { f } else { e },
+                ]
+            } else {
+                [e; 4]
+            };
+
+            for (i, rgb) in pixels.into_iter().enumerate() {
+                put(out, width, x * 2 + i % 2, y * 2 + i / 2, rgb);
+            }
+        }
+    }
+}
+
+pub(super) fn scale3x(src: &Image, out: &mut [u8]) {
+    let width = src.width * 3;
+
+    for y in 0..src.height {
+        for x in 0..src.width {
+            let (sx, sy) = (x as isize, y as isize);
+            let a = src.get(sx - 1, sy - 1);
+            let b = src.get(sx, sy - 1);
+            let c = src.get(sx + 1, sy - 1);
+            let d = src.get(sx - 1, sy);
+            let e = src.get(sx, sy);
+            let f = src.get(sx + 1, sy);
+            let g = src.get(sx - 1, sy + 1);
+            let h = src.get(sx, sy + 1);
+            let i = src.get(sx + 1, sy + 1);
+
+            let pixels = if b != h && d != f {
+                [
+                    if d == b { d } else { e },
+                    if (d == b && e != c) || (b == f && e != a) {
+                        b
+                    } else {
+                        e
+                    },
+                    if b == f { f } else { e },
+                    if (d == b && e != g) || (d == h && e != a) {
+                        d
+                    } else {
+                        e
+                    },
+                    e,
+                    if (b == f && e != i) || (h == f && e != c) {
+                        f
+                    } else {
+                        e
+                    },
+                    if d == h { d } else { e },
+                    if (d == h && e != i) || (h == f && e != g) {
+                        h
+                    } else {
+                        e
+                    },
+                    if h == f { f } else { e },
+                ]
+            } else {
+                [e; 9]
+            };
+
+            for (n, rgb) in pixels.into_iter().enumerate() {
+                put(out, width, x * 3 + n % 3, y * 3 + n / 3, rgb);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::render::utils::palette::Palette;
+
+    use super::{scale2x, Image};
+
+    #[test]
+    fn test_scale2x_diagonal() {
+        // NOTE: 斜めの境界に接する角は削られる
+        //   # .      # # . .
+        //   . .  ->  # . . .
+        //            . . . .
+        //            . . . .
+        let palette = Palette::default();
+        let data = [0x30, 0x0D, 0x0D, 0x0D];
+        let src = Image::new(&data, &palette, 2, 2);
+        let (w, b) = (palette.get(0x30, 0), palette.get(0x0D, 0));
+
+        let mut out = vec![0; 4 * 4 * 3];
+        scale2x(&src, &mut out);
+
+        let pixel = |x: usize, y: usize| {
+            let base = (y * 4 + x) * 3;
+            (out[base], out[base + 1], out[base + 2])
+        };
+        assert_eq!(pixel(0, 0), w);
+        assert_eq!(pixel(1, 0), w);
+        assert_eq!(pixel(0, 1), w);
+        assert_eq!(pixel(1, 1), b);
+        assert_eq!(pixel(2, 0), b);
+    }
+}
@@ -0,0 +1,55 @@
+use super::{mix, put, to_yuv, Image, Rgb};
+
+fn distance(a: Rgb, b: Rgb) -> f32 {
+    let (ay, au, av) = to_yuv(a);
+    let (by, bu, bv) = to_yuv(b);
+    48.0 * (ay - by).abs() + 7.0 * (au - bu).abs() + 6.0 * (av - bv).abs()
+}
+
+// NOTE: Hyllian の 2xBR (level 1)。右下の角を基準に書き、(dx, dy) で反転して 4 隅に使う
+//          A1 B1 C1
+//       A0 A  B  C  C4
+//       D0 D  E  F  F4
+//       G0 G  H  I  I4
+//          G5 H5 I5
+fn corner(src: &Image, x: isize, y: isize, dx: isize, dy: isize) -> Rgb {
+    let p = |u: isize, v: isize| src.get(x + u * dx, y + v * dy);
+
+    let e = p(0, 0);
+    let (b, c, d, f) = (p(0, -1), p(1, -1), p(-1, 0), p(1, 0));
+    let (g, h, i) = (p(-1, 1), p(0, 1), p(1, 1));
+    let (f4, h5, i4, i5) = (p(2, 0), p(0, 2), p(2, 1), p(1, 2));
+
+    if e == f || e == h {
+        return e;
+    }
+
+    let edge =
+        distance(e, c) + distance(e, g) + distance(i, f4) + distance(i, h5) + 4.0 * distance(h, f);
+    let cross =
+        distance(h, d) + distance(h, i5) + distance(f, i4) + distance(f, b) + 4.0 * distance(e, i);
+
+    if edge < cross {
+        let new_pixel = if distance(e, f) <= distance(e, h) {
+            f
+        } else {
+            h
+        };
+        mix(&[(e, 1), (new_pixel, 1)])
+    } else {
+        e
+    }
+}
+
+pub(super) fn xbr2x(src: &Image, out: &mut [u8]) {
+    let width = src.width * 2;
+
+    for y in 0..src.height {
+        for x in 0..src.width {
+            for (n, (dx, dy)) in [(-1, -1), (1, -1), (-1, 1), (1, 1)].into_iter().enumerate() {
+                let rgb = corner(src, x as isize, y as isize, dx, dy);
+                put(out, width, x * 2 + n % 2, y * 2 + n / 2, rgb);
+            }
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use utils::frame::Frame;
 
+pub mod filter;
 pub mod utils;
 
 pub trait Renderer {
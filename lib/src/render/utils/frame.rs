@@ -1,126 +1,177 @@
-use crate::ppu::PPU;
+use crate::{
+    ppu::{PatternFetchHook, PPU},
+    render::filter::Filter,
+};
 
-use super::{palette::SYSTEM_PALETTE, rect::Rect};
+use super::{palette::Palette, png::encode_png, rect::Rect};
 
+// NOTE: ピクセルは RGB ではなく emphasis << 6 | color の 9bit のパレット番号で保持し、
+//       表示側が必要なときに to_rgb / to_rgba で呼び出し側のバッファへ変換する
 pub struct Frame {
-    pub data: Vec<u8>,
+    pub palette_indices: Vec<u16>,
+    palette: Palette,
 }
 
 impl Frame {
-    const WIDTH: usize = 256;
-    const HEIGHT: usize = 240;
+    pub const WIDTH: usize = 256;
+    pub const HEIGHT: usize = 240;
 
     pub fn new() -> Self {
         Self {
-            data: vec![0; Self::WIDTH * Self::HEIGHT * 3],
+            palette_indices: vec![0; Self::WIDTH * Self::HEIGHT],
+            palette: Palette::default(),
         }
     }
 
-    pub fn set_pixel(&mut self, x: usize, y: usize, rgb: (u8, u8, u8)) {
-        let base = y * 3 * Self::WIDTH + x * 3;
-        if base + 2 < self.data.len() {
-            self.data[base] = rgb.0;
-            self.data[base + 1] = rgb.1;
-            self.data[base + 2] = rgb.2;
+    pub fn palette(&self) -> &Palette {
+        &self.palette
+    }
+
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: u8, emphasis: u8) {
+        let idx = y * Self::WIDTH + x;
+        if idx < self.palette_indices.len() {
+            self.palette_indices[idx] = ((emphasis as u16 & 0x07) << 6) | (color as u16 & 0x3F);
         }
     }
 
     pub fn get_pixel(&self, x: usize, y: usize) -> (u8, u8, u8) {
-        let base = y * 3 * Self::WIDTH + x * 3;
-        if base + 2 >= self.data.len() {
+        let idx = y * Self::WIDTH + x;
+        if idx >= self.palette_indices.len() {
             panic!("out of range");
         }
 
-        (self.data[base], self.data[base + 1], self.data[base + 2])
+        self.palette.lookup(self.palette_indices[idx])
+    }
+
+    pub fn to_rgb(&self, out: &mut [u8]) {
+        for (rgb, &index) in out.chunks_exact_mut(3).zip(&self.palette_indices) {
+            let (r, g, b) = self.palette.lookup(index);
+            rgb.copy_from_slice(&[r, g, b]);
+        }
+    }
+
+    // NOTE: フィルタを通した解像度で PNG にする。Filter::None なら 256x240
+    pub fn to_png(&self, filter: Filter) -> Vec<u8> {
+        let mut rgb = vec![];
+        filter.apply(self, &mut rgb);
+        let (width, height) = filter.output_size();
+        encode_png(&rgb, width, height)
+    }
+
+    pub fn to_rgba(&self, out: &mut [u8]) {
+        for (rgba, &index) in out.chunks_exact_mut(4).zip(&self.palette_indices) {
+            let (r, g, b) = self.palette.lookup(index);
+            rgba.copy_from_slice(&[r, g, b, 0xFF]);
+        }
     }
 
-    pub fn render(&mut self, ppu: &PPU) {
+    // NOTE: パターンテーブルは実機のフェッチ順に読む。スキャンラインごとに背景を左から読み、続けてそのラインに
+    //       かかるスプライトを OAM の順に 8 個まで読む。読んだタイルでバンクを切り替えるマッパーのために、読むたびに on_fetch を呼ぶ。
+    //       フレームは NMI の立ち上がりでまとめて描くので、CPU がフレームの途中で書いたバンクの切り替えは反映されない
+    pub fn render(&mut self, ppu: &mut PPU, on_fetch: &mut PatternFetchHook) {
+        // NOTE: 背景で覆われない領域はバックドロップ色になる
+        let backdrop = ppu.get_backdrop_color() as u16 & 0x3F;
+        let backdrop = ((ppu.get_emphasis() as u16) << 6) | backdrop;
+        self.palette_indices.fill(backdrop);
+
         let (scroll_x, scroll_y) = ppu.get_scroll();
+        let (scroll_x, scroll_y) = (scroll_x as usize, scroll_y as usize);
 
         let (main_name_table, sub_name_table) = ppu.get_name_table();
+        let (main_name_table, sub_name_table) = (main_name_table.to_vec(), sub_name_table.to_vec());
+        let main_view_port = Rect::new(scroll_x, scroll_y, 256, 240);
+        let sub_view_port = Rect::new(0, 0, scroll_x, 240);
 
-        self.render_bg(
-            ppu,
-            main_name_table,
-            Rect::new(scroll_x as usize, scroll_y as usize, 256, 240),
-            -(scroll_x as isize),
-            -(scroll_y as isize),
-        );
-        self.render_bg(
-            ppu,
-            sub_name_table,
-            Rect::new(0, 0, scroll_x as usize, 240),
-            (256 - scroll_x as usize) as isize,
-            0,
-        );
-        self.render_sprite(ppu);
+        let oam_data = *ppu.get_oam_data();
+        let mut sprite_rows: SpriteRows = [[None; 8]; 64];
+
+        for y in 0..Self::HEIGHT {
+            self.render_bg_line(
+                ppu,
+                on_fetch,
+                &main_name_table,
+                &main_view_port,
+                y + scroll_y,
+                (-(scroll_x as isize), -(scroll_y as isize)),
+            );
+            self.render_bg_line(
+                ppu,
+                on_fetch,
+                &sub_name_table,
+                &sub_view_port,
+                y,
+                ((256 - scroll_x) as isize, 0),
+            );
+            fetch_sprite_rows(ppu, on_fetch, &oam_data, y, &mut sprite_rows);
+        }
+        self.render_sprite(ppu, &oam_data, &sprite_rows);
     }
 
-    fn render_bg(
+    fn render_bg_line(
         &mut self,
-        ppu: &PPU,
+        ppu: &mut PPU,
+        on_fetch: &mut PatternFetchHook,
         name_table: &[u8],
-        view_port: Rect,
-        shift_x: isize,
-        shift_y: isize,
+        view_port: &Rect,
+        pixel_y: usize,
+        (shift_x, shift_y): (isize, isize),
     ) {
         let bank = ppu.background_pattern_addr();
+        let emphasis = ppu.get_emphasis();
         let attr_table = &name_table[0x3C0..0x400];
+        let tile_row = pixel_y / 8;
+
+        for tile_column in 0..32 {
+            // NOTE: 画面に出ないタイルは読まない
+            if !(0..=7).any(|x| view_port.with_in(tile_column * 8 + x, pixel_y)) {
+                continue;
+            }
 
-        for i in 0..0x3C0 {
-            let tile_column = i % 32;
-            let tile_row = i / 32;
-            let tile_idx = name_table[i] as u16;
-            // TODO: ↓ get_sprite_tile じゃないほうが良さそう
-            let tile = ppu.get_sprite_tile(bank, tile_idx);
-            let palette = ppu.get_bg_palette(attr_table, tile_column, tile_row);
+            let tile_idx = name_table[tile_row * 32 + tile_column] as u16;
+            let (mut upper, mut lower) =
+                ppu.fetch_pattern_row(bank + tile_idx * 16 + (pixel_y % 8) as u16, on_fetch);
+            let bg_palette = ppu.get_bg_palette(attr_table, tile_column, tile_row);
 
-            for y in 0..=7 {
-                let mut upper = tile[y];
-                let mut lower = tile[y + 8];
+            for x in (0..=7).rev() {
+                let value = (1 & lower) << 1 | (1 & upper);
+                upper = upper >> 1;
+                lower = lower >> 1;
+                let color = bg_palette[value as usize];
 
-                for x in (0..=7).rev() {
-                    let value = (1 & lower) << 1 | (1 & upper);
-                    upper = upper >> 1;
-                    lower = lower >> 1;
-                    let palette_idx = palette[value as usize] as usize;
-                    let rgb = SYSTEM_PALETTE[palette_idx];
-
-                    let pixel_x = tile_column * 8 + x;
-                    let pixel_y = tile_row * 8 + y;
-
-                    if view_port.with_in(pixel_x, pixel_y) {
-                        self.set_pixel(
-                            (shift_x + pixel_x as isize) as usize,
-                            (shift_y + pixel_y as isize) as usize,
-                            rgb,
-                        );
-                    }
+                let pixel_x = tile_column * 8 + x;
+
+                if view_port.with_in(pixel_x, pixel_y) {
+                    self.set_pixel(
+                        (shift_x + pixel_x as isize) as usize,
+                        (shift_y + pixel_y as isize) as usize,
+                        color,
+                        emphasis,
+                    );
                 }
             }
         }
     }
 
-    fn render_sprite(&mut self, ppu: &PPU) {
-        let oam_data = ppu.get_oam_data();
+    fn render_sprite(&mut self, ppu: &PPU, oam_data: &[u8; 256], sprite_rows: &SpriteRows) {
+        let emphasis = ppu.get_emphasis();
 
         for i in (0..oam_data.len()).step_by(4).rev() {
-            let idx = oam_data[i + 1] as u16;
             let tile_x = oam_data[i + 3] as usize;
             let tile_y = oam_data[i] as usize;
             let attr = oam_data[i + 2];
 
-            let flip_vertical = attr & 0x80 != 0;
             let flip_horizontal = attr & 0x40 != 0;
             let palette_idx = attr & 0b11;
             let sprite_palette = ppu.get_sprite_palette(palette_idx);
 
-            let bank = ppu.sprite_pattern_addr();
-            let tile = ppu.get_sprite_tile(bank, idx);
-
-            for y in 0..=7 {
-                let mut upper = tile[y];
-                let mut lower = tile[y + 8];
+            for (y, row) in sprite_rows[i / 4].iter().enumerate() {
+                let Some((mut upper, mut lower)) = *row else {
+                    continue;
+                };
 
                 for x in (0..=7).rev() {
                     let value = (1 & lower) << 1 | (1 & upper);
@@ -131,16 +182,99 @@ impl Frame {
                         continue;
                     }
 
-                    let palette_idx = sprite_palette[value as usize] as usize;
-                    let rgb = SYSTEM_PALETTE[palette_idx];
-                    match (flip_horizontal, flip_vertical) {
-                        (false, false) => self.set_pixel(tile_x + x, tile_y + y, rgb),
-                        (true, false) => self.set_pixel(tile_x + 7 - x, tile_y + y, rgb),
-                        (false, true) => self.set_pixel(tile_x + x, tile_y + 7 - y, rgb),
-                        (true, true) => self.set_pixel(tile_x + 7 - x, tile_y + 7 - y, rgb),
-                    }
+                    let color = sprite_palette[value as usize];
+                    let pixel_x = if flip_horizontal {
+                        tile_x + 7 - x
+                    } else {
+                        tile_x + x
+                    };
+                    self.set_pixel(pixel_x, tile_y + y, color, emphasis);
                 }
             }
         }
     }
 }
+
+// NOTE: スプライトごとに、画面上の各行に出すパターン (上下反転済み)。画面外の行や、1 ラインに 9 個目以降のスプライトは読まないので None
+type SpriteRows = [[Option<(u8, u8)>; 8]; 64];
+
+const SPRITES_PER_LINE: usize = 8;
+
+fn fetch_sprite_rows(
+    ppu: &mut PPU,
+    on_fetch: &mut PatternFetchHook,
+    oam_data: &[u8; 256],
+    pixel_y: usize,
+    sprite_rows: &mut SpriteRows,
+) {
+    let bank = ppu.sprite_pattern_addr();
+
+    let sprites = oam_data
+        .chunks_exact(4)
+        .enumerate()
+        .filter(|(_, sprite)| (sprite[0] as usize..sprite[0] as usize + 8).contains(&pixel_y))
+        .take(SPRITES_PER_LINE)
+        .collect::<Vec<_>>();
+
+    for &(i, sprite) in &sprites {
+        let row = pixel_y - sprite[0] as usize;
+        let flip_vertical = sprite[2] & 0x80 != 0;
+        let fine_y = if flip_vertical { 7 - row } else { row };
+        let addr = bank + sprite[1] as u16 * 16 + fine_y as u16;
+        sprite_rows[i][row] = Some(ppu.fetch_pattern_row(addr, on_fetch));
+    }
+
+    // NOTE: 空いたスロットも実機は Y とタイル番号が $FF のスプライトとして読む。マッパーが数えるので読み捨てる
+    let fine_y = (pixel_y as u16).wrapping_sub(0xFF) & 0x07;
+    for _ in sprites.len()..SPRITES_PER_LINE {
+        ppu.fetch_pattern_row(bank + 0xFF * 16 + fine_y, on_fetch);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Frame;
+    use crate::{ppu::PPU, rom::Mirroring};
+
+    #[test]
+    fn test_convert_palette_indices() {
+        let mut frame = Frame::new();
+        frame.set_pixel(0, 0, 0x16, 0);
+        frame.set_pixel(1, 0, 0x16, 0b001);
+
+        let red = frame.palette().get(0x16, 0);
+        let emphasized = frame.palette().get(0x16, 0b001);
+        assert_eq!(frame.palette_indices[1], 0x40 | 0x16);
+        assert_eq!(frame.get_pixel(1, 0), emphasized);
+
+        let mut rgb = vec![0; Frame::WIDTH * Frame::HEIGHT * 3];
+        frame.to_rgb(&mut rgb);
+        assert_eq!(&rgb[0..3], &[red.0, red.1, red.2]);
+
+        let mut rgba = vec![0; Frame::WIDTH * Frame::HEIGHT * 4];
+        frame.to_rgba(&mut rgba);
+        assert_eq!(
+            &rgba[4..8],
+            &[emphasized.0, emphasized.1, emphasized.2, 0xFF]
+        );
+    }
+
+    #[test]
+    fn test_pattern_fetch_order() {
+        let mut ppu = PPU::new(vec![0; 0x2000], false, Mirroring::Horizontal);
+        let mut fetched = vec![];
+        Frame::new().render(&mut ppu, &mut |addr| {
+            fetched.push(addr);
+            None
+        });
+
+        // NOTE: OAM が全部 0 なので、最初の 8 ラインは背景 32 タイルのあとに先頭のスプライト 8 個を読む。
+        //       それ以降のラインはスプライトがないので、代わりにタイル $FF を 8 回読む
+        let line = |addr: u16, tiles: usize| [addr, addr + 8].repeat(tiles);
+        assert_eq!(fetched[..64], line(0, 32));
+        assert_eq!(fetched[64..80], line(0, 8));
+        assert_eq!(fetched[80..144], line(1, 32));
+        assert_eq!(fetched[8 * 80 + 64..9 * 80], line(0xFF1, 8));
+        assert_eq!(fetched.len(), 240 * 80);
+    }
+}
@@ -1,3 +1,4 @@
 pub mod frame;
-pub(super) mod palette;
+pub mod palette;
+pub mod png;
 pub(super) mod rect;
@@ -1,3 +1,5 @@
+use std::f32::consts::PI;
+
 pub static SYSTEM_PALETTE: [(u8, u8, u8); 64] = [
     (0x80, 0x80, 0x80),
     (0x00, 0x3D, 0xA6),
@@ -64,3 +66,262 @@ pub static SYSTEM_PALETTE: [(u8, u8, u8); 64] = [
     (0x11, 0x11, 0x11),
     (0x11, 0x11, 0x11),
 ];
+
+// NOTE: RP2C03 (RGB PPU) の 3bit/ch の値
+static RGB_PPU_PALETTE: [u16; 64] = [
+    0o333, 0o014, 0o006, 0o326, 0o403, 0o503, 0o510, 0o420, 0o320, 0o120, 0o031, 0o040, 0o022,
+    0o000, 0o000, 0o000, 0o555, 0o036, 0o027, 0o407, 0o507, 0o704, 0o700, 0o630, 0o430, 0o140,
+    0o040, 0o053, 0o044, 0o000, 0o000, 0o000, 0o777, 0o357, 0o447, 0o637, 0o707, 0o737, 0o740,
+    0o750, 0o660, 0o360, 0o070, 0o276, 0o077, 0o000, 0o000, 0o000, 0o777, 0o567, 0o657, 0o757,
+    0o747, 0o755, 0o764, 0o772, 0o773, 0o572, 0o473, 0o276, 0o467, 0o000, 0o000, 0o000,
+];
+
+const COLORS: usize = 64;
+const EMPHASIS_VARIANTS: usize = 8;
+const EMPHASIS_ATTENUATION: f32 = 0.746;
+
+pub const BUILTIN_PALETTES: [&str; 3] = ["default", "2c03", "composite"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+    colors: Vec<(u8, u8, u8)>,
+}
+
+impl Palette {
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "default" => Some(Self::default()),
+            "2c03" => Some(Self::rgb_ppu()),
+            "composite" => Some(Self::composite()),
+            _ => None,
+        }
+    }
+
+    // NOTE: 組み込みパレットの名前か .pal ファイルのパス
+    pub fn load(name: &str) -> Result<Self, String> {
+        if let Some(palette) = Self::builtin(name) {
+            return Ok(palette);
+        }
+
+        let data = std::fs::read(name).map_err(|e| format!("{}: {}", name, e))?;
+        Self::from_pal(&data)
+    }
+
+    pub fn from_pal(data: &[u8]) -> Result<Self, String> {
+        let colors = data
+            .chunks_exact(3)
+            .map(|rgb| (rgb[0], rgb[1], rgb[2]))
+            .collect::<Vec<_>>();
+
+        match data.len() {
+            192 => Ok(Self::with_attenuated_emphasis(&colors)),
+            1536 => Ok(Self { colors }),
+            len => Err(format!(
+                "Invalid palette size: {} bytes (expected 192 or 1536)",
+                len
+            )),
+        }
+    }
+
+    // NOTE: emphasis は PPUMASK の bit5-7 を 0-7 にしたもの (bit0: R, bit1: G, bit2: B)
+    pub fn get(&self, color: u8, emphasis: u8) -> (u8, u8, u8) {
+        let idx = (emphasis as usize & 0x07) * COLORS + (color as usize & 0x3F);
+        self.colors[idx]
+    }
+
+    // NOTE: Frame が保持する emphasis << 6 | color の 9bit をそのまま引く
+    pub fn lookup(&self, index: u16) -> (u8, u8, u8) {
+        self.colors[index as usize & 0x1FF]
+    }
+
+    fn with_attenuated_emphasis(base: &[(u8, u8, u8)]) -> Self {
+        let attenuate = |value: u8, attenuated: bool| {
+            if attenuated {
+                (value as f32 * EMPHASIS_ATTENUATION) as u8
+            } else {
+                value
+            }
+        };
+
+        let mut colors = Vec::with_capacity(COLORS * EMPHASIS_VARIANTS);
+        for emphasis in 0..EMPHASIS_VARIANTS {
+            // NOTE: 強調されていないチャンネルが減衰する
+            let (r, g, b) = (emphasis & 0b001, emphasis & 0b010, emphasis & 0b100);
+            for &(red, green, blue) in base {
+                colors.push((
+                    attenuate(red, g != 0 || b != 0),
+                    attenuate(green, r != 0 || b != 0),
+                    attenuate(blue, r != 0 || g != 0),
+                ));
+            }
+        }
+
+        Self { colors }
+    }
+
+    fn rgb_ppu() -> Self {
+        let level = |value: u16| (value as u32 * 255 / 7) as u8;
+
+        let mut colors = Vec::with_capacity(COLORS * EMPHASIS_VARIANTS);
+        for emphasis in 0..EMPHASIS_VARIANTS {
+            // NOTE: RGB PPU の emphasis は減衰ではなく該当チャンネルを最大値にする
+            for &rgb in RGB_PPU_PALETTE.iter() {
+                let r = if emphasis & 0b001 != 0 { 7 } else { rgb >> 6 };
+                let g = if emphasis & 0b010 != 0 {
+                    7
+                } else {
+                    (rgb >> 3) & 0x07
+                };
+                let b = if emphasis & 0b100 != 0 { 7 } else { rgb & 0x07 };
+                colors.push((level(r), level(g), level(b)));
+            }
+        }
+
+        Self { colors }
+    }
+
+    fn composite() -> Self {
+        let mut colors = Vec::with_capacity(COLORS * EMPHASIS_VARIANTS);
+        for pixel in 0..(COLORS * EMPHASIS_VARIANTS) as u16 {
+            colors.push(decode_composite(pixel));
+        }
+
+        Self { colors }
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::with_attenuated_emphasis(&SYSTEM_PALETTE)
+    }
+}
+
+// NOTE: 2C02 の出力電圧 (V)。前半 4 つが low、後半 4 つが high
+const SIGNAL_LEVELS: [f32; 8] = [0.350, 0.518, 0.962, 1.550, 1.094, 1.506, 1.962, 1.962];
+const SIGNAL_BLACK: f32 = 0.518;
+const SIGNAL_WHITE: f32 = 1.962;
+
+// 9bit のピクセル値 (emphasis << 6 | color) から 12 位相分のうち 1 つの信号レベルを求める
+pub(crate) fn composite_signal(pixel: u16, phase: usize) -> f32 {
+    let color = (pixel & 0x0F) as usize;
+    let level = if color > 0x0D {
+        1
+    } else {
+        ((pixel >> 4) & 0x03) as usize
+    };
+    let emphasis = pixel >> 6;
+
+    let low = SIGNAL_LEVELS[level + if color == 0x00 { 4 } else { 0 }];
+    let high = SIGNAL_LEVELS[level + if color < 0x0D { 4 } else { 0 }];
+
+    let in_color_phase = |color: usize| (color + phase) % 12 < 6;
+    let signal = if in_color_phase(color) { high } else { low };
+
+    let attenuated = (emphasis & 0b001 != 0 && in_color_phase(0))
+        || (emphasis & 0b010 != 0 && in_color_phase(4))
+        || (emphasis & 0b100 != 0 && in_color_phase(8));
+    let signal = if attenuated {
+        signal * EMPHASIS_ATTENUATION
+    } else {
+        signal
+    };
+
+    (signal - SIGNAL_BLACK) / (SIGNAL_WHITE - SIGNAL_BLACK)
+}
+
+pub(crate) fn yiq_to_rgb(y: f32, i: f32, q: f32) -> (u8, u8, u8) {
+    let clamp = |value: f32| (value * 255.0).clamp(0.0, 255.0) as u8;
+    (
+        clamp(y + 0.946882 * i + 0.623557 * q),
+        clamp(y - 0.274788 * i - 0.635691 * q),
+        clamp(y - 1.108545 * i + 1.709007 * q),
+    )
+}
+
+pub(crate) fn color_carrier(phase: f32) -> (f32, f32) {
+    let angle = PI * (phase + 4.0) / 6.0;
+    (angle.cos(), angle.sin())
+}
+
+fn decode_composite(pixel: u16) -> (u8, u8, u8) {
+    let (mut y, mut i, mut q) = (0.0, 0.0, 0.0);
+    for phase in 0..12 {
+        let signal = composite_signal(pixel, phase) / 12.0;
+        let (cos, sin) = color_carrier(phase as f32);
+        y += signal;
+        i += signal * cos;
+        q += signal * sin;
+    }
+
+    yiq_to_rgb(y, i, q)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Palette, BUILTIN_PALETTES, SYSTEM_PALETTE};
+
+    #[test]
+    fn test_builtin() {
+        for name in BUILTIN_PALETTES {
+            let palette = Palette::builtin(name).unwrap();
+            assert_eq!(palette.get(0x0D, 0), (0, 0, 0), "{}", name);
+        }
+        assert!(Palette::builtin("unknown").is_none());
+    }
+
+    #[test]
+    fn test_load() {
+        assert_eq!(Palette::load("2C03"), Ok(Palette::rgb_ppu()));
+
+        let path = std::env::temp_dir().join("sen-test-load.pal");
+        std::fs::write(&path, vec![0x80; 192]).unwrap();
+        let palette = Palette::load(path.to_str().unwrap()).unwrap();
+        assert_eq!(palette.get(0x00, 0), (0x80, 0x80, 0x80));
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(Palette::load("missing.pal").is_err());
+    }
+
+    #[test]
+    fn test_from_pal_192() {
+        let data = SYSTEM_PALETTE
+            .iter()
+            .flat_map(|&(r, g, b)| [r, g, b])
+            .collect::<Vec<_>>();
+        let palette = Palette::from_pal(&data).unwrap();
+
+        assert_eq!(palette, Palette::default());
+        assert_eq!(palette.get(0x30, 0), (0xFF, 0xFF, 0xFF));
+        // NOTE: 赤を強調すると緑と青が減衰する
+        assert_eq!(palette.get(0x30, 0b001), (0xFF, 0xBE, 0xBE));
+    }
+
+    #[test]
+    fn test_from_pal_1536() {
+        let mut data = vec![0; 1536];
+        data[64 * 3 * 5 + 0x21 * 3..][..3].copy_from_slice(&[1, 2, 3]);
+        let palette = Palette::from_pal(&data).unwrap();
+
+        assert_eq!(palette.get(0x21, 0b101), (1, 2, 3));
+        assert_eq!(palette.get(0x21, 0), (0, 0, 0));
+    }
+
+    #[test]
+    fn test_from_pal_invalid_size() {
+        assert!(Palette::from_pal(&[0; 100]).is_err());
+    }
+
+    #[test]
+    fn test_composite() {
+        let palette = Palette::builtin("composite").unwrap();
+
+        let (r, g, b) = palette.get(0x16, 0);
+        assert!(r > g && r > b);
+        let (r, g, b) = palette.get(0x1A, 0);
+        assert!(g > r && g > b);
+        let (r, g, b) = palette.get(0x12, 0);
+        assert!(b > r && b > g);
+        let (r, g, b) = palette.get(0x30, 0);
+        assert!(r > 0xF0 && g > 0xF0 && b > 0xF0);
+    }
+}
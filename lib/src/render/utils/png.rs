@@ -0,0 +1,53 @@
+// NOTE: RGB 8bit のバッファを PNG にする。Vec への書き込みなので失敗するのはサイズが合わないときだけ
+pub fn encode_png(rgb: &[u8], width: usize, height: usize) -> Vec<u8> {
+    assert_eq!(rgb.len(), width * height * 3, "buffer size mismatch");
+
+    let mut out = vec![];
+    let mut encoder = png::Encoder::new(&mut out, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header().unwrap();
+    writer.write_image_data(rgb).unwrap();
+    writer.finish().unwrap();
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use crate::render::{filter::Filter, utils::frame::Frame};
+
+    use super::encode_png;
+
+    fn decode(data: &[u8]) -> (u32, u32, Vec<u8>) {
+        let decoder = png::Decoder::new(data);
+        let mut reader = decoder.read_info().unwrap();
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf).unwrap();
+        buf.truncate(info.buffer_size());
+        (info.width, info.height, buf)
+    }
+
+    #[test]
+    fn test_encode_png() {
+        let rgb = [0xFF, 0x00, 0x00, 0x00, 0xFF, 0x00];
+        let (width, height, data) = decode(&encode_png(&rgb, 2, 1));
+
+        assert_eq!((width, height), (2, 1));
+        assert_eq!(data, rgb);
+    }
+
+    #[test]
+    fn test_frame_to_png() {
+        let mut frame = Frame::new();
+        frame.set_pixel(1, 0, 0x16, 0);
+
+        let (width, height, data) = decode(&frame.to_png(Filter::None));
+        assert_eq!((width, height), (256, 240));
+        let (r, g, b) = frame.get_pixel(1, 0);
+        assert_eq!(&data[3..6], &[r, g, b]);
+
+        let (width, height, _) = decode(&frame.to_png(Filter::Scale2x));
+        assert_eq!((width, height), (512, 480));
+    }
+}
@@ -1,12 +1,17 @@
+use crate::region::Region;
+
 const NES_TAG: &[u8] = &[0x4E, 0x45, 0x53, 0x1A];
 const PRG_ROM_PAGE_SIZE: usize = 16 * 1024;
 const CHR_ROM_PAGE_SIZE: usize = 8 * 1024;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum Mirroring {
     Horizontal,
     Vertical,
     FourScreen,
+    // NOTE: マッパーが切り替える 1 画面ミラーリング。Lower は VRAM の前半 1KB、Upper は後半 1KB
+    SingleScreenLower,
+    SingleScreenUpper,
 }
 
 pub struct Rom {
@@ -14,7 +19,10 @@ pub struct Rom {
     pub chr_rom: Vec<u8>,
     pub is_chr_ram: bool,
     pub mapper: u8,
+    // NOTE: NES 2.0 ヘッダーのサブマッパー。iNES では 0
+    pub submapper: u8,
     pub screen_mirroring: Mirroring,
+    pub region: Option<Region>,
 }
 
 impl Rom {
@@ -33,6 +41,19 @@ impl Rom {
             (false, false) => Mirroring::Horizontal,
         };
 
+        let is_nes2 = raw[7] & 0x0C == 0x08;
+        let submapper = if is_nes2 { raw[8] >> 4 } else { 0 };
+        let region = if is_nes2 {
+            match raw[12] & 0x03 {
+                0 | 2 => Some(Region::Ntsc),
+                1 => Some(Region::Pal),
+                3 => Some(Region::Dendy),
+                _ => unreachable!(),
+            }
+        } else {
+            None
+        };
+
         let prg_rom_size = raw[4] as usize * PRG_ROM_PAGE_SIZE;
         let chr_rom_size: usize = raw[5] as usize * CHR_ROM_PAGE_SIZE;
 
@@ -52,7 +73,9 @@ impl Rom {
             chr_rom,
             is_chr_ram: chr_rom_size == 0,
             mapper,
+            submapper,
             screen_mirroring,
+            region,
         })
     }
 }
@@ -67,7 +90,7 @@ mod tests {
         let mut rom = vec![
             0x4E, 0x45, 0x53, 0x1A, // NES\x1A
             0x01, // PRG-ROM サイズ (1ページ = 16KB)
-            0x00, // CHR-ROM サイズ (0ページ = キャラクターデータなし)
+            0x01, // CHR-ROM サイズ (1ページ = 8KB)
             0x00, // フラグ1
             0x00, // フラグ2
         ];
@@ -81,6 +104,7 @@ mod tests {
         program.resize(16 * 1024, 0x00);
 
         rom.extend(program);
+        rom.extend(vec![0x00; 8 * 1024]);
 
         rom
     });
@@ -89,8 +113,32 @@ mod tests {
     fn test_rom_new() {
         let rom = Rom::new(&TEST_ROM_DATA).unwrap();
         assert_eq!(rom.prg_rom.len(), 16 * 1024);
-        assert_eq!(rom.chr_rom.len(), 0);
+        assert_eq!(rom.chr_rom.len(), 8 * 1024);
+        assert!(!rom.is_chr_ram);
         assert_eq!(rom.mapper, 0);
         assert_eq!(rom.screen_mirroring, Mirroring::Horizontal);
+        assert_eq!(rom.region, None);
+    }
+
+    #[test]
+    fn test_rom_nes2_region() {
+        let mut raw = vec![
+            0x4E, 0x45, 0x53, 0x1A, // NES\x1A
+            0x01, // PRG-ROM サイズ
+            0x00, // CHR-ROM サイズ
+            0x00, // フラグ1
+            0x08, // フラグ2 (NES 2.0)
+        ];
+        raw.resize(16 + 16 * 1024, 0x00);
+
+        raw[12] = 0x01;
+        assert_eq!(Rom::new(&raw).unwrap().region, Some(Region::Pal));
+
+        raw[12] = 0x03;
+        assert_eq!(Rom::new(&raw).unwrap().region, Some(Region::Dendy));
+
+        // NOTE: マルチリージョンは NTSC として扱う
+        raw[12] = 0x02;
+        assert_eq!(Rom::new(&raw).unwrap().region, Some(Region::Ntsc));
     }
 }
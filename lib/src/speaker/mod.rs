@@ -28,8 +28,42 @@ pub enum SpeakerEvent {
     TriangleNote {
         hz: f32,
     },
+    // NOTE: 拡張音源の出力を sample_rate で間引いた PCM。EXPANSION_CH に送る
+    ExpansionSamples {
+        sample_rate: u32,
+        samples: Vec<f32>,
+    },
 }
 
+pub const EXPANSION_CH: u8 = 6;
+
 pub trait Speaker {
     fn send(&self, ch: u8, event: SpeakerEvent);
 }
+
+#[cfg(test)]
+pub(crate) mod test {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::{Speaker, SpeakerEvent};
+
+    type Sent = Vec<(u8, Vec<f32>)>;
+
+    // NOTE: 送られてきた拡張音源の PCM を (ch, サンプル) の形で貯める
+    #[derive(Clone, Default)]
+    pub(crate) struct ExpansionSpeaker(Rc<RefCell<Sent>>);
+
+    impl ExpansionSpeaker {
+        pub(crate) fn sent(&self) -> Sent {
+            self.0.borrow().clone()
+        }
+    }
+
+    impl Speaker for ExpansionSpeaker {
+        fn send(&self, ch: u8, event: SpeakerEvent) {
+            if let SpeakerEvent::ExpansionSamples { samples, .. } = event {
+                self.0.borrow_mut().push((ch, samples));
+            }
+        }
+    }
+}
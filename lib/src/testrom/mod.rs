@@ -0,0 +1,360 @@
+use std::fmt::Display;
+
+use crate::{
+    debugger::{RunMode, StopReason},
+    emulator::Emulator,
+    joypad::{button::JoypadButton, register::Joypad, JoypadHandler},
+    render::{utils::frame::Frame, Renderer},
+    speaker::{Speaker, SpeakerEvent},
+};
+
+// NOTE: blargg 系のテスト ROM は $6000 に状態、$6001-$6003 に署名、$6004 から NUL 終端のメッセージを書く
+const STATUS: u16 = 0x6000;
+const SIGNATURE: u16 = 0x6001;
+const MESSAGE: u16 = 0x6004;
+const MESSAGE_END: u16 = 0x7FFF;
+const SIGNATURE_BYTES: [u8; 3] = [0xDE, 0xB0, 0x61];
+
+const RUNNING: u8 = 0x80;
+const NEEDS_RESET: u8 = 0x81;
+// NOTE: リセット要求から 100ms 以上待ってからリセットする
+const RESET_DELAY_FRAMES: usize = 6;
+const INSTRUCTIONS_PER_FRAME_LIMIT: usize = 100_000;
+
+pub const DEFAULT_TIMEOUT_FRAMES: usize = 60 * 60;
+
+pub struct NullSpeaker;
+
+impl Speaker for NullSpeaker {
+    fn send(&self, _ch: u8, _event: SpeakerEvent) {}
+}
+
+pub struct NullJoypadHandler;
+
+impl JoypadHandler for NullJoypadHandler {
+    fn handle(&mut self, _joypad: &mut Joypad) {}
+}
+
+pub struct NullRenderer;
+
+impl Renderer for NullRenderer {
+    fn render(&mut self, _frame: &Frame) {}
+}
+
+// NOTE: (フレーム番号, ボタン) の列。次の入力までそのボタンを押し続ける
+pub type InputScript = Vec<(usize, JoypadButton)>;
+
+pub struct ScriptedJoypadHandler {
+    script: InputScript,
+    frame: usize,
+}
+
+impl ScriptedJoypadHandler {
+    pub fn new(mut script: InputScript) -> Self {
+        script.sort_by_key(|(frame, _)| *frame);
+        Self { script, frame: 0 }
+    }
+}
+
+impl JoypadHandler for ScriptedJoypadHandler {
+    fn handle(&mut self, joypad: &mut Joypad) {
+        let buttons = self
+            .script
+            .iter()
+            .take_while(|(frame, _)| *frame <= self.frame)
+            .last()
+            .map_or(JoypadButton::empty(), |(_, buttons)| *buttons);
+        joypad.set_button_pressed(JoypadButton::all(), false);
+        joypad.set_button_pressed(buttons, true);
+        self.frame += 1;
+    }
+}
+
+// NOTE: 最後に描画されたフレームを RGB で持っておく
+pub struct CaptureRenderer {
+    rgb: Vec<u8>,
+}
+
+impl Default for CaptureRenderer {
+    fn default() -> Self {
+        Self {
+            rgb: vec![0; Frame::WIDTH * Frame::HEIGHT * 3],
+        }
+    }
+}
+
+impl CaptureRenderer {
+    pub fn rgb(&self) -> &[u8] {
+        &self.rgb
+    }
+}
+
+impl Renderer for CaptureRenderer {
+    fn render(&mut self, frame: &Frame) {
+        frame.to_rgb(&mut self.rgb);
+    }
+}
+
+pub type HeadlessEmulator = Emulator<NullSpeaker, NullJoypadHandler, NullRenderer>;
+
+pub fn headless_emulator(rom_data: Vec<u8>) -> HeadlessEmulator {
+    Emulator::new(rom_data, NullSpeaker, NullJoypadHandler, NullRenderer)
+}
+
+// NOTE: frames フレーム実行した時点の画面を RGB で返す
+pub fn capture(rom_data: Vec<u8>, frames: usize, script: InputScript) -> Vec<u8> {
+    let mut emulator = Emulator::new(
+        rom_data,
+        NullSpeaker,
+        ScriptedJoypadHandler::new(script),
+        CaptureRenderer::default(),
+    );
+    emulator.reset();
+    while emulator.frame_count() < frames {
+        if emulator.run(RunMode::ToFrame, INSTRUCTIONS_PER_FRAME_LIMIT) == StopReason::Halted {
+            break;
+        }
+    }
+    emulator.renderer_mut().rgb().to_vec()
+}
+
+// NOTE: "30:START 35:- 90:A+RIGHT" の形式。"-" はすべて離す
+pub fn parse_input_script(s: &str) -> Result<InputScript, String> {
+    s.split_whitespace()
+        .map(|event| {
+            let (frame, buttons) = event
+                .split_once(':')
+                .ok_or_else(|| format!("Invalid input event: {}", event))?;
+            let frame = frame
+                .parse::<usize>()
+                .map_err(|_| format!("Invalid frame: {}", frame))?;
+            let buttons = match buttons {
+                "-" | "" => JoypadButton::empty(),
+                _ => buttons
+                    .split('+')
+                    .map(parse_button)
+                    .collect::<Result<JoypadButton, String>>()?,
+            };
+            Ok((frame, buttons))
+        })
+        .collect()
+}
+
+fn parse_button(s: &str) -> Result<JoypadButton, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "a" => Ok(JoypadButton::BUTTON_A),
+        "b" => Ok(JoypadButton::BUTTON_B),
+        "select" => Ok(JoypadButton::SELECT),
+        "start" => Ok(JoypadButton::START),
+        "up" => Ok(JoypadButton::UP),
+        "down" => Ok(JoypadButton::DOWN),
+        "left" => Ok(JoypadButton::LEFT),
+        "right" => Ok(JoypadButton::RIGHT),
+        _ => Err(format!("Unknown button: {}", s)),
+    }
+}
+
+// NOTE: FNV-1a。ゴールデンイメージの比較結果を短く表示するために使う
+pub fn frame_hash(rgb: &[u8]) -> u64 {
+    rgb.iter().fold(0xCBF2_9CE4_8422_2325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x0000_0100_0000_01B3)
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestStatus {
+    Passed,
+    Failed(u8),
+    Timeout,
+    Halted,
+}
+
+impl Display for TestStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TestStatus::Passed => write!(f, "passed"),
+            TestStatus::Failed(code) => write!(f, "failed (${:02X})", code),
+            TestStatus::Timeout => write!(f, "timed out"),
+            TestStatus::Halted => write!(f, "halted"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestOutcome {
+    pub status: TestStatus,
+    pub message: String,
+    pub frames: usize,
+}
+
+pub fn run(rom_data: Vec<u8>, timeout_frames: usize) -> TestOutcome {
+    let mut emulator = headless_emulator(rom_data);
+    emulator.reset();
+    run_emulator(&mut emulator, timeout_frames)
+}
+
+pub fn run_emulator<S, J, R>(emulator: &mut Emulator<S, J, R>, timeout_frames: usize) -> TestOutcome
+where
+    S: Speaker,
+    J: JoypadHandler,
+    R: Renderer,
+{
+    let mut reset_at = None;
+
+    let status = loop {
+        let frame = emulator.frame_count();
+        if frame >= timeout_frames {
+            break TestStatus::Timeout;
+        }
+        if emulator.run(RunMode::ToFrame, INSTRUCTIONS_PER_FRAME_LIMIT) == StopReason::Halted {
+            break TestStatus::Halted;
+        }
+
+        // NOTE: 署名が書かれるまでは $6000 の値は意味を持たない
+        match read_status(emulator) {
+            None | Some(RUNNING) => {}
+            Some(NEEDS_RESET) => {
+                let at = *reset_at.get_or_insert(frame + RESET_DELAY_FRAMES);
+                if frame >= at {
+                    emulator.reset();
+                    reset_at = None;
+                }
+            }
+            Some(0) => break TestStatus::Passed,
+            Some(code) => break TestStatus::Failed(code),
+        }
+    };
+
+    TestOutcome {
+        status,
+        message: read_message(emulator),
+        frames: emulator.frame_count(),
+    }
+}
+
+fn read_status<S, J, R>(emulator: &Emulator<S, J, R>) -> Option<u8>
+where
+    S: Speaker,
+    J: JoypadHandler,
+    R: Renderer,
+{
+    let signature = [0, 1, 2].map(|i| emulator.peek(SIGNATURE + i));
+    (signature == SIGNATURE_BYTES).then(|| emulator.peek(STATUS))
+}
+
+fn read_message<S, J, R>(emulator: &Emulator<S, J, R>) -> String
+where
+    S: Speaker,
+    J: JoypadHandler,
+    R: Renderer,
+{
+    if read_status(emulator).is_none() {
+        return String::new();
+    }
+
+    let bytes = (MESSAGE..=MESSAGE_END)
+        .map(|addr| emulator.peek(addr))
+        .take_while(|&b| b != 0)
+        .collect::<Vec<_>>();
+    String::from_utf8_lossy(&bytes).trim_end().to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use test_case::test_case;
+
+    use crate::joypad::{button::JoypadButton, register::Joypad, JoypadHandler};
+
+    use super::{
+        parse_input_script, run, ScriptedJoypadHandler, TestStatus, NEEDS_RESET, SIGNATURE,
+        SIGNATURE_BYTES, STATUS,
+    };
+
+    fn store(program: &mut Vec<u8>, addr: u16, value: u8) {
+        program.extend([0xA9, value, 0x8D, addr as u8, (addr >> 8) as u8]);
+    }
+
+    fn spin(program: &mut Vec<u8>) {
+        let addr = 0x8000 + program.len() as u16;
+        program.extend([0x4C, addr as u8, (addr >> 8) as u8]);
+    }
+
+    // NOTE: $6000 に結果を書いて止まるだけの ROM
+    fn test_rom(status: u8, needs_reset: bool) -> Vec<u8> {
+        let mut program = vec![];
+        for (i, b) in SIGNATURE_BYTES.iter().chain(b"Done\n\0").enumerate() {
+            store(&mut program, SIGNATURE + i as u16, *b);
+        }
+        if needs_reset {
+            // NOTE: RAM はリセットで消えないので、$0000 で 2 回目かどうかを見分ける
+            program.extend([0xAD, 0x00, 0x00, 0xD0, 0x0B, 0xEE, 0x00, 0x00]);
+            store(&mut program, STATUS, NEEDS_RESET);
+            spin(&mut program);
+        }
+        store(&mut program, STATUS, status);
+        spin(&mut program);
+
+        let mut raw = vec![0x4E, 0x45, 0x53, 0x1A, 0x01, 0x00, 0x00, 0x00];
+        raw.resize(16, 0x00);
+        let mut prg_rom = vec![0xEA; 0x4000];
+        prg_rom[..program.len()].copy_from_slice(&program);
+        prg_rom[0x3FFC] = 0x00;
+        prg_rom[0x3FFD] = 0x80;
+        raw.extend(prg_rom);
+        raw
+    }
+
+    #[test_case(0x00, false, TestStatus::Passed ; "passed")]
+    #[test_case(0x03, false, TestStatus::Failed(0x03) ; "failed")]
+    #[test_case(0x00, true, TestStatus::Passed ; "needs reset")]
+    fn test_run(status: u8, needs_reset: bool, expected: TestStatus) {
+        let outcome = run(test_rom(status, needs_reset), 60);
+
+        assert_eq!(outcome.status, expected);
+        assert_eq!(outcome.message, "Done");
+    }
+
+    #[test]
+    fn test_timeout() {
+        let mut raw = test_rom(0x00, false);
+        // NOTE: 先頭で無限ループさせて署名を書かせない
+        raw[16..19].copy_from_slice(&[0x4C, 0x00, 0x80]);
+
+        let outcome = run(raw, 10);
+        assert_eq!(outcome.status, TestStatus::Timeout);
+        assert_eq!(outcome.message, "");
+    }
+
+    #[test]
+    fn test_parse_input_script() {
+        assert_eq!(
+            parse_input_script("30:START 35:- 90:a+Right")
+                .unwrap()
+                .iter()
+                .map(|(frame, buttons)| (*frame, buttons.bits()))
+                .collect::<Vec<_>>(),
+            vec![(30, 0x08), (35, 0x00), (90, 0x81)]
+        );
+        assert_eq!(
+            parse_input_script("10:X").err(),
+            Some(String::from("Unknown button: X"))
+        );
+    }
+
+    #[test]
+    fn test_scripted_joypad() {
+        let mut handler = ScriptedJoypadHandler::new(vec![
+            (2, JoypadButton::empty()),
+            (1, JoypadButton::BUTTON_A),
+        ]);
+        let mut joypad = Joypad::new();
+
+        let mut pressed = vec![];
+        for _ in 0..3 {
+            handler.handle(&mut joypad);
+            joypad.write(1);
+            pressed.push(joypad.read());
+        }
+        assert_eq!(pressed, vec![0, 1, 0]);
+    }
+}
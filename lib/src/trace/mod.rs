@@ -0,0 +1,645 @@
+use std::{
+    collections::VecDeque,
+    fmt::Display,
+    fs::File,
+    io::{self, LineWriter, Write},
+    ops::RangeInclusive,
+    path::PathBuf,
+    str::FromStr,
+};
+
+use crate::{
+    cpu::{
+        addressing_mode::AddressingMode,
+        opecode::{Mnemonic, OPCODE_TABLE},
+    },
+    debugger::Registers,
+    disasm::{disassemble_with_symbols, Symbols},
+};
+
+const DEFAULT_CAPACITY: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TraceFormat {
+    #[default]
+    Nestest,
+    Fceux,
+    Mesen,
+}
+
+impl FromStr for TraceFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "nestest" => Ok(TraceFormat::Nestest),
+            "fceux" => Ok(TraceFormat::Fceux),
+            "mesen" => Ok(TraceFormat::Mesen),
+            _ => Err(format!("Unknown trace format: {}", s)),
+        }
+    }
+}
+
+impl Display for TraceFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            TraceFormat::Nestest => "nestest",
+            TraceFormat::Fceux => "fceux",
+            TraceFormat::Mesen => "mesen",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// NOTE: フロントエンド共通のトレース設定。clap フィーチャーでコマンドライン引数になる
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "clap", derive(clap::Args))]
+pub struct TraceOptions {
+    #[cfg_attr(
+        feature = "clap",
+        arg(long, help = "Write an instruction trace to this file")
+    )]
+    pub trace: Option<PathBuf>,
+    #[cfg_attr(
+        feature = "clap",
+        arg(
+            long,
+            default_value = "nestest",
+            help = "Trace format: nestest, fceux or mesen"
+        )
+    )]
+    pub trace_format: TraceFormat,
+    #[cfg_attr(
+        feature = "clap",
+        arg(
+            long,
+            value_parser = parse_pc_range,
+            help = "Only trace PCs in this range (e.g. 8000-80FF)"
+        )
+    )]
+    pub trace_pc: Option<RangeInclusive<u16>>,
+    #[cfg_attr(
+        feature = "clap",
+        arg(
+            long,
+            value_parser = parse_frame_range,
+            help = "Only trace frames in this range (e.g. 60-120)"
+        )
+    )]
+    pub trace_frames: Option<RangeInclusive<usize>>,
+}
+
+impl TraceOptions {
+    // NOTE: 終了時に process::exit で抜けるフロントエンドもあるので、バッファに残らないよう行ごとに書き出す
+    pub fn logger(&self) -> io::Result<Option<TraceLogger>> {
+        let Some(path) = &self.trace else {
+            return Ok(None);
+        };
+
+        let file = File::create(path)?;
+        let mut logger = TraceLogger::new(self.trace_format);
+        logger.set_writer(Some(Box::new(LineWriter::new(file))));
+        logger.set_pc_range(self.trace_pc.clone());
+        logger.set_frame_range(self.trace_frames.clone());
+        Ok(Some(logger))
+    }
+}
+
+// NOTE: 命令を実行する直前の状態
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct TraceState {
+    pub(crate) registers: Registers,
+    pub(crate) cycles: usize,
+    pub(crate) scanline: u16,
+    pub(crate) dot: usize,
+    pub(crate) frame: usize,
+}
+
+pub struct TraceLogger {
+    format: TraceFormat,
+    pc_range: Option<RangeInclusive<u16>>,
+    frame_range: Option<RangeInclusive<usize>>,
+    capacity: usize,
+    history: VecDeque<String>,
+    writer: Option<Box<dyn Write>>,
+    error: Option<String>,
+}
+
+impl TraceLogger {
+    pub fn new(format: TraceFormat) -> Self {
+        Self {
+            format,
+            pc_range: None,
+            frame_range: None,
+            capacity: DEFAULT_CAPACITY,
+            history: VecDeque::with_capacity(DEFAULT_CAPACITY),
+            writer: None,
+            error: None,
+        }
+    }
+
+    pub fn format(&self) -> TraceFormat {
+        self.format
+    }
+
+    pub fn set_writer(&mut self, writer: Option<Box<dyn Write>>) {
+        self.writer = writer;
+        self.error = None;
+    }
+
+    pub fn set_pc_range(&mut self, range: Option<RangeInclusive<u16>>) {
+        self.pc_range = range;
+    }
+
+    pub fn set_frame_range(&mut self, range: Option<RangeInclusive<usize>>) {
+        self.frame_range = range;
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.history.len() > capacity {
+            self.history.pop_front();
+        }
+    }
+
+    // NOTE: 古い順に並ぶ。最後の要素が最後に実行した命令
+    pub fn history(&self) -> impl Iterator<Item = &str> {
+        self.history.iter().map(|s| s.as_str())
+    }
+
+    pub fn clear(&mut self) {
+        self.history.clear();
+    }
+
+    // NOTE: 書き込みに失敗したらファイル出力だけ止めて、リングバッファへの記録は続ける
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    pub fn flush(&mut self) {
+        if let Some(writer) = &mut self.writer {
+            if let Err(e) = writer.flush() {
+                self.error = Some(e.to_string());
+                self.writer = None;
+            }
+        }
+    }
+
+    pub(crate) fn is_enabled(&self, pc: u16, frame: usize) -> bool {
+        let in_pc = self.pc_range.as_ref().is_none_or(|r| r.contains(&pc));
+        let in_frame = self.frame_range.as_ref().is_none_or(|r| r.contains(&frame));
+        in_pc && in_frame
+    }
+
+    pub(crate) fn log<F: Fn(u16) -> u8>(&mut self, state: &TraceState, symbols: &Symbols, peek: F) {
+        let line = format_line(self.format, state, symbols, peek);
+
+        if let Some(writer) = &mut self.writer {
+            if let Err(e) = writeln!(writer, "{}", line) {
+                self.error = Some(e.to_string());
+                self.writer = None;
+            }
+        }
+
+        if self.capacity == 0 {
+            return;
+        }
+        if self.history.len() == self.capacity {
+            self.history.pop_front();
+        }
+        self.history.push_back(line);
+    }
+}
+
+impl Drop for TraceLogger {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+// NOTE: "8000-80FF" のような 16 進の範囲。単独の値も受け付ける
+pub fn parse_pc_range(s: &str) -> Result<RangeInclusive<u16>, String> {
+    parse_range(s, |v| {
+        u16::from_str_radix(v.trim_start_matches('$'), 16)
+            .map_err(|_| format!("Invalid address: {}", v))
+    })
+}
+
+pub fn parse_frame_range(s: &str) -> Result<RangeInclusive<usize>, String> {
+    parse_range(s, |v| {
+        v.parse::<usize>()
+            .map_err(|_| format!("Invalid frame: {}", v))
+    })
+}
+
+fn parse_range<T: PartialOrd + Copy>(
+    s: &str,
+    parse: impl Fn(&str) -> Result<T, String>,
+) -> Result<RangeInclusive<T>, String> {
+    let (start, end) = match s.split_once('-') {
+        Some((start, end)) => (parse(start.trim())?, parse(end.trim())?),
+        None => {
+            let value = parse(s.trim())?;
+            (value, value)
+        }
+    };
+    if start > end {
+        return Err(format!("Invalid range: {}", s));
+    }
+    Ok(start..=end)
+}
+
+fn format_line<F: Fn(u16) -> u8>(
+    format: TraceFormat,
+    state: &TraceState,
+    symbols: &Symbols,
+    peek: F,
+) -> String {
+    let r = &state.registers;
+    let bytes = [
+        peek(r.pc),
+        peek(r.pc.wrapping_add(1)),
+        peek(r.pc.wrapping_add(2)),
+    ];
+    let instruction = disassemble_with_symbols(&bytes, r.pc, symbols).remove(0);
+    let effective = effective_operand(&bytes, r, &peek);
+
+    let hex = instruction
+        .bytes
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let name = if instruction.is_unofficial {
+        format!("*{}", instruction.mnemonic)
+    } else {
+        instruction.mnemonic.to_string()
+    };
+    // NOTE: PC にラベルがあれば命令の前に "label: " を付ける
+    let label = instruction
+        .label
+        .as_ref()
+        .map(|l| format!("{}: ", l))
+        .unwrap_or_default();
+
+    match format {
+        TraceFormat::Nestest => {
+            let asm = format!(
+                "{:04X}  {:8} {}{:>4} {}",
+                r.pc,
+                hex,
+                label.trim_end(),
+                name,
+                nestest_operand(&instruction.operand, &effective)
+            );
+            format!(
+                "{:47} A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} PPU:{:3},{:3} CYC:{}",
+                asm.trim_end(),
+                r.a,
+                r.x,
+                r.y,
+                r.status,
+                r.sp,
+                state.scanline,
+                state.dot,
+                state.cycles
+            )
+        }
+        TraceFormat::Fceux => {
+            let asm = format!(
+                "{}{} {}{}",
+                label,
+                name,
+                instruction.operand,
+                fceux_annotation(&effective)
+            );
+            format!(
+                "${:04X}:{:9} {:30} A:{:02X} X:{:02X} Y:{:02X} S:{:02X} P:{}",
+                r.pc,
+                hex,
+                asm.trim_end(),
+                r.a,
+                r.x,
+                r.y,
+                r.sp,
+                flags(r.status)
+            )
+        }
+        TraceFormat::Mesen => {
+            let asm = format!(
+                "{}{} {}{}",
+                label,
+                name,
+                instruction.operand,
+                mesen_annotation(&effective)
+            );
+            format!(
+                "{:04X}  {:9} {:32} A:{:02X} X:{:02X} Y:{:02X} S:{:02X} P:{} V:{:<3} H:{:<3} Fr:{} Cycle:{}",
+                r.pc,
+                hex,
+                asm.trim_end(),
+                r.a,
+                r.x,
+                r.y,
+                r.sp,
+                flags(r.status),
+                state.scanline,
+                state.dot,
+                state.frame,
+                state.cycles
+            )
+        }
+    }
+}
+
+// NOTE: 実行前の値を副作用なしで読んだ実効アドレスと、そこにある値
+enum Effective {
+    None,
+    Direct(u8),
+    Indexed(u16, u8, bool),
+    IndexedIndirect(u8, u16, u8),
+    IndirectIndexed(u16, u16, u8),
+    Jump(u16),
+}
+
+fn effective_operand<F: Fn(u16) -> u8>(bytes: &[u8; 3], r: &Registers, peek: &F) -> Effective {
+    let op = OPCODE_TABLE[bytes[0] as usize];
+    let value = (bytes[2] as u16) << 8 | bytes[1] as u16;
+    let zp = bytes[1];
+    let read_zp_u16 =
+        |ptr: u8| (peek(ptr.wrapping_add(1) as u16) as u16) << 8 | peek(ptr as u16) as u16;
+    let is_jump = op.mnemonic == Mnemonic::Jmp || op.mnemonic == Mnemonic::Jsr;
+
+    match op.addr_mode {
+        AddressingMode::ZeroPage => Effective::Direct(peek(zp as u16)),
+        AddressingMode::Absolute if is_jump => Effective::None,
+        AddressingMode::Absolute => Effective::Direct(peek(value)),
+        AddressingMode::ZeroPageX => {
+            let addr = zp.wrapping_add(r.x) as u16;
+            Effective::Indexed(addr, peek(addr), true)
+        }
+        AddressingMode::ZeroPageY => {
+            let addr = zp.wrapping_add(r.y) as u16;
+            Effective::Indexed(addr, peek(addr), true)
+        }
+        AddressingMode::AbsoluteX => {
+            let addr = value.wrapping_add(r.x as u16);
+            Effective::Indexed(addr, peek(addr), false)
+        }
+        AddressingMode::AbsoluteY => {
+            let addr = value.wrapping_add(r.y as u16);
+            Effective::Indexed(addr, peek(addr), false)
+        }
+        AddressingMode::IndirectX => {
+            let ptr = zp.wrapping_add(r.x);
+            let addr = read_zp_u16(ptr);
+            Effective::IndexedIndirect(ptr, addr, peek(addr))
+        }
+        AddressingMode::IndirectY => {
+            let base = read_zp_u16(zp);
+            let addr = base.wrapping_add(r.y as u16);
+            Effective::IndirectIndexed(base, addr, peek(addr))
+        }
+        AddressingMode::Indirect => {
+            // NOTE: ページ境界をまたがない 6502 のバグを再現する
+            let hi = (value & 0xFF00) | (value as u8).wrapping_add(1) as u16;
+            Effective::Jump((peek(hi) as u16) << 8 | peek(value) as u16)
+        }
+        _ => Effective::None,
+    }
+}
+
+fn nestest_operand(operand: &str, effective: &Effective) -> String {
+    match *effective {
+        Effective::None => operand.to_string(),
+        Effective::Direct(value) => format!("{} = {:02X}", operand, value),
+        Effective::Indexed(addr, value, true) => {
+            format!("{} @ {:02X} = {:02X}", operand, addr, value)
+        }
+        Effective::Indexed(addr, value, false) => {
+            format!("{} @ {:04X} = {:02X}", operand, addr, value)
+        }
+        Effective::IndexedIndirect(ptr, addr, value) => {
+            format!("{} @ {:02X} = {:04X} = {:02X}", operand, ptr, addr, value)
+        }
+        Effective::IndirectIndexed(base, addr, value) => {
+            format!("{} = {:04X} @ {:04X} = {:02X}", operand, base, addr, value)
+        }
+        Effective::Jump(addr) => format!("{} = {:04X}", operand, addr),
+    }
+}
+
+fn fceux_annotation(effective: &Effective) -> String {
+    match *effective {
+        Effective::None => String::new(),
+        Effective::Direct(value) => format!(" = #${:02X}", value),
+        Effective::Indexed(addr, value, _)
+        | Effective::IndexedIndirect(_, addr, value)
+        | Effective::IndirectIndexed(_, addr, value) => {
+            format!(" @ ${:04X} = #${:02X}", addr, value)
+        }
+        Effective::Jump(addr) => format!(" = ${:04X}", addr),
+    }
+}
+
+fn mesen_annotation(effective: &Effective) -> String {
+    match *effective {
+        Effective::None => String::new(),
+        Effective::Direct(value) => format!(" = ${:02X}", value),
+        Effective::Indexed(addr, value, _)
+        | Effective::IndexedIndirect(_, addr, value)
+        | Effective::IndirectIndexed(_, addr, value) => {
+            format!(" [${:04X}] = ${:02X}", addr, value)
+        }
+        Effective::Jump(addr) => format!(" [${:04X}]", addr),
+    }
+}
+
+fn flags(status: u8) -> String {
+    "NVUBDIZC"
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            if status & (0x80 >> i) != 0 {
+                c
+            } else {
+                c.to_ascii_lowercase()
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use test_case::test_case;
+
+    use crate::debugger::Registers;
+
+    use super::*;
+
+    fn state(pc: u16) -> TraceState {
+        TraceState {
+            registers: Registers {
+                pc,
+                sp: 0xFD,
+                a: 0x00,
+                x: 0x05,
+                y: 0x10,
+                status: 0x24,
+            },
+            cycles: 7,
+            scanline: 0,
+            dot: 21,
+            frame: 0,
+        }
+    }
+
+    fn memory(code: &[u8]) -> impl Fn(u16) -> u8 + '_ {
+        move |addr| match addr {
+            0xC000..=0xC002 => code.get((addr - 0xC000) as usize).copied().unwrap_or(0),
+            0x0080 => 0x00,
+            0x0081 => 0x02,
+            0x0085 => 0x04,
+            0x0086 => 0x03,
+            0x0205 => 0x11,
+            0x0210 => 0x22,
+            0x0304 => 0x33,
+            _ => 0x00,
+        }
+    }
+
+    #[test_case(&[0x4C, 0xF5, 0xC5], "C000  4C F5 C5  JMP $C5F5                       A:00 X:05 Y:10 P:24 SP:FD PPU:  0, 21 CYC:7" ; "jmp")]
+    #[test_case(&[0xBD, 0x00, 0x02], "C000  BD 00 02  LDA $0200,X @ 0205 = 11         A:00 X:05 Y:10 P:24 SP:FD PPU:  0, 21 CYC:7" ; "absolute x")]
+    #[test_case(&[0xB5, 0x80], "C000  B5 80     LDA $80,X @ 85 = 04             A:00 X:05 Y:10 P:24 SP:FD PPU:  0, 21 CYC:7" ; "zero page x")]
+    #[test_case(&[0xA1, 0x80], "C000  A1 80     LDA ($80,X) @ 85 = 0304 = 33    A:00 X:05 Y:10 P:24 SP:FD PPU:  0, 21 CYC:7" ; "indirect x")]
+    #[test_case(&[0xB1, 0x80], "C000  B1 80     LDA ($80),Y = 0200 @ 0210 = 22  A:00 X:05 Y:10 P:24 SP:FD PPU:  0, 21 CYC:7" ; "indirect y")]
+    #[test_case(&[0x4A], "C000  4A        LSR A                           A:00 X:05 Y:10 P:24 SP:FD PPU:  0, 21 CYC:7" ; "accumulator")]
+    #[test_case(&[0xA7, 0x80], "C000  A7 80    *LAX $80 = 00                    A:00 X:05 Y:10 P:24 SP:FD PPU:  0, 21 CYC:7" ; "unofficial")]
+    fn test_nestest(code: &[u8], expected: &str) {
+        let line = format_line(
+            TraceFormat::Nestest,
+            &state(0xC000),
+            &Symbols::default(),
+            memory(code),
+        );
+        assert_eq!(line, expected);
+    }
+
+    #[test]
+    fn test_fceux() {
+        let line = format_line(
+            TraceFormat::Fceux,
+            &state(0xC000),
+            &Symbols::default(),
+            memory(&[0xBD, 0x00, 0x02]),
+        );
+        assert_eq!(
+            line,
+            "$C000:BD 00 02  LDA $0200,X @ $0205 = #$11     A:00 X:05 Y:10 S:FD P:nvUbdIzc"
+        );
+    }
+
+    #[test]
+    fn test_mesen() {
+        let line = format_line(
+            TraceFormat::Mesen,
+            &state(0xC000),
+            &Symbols::default(),
+            memory(&[0xA5, 0x81]),
+        );
+        assert_eq!(
+            line,
+            "C000  A5 81     LDA $81 = $02                    A:00 X:05 Y:10 S:FD P:nvUbdIzc V:0   H:21  Fr:0 Cycle:7"
+        );
+    }
+
+    #[test]
+    fn test_symbols() {
+        let mut symbols = Symbols::default();
+        symbols.insert(0xC000, "reset");
+        symbols.insert(0x0081, "ptr");
+        symbols.insert(0xC5F5, "main");
+
+        let line = format_line(
+            TraceFormat::Nestest,
+            &state(0xC000),
+            &symbols,
+            memory(&[0xA5, 0x81]),
+        );
+        assert_eq!(
+            line,
+            "C000  A5 81    reset: LDA ptr = 02              A:00 X:05 Y:10 P:24 SP:FD PPU:  0, 21 CYC:7"
+        );
+
+        let line = format_line(
+            TraceFormat::Mesen,
+            &state(0xC000),
+            &symbols,
+            memory(&[0x4C, 0xF5, 0xC5]),
+        );
+        assert_eq!(
+            line,
+            "C000  4C F5 C5  reset: JMP main                  A:00 X:05 Y:10 S:FD P:nvUbdIzc V:0   H:21  Fr:0 Cycle:7"
+        );
+    }
+
+    #[test]
+    fn test_history() {
+        let mut logger = TraceLogger::new(TraceFormat::Nestest);
+        logger.set_capacity(2);
+        for pc in [0xC000, 0xC001, 0xC002] {
+            logger.log(&state(pc), &Symbols::default(), |_| 0xEA);
+        }
+
+        let pcs = logger.history().map(|l| &l[..4]).collect::<Vec<_>>();
+        assert_eq!(pcs, vec!["C001", "C002"]);
+    }
+
+    #[test]
+    fn test_options() {
+        assert!(TraceOptions::default().logger().unwrap().is_none());
+
+        let path = std::env::temp_dir().join(format!("sen-trace-{}.log", std::process::id()));
+        let options = TraceOptions {
+            trace: Some(path.clone()),
+            trace_format: TraceFormat::Mesen,
+            trace_pc: Some(0xC000..=0xC0FF),
+            trace_frames: None,
+        };
+        let mut logger = options.logger().unwrap().unwrap();
+        assert_eq!(logger.format(), TraceFormat::Mesen);
+        assert!(logger.is_enabled(0xC000, 0));
+        assert!(!logger.is_enabled(0xC100, 0));
+
+        logger.log(&state(0xC000), &Symbols::default(), |_| 0xEA);
+        drop(logger);
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(text.starts_with("C000  EA        NOP"));
+    }
+
+    #[test]
+    fn test_filter() {
+        let mut logger = TraceLogger::new(TraceFormat::Nestest);
+        logger.set_pc_range(Some(0x8000..=0x80FF));
+        logger.set_frame_range(Some(10..=20));
+
+        assert!(logger.is_enabled(0x8000, 10));
+        assert!(!logger.is_enabled(0x8100, 10));
+        assert!(!logger.is_enabled(0x8000, 21));
+    }
+
+    #[test_case("8000-80FF", Ok(0x8000..=0x80FF))]
+    #[test_case("$C000", Ok(0xC000..=0xC000))]
+    #[test_case("9000-8000", Err(String::from("Invalid range: 9000-8000")))]
+    #[test_case("zz", Err(String::from("Invalid address: zz")))]
+    fn test_parse_pc_range(s: &str, expected: Result<RangeInclusive<u16>, String>) {
+        assert_eq!(parse_pc_range(s), expected);
+    }
+
+    #[test]
+    fn test_parse_frame_range() {
+        assert_eq!(parse_frame_range("10-20"), Ok(10..=20));
+        assert_eq!(parse_frame_range("5"), Ok(5..=5));
+    }
+}
@@ -0,0 +1,26 @@
+// NOTE: 統合テストごとに別クレートとしてコンパイルされるので、使わない関数があっても警告しない
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+
+pub fn data_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("data")
+}
+
+// NOTE: 無ければテストを失敗させる
+pub fn read_data(name: &str) -> Vec<u8> {
+    let path = data_dir().join(name);
+    std::fs::read(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e))
+}
+
+// NOTE: 配布条件の都合で同梱していないデータ用。cargo make で取ってくるまでは無いので、テストを飛ばせるように None を返す
+pub fn try_read_data(name: &str) -> Option<Vec<u8>> {
+    let path = data_dir().join(name);
+    let data = std::fs::read(&path).ok();
+    if data.is_none() {
+        eprintln!("skipping: {} not found", path.display());
+    }
+    data
+}
@@ -0,0 +1,222 @@
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+};
+
+use lib::{
+    render::utils::{frame::Frame, png::encode_png},
+    testrom::{capture, frame_hash, parse_input_script},
+};
+
+mod common;
+
+// NOTE: ゴールデンイメージは tests/data/golden/<名前>.png。UPDATE_GOLDEN=1 で実行すると今の出力で上書きする。
+//       同梱していない ROM は golden.txt に "<名前> <ROM> <フレーム数> [入力...]" の形で書く
+const GOLDEN_DIR: &str = "golden";
+const MANIFEST: &str = "golden.txt";
+
+#[test]
+fn test_scene() {
+    assert_golden("scene", scene_rom(), 3, "");
+}
+
+#[test]
+fn test_scene_with_input() {
+    // NOTE: A ボタンを押している間は赤の強調がかかる
+    assert_golden("scene_emphasis", scene_rom(), 3, "0:A");
+}
+
+#[test]
+fn test_manifest() {
+    let path = common::data_dir().join(GOLDEN_DIR).join(MANIFEST);
+    let Ok(text) = std::fs::read_to_string(&path) else {
+        eprintln!("skipping: {} not found", path.display());
+        return;
+    };
+
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let mut fields = line.splitn(4, ' ');
+        let name = fields.next().unwrap();
+        let rom = fields.next().unwrap();
+        let frames = fields.next().unwrap().parse::<usize>().unwrap();
+        let script = fields.next().unwrap_or("");
+
+        assert_golden(name, common::read_data(rom), frames, script);
+    }
+}
+
+fn assert_golden(name: &str, rom_data: Vec<u8>, frames: usize, script: &str) {
+    let script = parse_input_script(script).unwrap();
+    let actual = capture(rom_data, frames, script);
+
+    let golden = common::data_dir()
+        .join(GOLDEN_DIR)
+        .join(format!("{}.png", name));
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        write_png(&golden, &actual);
+        return;
+    }
+
+    let out_dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(GOLDEN_DIR);
+    let actual_path = out_dir.join(format!("{}.actual.png", name));
+
+    let Some(expected) = read_png(&golden) else {
+        write_png(&actual_path, &actual);
+        panic!(
+            "{}: no golden image at {}, wrote {}",
+            name,
+            golden.display(),
+            actual_path.display()
+        );
+    };
+
+    if expected != actual {
+        let diff_path = out_dir.join(format!("{}.diff.png", name));
+        write_png(&actual_path, &actual);
+        write_png(&diff_path, &diff(&expected, &actual));
+
+        let pixels = expected
+            .chunks_exact(3)
+            .zip(actual.chunks_exact(3))
+            .filter(|(e, a)| e != a)
+            .count();
+        panic!(
+            "{}: {} pixels differ (expected {:016x}, got {:016x})\n  actual: {}\n  diff: {}",
+            name,
+            pixels,
+            frame_hash(&expected),
+            frame_hash(&actual),
+            actual_path.display(),
+            diff_path.display()
+        );
+    }
+}
+
+// NOTE: 一致するピクセルは暗く、異なるピクセルは赤で塗る
+fn diff(expected: &[u8], actual: &[u8]) -> Vec<u8> {
+    expected
+        .chunks_exact(3)
+        .zip(actual.chunks_exact(3))
+        .flat_map(|(e, a)| {
+            if e == a {
+                [e[0] / 4, e[1] / 4, e[2] / 4]
+            } else {
+                [0xFF, 0x00, 0x00]
+            }
+        })
+        .collect()
+}
+
+fn read_png(path: &Path) -> Option<Vec<u8>> {
+    let decoder = png::Decoder::new(File::open(path).ok()?);
+    let mut reader = decoder.read_info().unwrap();
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).unwrap();
+    assert_eq!(info.color_type, png::ColorType::Rgb);
+    buf.truncate(info.buffer_size());
+    Some(buf)
+}
+
+fn write_png(path: &Path, rgb: &[u8]) {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, encode_png(rgb, Frame::WIDTH, Frame::HEIGHT)).unwrap();
+}
+
+// NOTE: CHR-RAM にタイルを書き、背景 1 行半とスプライト 1 枚を出して X 方向に 12 ドットスクロールする。
+//       フレームは NMI のタイミングで描かれるので NMI を有効にしておく
+fn scene_rom() -> Vec<u8> {
+    #[rustfmt::skip]
+    let program = [
+        0x78,             // $8000: SEI
+        0xD8,             // $8001: CLD
+        0xA2, 0xFF,       // $8002: LDX #$FF
+        0x9A,             // $8004: TXS
+        0xA9, 0x00,       // $8005: LDA #$00
+        0x8D, 0x00, 0x20, // $8007: STA $2000
+        0x8D, 0x01, 0x20, // $800A: STA $2001
+        0xAD, 0x02, 0x20, // $800D: LDA $2002
+        0xA9, 0x00,       // $8010: LDA #$00
+        0x8D, 0x06, 0x20, // $8012: STA $2006
+        0xA9, 0x10,       // $8015: LDA #$10
+        0x8D, 0x06, 0x20, // $8017: STA $2006
+        0xA2, 0x00,       // $801A: LDX #$00
+        0xBD, 0x91, 0x80, // $801C: LDA $8091,X
+        0x8D, 0x07, 0x20, // $801F: STA $2007
+        0xE8,             // $8022: INX
+        0xE0, 0x10,       // $8023: CPX #$10
+        0xD0, 0xF5,       // $8025: BNE $801C
+        0xA9, 0x3F,       // $8027: LDA #$3F
+        0x8D, 0x06, 0x20, // $8029: STA $2006
+        0xA9, 0x00,       // $802C: LDA #$00
+        0x8D, 0x06, 0x20, // $802E: STA $2006
+        0xA2, 0x00,       // $8031: LDX #$00
+        0xBD, 0xA1, 0x80, // $8033: LDA $80A1,X
+        0x8D, 0x07, 0x20, // $8036: STA $2007
+        0xE8,             // $8039: INX
+        0xE0, 0x20,       // $803A: CPX #$20
+        0xD0, 0xF5,       // $803C: BNE $8033
+        0xA9, 0x21,       // $803E: LDA #$21
+        0x8D, 0x06, 0x20, // $8040: STA $2006
+        0xA9, 0x40,       // $8043: LDA #$40
+        0x8D, 0x06, 0x20, // $8045: STA $2006
+        0xA9, 0x01,       // $8048: LDA #$01
+        0xA2, 0x30,       // $804A: LDX #$30
+        0x8D, 0x07, 0x20, // $804C: STA $2007
+        0xCA,             // $804F: DEX
+        0xD0, 0xFA,       // $8050: BNE $804C
+        0xA2, 0x00,       // $8052: LDX #$00
+        0xBD, 0xC1, 0x80, // $8054: LDA $80C1,X
+        0x9D, 0x00, 0x02, // $8057: STA $0200,X
+        0xE8,             // $805A: INX
+        0xE0, 0x04,       // $805B: CPX #$04
+        0xD0, 0xF5,       // $805D: BNE $8054
+        0xA9, 0x02,       // $805F: LDA #$02
+        0x8D, 0x14, 0x40, // $8061: STA $4014
+        0xA9, 0x0C,       // $8064: LDA #$0C
+        0x8D, 0x05, 0x20, // $8066: STA $2005
+        0xA9, 0x00,       // $8069: LDA #$00
+        0x8D, 0x05, 0x20, // $806B: STA $2005
+        0xA9, 0x80,       // $806E: LDA #$80
+        0x8D, 0x00, 0x20, // $8070: STA $2000
+        0xA9, 0x1E,       // $8073: LDA #$1E
+        0x8D, 0x01, 0x20, // $8075: STA $2001
+        0xA9, 0x01,       // $8078: LDA #$01
+        0x8D, 0x16, 0x40, // $807A: STA $4016
+        0xA9, 0x00,       // $807D: LDA #$00
+        0x8D, 0x16, 0x40, // $807F: STA $4016
+        0xAD, 0x16, 0x40, // $8082: LDA $4016
+        0x29, 0x01,       // $8085: AND #$01
+        0xF0, 0xEF,       // $8087: BEQ $8078
+        0xA9, 0x3E,       // $8089: LDA #$3E
+        0x8D, 0x01, 0x20, // $808B: STA $2001
+        0x4C, 0x78, 0x80, // $808E: JMP $8078
+    ];
+    #[rustfmt::skip]
+    let data = [
+        0xAA, 0x55, 0xAA, 0x55, 0xAA, 0x55, 0xAA, 0x55, // $8091: タイル 1 (下位)
+        0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00, // $8099: タイル 1 (上位)
+        0x0F, 0x16, 0x2A, 0x12, 0x0F, 0x16, 0x2A, 0x12, // $80A1: 背景パレット
+        0x0F, 0x16, 0x2A, 0x12, 0x0F, 0x16, 0x2A, 0x12,
+        0x0F, 0x30, 0x27, 0x1A, 0x0F, 0x30, 0x27, 0x1A, // $80B1: スプライトパレット
+        0x0F, 0x30, 0x27, 0x1A, 0x0F, 0x30, 0x27, 0x1A,
+        0x64, 0x01, 0x00, 0x78,                         // $80C1: Y, タイル, 属性, X
+        0x40,                                           // $80C5: NMI: RTI
+    ];
+
+    let mut raw = vec![0x4E, 0x45, 0x53, 0x1A, 0x01, 0x00, 0x00, 0x00];
+    raw.resize(16, 0x00);
+    let mut prg_rom = vec![0xEA; 0x4000];
+    prg_rom[..program.len()].copy_from_slice(&program);
+    prg_rom[program.len()..program.len() + data.len()].copy_from_slice(&data);
+    prg_rom[0x3FFA] = 0xC5;
+    prg_rom[0x3FFB] = 0x80;
+    prg_rom[0x3FFC] = 0x00;
+    prg_rom[0x3FFD] = 0x80;
+    raw.extend(prg_rom);
+    raw
+}
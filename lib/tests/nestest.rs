@@ -0,0 +1,58 @@
+use lib::{
+    testrom::headless_emulator,
+    trace::{TraceFormat, TraceLogger},
+};
+
+mod common;
+
+const CONTEXT: usize = 5;
+
+// NOTE: nestest.nes と nestest.log は配布条件の都合で同梱していないので、cargo make fetch-nestest で取ってくる。
+//       無いときは飛ばす
+#[test]
+fn test_nestest() {
+    let (Some(rom), Some(log)) = (
+        common::try_read_data("nestest.nes"),
+        common::try_read_data("nestest.log"),
+    ) else {
+        return;
+    };
+    let expected = String::from_utf8_lossy(&log)
+        .lines()
+        .map(|l| l.trim_end().to_string())
+        .collect::<Vec<_>>();
+
+    let mut emulator = headless_emulator(rom);
+    let mut logger = TraceLogger::new(TraceFormat::Nestest);
+    logger.set_capacity(1);
+    emulator.set_trace_logger(Some(logger));
+    // NOTE: 自動実行モード。画面を使わずに全命令を試す
+    emulator.reset_with_pc(0xC000);
+
+    let mut actual: Vec<String> = vec![];
+    for (n, line) in expected.iter().enumerate() {
+        emulator.step();
+        let trace = emulator
+            .trace_logger()
+            .and_then(|logger| logger.history().last())
+            .unwrap()
+            .to_string();
+
+        if trace != *line {
+            let begin = n.saturating_sub(CONTEXT);
+            let context = actual[begin..]
+                .iter()
+                .map(|l| format!("  {}", l))
+                .collect::<Vec<_>>()
+                .join("\n");
+            panic!(
+                "diverged at line {}\n{}\nexpected:\n  {}\nactual:\n  {}",
+                n + 1,
+                context,
+                line,
+                trace
+            );
+        }
+        actual.push(trace);
+    }
+}
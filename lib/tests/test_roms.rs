@@ -0,0 +1,115 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use lib::testrom::{self, TestStatus, DEFAULT_TIMEOUT_FRAMES};
+
+mod common;
+
+// NOTE: tests/data/test_roms 以下の ROM はすべて成功を期待する。
+//       既知の失敗は expected.txt に "<相対パス> <ステータス>" の形で書く (ステータスは $6000 の値、timeout、halted)。
+//       skip は実行しない。"/" で終わるパスはそのディレクトリ以下すべてに当てはまる
+const ROM_DIR: &str = "test_roms";
+const EXPECTED: &str = "expected.txt";
+
+// NOTE: テスト ROM は配布条件の都合で同梱していないので、cargo make fetch-test-roms で取ってくる。無いときは飛ばす
+#[test]
+fn test_roms() {
+    let dir = common::data_dir().join(ROM_DIR);
+    let expected = read_expected(&dir);
+    let mut roms = vec![];
+    collect_roms(&dir, &mut roms);
+    roms.sort();
+    if roms.is_empty() {
+        eprintln!("skipping: no test ROMs in {}", dir.display());
+        return;
+    }
+
+    let mut failures = vec![];
+    for path in &roms {
+        let name = path
+            .strip_prefix(&dir)
+            .unwrap()
+            .to_string_lossy()
+            .replace('\\', "/");
+        let Some(want) = lookup(&expected, &name) else {
+            continue;
+        };
+
+        let outcome = testrom::run(std::fs::read(path).unwrap(), DEFAULT_TIMEOUT_FRAMES);
+        if outcome.status != want {
+            failures.push(format!(
+                "{}: expected {}, got {}\n    {}",
+                name,
+                want,
+                outcome.status,
+                outcome.message.replace('\n', "\n    ")
+            ));
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{} of {} test ROMs regressed\n{}",
+        failures.len(),
+        roms.len(),
+        failures.join("\n")
+    );
+}
+
+fn lookup(expected: &HashMap<String, Option<TestStatus>>, name: &str) -> Option<TestStatus> {
+    if let Some(&want) = expected.get(name) {
+        return want;
+    }
+    expected
+        .iter()
+        .filter(|(path, _)| path.ends_with('/') && name.starts_with(path.as_str()))
+        .max_by_key(|(path, _)| path.len())
+        .map_or(Some(TestStatus::Passed), |(_, &want)| want)
+}
+
+fn collect_roms(dir: &Path, roms: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            collect_roms(&path, roms);
+        } else if path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("nes"))
+        {
+            roms.push(path);
+        }
+    }
+}
+
+fn read_expected(dir: &Path) -> HashMap<String, Option<TestStatus>> {
+    let Ok(text) = std::fs::read_to_string(dir.join(EXPECTED)) else {
+        return HashMap::new();
+    };
+
+    text.lines()
+        .map(|line| line.split('#').next().unwrap().trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let (name, status) = line.rsplit_once(' ').unwrap();
+            (name.trim().to_string(), parse_status(status))
+        })
+        .collect()
+}
+
+fn parse_status(s: &str) -> Option<TestStatus> {
+    match s.to_ascii_lowercase().as_str() {
+        "skip" => None,
+        "timeout" => Some(TestStatus::Timeout),
+        "halted" => Some(TestStatus::Halted),
+        "passed" | "0" => Some(TestStatus::Passed),
+        code => {
+            let code = code.trim_start_matches('$');
+            Some(TestStatus::Failed(u8::from_str_radix(code, 16).unwrap()))
+        }
+    }
+}
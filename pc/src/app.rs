@@ -1,25 +1,121 @@
-use std::io;
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
 
 use crate::emulator::Sdl2Emulator;
 use clap::Parser;
+use lib::{
+    fds::{is_fds_image, sidecar_path},
+    region::Region,
+    render::{
+        filter::{ntsc::NtscMode, Filter},
+        utils::palette::Palette,
+    },
+    trace::TraceOptions,
+};
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 pub struct App {
     path: String,
+    #[arg(
+        long,
+        help = "ntsc, pal or dendy (default: detected from the ROM header)"
+    )]
+    region: Option<Region>,
+    #[arg(
+        long,
+        help = "Built-in palette (default, 2c03, composite) or path to a .pal file"
+    )]
+    palette: Option<String>,
+    #[arg(
+        long,
+        default_value = "none",
+        help = "Upscaling filter: none, scale2x, scale3x, hq2x, xbr or crt"
+    )]
+    filter: Filter,
+    #[arg(
+        long,
+        help = "NTSC filter mode: composite, svideo or rgb (toggle with N key)"
+    )]
+    ntsc: Option<NtscMode>,
+    #[command(flatten)]
+    trace: TraceOptions,
+    #[arg(
+        long,
+        help = "Record video and the mixed APU output to <prefix>.y4m and <prefix>.wav (toggle with F9 key)"
+    )]
+    record_av: Option<String>,
+    #[arg(long, requires = "record_av", help = "Also record <prefix>.gif")]
+    record_gif: bool,
+    #[arg(long, help = "Write the mixed APU output to this WAV file")]
+    dump_audio: Option<PathBuf>,
+    #[arg(
+        long,
+        requires = "dump_audio",
+        help = "Also write each channel to <name>.<channel>.wav (pulse1, pulse2, triangle, noise, dmc, expansion)"
+    )]
+    audio_stems: bool,
+    #[arg(
+        long,
+        help = "Famicom Disk System BIOS (disksys.rom), required for .fds images (switch sides with F5 key)"
+    )]
+    fds_bios: Option<PathBuf>,
 }
 
 #[derive(Debug)]
 pub enum Error {
     Io(io::Error),
+    InvalidPalette(String),
+    InvalidFds(String),
+    Record(String),
     FailedJoin,
 }
 
 impl App {
     pub fn run(&self) -> Result<(), Error> {
         let rom_data = std::fs::read(&self.path).map_err(Error::Io)?;
+        let palette = self
+            .palette
+            .as_deref()
+            .map(Palette::load)
+            .transpose()
+            .map_err(Error::InvalidPalette)?;
+        let trace_logger = self.trace.logger().map_err(Error::Io)?;
 
-        let mut emulator = Sdl2Emulator::new(rom_data);
+        let mut emulator = if is_fds_image(&rom_data) {
+            let Some(bios_path) = &self.fds_bios else {
+                return Err(Error::InvalidFds("FDS images need --fds-bios".to_string()));
+            };
+            let bios = std::fs::read(bios_path).map_err(Error::Io)?;
+            let sidecar = sidecar_path(Path::new(&self.path));
+            let patch = match std::fs::read(&sidecar) {
+                Ok(patch) => Some(patch),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+                Err(e) => return Err(Error::Io(e)),
+            };
+            Sdl2Emulator::new_fds(rom_data, patch, bios, sidecar, self.region)
+                .map_err(Error::InvalidFds)?
+        } else {
+            Sdl2Emulator::new(rom_data, self.region)
+        };
+        if let Some(palette) = palette {
+            emulator.set_palette(palette);
+        }
+        emulator.set_filter(self.filter);
+        emulator.set_ntsc_filter(self.ntsc);
+        emulator.set_trace_logger(trace_logger);
+        if let Some(prefix) = &self.record_av {
+            emulator
+                .start_recording(prefix, self.record_gif)
+                .map_err(Error::Record)?;
+        }
+        if let Some(path) = &self.dump_audio {
+            emulator
+                .start_audio_dump(path, self.audio_stems)
+                .map_err(Error::Record)?;
+        }
 
         emulator.reset();
         loop {
@@ -0,0 +1,9 @@
+use lib::diagnostics::{Diagnostic, DiagnosticsSink};
+
+pub struct StderrSink;
+
+impl DiagnosticsSink for StderrSink {
+    fn report(&mut self, diagnostic: &Diagnostic) {
+        eprintln!("{}", diagnostic);
+    }
+}
@@ -1,13 +1,64 @@
-use lib::emulator::Emulator;
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
 
-use crate::{joypad::Sdl2JoypadHandler, renderer::Sdl2Renderer, speaker::SdlSpeaker};
+use lib::{
+    audio::DEFAULT_SAMPLE_RATE,
+    emulator::Emulator,
+    record::{audio_dump, Recorder},
+    region::Region,
+    render::{
+        filter::{ntsc::NtscMode, Filter},
+        utils::palette::Palette,
+    },
+    trace::TraceLogger,
+};
+
+use crate::{
+    diagnostics::StderrSink,
+    joypad::{Hotkey, HotkeyQueue, Sdl2JoypadHandler},
+    renderer::Sdl2Renderer,
+    speaker::SdlSpeaker,
+};
+
+type SdlEmulator = Emulator<SdlSpeaker, Sdl2JoypadHandler, Sdl2Renderer>;
 
 pub struct Sdl2Emulator {
-    emulator: Emulator<SdlSpeaker, Sdl2JoypadHandler, Sdl2Renderer>,
+    emulator: SdlEmulator,
+    hotkeys: HotkeyQueue,
+    // NOTE: FDS のディスクへの書き込みを終了時に保存する先
+    disk_sidecar: Option<PathBuf>,
 }
 
 impl Sdl2Emulator {
-    pub fn new(raw: Vec<u8>) -> Self {
+    pub fn new(raw: Vec<u8>, region: Option<Region>) -> Self {
+        let emulator = Self::with_emulator(region, |speaker, joypad_handler, renderer| {
+            Ok(Emulator::new(raw, speaker, joypad_handler, renderer))
+        });
+        emulator.unwrap()
+    }
+
+    pub fn new_fds(
+        image: Vec<u8>,
+        patch: Option<Vec<u8>>,
+        bios: Vec<u8>,
+        sidecar: PathBuf,
+        region: Option<Region>,
+    ) -> Result<Self, String> {
+        let mut emulator = Self::with_emulator(region, |speaker, joypad_handler, renderer| {
+            Emulator::new_fds(image, patch, bios, speaker, joypad_handler, renderer)
+        })?;
+        emulator.disk_sidecar = Some(sidecar);
+        Ok(emulator)
+    }
+
+    fn with_emulator<F>(region: Option<Region>, create: F) -> Result<Self, String>
+    where
+        F: FnOnce(SdlSpeaker, Sdl2JoypadHandler, Sdl2Renderer) -> Result<SdlEmulator, String>,
+    {
         let sdl_context = sdl2::init().unwrap();
         let video_subsystem = sdl_context.video().unwrap();
         let window = video_subsystem
@@ -21,19 +72,132 @@ impl Sdl2Emulator {
         let creator = canvas.texture_creator();
 
         let speaker = SdlSpeaker::new(&sdl_context);
-        let joypad_handler = Sdl2JoypadHandler::new(event_pump);
-        let renderer = Sdl2Renderer::new(canvas, creator);
+        let hotkeys = HotkeyQueue::default();
+        let joypad_handler = Sdl2JoypadHandler::new(event_pump, hotkeys.clone());
+        let renderer = Sdl2Renderer::new(canvas, creator, hotkeys.clone());
 
-        let emulator = Emulator::new(raw, speaker, joypad_handler, renderer);
+        let mut emulator = create(speaker, joypad_handler, renderer)?;
+        emulator.diagnostics().set_sink(Some(Box::new(StderrSink)));
+        if let Some(region) = region {
+            emulator.set_region(region);
+        }
+        let frame_rate = emulator.frame_rate();
+        emulator.renderer_mut().set_frame_rate(frame_rate);
 
-        Self { emulator }
+        Ok(Self {
+            emulator,
+            hotkeys,
+            disk_sidecar: None,
+        })
     }
 
     pub fn step(&mut self) {
         self.emulator.step();
+        self.handle_hotkeys();
+    }
+
+    fn handle_hotkeys(&mut self) {
+        let hotkeys = self.hotkeys.borrow_mut().drain(..).collect::<Vec<_>>();
+        for hotkey in hotkeys {
+            match hotkey {
+                Hotkey::ToggleRecording => self.toggle_recording(),
+                Hotkey::SwitchDiskSide => self.switch_disk_side(),
+                Hotkey::Quit => {
+                    self.stop_recording();
+                    if let Some(Err(e)) = self.emulator.stop_audio_dump() {
+                        eprintln!("Failed to dump audio: {}", e);
+                    }
+                    self.save_disk();
+                    std::process::exit(0);
+                }
+                // NOTE: 描画まわりはレンダラーが処理する
+                hotkey => self.hotkeys.borrow_mut().push_back(hotkey),
+            }
+        }
+    }
+
+    // NOTE: prefix.y4m と prefix.wav (gif なら prefix.gif も) に書き出す
+    pub fn start_recording(&mut self, prefix: &str, gif: bool) -> Result<(), String> {
+        let create = |ext: &str| {
+            let path = format!("{}.{}", prefix, ext);
+            File::create(&path)
+                .map(BufWriter::new)
+                .map_err(|e| format!("{}: {}", path, e))
+        };
+
+        let mut recorder = Recorder::new(self.emulator.frame_rate(), DEFAULT_SAMPLE_RATE);
+        recorder.set_video(Box::new(create("y4m")?))?;
+        recorder.set_audio(Box::new(create("wav")?))?;
+        if gif {
+            recorder.set_gif(Box::new(create("gif")?), 2)?;
+        }
+        self.emulator.start_recording(recorder)?;
+        eprintln!("Recording to {}.*", prefix);
+        Ok(())
+    }
+
+    pub fn stop_recording(&mut self) {
+        match self.emulator.stop_recording() {
+            Some(Ok(())) => eprintln!("Recording stopped"),
+            Some(Err(e)) => eprintln!("Failed to record: {}", e),
+            None => {}
+        }
+    }
+
+    pub fn start_audio_dump(&mut self, path: &Path, stems: bool) -> Result<(), String> {
+        let recorder = audio_dump(path, stems, DEFAULT_SAMPLE_RATE)?;
+        self.emulator.start_audio_dump(recorder)
+    }
+
+    fn toggle_recording(&mut self) {
+        if self.emulator.is_recording() {
+            self.stop_recording();
+            return;
+        }
+
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        if let Err(e) = self.start_recording(&format!("recording-{}", millis), false) {
+            eprintln!("Failed to record: {}", e);
+        }
+    }
+
+    fn switch_disk_side(&mut self) {
+        if self.emulator.disk_sides() == 0 {
+            return;
+        }
+        self.emulator.switch_disk_side();
+        eprintln!("Disk ejected, switching side");
+    }
+
+    fn save_disk(&self) {
+        let (Some(path), Some(patch)) = (&self.disk_sidecar, self.emulator.disk_patch()) else {
+            return;
+        };
+        if let Err(e) = std::fs::write(path, patch) {
+            eprintln!("Failed to save disk: {}: {}", path.display(), e);
+        }
     }
 
     pub fn reset(&mut self) {
         self.emulator.reset();
     }
+
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.emulator.set_palette(palette);
+    }
+
+    pub fn set_filter(&mut self, filter: Filter) {
+        self.emulator.renderer_mut().set_filter(filter);
+    }
+
+    pub fn set_ntsc_filter(&mut self, mode: Option<NtscMode>) {
+        self.emulator.renderer_mut().set_ntsc_filter(mode);
+    }
+
+    pub fn set_trace_logger(&mut self, logger: Option<TraceLogger>) {
+        self.emulator.set_trace_logger(logger);
+    }
 }
@@ -1,7 +1,18 @@
 use lib::joypad::{button::JoypadButton, register::Joypad, JoypadHandler};
 use once_cell::sync::Lazy;
 use sdl2::{event::Event, keyboard::Keycode, EventPump};
-use std::collections::HashMap;
+use std::{cell::RefCell, collections::HashMap, collections::VecDeque, rc::Rc};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hotkey {
+    ToggleNtscFilter,
+    Screenshot,
+    ToggleRecording,
+    SwitchDiskSide,
+    Quit,
+}
+
+pub type HotkeyQueue = Rc<RefCell<VecDeque<Hotkey>>>;
 
 const KEY_MAP: Lazy<HashMap<Keycode, JoypadButton>> = Lazy::new(|| {
     let mut key_map = HashMap::new();
@@ -19,11 +30,15 @@ const KEY_MAP: Lazy<HashMap<Keycode, JoypadButton>> = Lazy::new(|| {
 
 pub struct Sdl2JoypadHandler {
     event_pump: EventPump,
+    hotkeys: HotkeyQueue,
 }
 
 impl Sdl2JoypadHandler {
-    pub fn new(event_pump: EventPump) -> Self {
-        Self { event_pump }
+    pub fn new(event_pump: EventPump, hotkeys: HotkeyQueue) -> Self {
+        Self {
+            event_pump,
+            hotkeys,
+        }
     }
 }
 
@@ -35,7 +50,34 @@ impl JoypadHandler for Sdl2JoypadHandler {
                 | Event::KeyDown {
                     keycode: Some(Keycode::Escape),
                     ..
-                } => std::process::exit(0),
+                } => self.hotkeys.borrow_mut().push_back(Hotkey::Quit),
+
+                Event::KeyDown {
+                    keycode: Some(Keycode::N),
+                    repeat: false,
+                    ..
+                } => self
+                    .hotkeys
+                    .borrow_mut()
+                    .push_back(Hotkey::ToggleNtscFilter),
+
+                Event::KeyDown {
+                    keycode: Some(Keycode::F12),
+                    repeat: false,
+                    ..
+                } => self.hotkeys.borrow_mut().push_back(Hotkey::Screenshot),
+
+                Event::KeyDown {
+                    keycode: Some(Keycode::F9),
+                    repeat: false,
+                    ..
+                } => self.hotkeys.borrow_mut().push_back(Hotkey::ToggleRecording),
+
+                Event::KeyDown {
+                    keycode: Some(Keycode::F5),
+                    repeat: false,
+                    ..
+                } => self.hotkeys.borrow_mut().push_back(Hotkey::SwitchDiskSide),
 
                 Event::KeyDown { keycode, .. } => {
                     if let Some(key) = KEY_MAP.get(&keycode.unwrap_or(Keycode::Ampersand)) {
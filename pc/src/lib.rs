@@ -1,4 +1,5 @@
 pub mod app;
+mod diagnostics;
 mod emulator;
 mod joypad;
 mod renderer;
@@ -1,31 +1,140 @@
-use lib::render::{utils::frame::Frame, Renderer};
+use std::{
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use lib::render::{
+    filter::{
+        ntsc::{NtscFilter, NtscMode},
+        Filter,
+    },
+    utils::{frame::Frame, png::encode_png},
+    Renderer,
+};
 use sdl2::{
     pixels::PixelFormatEnum,
     render::{Canvas, TextureCreator},
     video::{Window, WindowContext},
 };
 
+use crate::joypad::{Hotkey, HotkeyQueue};
+
 pub struct Sdl2Renderer {
     canvas: Canvas<Window>,
     texture_creator: TextureCreator<WindowContext>,
+    frame_duration: Duration,
+    next_frame: Instant,
+    hotkeys: HotkeyQueue,
+    filter: Filter,
+    ntsc_filter: Option<NtscFilter>,
+    buffer: Vec<u8>,
+    output_size: (usize, usize),
 }
 
 impl Sdl2Renderer {
-    pub fn new(canvas: Canvas<Window>, texture_creator: TextureCreator<WindowContext>) -> Self {
+    pub fn new(
+        canvas: Canvas<Window>,
+        texture_creator: TextureCreator<WindowContext>,
+        hotkeys: HotkeyQueue,
+    ) -> Self {
         Self {
             canvas,
             texture_creator,
+            frame_duration: Duration::ZERO,
+            next_frame: Instant::now(),
+            hotkeys,
+            filter: Filter::None,
+            ntsc_filter: None,
+            buffer: vec![],
+            output_size: (0, 0),
+        }
+    }
+
+    pub fn set_filter(&mut self, filter: Filter) {
+        self.filter = filter;
+    }
+
+    pub fn set_ntsc_filter(&mut self, mode: Option<NtscMode>) {
+        self.ntsc_filter = mode.map(NtscFilter::new);
+    }
+
+    fn toggle_ntsc_filter(&mut self) {
+        // NOTE: OFF -> Composite -> S-Video -> RGB -> OFF の順に切り替える
+        let next = match self.ntsc_filter.as_ref().map(|filter| filter.mode()) {
+            None => Some(NtscMode::Composite),
+            Some(NtscMode::Composite) => Some(NtscMode::SVideo),
+            Some(NtscMode::SVideo) => Some(NtscMode::Rgb),
+            Some(NtscMode::Rgb) => None,
+        };
+        self.set_ntsc_filter(next);
+    }
+
+    fn handle_hotkeys(&mut self) {
+        let hotkeys = self.hotkeys.borrow_mut().drain(..).collect::<Vec<_>>();
+        for hotkey in hotkeys {
+            match hotkey {
+                Hotkey::ToggleNtscFilter => self.toggle_ntsc_filter(),
+                Hotkey::Screenshot => self.save_screenshot(),
+                // NOTE: 録画と終了はエミュレーター側で処理するので戻しておく
+                hotkey => self.hotkeys.borrow_mut().push_back(hotkey),
+            }
         }
     }
+
+    // NOTE: 画面に出ているもの (フィルタ適用後) をそのままカレントディレクトリに保存する
+    fn save_screenshot(&self) {
+        let (width, height) = self.output_size;
+        if width == 0 {
+            return;
+        }
+
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let path = format!("screenshot-{}.png", millis);
+        match std::fs::write(&path, encode_png(&self.buffer, width, height)) {
+            Ok(()) => eprintln!("Saved {}", path),
+            Err(e) => eprintln!("Failed to save {}: {}", path, e),
+        }
+    }
+
+    pub fn set_frame_rate(&mut self, frame_rate: f32) {
+        self.frame_duration = Duration::from_secs_f32(1.0 / frame_rate);
+    }
+
+    fn wait_next_frame(&mut self) {
+        let now = Instant::now();
+        if self.next_frame > now {
+            thread::sleep(self.next_frame - now);
+        }
+        self.next_frame = self.next_frame.max(now) + self.frame_duration;
+    }
 }
 
 impl Renderer for Sdl2Renderer {
     fn render(&mut self, frame: &Frame) {
+        self.wait_next_frame();
+        self.handle_hotkeys();
+
+        // NOTE: NTSC フィルタが有効な場合はそちらを優先する
+        let (width, height) = match self.ntsc_filter.as_mut() {
+            Some(ntsc_filter) => {
+                ntsc_filter.apply(frame, &mut self.buffer);
+                (NtscFilter::WIDTH, NtscFilter::HEIGHT)
+            }
+            None => {
+                self.filter.apply(frame, &mut self.buffer);
+                self.filter.output_size()
+            }
+        };
+        self.output_size = (width, height);
+
         let mut texture = self
             .texture_creator
-            .create_texture_target(PixelFormatEnum::RGB24, 256, 240)
+            .create_texture_target(PixelFormatEnum::RGB24, width as u32, height as u32)
             .unwrap();
-        texture.update(None, &frame.data, 256 * 3).unwrap();
+        texture.update(None, &self.buffer, width * 3).unwrap();
         self.canvas.copy(&texture, None, None).unwrap();
         self.canvas.present();
     }
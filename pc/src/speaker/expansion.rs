@@ -0,0 +1,56 @@
+use std::{
+    collections::VecDeque,
+    sync::mpsc::{channel, Receiver, Sender},
+};
+
+use sdl2::audio::{AudioCallback, AudioDevice};
+
+// NOTE: エミュレータの方が速く進んだときに遅延が溜まり続けないよう、これより古いサンプルは捨てる
+const MAX_BUFFERED_SECONDS: f32 = 0.1;
+
+pub struct ExpansionStream {
+    receiver: Receiver<Vec<f32>>,
+    buffer: VecDeque<f32>,
+    max_buffered: usize,
+}
+
+impl AudioCallback for ExpansionStream {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [Self::Channel]) {
+        while let Ok(samples) = self.receiver.try_recv() {
+            self.buffer.extend(samples);
+        }
+        if self.buffer.len() > self.max_buffered {
+            let excess = self.buffer.len() - self.max_buffered;
+            self.buffer.drain(..excess);
+        }
+
+        for x in out.iter_mut() {
+            *x = self.buffer.pop_front().unwrap_or(0.0);
+        }
+    }
+}
+
+pub fn create_expansion_stream(
+    ctx: &sdl2::Sdl,
+    sample_rate: u32,
+) -> (AudioDevice<ExpansionStream>, Sender<Vec<f32>>) {
+    let audio_subsystem = ctx.audio().unwrap();
+    let (sender, receiver) = channel::<Vec<f32>>();
+    let spec = sdl2::audio::AudioSpecDesired {
+        freq: Some(sample_rate as i32),
+        channels: Some(1),
+        samples: None,
+    };
+
+    let device = audio_subsystem
+        .open_playback(None, &spec, move |spec| ExpansionStream {
+            receiver,
+            buffer: VecDeque::new(),
+            max_buffered: (spec.freq as f32 * MAX_BUFFERED_SECONDS) as usize,
+        })
+        .unwrap();
+
+    (device, sender)
+}
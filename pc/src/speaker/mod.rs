@@ -1,12 +1,17 @@
 use std::sync::mpsc::Sender;
 
+use expansion::ExpansionStream;
 use noise::{Noise, NoiseNote};
 use sdl2::audio::AudioDevice;
 use square_wave::{SquareNote, SquareWave};
 use triangle_wave::{TriangleNote, TriangleWave};
 
-use lib::speaker::{Speaker, SpeakerEvent};
+use lib::{
+    audio::DEFAULT_SAMPLE_RATE,
+    speaker::{Speaker, SpeakerEvent, EXPANSION_CH},
+};
 
+mod expansion;
 mod noise;
 mod square_wave;
 mod triangle_wave;
@@ -20,6 +25,9 @@ pub struct SdlSpeaker {
     ch3_sender: Sender<TriangleNote>,
     ch4_device: AudioDevice<Noise>,
     ch4_sender: Sender<NoiseNote>,
+    // NOTE: 落とすと再生が止まるので持っておくだけ
+    _expansion_device: AudioDevice<ExpansionStream>,
+    expansion_sender: Sender<Vec<f32>>,
 }
 
 impl SdlSpeaker {
@@ -28,11 +36,14 @@ impl SdlSpeaker {
         let (ch2_device, ch2_sender) = square_wave::create_square_wave(ctx);
         let (ch3_device, ch3_sender) = triangle_wave::create_triangle_wave(ctx);
         let (ch4_device, ch4_sender) = noise::create_noise(ctx);
+        let (expansion_device, expansion_sender) =
+            expansion::create_expansion_stream(ctx, DEFAULT_SAMPLE_RATE);
 
         ch1_device.resume();
         ch2_device.resume();
         ch3_device.resume();
         ch4_device.resume();
+        expansion_device.resume();
 
         Self {
             ch1_device,
@@ -43,6 +54,8 @@ impl SdlSpeaker {
             ch3_sender,
             ch4_device,
             ch4_sender,
+            _expansion_device: expansion_device,
+            expansion_sender,
         }
     }
 }
@@ -68,6 +81,9 @@ impl Speaker for SdlSpeaker {
                     .send(NoiseNote::new(hz, volume, mode))
                     .unwrap();
             }
+            (EXPANSION_CH, SpeakerEvent::ExpansionSamples { samples, .. }) => {
+                self.expansion_sender.send(samples).unwrap();
+            }
             _ => {}
         }
     }
@@ -1,5 +1,5 @@
 import styles from "./App.module.scss";
-import { useRef, useState } from "react";
+import { useEffect, useRef, useState } from "react";
 import { DndContext, DragEndEvent } from "@dnd-kit/core";
 import { TileMap } from "./components/TileMap";
 import roomTiles from "./assets/room.json";
@@ -9,7 +9,15 @@ import { NES } from "./components/nes";
 import { DropArea } from "./components/dropArea";
 import { Cartridge } from "./components/cartridge";
 import { readFile } from "./lib/file";
-import { Emulator } from "./lib/emulator";
+import { Emulator, NtscMode } from "./lib/emulator";
+
+// N キーで OFF -> Composite -> S-Video -> RGB -> OFF と切り替える
+const NTSC_MODES: (NtscMode | undefined)[] = [
+  undefined,
+  "composite",
+  "svideo",
+  "rgb",
+];
 
 type Rom = {
   isInsert: boolean;
@@ -25,6 +33,22 @@ export const App = () => {
   const [emulator, setEmulator] = useState<Emulator>();
   const [roms, setRoms] = useState<Rom[]>([]);
   const [isZoom, setIsZoom] = useState(false);
+  const [ntscModeIndex, setNtscModeIndex] = useState(0);
+
+  useEffect(() => {
+    const handleKeyDown = (e: KeyboardEvent) => {
+      if (e.code === "KeyN" && !e.repeat) {
+        setNtscModeIndex((index) => (index + 1) % NTSC_MODES.length);
+      }
+    };
+
+    window.addEventListener("keydown", handleKeyDown);
+    return () => window.removeEventListener("keydown", handleKeyDown);
+  }, []);
+
+  useEffect(() => {
+    emulator?.setNtscFilter(NTSC_MODES[ntscModeIndex]);
+  }, [emulator, ntscModeIndex]);
 
   const handleAddRom = (file: File) => {
     const pos = {
@@ -3,6 +3,8 @@ import { JoypadHandler } from "./joypadHandler";
 import { Renderer } from "./renderer";
 import { Speaker } from "./speaker";
 
+export type NtscMode = "composite" | "svideo" | "rgb";
+
 export class Emulator {
   private speaker: Speaker;
   private joypadHandler: JoypadHandler;
@@ -35,6 +37,15 @@ export class Emulator {
     this.speaker.setVolume(volume);
   }
 
+  setNtscFilter(mode: NtscMode | undefined) {
+    this.emulator.setNtscFilter(mode);
+  }
+
+  screenshot(filter?: string): Blob {
+    const png = this.emulator.screenshot(filter);
+    return new Blob([png], { type: "image/png" });
+  }
+
   start() {
     this.reset();
 
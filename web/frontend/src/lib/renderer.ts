@@ -1,34 +1,20 @@
-import { RenderBuffer } from "../bindings/RenderBuffer";
-
-const WIDTH = 256;
-const HEIGHT = 240;
-
 export class Renderer {
   constructor(private canvas: HTMLCanvasElement) {}
 
-  render(buf: RenderBuffer) {
+  // data は wasm のメモリを直接参照しているので、この関数の中で使い切る
+  render(data: Uint8ClampedArray, width: number, height: number) {
     const ctx = this.canvas.getContext("2d");
     if (!ctx) {
       return;
     }
 
-    const imageData = ctx.createImageData(WIDTH, HEIGHT);
-    for (let y = 0; y < HEIGHT; y++) {
-      for (let x = 0; x < WIDTH; x++) {
-        const base = (y * WIDTH + x) * 3;
-        const r = buf.data[base];
-        const g = buf.data[base + 1];
-        const b = buf.data[base + 2];
-
-        const offset = (y * WIDTH + x) * 4;
-        imageData.data[offset] = r;
-        imageData.data[offset + 1] = g;
-        imageData.data[offset + 2] = b;
-        imageData.data[offset + 3] = 255;
-      }
+    // NTSC フィルタ有効時は横幅が変わるので、バッファに合わせて canvas の解像度を変える
+    if (this.canvas.width !== width || this.canvas.height !== height) {
+      this.canvas.width = width;
+      this.canvas.height = height;
     }
 
-    ctx.putImageData(imageData, 0, 0);
+    ctx.putImageData(new ImageData(data, width, height), 0, 0);
   }
 
   setCanvas(canvas: HTMLCanvasElement) {
@@ -41,6 +27,6 @@ export class Renderer {
       return;
     }
 
-    ctx.clearRect(0, 0, WIDTH, HEIGHT);
+    ctx.clearRect(0, 0, this.canvas.width, this.canvas.height);
   }
 }
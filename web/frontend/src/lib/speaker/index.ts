@@ -3,12 +3,17 @@ import { JsSpeakerEvent } from "../../bindings/JsSpeakerEvent";
 import { TriangleWave, createTriangleWave } from "./triangleWave";
 import { createNoiseNode, LONG, NoiseNode, SHORT } from "./noise";
 
+const EXPANSION_CH = 6;
+const MAX_EXPANSION_DELAY = 0.1;
+
 export class Speaker {
   private gain: GainNode;
   private ch1: RectWave | undefined;
   private ch2: RectWave | undefined;
   private ch3: TriangleWave | undefined;
   private ch4: NoiseNode | undefined;
+  // 拡張音源の PCM を途切れないように並べて再生する
+  private expansionTime = 0;
 
   constructor(private context: AudioContext) {
     this.gain = context.createGain();
@@ -29,6 +34,7 @@ export class Speaker {
   }
 
   reset() {
+    this.expansionTime = 0;
     void this.initChannel();
   }
 
@@ -81,6 +87,30 @@ export class Speaker {
           }
         }
         return;
+      case EXPANSION_CH:
+        if (event.type === "ExpansionSamples") {
+          this.playExpansion(event.sample_rate, event.samples);
+        }
+        return;
+    }
+  }
+
+  private playExpansion(sampleRate: number, samples: number[]) {
+    const buffer = this.context.createBuffer(1, samples.length, sampleRate);
+    buffer.copyToChannel(Float32Array.from(samples), 0);
+    const source = this.context.createBufferSource();
+    source.buffer = buffer;
+    source.connect(this.gain);
+
+    // 再生が追いついたり、先に積みすぎたりしたら今の時刻から並べ直す
+    const now = this.context.currentTime;
+    if (
+      this.expansionTime < now ||
+      this.expansionTime > now + MAX_EXPANSION_DELAY
+    ) {
+      this.expansionTime = now;
     }
+    source.start(this.expansionTime);
+    this.expansionTime += buffer.duration;
   }
 }
@@ -0,0 +1,11 @@
+use lib::diagnostics::{Diagnostic, DiagnosticsSink};
+
+use crate::utils::warn;
+
+pub struct ConsoleSink;
+
+impl DiagnosticsSink for ConsoleSink {
+    fn report(&mut self, diagnostic: &Diagnostic) {
+        warn(&diagnostic.to_string());
+    }
+}
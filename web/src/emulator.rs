@@ -1,8 +1,12 @@
-use wasm_bindgen::prelude::wasm_bindgen;
+use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
 
-use lib::emulator::Emulator;
+use lib::{
+    emulator::Emulator,
+    render::filter::{ntsc::NtscMode, Filter},
+};
 
 use crate::{
+    diagnostics::ConsoleSink,
     joypad::{JsJoypadHandler, WebJoypadHandler},
     renderer::{JsRenderer, WebRenderer},
     speaker::{JsSpeaker, WebSpeaker},
@@ -22,14 +26,15 @@ impl WebEmulator {
         handler: JsJoypadHandler,
         renderer: JsRenderer,
     ) -> Self {
-        Self {
-            emulator: Emulator::new(
-                rom_data,
-                WebSpeaker::new(speaker),
-                WebJoypadHandler::new(handler),
-                WebRenderer::new(renderer),
-            ),
-        }
+        let emulator = Emulator::new(
+            rom_data,
+            WebSpeaker::new(speaker),
+            WebJoypadHandler::new(handler),
+            WebRenderer::new(renderer),
+        );
+        emulator.diagnostics().set_sink(Some(Box::new(ConsoleSink)));
+
+        Self { emulator }
     }
 
     #[wasm_bindgen]
@@ -41,4 +46,25 @@ impl WebEmulator {
     pub fn step(&mut self) {
         self.emulator.step();
     }
+
+    // NOTE: 最後に描画したフレームの PNG。filter を指定するとその解像度で出す
+    #[wasm_bindgen]
+    pub fn screenshot(&self, filter: Option<String>) -> Result<Vec<u8>, JsValue> {
+        let filter = filter
+            .map(|filter| filter.parse::<Filter>())
+            .transpose()
+            .map_err(|e| JsValue::from_str(&e))?
+            .unwrap_or_default();
+        Ok(self.emulator.screenshot(filter))
+    }
+
+    #[wasm_bindgen(js_name = setNtscFilter)]
+    pub fn set_ntsc_filter(&mut self, mode: Option<String>) -> Result<(), JsValue> {
+        let mode = mode
+            .map(|mode| mode.parse::<NtscMode>())
+            .transpose()
+            .map_err(|e| JsValue::from_str(&e))?;
+        self.emulator.renderer_mut().set_ntsc_filter(mode);
+        Ok(())
+    }
 }
@@ -1,3 +1,4 @@
+mod diagnostics;
 mod emulator;
 mod joypad;
 mod renderer;
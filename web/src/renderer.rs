@@ -1,38 +1,67 @@
-use lib::render::{utils::frame::Frame, Renderer};
-use serde::{Deserialize, Serialize};
-use ts_rs::TS;
-use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
+use js_sys::Uint8ClampedArray;
+use lib::render::{
+    filter::ntsc::{NtscFilter, NtscMode},
+    utils::frame::Frame,
+    Renderer,
+};
+use wasm_bindgen::prelude::wasm_bindgen;
 
 #[wasm_bindgen]
 extern "C" {
     pub type JsRenderer;
 
     #[wasm_bindgen(method, js_name = render)]
-    fn render(this: &JsRenderer, frame: &JsValue);
-}
-
-#[derive(TS, Serialize, Deserialize)]
-#[ts(export)]
-struct RenderBuffer {
-    data: Vec<u8>,
+    fn render(this: &JsRenderer, data: &Uint8ClampedArray, width: u32, height: u32);
 }
 
 pub struct WebRenderer {
     renderer: JsRenderer,
+    ntsc_filter: Option<NtscFilter>,
+    ntsc_buffer: Vec<u8>,
+    buffer: Vec<u8>,
 }
 
 impl WebRenderer {
     pub fn new(renderer: JsRenderer) -> Self {
-        Self { renderer }
+        Self {
+            renderer,
+            ntsc_filter: None,
+            ntsc_buffer: vec![],
+            buffer: vec![0; Frame::WIDTH * Frame::HEIGHT * 4],
+        }
+    }
+
+    pub fn set_ntsc_filter(&mut self, mode: Option<NtscMode>) {
+        self.ntsc_filter = mode.map(NtscFilter::new);
     }
 }
 
 impl Renderer for WebRenderer {
     fn render(&mut self, frame: &Frame) {
-        let buf = RenderBuffer {
-            data: frame.data.clone(),
+        let (width, height) = match self.ntsc_filter.as_mut() {
+            Some(filter) => {
+                filter.apply(frame, &mut self.ntsc_buffer);
+                self.buffer
+                    .resize(NtscFilter::WIDTH * NtscFilter::HEIGHT * 4, 0);
+                for (rgba, rgb) in self
+                    .buffer
+                    .chunks_exact_mut(4)
+                    .zip(self.ntsc_buffer.chunks_exact(3))
+                {
+                    rgba.copy_from_slice(&[rgb[0], rgb[1], rgb[2], 0xFF]);
+                }
+                (NtscFilter::WIDTH, NtscFilter::HEIGHT)
+            }
+            None => {
+                self.buffer.resize(Frame::WIDTH * Frame::HEIGHT * 4, 0);
+                frame.to_rgba(&mut self.buffer);
+                (Frame::WIDTH, Frame::HEIGHT)
+            }
         };
-        let frame_value = serde_wasm_bindgen::to_value(&buf).unwrap();
-        self.renderer.render(&frame_value);
+
+        // NOTE: wasm のメモリをコピーせずに直接参照する。
+        //       JS 側は render の中で使い切ること (wasm のメモリが伸びると view は無効になる)
+        let view = unsafe { Uint8ClampedArray::view(&self.buffer) };
+        self.renderer.render(&view, width as u32, height as u32);
     }
 }
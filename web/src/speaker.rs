@@ -36,6 +36,10 @@ enum JsSpeakerEvent {
     TriangleNote {
         hz: f32,
     },
+    ExpansionSamples {
+        sample_rate: u32,
+        samples: Vec<f32>,
+    },
 }
 
 impl From<SpeakerEvent> for JsSpeakerEvent {
@@ -53,6 +57,13 @@ impl From<SpeakerEvent> for JsSpeakerEvent {
                 volume,
             },
             SpeakerEvent::TriangleNote { hz } => JsSpeakerEvent::TriangleNote { hz },
+            SpeakerEvent::ExpansionSamples {
+                sample_rate,
+                samples,
+            } => JsSpeakerEvent::ExpansionSamples {
+                sample_rate,
+                samples,
+            },
         }
     }
 }